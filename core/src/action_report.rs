@@ -1,7 +1,7 @@
 use derive_more::From;
 use ergo_lib::ergo_chain_types::EcPoint;
 
-use crate::oracle_types::Rate;
+use crate::datapoint_audit::DatapointSnapshot;
 
 #[derive(Debug)]
 pub struct RefreshActionReport {
@@ -10,7 +10,8 @@ pub struct RefreshActionReport {
 
 #[derive(Debug)]
 pub struct PublishDatapointActionReport {
-    pub posted_datapoint: Rate,
+    /// Posted datapoint tagged with the height it was computed at and its sources
+    pub snapshot: DatapointSnapshot,
 }
 
 #[derive(Debug, From)]
@@ -40,6 +41,12 @@ impl ActionReportStorage {
         }
     }
 
+    /// Drops the action reports, e.g. after a reorg orphaned the actions
+    pub fn invalidate_reports(&mut self) {
+        self.refresh = None;
+        self.publish_datapoint = None;
+    }
+
     pub fn get_last_refresh_report(&self) -> Option<&RefreshActionReport> {
         self.refresh.as_ref()
     }
@@ -2,15 +2,23 @@
 /// by an oracle part of the oracle pool. These actions
 /// are implemented on the `OraclePool` struct.
 use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
 
 use derive_more::From;
 use ergo_node_interface::node_interface::NodeError;
 use thiserror::Error;
 
 use crate::explorer_api::ergo_explorer_transaction_link;
+use crate::funding::FundedWallet;
 use crate::node_interface::node_api::NodeApi;
 use crate::node_interface::node_api::NodeApiError;
 use crate::oracle_config::ORACLE_CONFIG;
+use crate::pending_datapoint;
+use crate::refresh_debug::RefreshDebugBundle;
+use crate::tx_retry::is_retryable;
+use crate::tx_retry::QueuedTransaction;
+use crate::tx_retry::RetryTarget;
 
 mod action_result;
 
@@ -21,9 +29,20 @@ pub enum PoolAction {
     PublishDatapoint(PublishDataPointAction),
 }
 
+impl PoolAction {
+    pub fn tx(&self) -> &UnsignedTransaction {
+        match self {
+            PoolAction::Refresh(action) => &action.tx,
+            PoolAction::PublishDatapoint(action) => &action.tx,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RefreshAction {
     pub tx: UnsignedTransaction,
+    /// Boxes spent by the tx, in the order of its inputs
+    pub input_boxes: Vec<ErgoBox>,
 }
 
 #[derive(Debug)]
@@ -35,52 +54,136 @@ pub struct PublishDataPointAction {
 pub enum ActionExecError {
     #[error("node error: {0}")]
     NodeError(#[from] NodeApiError),
+    #[error("failed to sign or submit the tx, queued it for retry: {0}")]
+    QueuedForRetry(NodeApiError),
 }
 
-pub fn execute_action(action: PoolAction, node_api: &NodeApi) -> Result<(), anyhow::Error> {
+/// Returns the id of the submitted tx, or `None` if the node rejected it as already submitted.
+/// The tx is signed by the funding node if `funded_wallet` is given (the tx was built from its
+/// boxes), otherwise by the oracle node.
+/// If `retry_queue` is given, the tx is queued (expiring at its height) when the signing or the
+/// submission fails for a reason other than the node rejecting it.
+pub fn execute_action(
+    action: PoolAction,
+    node_api: &NodeApi,
+    funded_wallet: Option<&FundedWallet>,
+    retry_queue: Option<RetryTarget>,
+) -> Result<Option<TxId>, anyhow::Error> {
     let exec_res = match action {
-        PoolAction::Refresh(action) => execute_refresh_action(action, node_api),
-        PoolAction::PublishDatapoint(action) => execute_publish_datapoint_action(action, node_api),
+        PoolAction::Refresh(action) => {
+            execute_refresh_action(action, node_api, funded_wallet, retry_queue)
+        }
+        PoolAction::PublishDatapoint(action) => {
+            execute_publish_datapoint_action(action, node_api, funded_wallet, retry_queue)
+        }
     };
     match exec_res {
-        Ok(_) => Ok(()),
+        Ok(tx_id) => Ok(Some(tx_id)),
+        Err(ActionExecError::QueuedForRetry(e)) => {
+            log::warn!(
+                "Failed to sign or submit the tx, queued it for retry: {}",
+                e
+            );
+            Ok(None)
+        }
         Err(ActionExecError::NodeError(NodeApiError::NodeInterfaceError(
             NodeError::BadRequest(msg),
-        ))) if msg.as_str() == "Double spending attempt"
-            || msg.contains("it is invalidated earlier or the pool is full")
-            || msg.contains("it is already in the mempool")
-            || msg.contains("Not enough boxes to spend") // node cannot find all the input boxes due to them being spent in previous tx (last main loop iteration), see https://github.com/ergoplatform/oracle-core/issues/220
-            =>
-        {
+        ))) if is_already_submitted(&msg) => {
             log::debug!("Node rejected tx with error: {msg}");
-            Ok(())
+            Ok(None)
         }
         Err(e) => Err(e.into()),
     }
 }
 
+/// Whether the node rejected the tx because it (or another tx spending the same boxes) was
+/// already submitted
+fn is_already_submitted(node_msg: &str) -> bool {
+    node_msg == "Double spending attempt"
+        || node_msg.contains("it is invalidated earlier or the pool is full")
+        || node_msg.contains("it is already in the mempool")
+        || node_msg.contains("Not enough boxes to spend") // node cannot find all the input boxes due to them being spent in previous tx (last main loop iteration), see https://github.com/ergoplatform/oracle-core/issues/220
+}
+
+/// Signs and submits the tx, putting the tx into the retry queue (if given) on a retryable
+/// signing or submission failure (unsigned if the signing failed)
+fn sign_and_submit(
+    unsigned_tx: &UnsignedTransaction,
+    node_api: &NodeApi,
+    funded_wallet: Option<&FundedWallet>,
+    retry_queue: Option<RetryTarget>,
+) -> Result<TxId, ActionExecError> {
+    let signed_tx = match funded_wallet {
+        Some(funded_wallet) => funded_wallet.sign_transaction(unsigned_tx),
+        None => node_api.sign_transaction(unsigned_tx),
+    };
+    let (queued_tx, e) = match signed_tx {
+        Ok(signed_tx) => match node_api.submit_transaction(&signed_tx) {
+            Ok(tx_id) => return Ok(tx_id),
+            Err(e) => (QueuedTransaction::Signed(signed_tx), e),
+        },
+        Err(e) => (QueuedTransaction::Unsigned(unsigned_tx.clone()), e),
+    };
+    match retry_queue {
+        Some(target) if is_retryable(&e) => {
+            target
+                .queue
+                .push(queued_tx, target.expires_at, target.datapoint);
+            Err(ActionExecError::QueuedForRetry(e))
+        }
+        Some(_) | None => Err(e.into()),
+    }
+}
+
 fn execute_refresh_action(
     action: RefreshAction,
     node_api: &NodeApi,
-) -> Result<(), ActionExecError> {
-    let tx_id = node_api.sign_and_submit_transaction(&action.tx)?;
-    let network_prefix = &ORACLE_CONFIG.oracle_address.network();
+    funded_wallet: Option<&FundedWallet>,
+    retry_queue: Option<RetryTarget>,
+) -> Result<TxId, ActionExecError> {
+    let tx_id = sign_and_submit(&action.tx, node_api, funded_wallet, retry_queue).map_err(|e| {
+        if let ActionExecError::NodeError(NodeApiError::NodeInterfaceError(
+            NodeError::BadRequest(msg),
+        )) = &e
+        {
+            if !is_already_submitted(msg) {
+                save_refresh_debug_bundle(&action, msg);
+            }
+        }
+        e
+    })?;
+    let network_prefix = &ORACLE_CONFIG.network_prefix();
     log::info!(
         "Refresh tx published. Check status: {}",
         ergo_explorer_transaction_link(tx_id, *network_prefix)
     );
-    Ok(())
+    Ok(tx_id)
+}
+
+fn save_refresh_debug_bundle(action: &RefreshAction, node_msg: &str) {
+    let path = RefreshDebugBundle::file_path();
+    match RefreshDebugBundle::new(action, node_msg.to_string()).save(&path) {
+        Ok(()) => log::error!(
+            "Refresh tx rejected by the node, saved the tx and its inputs to {} (also served by \
+            the /refreshDebugBundle endpoint) to share with the other pool operators",
+            path.display()
+        ),
+        Err(e) => log::error!("Failed to save the refresh debug bundle: {:?}", e),
+    }
 }
 
 fn execute_publish_datapoint_action(
     action: PublishDataPointAction,
     node_api: &NodeApi,
-) -> Result<(), ActionExecError> {
-    let tx_id = node_api.sign_and_submit_transaction(&action.tx)?;
-    let network_prefix = &ORACLE_CONFIG.oracle_address.network();
+    funded_wallet: Option<&FundedWallet>,
+    retry_queue: Option<RetryTarget>,
+) -> Result<TxId, ActionExecError> {
+    let tx_id = sign_and_submit(&action.tx, node_api, funded_wallet, retry_queue)?;
+    pending_datapoint::record(&action.tx);
+    let network_prefix = &ORACLE_CONFIG.network_prefix();
     log::info!(
         "Datapoint tx published. Check status: {}",
         ergo_explorer_transaction_link(tx_id, *network_prefix)
     );
-    Ok(())
+    Ok(tx_id)
 }
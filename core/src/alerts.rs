@@ -0,0 +1,323 @@
+//! Alerts on the oracle and pool problems. Every alert is logged and also sent to the channels
+//! routed for its category in the oracle config (`alerts`), so that e.g. a missed epoch goes to
+//! Telegram while a degraded source is only logged. An alert is sent when its condition starts
+//! and when it's resolved, not on every main loop iteration.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::oracle_config::ORACLE_CONFIG_OPT;
+use crate::oracle_config::REDACTED;
+
+/// Default `low_balance_threshold`, 1 ERG
+const DEFAULT_LOW_BALANCE_THRESHOLD: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCategory {
+    /// Our datapoint box is behind the pool box
+    MissedEpoch,
+    /// The pool box was not refreshed in time
+    PoolStale,
+    /// The oracle wallet balance is below `low_balance_threshold`
+    LowBalance,
+    /// The datapoint could not be fetched from the source
+    SourceDegraded,
+    /// Other oracles operate under contract parameters different from the pool config
+    ContractParamsMismatch,
+    /// Our txs are repeatedly rejected for a failed script, the oracle stopped submitting
+    SafeMode,
+    /// The pool admin signaled to stop posting (`pause_signal`)
+    Paused,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+impl AlertCategory {
+    pub fn severity(&self) -> Severity {
+        match self {
+            AlertCategory::MissedEpoch
+            | AlertCategory::LowBalance
+            | AlertCategory::SourceDegraded
+            | AlertCategory::Paused => Severity::Warning,
+            AlertCategory::PoolStale
+            | AlertCategory::ContractParamsMismatch
+            | AlertCategory::SafeMode => Severity::Critical,
+        }
+    }
+}
+
+/// Where the alerts are sent
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertChannel {
+    /// POST of a JSON `{"category", "severity", "message", "resolved"}`, e.g. to a mail gateway
+    Webhook { url: Url },
+    /// Message of a Telegram bot to the chat
+    Telegram { bot_token: String, chat_id: String },
+}
+
+// the bot token grants control over the bot
+impl std::fmt::Debug for AlertChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertChannel::Webhook { url } => f.debug_struct("Webhook").field("url", url).finish(),
+            AlertChannel::Telegram { chat_id, .. } => f
+                .debug_struct("Telegram")
+                .field("bot_token", &REDACTED)
+                .field("chat_id", chat_id)
+                .finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    /// Channels by name
+    pub channels: HashMap<String, AlertChannel>,
+    /// Names of the channels the alerts of the category are sent to
+    pub routes: HashMap<AlertCategory, Vec<String>>,
+    /// Names of the channels for the categories not listed in `routes`
+    pub default_route: Vec<String>,
+    /// Oracle wallet balance (in nanoERG) below which the `low_balance` alert is raised
+    pub low_balance_threshold: u64,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            channels: HashMap::new(),
+            routes: HashMap::new(),
+            default_route: Vec::new(),
+            low_balance_threshold: DEFAULT_LOW_BALANCE_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AlertsConfigError {
+    #[error("alert route refers to unknown channel {0}")]
+    UnknownChannel(String),
+}
+
+impl AlertsConfig {
+    /// Checks that the routes refer to the configured channels
+    pub fn validate(&self) -> Result<(), AlertsConfigError> {
+        match self
+            .routes
+            .values()
+            .flatten()
+            .chain(self.default_route.iter())
+            .find(|name| !self.channels.contains_key(*name))
+        {
+            Some(name) => Err(AlertsConfigError::UnknownChannel(name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Channels (with names) the alerts of the category are sent to
+    fn channels_for(&self, category: AlertCategory) -> Vec<(&String, &AlertChannel)> {
+        self.routes
+            .get(&category)
+            .unwrap_or(&self.default_route)
+            .iter()
+            .filter_map(|name| self.channels.get_key_value(name))
+            .collect()
+    }
+}
+
+/// Categories of the currently firing alerts
+#[derive(Debug, Default)]
+struct Alerter {
+    firing: HashSet<AlertCategory>,
+}
+
+impl Alerter {
+    /// `Some(true)` if the alert starts firing, `Some(false)` if it's resolved, `None` if the
+    /// state didn't change
+    fn transition(&mut self, category: AlertCategory, active: bool) -> Option<bool> {
+        if active {
+            self.firing.insert(category).then_some(true)
+        } else {
+            self.firing.remove(&category).then_some(false)
+        }
+    }
+}
+
+lazy_static! {
+    static ref ALERTER: Mutex<Alerter> = Mutex::new(Alerter::default());
+}
+
+/// Sets whether the alert condition holds. The alert is logged and sent to its channels when it
+/// starts and when it's resolved.
+pub fn update(category: AlertCategory, active: bool, message: &str) {
+    let resolved = match ALERTER.lock().unwrap().transition(category, active) {
+        Some(fired) => !fired,
+        None => return,
+    };
+    let text = format_message(category, message, resolved);
+    if resolved {
+        log::info!("{}", text);
+    } else {
+        log::warn!("{}", text);
+    }
+    if let Ok(config) = ORACLE_CONFIG_OPT.as_ref() {
+        for (name, channel) in config.alerts.channels_for(category) {
+            if let Err(e) = send(channel, category, message, resolved) {
+                log::warn!("Failed to send the alert to channel {}: {}", name, e);
+            }
+        }
+    }
+}
+
+fn format_message(category: AlertCategory, message: &str, resolved: bool) -> String {
+    let category_name = serde_json::to_value(category).unwrap();
+    let category_name = category_name.as_str().unwrap_or_default();
+    if resolved {
+        format!("[RESOLVED] {}", category_name)
+    } else {
+        let severity = match category.severity() {
+            Severity::Warning => "WARNING",
+            Severity::Critical => "CRITICAL",
+        };
+        format!("[{}] {}: {}", severity, category_name, message)
+    }
+}
+
+fn send(
+    channel: &AlertChannel,
+    category: AlertCategory,
+    message: &str,
+    resolved: bool,
+) -> Result<(), reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let (url, body) = match channel {
+        AlertChannel::Webhook { url } => (
+            url.to_string(),
+            json!({
+                "category": category,
+                "severity": category.severity(),
+                "message": message,
+                "resolved": resolved,
+            }),
+        ),
+        AlertChannel::Telegram { bot_token, chat_id } => (
+            format!("https://api.telegram.org/bot{}/sendMessage", bot_token),
+            json!({
+                "chat_id": chat_id,
+                "text": format_message(category, message, resolved),
+            }),
+        ),
+    };
+    client
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition() {
+        let mut alerter = Alerter::default();
+        assert_eq!(alerter.transition(AlertCategory::PoolStale, false), None);
+        assert_eq!(
+            alerter.transition(AlertCategory::PoolStale, true),
+            Some(true)
+        );
+        assert_eq!(alerter.transition(AlertCategory::PoolStale, true), None);
+        assert_eq!(
+            alerter.transition(AlertCategory::LowBalance, true),
+            Some(true)
+        );
+        assert_eq!(
+            alerter.transition(AlertCategory::PoolStale, false),
+            Some(false)
+        );
+        assert_eq!(alerter.transition(AlertCategory::PoolStale, false), None);
+    }
+
+    #[test]
+    fn test_routes() {
+        let config: AlertsConfig = serde_yaml::from_str(
+            r#"
+channels:
+  telegram:
+    type: telegram
+    bot_token: "123:abc"
+    chat_id: "42"
+  email:
+    type: webhook
+    url: http://127.0.0.1:8080/mail
+routes:
+  missed_epoch: [telegram]
+  low_balance: [email]
+  source_degraded: []
+default_route: [telegram, email]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.low_balance_threshold, DEFAULT_LOW_BALANCE_THRESHOLD);
+        let names = |category| {
+            config
+                .channels_for(category)
+                .into_iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(AlertCategory::MissedEpoch), vec!["telegram"]);
+        assert_eq!(names(AlertCategory::LowBalance), vec!["email"]);
+        assert!(names(AlertCategory::SourceDegraded).is_empty());
+        assert_eq!(names(AlertCategory::PoolStale), vec!["telegram", "email"]);
+
+        let unknown_channel = AlertsConfig {
+            default_route: vec!["sms".to_string()],
+            ..config
+        };
+        assert_eq!(
+            unknown_channel.validate(),
+            Err(AlertsConfigError::UnknownChannel("sms".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_format_message() {
+        assert_eq!(
+            format_message(AlertCategory::PoolStale, "pool box is 40 blocks old", false),
+            "[CRITICAL] pool_stale: pool box is 40 blocks old"
+        );
+        assert_eq!(
+            format_message(AlertCategory::PoolStale, "pool box is 40 blocks old", true),
+            "[RESOLVED] pool_stale"
+        );
+    }
+
+    #[test]
+    fn test_debug_redacts_bot_token() {
+        let channel = AlertChannel::Telegram {
+            bot_token: "123:secret".to_string(),
+            chat_id: "42".to_string(),
+        };
+        assert!(!format!("{:?}", channel).contains("secret"));
+    }
+}
@@ -1,18 +1,45 @@
 use std::convert::From;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
+pub mod grafana;
+pub mod schema;
+
+use self::grafana::{AnnotationRequest, GrafanaError, HeightClock, QueryRequest};
+use self::schema::{
+    OracleStatusResponse, PoolStatusResponse, ORACLE_STATUS_SCHEMA_VERSION,
+    POOL_STATUS_SCHEMA_VERSION,
+};
 use crate::box_kind::PoolBox;
+use crate::datapoint_audit;
+use crate::epoch_archive::EpochArchive;
+use crate::error_code::{error_code, error_code_of, ErrorCode};
+use crate::explorer_api::explorer_url::default_explorer_api_url;
+use crate::explorer_api::ExplorerApi;
+use crate::fee_ledger::FeeLedger;
+use crate::ip_allowlist::allowlist_middleware;
 use crate::monitor::{
-    check_oracle_health, check_pool_health, HealthStatus, OracleHealth, PoolHealth,
+    check_my_datapoint_rank, check_oracle_health, check_pool_health, HealthStatus, OracleHealth,
+    PoolHealth,
 };
 use crate::node_interface::node_api::{NodeApi, NodeApiError};
 use crate::oracle_config::{ORACLE_CONFIG, ORACLE_SECRETS};
-use crate::oracle_state::{DataSourceError, LocalDatapointState, OraclePool};
+use crate::oracle_state::{DataSourceError, OraclePool};
+use crate::oracle_types::{BlockHeight, EpochCounter, Rate};
 use crate::pool_config::POOL_CONFIG;
-use axum::http::StatusCode;
+use crate::refresh_debug::{load_last_bundle, RefreshDebugBundle};
+use crate::safe_mode;
+use axum::extract::Query;
+use axum::http::header::{ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware;
 use axum::response::{IntoResponse, Response};
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Json, Router};
 use crossbeam::channel::Receiver;
 use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder};
@@ -30,6 +57,16 @@ async fn root() -> &'static str {
         /oracleStatus - status of the oracle
         /oracleHealth - returns OK if our collected datapoint box height is the same as the pool box height OR our posted datapoint box height is greater than the pool box height
         /poolHealth - returns OK if the pool box height is greater or equal to (current height - epoch length)
+        (both health endpoints return 503 while the node is rescanning the wallet and scans)
+        /safeMode - whether the oracle stopped submitting txs after repeated script failure rejections, with the last rejection
+        /waitForNextEpoch?epoch_id=<id>&timeout_secs=<secs> - holds the request until the pool box epoch counter is greater than epoch_id (current epoch by default) or the timeout
+        /datapointRank - position of our datapoint among the datapoints posted for the current epoch, whether the refresh filters it out and its deviation from the consensus over the recent epochs
+        /epochHistory?from_epoch_id=<id>&limit=<n> - archived pool epochs (rate, height, box and tx ids) starting from the epoch
+        /tokenEconomics - reward token supply, amounts locked in the pool box and distributed to the oracles, and the projected emission runway
+        /refreshDebugBundle - the last refresh tx rejected by the node with its input boxes, to share with the other pool operators
+        /feeHistory - fees paid by our transactions per day and per epoch (last 90 days)
+        /datapointAudit?from_height=<height>&limit=<n> - our posted datapoints with the height and time they were computed at and the rate of each source
+        /search, /query, /annotations (POST) - Grafana simple-json-datasource endpoints charting the pool rate and our datapoints
         "
 }
 
@@ -38,48 +75,42 @@ async fn oracle_info() -> impl IntoResponse {
     let conf = &ORACLE_CONFIG;
     Json(json! ( {
         "oracle_address": conf.oracle_address.to_base58(),
+        "network": conf.network().to_string(),
         "base_fee": conf.base_fee,
     } ))
 }
 
 /// Status of the oracle
-async fn oracle_status(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
+async fn oracle_status(
+    oracle_pool: Arc<OraclePool>,
+) -> Result<Json<OracleStatusResponse>, ApiError> {
     let json = task::spawn_blocking(|| oracle_status_sync(oracle_pool))
         .await
         .unwrap()?;
     Ok(json)
 }
 
-fn oracle_status_sync(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
+fn oracle_status_sync(
+    oracle_pool: Arc<OraclePool>,
+) -> Result<Json<OracleStatusResponse>, ApiError> {
     let live_epoch = oracle_pool.get_live_epoch_state()?;
-    if let Some(local_datapoint_box_state) = live_epoch.local_datapoint_box_state {
-        let json = match local_datapoint_box_state {
-            LocalDatapointState::Collected { height } => json!( {
-                "status": "collected",
-                "height": height,
-            }),
-            LocalDatapointState::Posted { epoch_id, height } => json!( {
-                "status": "posted",
-                "epoch_id": epoch_id,
-                "height": height,
-            }),
-        };
-        let oracle_health = oracle_health_sync(oracle_pool)?;
-        Ok(Json(json!({
-                "local_datapoint_box_state": json,
-                "oracle_health": oracle_health,
-        })))
+    let oracle_health = if live_epoch.local_datapoint_box_state.is_some() {
+        Some((&oracle_health_sync(oracle_pool)?).into())
     } else {
-        Ok(Json(json!({
-                "local_datapoint_box_state": "No local datapoint box",
-        })))
-    }
+        None
+    };
+    Ok(Json(OracleStatusResponse {
+        schema_version: ORACLE_STATUS_SCHEMA_VERSION,
+        local_datapoint_box_state: live_epoch.local_datapoint_box_state.map(Into::into),
+        oracle_health,
+        safe_mode: safe_mode::is_active(),
+    }))
 }
 
 // Basic information about the oracle pool
 async fn pool_info() -> impl IntoResponse {
     let conf = &POOL_CONFIG;
-    let network = &ORACLE_CONFIG.oracle_address.network();
+    let network = &ORACLE_CONFIG.network_prefix();
     let address_encoder = AddressEncoder::new(*network);
     let pool_box_address = Address::P2S(
         conf.pool_box_wrapper_inputs
@@ -119,42 +150,57 @@ async fn pool_info() -> impl IntoResponse {
     }))
 }
 
-/// Status of the oracle pool
-async fn pool_status(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
-    let json = task::spawn_blocking(|| pool_status_sync(oracle_pool))
+/// Status of the oracle pool. The ETag is the pool box id and the current height, so a client
+/// polling with `If-None-Match` gets a 304 until the next block.
+async fn pool_status(
+    oracle_pool: Arc<OraclePool>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    task::spawn_blocking(move || pool_status_sync(oracle_pool, &headers))
         .await
-        .unwrap()?;
-    Ok(json)
+        .unwrap()
 }
 
-fn pool_status_sync(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
+fn pool_status_sync(
+    oracle_pool: Arc<OraclePool>,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
     let node_api = NodeApi::new(
         ORACLE_SECRETS.node_api_key.clone(),
         ORACLE_SECRETS.wallet_password.clone(),
         &ORACLE_CONFIG.node_url,
     );
-    let current_height = node_api.node.current_block_height()? as u32;
+    let current_height = BlockHeight(node_api.node.current_block_height()? as u32);
     let pool_box = oracle_pool.get_pool_box_source().get_pool_box()?;
+    let etag = etag(format!(
+        "{}-{}",
+        String::from(pool_box.get_box().box_id()),
+        current_height
+    ));
+    if is_not_modified(headers, &etag) {
+        return Ok(not_modified(etag));
+    }
     let epoch_length = POOL_CONFIG
         .refresh_box_wrapper_inputs
         .contract_inputs
         .contract_parameters()
         .epoch_length();
-    let pool_box_height = pool_box.get_box().creation_height;
-    let epoch_end_height = pool_box_height + epoch_length.0 as u32;
+    let pool_box_height = BlockHeight(pool_box.get_box().creation_height);
+    let epoch_end_height = pool_box_height + epoch_length;
     let pool_health = pool_health_sync(oracle_pool)?;
     let active_oracle_count = pool_health.details.active_oracle_boxes.len();
-    let json = Json(json!({
-        "latest_pool_datapoint": pool_box.rate(),
-        "latest_pool_box_height": pool_box_height,
-        "pool_box_epoch_id" : pool_box.epoch_counter(),
-        "current_block_height": current_height,
-        "epoch_end_height": epoch_end_height,
-        "reward_tokens_in_pool_box": pool_box.reward_token().amount.as_u64(),
-        "number_of_oracles": active_oracle_count,
-        "pool_health": pool_health,
-    }));
-    Ok(json)
+    let status = PoolStatusResponse {
+        schema_version: POOL_STATUS_SCHEMA_VERSION,
+        latest_pool_datapoint: pool_box.rate().into(),
+        latest_pool_box_height: pool_box_height,
+        pool_box_epoch_id: pool_box.epoch_counter(),
+        current_block_height: current_height,
+        epoch_end_height,
+        reward_tokens_in_pool_box: *pool_box.reward_token().amount.as_u64(),
+        number_of_oracles: active_oracle_count,
+        pool_health: (&pool_health).into(),
+    };
+    Ok(([(ETAG, etag)], Json(status)).into_response())
 }
 
 /// Block height of the Ergo blockchain
@@ -181,15 +227,50 @@ async fn require_datapoint_repost(repost_receiver: Receiver<bool>) -> impl IntoR
     response_text
 }
 
+/// Returns the 503 response to the health checks while the node is rescanning, since the
+/// scans don't return the pool and oracle boxes until the rescan reaches them
+fn node_rescan_response() -> Result<Option<(StatusCode, Json<serde_json::Value>)>, ApiError> {
+    let node_api = NodeApi::new(
+        ORACLE_SECRETS.node_api_key.clone(),
+        ORACLE_SECRETS.wallet_password.clone(),
+        &ORACLE_CONFIG.node_url,
+    );
+    Ok(node_api.rescan_progress()?.map(|progress| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "status": HealthStatus::Down,
+                "details": "waiting for node rescan",
+                "rescan_progress": progress,
+            })),
+        )
+    }))
+}
+
 /// Return true if the our collected datapoint box height is the same as the pool box height
 /// and our posted datapoint box height is greater than the pool box height
 async fn oracle_health(oracle_pool: Arc<OraclePool>) -> impl IntoResponse {
+    match task::spawn_blocking(node_rescan_response).await.unwrap() {
+        Ok(Some(rescan_response)) => return rescan_response,
+        Ok(None) => (),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(e.to_json())),
+    };
+    if safe_mode::is_active() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "status": HealthStatus::Down,
+                "details": "safe mode, not submitting txs",
+                "safe_mode": safe_mode::status(),
+            })),
+        );
+    }
     let pool_health = match task::spawn_blocking(|| oracle_health_sync(oracle_pool))
         .await
         .unwrap()
     {
         Ok(v) => v,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(e.0))),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(e.to_json())),
     };
     // return 503 http error if pool_health.status is not ok
     if pool_health.status != HealthStatus::Ok {
@@ -230,12 +311,17 @@ fn oracle_health_sync(oracle_pool: Arc<OraclePool>) -> Result<OracleHealth, ApiE
 }
 
 async fn pool_health(oracle_pool: Arc<OraclePool>) -> impl IntoResponse {
+    match task::spawn_blocking(node_rescan_response).await.unwrap() {
+        Ok(Some(rescan_response)) => return rescan_response,
+        Ok(None) => (),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(e.to_json())),
+    };
     let pool_health = match task::spawn_blocking(|| pool_health_sync(oracle_pool))
         .await
         .unwrap()
     {
         Ok(v) => v,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(e.0))),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(e.to_json())),
     };
     // return 503 http error if pool_health.status is not ok
     if pool_health.status != HealthStatus::Ok {
@@ -259,7 +345,7 @@ fn pool_health_sync(oracle_pool: Arc<OraclePool>) -> Result<PoolHealth, ApiError
     let current_height = (node_api.node.current_block_height()? as u32).into();
     let pool_box = &oracle_pool.get_pool_box_source().get_pool_box()?;
     let pool_box_height = pool_box.get_box().creation_height.into();
-    let network_prefix = node_api.get_change_address()?.network();
+    let network_prefix = ORACLE_CONFIG.network_prefix();
     let pool_health = check_pool_health(
         current_height,
         pool_box_height,
@@ -270,23 +356,352 @@ fn pool_health_sync(oracle_pool: Arc<OraclePool>) -> Result<PoolHealth, ApiError
     Ok(pool_health)
 }
 
+/// Default time `/waitForNextEpoch` holds the request open
+const WAIT_FOR_NEXT_EPOCH_DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+/// Max. time `/waitForNextEpoch` holds the request open
+const WAIT_FOR_NEXT_EPOCH_MAX_TIMEOUT: Duration = Duration::from_secs(1800);
+const WAIT_FOR_NEXT_EPOCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, serde::Deserialize)]
+struct WaitForNextEpochParams {
+    /// Wait until the pool box epoch counter is greater than this one (current epoch if not set)
+    epoch_id: Option<EpochCounter>,
+    timeout_secs: Option<u64>,
+}
+
+/// Long-polling for the epoch transition
+async fn wait_for_next_epoch(
+    oracle_pool: Arc<OraclePool>,
+    Query(params): Query<WaitForNextEpochParams>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let timeout = params
+        .timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(WAIT_FOR_NEXT_EPOCH_DEFAULT_TIMEOUT)
+        .min(WAIT_FOR_NEXT_EPOCH_MAX_TIMEOUT);
+    let start = Instant::now();
+    let (mut epoch_id, mut rate, mut height) = current_pool_epoch(oracle_pool.clone()).await?;
+    let wait_past_epoch_id = params.epoch_id.unwrap_or(epoch_id);
+    let mut timed_out = false;
+    while epoch_id <= wait_past_epoch_id {
+        if start.elapsed() >= timeout {
+            timed_out = true;
+            break;
+        }
+        tokio::time::sleep(WAIT_FOR_NEXT_EPOCH_POLL_INTERVAL).await;
+        (epoch_id, rate, height) = current_pool_epoch(oracle_pool.clone()).await?;
+    }
+    Ok(Json(json!({
+        "timed_out": timed_out,
+        "pool_box_epoch_id": epoch_id,
+        "latest_pool_datapoint": rate,
+        "latest_pool_box_height": height,
+    })))
+}
+
+async fn current_pool_epoch(
+    oracle_pool: Arc<OraclePool>,
+) -> Result<(EpochCounter, Rate, u32), ApiError> {
+    let pool_box = task::spawn_blocking(move || oracle_pool.get_pool_box_source().get_pool_box())
+        .await
+        .unwrap()?;
+    Ok((
+        pool_box.epoch_counter(),
+        pool_box.rate(),
+        pool_box.get_box().creation_height,
+    ))
+}
+
+/// Position of our datapoint among the datapoints posted for the current epoch
+async fn datapoint_rank(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
+    let json = task::spawn_blocking(|| datapoint_rank_sync(oracle_pool))
+        .await
+        .unwrap()?;
+    Ok(json)
+}
+
+fn datapoint_rank_sync(oracle_pool: Arc<OraclePool>) -> Result<Json<serde_json::Value>, ApiError> {
+    match check_my_datapoint_rank(oracle_pool)? {
+        Some(datapoint_rank) => Ok(Json(serde_json::to_value(datapoint_rank).unwrap())),
+        None => Ok(Json(json!({
+            "datapoint_rank": "No datapoint posted for the current epoch",
+        }))),
+    }
+}
+
+/// Max. number of epochs returned by `/epochHistory`
+const EPOCH_HISTORY_MAX_LIMIT: usize = 1000;
+
+#[derive(Debug, serde::Deserialize)]
+struct EpochHistoryParams {
+    from_epoch_id: Option<EpochCounter>,
+    limit: Option<usize>,
+}
+
+/// Archived pool epochs, complete once the archive backfill from the explorer is done. The ETag
+/// is the archive version.
+async fn epoch_history(
+    epoch_archive: Arc<RwLock<EpochArchive>>,
+    Query(params): Query<EpochHistoryParams>,
+    headers: HeaderMap,
+) -> Response {
+    let limit = params
+        .limit
+        .unwrap_or(EPOCH_HISTORY_MAX_LIMIT)
+        .min(EPOCH_HISTORY_MAX_LIMIT);
+    let archive = epoch_archive.read().unwrap();
+    let etag = etag(archive.version());
+    if is_not_modified(&headers, &etag) {
+        return not_modified(etag);
+    }
+    let history = json!({
+        "backfill_complete": archive.backfill.complete,
+        "epochs": archive.records_from(params.from_epoch_id.unwrap_or(EpochCounter(0)), limit),
+    });
+    ([(ETAG, etag)], Json(history)).into_response()
+}
+
+/// Number of the last archived epoch payouts the reward emission rate is averaged over
+const TOKEN_ECONOMICS_EMISSION_WINDOW: usize = 100;
+
+/// Reward token supply and emission
+async fn token_economics(
+    oracle_pool: Arc<OraclePool>,
+    epoch_archive: Arc<RwLock<EpochArchive>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let json = task::spawn_blocking(|| token_economics_sync(oracle_pool, epoch_archive))
+        .await
+        .unwrap()?;
+    Ok(json)
+}
+
+fn token_economics_sync(
+    oracle_pool: Arc<OraclePool>,
+    epoch_archive: Arc<RwLock<EpochArchive>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let reward_token_id = POOL_CONFIG.token_ids.reward_token_id.token_id();
+    let explorer_api = ExplorerApi::new(
+        ORACLE_CONFIG
+            .explorer_url
+            .clone()
+            .unwrap_or_else(|| default_explorer_api_url(ORACLE_CONFIG.network_prefix())),
+    );
+    let total_supply = match explorer_api.get_token_emission_amount(reward_token_id) {
+        Ok(amount) => Some(amount),
+        Err(e) => {
+            log::warn!(
+                "Failed to get the reward token supply from the explorer: {}",
+                e
+            );
+            None
+        }
+    };
+    let pool_box = oracle_pool.get_pool_box_source().get_pool_box()?;
+    let locked_in_pool_box = *pool_box.reward_token().amount.as_u64();
+    let epoch_length = POOL_CONFIG
+        .refresh_box_wrapper_inputs
+        .contract_inputs
+        .contract_parameters()
+        .epoch_length()
+        .0 as u64;
+    let archive = epoch_archive.read().unwrap();
+    let emission_per_epoch = archive.avg_reward_tokens_per_epoch(TOKEN_ECONOMICS_EMISSION_WINDOW);
+    let runway_epochs = emission_per_epoch
+        .filter(|emission| *emission > 0.0)
+        .map(|emission| (locked_in_pool_box as f64 / emission) as u64);
+    Ok(Json(json!({
+        "reward_token_id": reward_token_id,
+        "total_supply": total_supply,
+        "locked_in_pool_box": locked_in_pool_box,
+        "distributed_to_oracles": archive.distributed_reward_tokens(),
+        "history_complete": archive.backfill.complete,
+        "emission_per_epoch": emission_per_epoch,
+        "runway_epochs": runway_epochs,
+        "runway_blocks": runway_epochs.map(|epochs| epochs * epoch_length),
+    })))
+}
+
+/// The last refresh tx rejected by the node (see `refresh_debug`)
+async fn refresh_debug_bundle() -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    match load_last_bundle(&RefreshDebugBundle::file_path())? {
+        Some(bundle) => Ok((StatusCode::OK, Json(bundle))),
+        None => Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "No refresh tx was rejected by the node"})),
+        )),
+    }
+}
+
+async fn fee_history() -> Result<Json<serde_json::Value>, ApiError> {
+    let ledger = FeeLedger::load(&FeeLedger::file_path())?;
+    Ok(Json(json!({
+        "total_fees": ledger.total_fees(),
+        "daily": ledger.daily(),
+        "per_epoch": ledger.per_epoch(),
+    })))
+}
+
+const DATAPOINT_AUDIT_MAX_LIMIT: usize = 1000;
+
+#[derive(Debug, serde::Deserialize)]
+struct DatapointAuditParams {
+    from_height: Option<BlockHeight>,
+    limit: Option<usize>,
+}
+
+/// Latest posted datapoints from the audit log, oldest first
+async fn datapoint_audit(
+    Query(params): Query<DatapointAuditParams>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let limit = params
+        .limit
+        .unwrap_or(DATAPOINT_AUDIT_MAX_LIMIT)
+        .min(DATAPOINT_AUDIT_MAX_LIMIT);
+    let snapshots = datapoint_audit::load(
+        &datapoint_audit::file_path(),
+        params.from_height.unwrap_or(BlockHeight(0)),
+        limit,
+    )?;
+    Ok(Json(json!({ "datapoints": snapshots })))
+}
+
+/// Safe mode state, with the count and the node message of the script failure rejections
+async fn safe_mode_status() -> impl IntoResponse {
+    Json(json!({ "safe_mode": safe_mode::status() }))
+}
+
+/// Anchors the Grafana timestamps at the current height of the node
+fn height_clock() -> Result<HeightClock, NodeError> {
+    let node_api = NodeApi::new(
+        ORACLE_SECRETS.node_api_key.clone(),
+        ORACLE_SECRETS.wallet_password.clone(),
+        &ORACLE_CONFIG.node_url,
+    );
+    let height = node_api.node.current_block_height()?;
+    let time_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    Ok(HeightClock {
+        height: BlockHeight(height as u32),
+        time_ms,
+    })
+}
+
+fn grafana_response<T: serde::Serialize>(result: Result<T, GrafanaError>) -> Response {
+    match result {
+        Ok(body) => Json(body).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
+/// Targets offered to Grafana
+async fn grafana_search() -> Json<Vec<&'static str>> {
+    Json(grafana::search())
+}
+
+/// Pool rate and our datapoints in the requested time range
+async fn grafana_query(
+    epoch_archive: Arc<RwLock<EpochArchive>>,
+    Json(request): Json<QueryRequest>,
+) -> Result<Response, ApiError> {
+    let clock = task::spawn_blocking(height_clock).await.unwrap()?;
+    let archive = epoch_archive.read().unwrap();
+    Ok(grafana_response(grafana::query(&archive, &request, clock)))
+}
+
+/// Pool epochs (or the epochs we missed) in the requested time range
+async fn grafana_annotations(
+    epoch_archive: Arc<RwLock<EpochArchive>>,
+    Json(request): Json<AnnotationRequest>,
+) -> Result<Response, ApiError> {
+    let clock = task::spawn_blocking(height_clock).await.unwrap()?;
+    let archive = epoch_archive.read().unwrap();
+    Ok(grafana_response(grafana::annotations(
+        &archive, &request, clock,
+    )))
+}
+
+/// Quoted ETag header value of the key
+fn etag(key: impl std::fmt::Display) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", key)).unwrap()
+}
+
+/// Whether the ETag is listed in the `If-None-Match` header of the request
+fn is_not_modified(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let etag = etag.to_str().unwrap_or_default();
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+fn not_modified(etag: HeaderValue) -> Response {
+    (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response()
+}
+
 pub async fn start_rest_server(
     repost_receiver: Receiver<bool>,
     oracle_pool: Arc<OraclePool>,
+    epoch_archive: Arc<RwLock<EpochArchive>>,
     api_port: u16,
 ) -> Result<(), anyhow::Error> {
     let op_clone = oracle_pool.clone();
     let op_clone2 = oracle_pool.clone();
     let op_clone3 = oracle_pool.clone();
+    let op_clone4 = oracle_pool.clone();
+    let op_clone5 = oracle_pool.clone();
+    let op_clone6 = oracle_pool.clone();
+    let archive_clone = epoch_archive.clone();
+    let archive_clone2 = epoch_archive.clone();
+    let archive_clone3 = epoch_archive.clone();
     let app = Router::new()
         .route("/", get(root))
         .route("/oracleInfo", get(oracle_info))
         .route("/oracleStatus", get(|| oracle_status(oracle_pool)))
         .route("/poolInfo", get(pool_info))
-        .route("/poolStatus", get(|| pool_status(op_clone)))
+        .route(
+            "/poolStatus",
+            get(|headers: HeaderMap| pool_status(op_clone, headers)),
+        )
         .route("/blockHeight", get(block_height))
         .route("/oracleHealth", get(|| oracle_health(op_clone2)))
         .route("/poolHealth", get(|| pool_health(op_clone3)))
+        .route("/datapointRank", get(|| datapoint_rank(op_clone4)))
+        .route(
+            "/waitForNextEpoch",
+            get(|params: Query<WaitForNextEpochParams>| wait_for_next_epoch(op_clone5, params)),
+        )
+        .route(
+            "/epochHistory",
+            get(|params: Query<EpochHistoryParams>, headers: HeaderMap| {
+                epoch_history(archive_clone, params, headers)
+            }),
+        )
+        .route(
+            "/tokenEconomics",
+            get(|| token_economics(op_clone6, epoch_archive)),
+        )
+        .route("/refreshDebugBundle", get(refresh_debug_bundle))
+        .route("/feeHistory", get(fee_history))
+        .route("/datapointAudit", get(datapoint_audit))
+        .route("/safeMode", get(safe_mode_status))
+        .route("/search", post(grafana_search))
+        .route(
+            "/query",
+            post(|request: Json<QueryRequest>| grafana_query(archive_clone2, request)),
+        )
+        .route(
+            "/annotations",
+            post(|request: Json<AnnotationRequest>| grafana_annotations(archive_clone3, request)),
+        )
         .route(
             "/requireDatapointRepost",
             get(|| require_datapoint_repost(repost_receiver)),
@@ -294,44 +709,91 @@ pub async fn start_rest_server(
         .layer(
             CorsLayer::new()
                 .allow_origin(tower_http::cors::Any)
-                .allow_methods([axum::http::Method::GET]),
-        );
+                .allow_methods([axum::http::Method::GET, axum::http::Method::POST])
+                .allow_headers([axum::http::header::CONTENT_TYPE]),
+        )
+        .layer(middleware::from_fn(allowlist_middleware));
     let addr = SocketAddr::from(([0, 0, 0, 0], api_port));
     log::info!("Starting REST server on {}", addr);
     axum::Server::try_bind(&addr)?
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
     Ok(())
 }
 
-struct ApiError(String);
+/// Error response with the stable code of the failure (see `error_code`)
+struct ApiError {
+    code: ErrorCode,
+    message: String,
+}
+
+impl ApiError {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "code": self.code,
+            "error": self.message,
+        })
+    }
+}
 
 impl From<DataSourceError> for ApiError {
     fn from(err: DataSourceError) -> Self {
-        ApiError(format!("DataSourceError: {}", err))
+        ApiError {
+            code: error_code_of(&err),
+            message: format!("DataSourceError: {}", err),
+        }
     }
 }
 
 impl From<NodeError> for ApiError {
     fn from(err: NodeError) -> Self {
-        ApiError(format!("NodeError: {}", err))
+        ApiError {
+            code: error_code_of(&err),
+            message: format!("NodeError: {}", err),
+        }
     }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        (StatusCode::INTERNAL_SERVER_ERROR, self.0).into_response()
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(self.to_json())).into_response()
     }
 }
 
 impl From<anyhow::Error> for ApiError {
     fn from(err: anyhow::Error) -> Self {
-        ApiError(format!("Error: {:?}", err))
+        ApiError {
+            code: error_code(&err),
+            message: format!("Error: {:?}", err),
+        }
     }
 }
 
 impl From<NodeApiError> for ApiError {
     fn from(err: NodeApiError) -> Self {
-        ApiError(format!("NodeApiError: {:?}", err))
+        ApiError {
+            code: error_code_of(&err),
+            message: format!("NodeApiError: {:?}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_not_modified() {
+        let etag = etag(42);
+        let request = |if_none_match: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(IF_NONE_MATCH, HeaderValue::from_str(if_none_match).unwrap());
+            headers
+        };
+        assert!(is_not_modified(&request("\"42\""), &etag));
+        assert!(is_not_modified(&request("\"41\", W/\"42\""), &etag));
+        assert!(is_not_modified(&request("*"), &etag));
+        assert!(!is_not_modified(&request("\"41\""), &etag));
+        assert!(!is_not_modified(&HeaderMap::new(), &etag));
     }
 }
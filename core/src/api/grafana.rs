@@ -0,0 +1,329 @@
+//! Grafana simple-json-datasource protocol (`/search`, `/query`, `/annotations`) backed by the
+//! epoch archive, to chart the pool rate and our datapoints. The archive knows only the block
+//! heights, the timestamps are estimated from the current height at 2 minutes per block.
+
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::epoch_archive::EpochArchive;
+use crate::oracle_types::BlockHeight;
+use crate::state::BLOCKS_PER_DAY;
+
+pub const POOL_RATE_TARGET: &str = "pool_rate";
+pub const MY_DATAPOINT_TARGET: &str = "my_datapoint";
+/// Annotation query marking the epochs we posted no datapoint for
+pub const MISSED_DATAPOINTS_QUERY: &str = "missed_datapoints";
+
+const BLOCK_TIME_MS: u64 = 24 * 60 * 60 * 1000 / BLOCKS_PER_DAY as u64;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GrafanaError {
+    #[error("invalid timestamp {0}, expected e.g. 2023-04-01T12:00:00.000Z")]
+    InvalidTimestamp(String),
+    #[error("unknown target {0}, expected {POOL_RATE_TARGET} or {MY_DATAPOINT_TARGET}")]
+    UnknownTarget(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimeRange {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryTarget {
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryRequest {
+    pub range: TimeRange,
+    pub targets: Vec<QueryTarget>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimeSeries {
+    pub target: String,
+    /// `[value, unix timestamp in ms]` pairs
+    pub datapoints: Vec<(i64, u64)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationQuery {
+    pub name: String,
+    #[serde(default)]
+    pub query: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnnotationRequest {
+    pub range: TimeRange,
+    pub annotation: AnnotationQuery,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Annotation {
+    pub annotation: AnnotationQuery,
+    /// Unix timestamp in ms
+    pub time: u64,
+    pub title: String,
+    pub text: String,
+}
+
+/// Converts between the block heights and the timestamps, anchored at a known block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeightClock {
+    pub height: BlockHeight,
+    /// Unix timestamp of the anchor block in ms
+    pub time_ms: u64,
+}
+
+impl HeightClock {
+    pub fn time_ms(&self, height: BlockHeight) -> u64 {
+        let blocks = height.0 as i64 - self.height.0 as i64;
+        (self.time_ms as i64 + blocks * BLOCK_TIME_MS as i64).max(0) as u64
+    }
+
+    pub fn height(&self, time_ms: u64) -> BlockHeight {
+        let blocks = (time_ms as i64 - self.time_ms as i64).div_euclid(BLOCK_TIME_MS as i64);
+        BlockHeight((self.height.0 as i64 + blocks).clamp(0, u32::MAX as i64) as u32)
+    }
+
+    /// Heights of the time range, widened by a block to include the blocks at its ends
+    fn heights(&self, range: &TimeRange) -> Result<(BlockHeight, BlockHeight), GrafanaError> {
+        let from = self.height(parse_timestamp_ms(&range.from)?);
+        let to = self.height(parse_timestamp_ms(&range.to)?);
+        Ok((
+            BlockHeight(from.0.saturating_sub(1)),
+            BlockHeight(to.0.saturating_add(1)),
+        ))
+    }
+}
+
+/// Days since 1970-01-01 of the proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parses the UTC timestamps sent by Grafana (`YYYY-MM-DDTHH:MM:SS[.fff]Z`) into unix ms
+pub fn parse_timestamp_ms(timestamp: &str) -> Result<u64, GrafanaError> {
+    let invalid = || GrafanaError::InvalidTimestamp(timestamp.to_string());
+    let rest = timestamp.strip_suffix('Z').ok_or_else(invalid)?;
+    let (date, time) = rest.split_once('T').ok_or_else(invalid)?;
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let number = |s: &str| s.parse::<i64>().map_err(|_| invalid());
+    let date: Vec<i64> = date.split('-').map(number).collect::<Result<_, _>>()?;
+    let time: Vec<i64> = time.split(':').map(number).collect::<Result<_, _>>()?;
+    let (&[year, month, day], &[hours, minutes, seconds]) = (date.as_slice(), time.as_slice())
+    else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hours > 23
+        || minutes > 59
+        || seconds > 60
+    {
+        return Err(invalid());
+    }
+    // milliseconds from the first 3 digits of the fraction
+    let millis = if fraction.is_empty() {
+        0
+    } else {
+        let digits: String = format!("{:0<3}", fraction).chars().take(3).collect();
+        number(&digits)?
+    };
+    let days = days_from_civil(year, month, day);
+    let ms = ((days * 24 + hours) * 60 + minutes) * 60_000 + seconds * 1000 + millis;
+    u64::try_from(ms).map_err(|_| invalid())
+}
+
+/// Metrics offered by `/search`
+pub fn search() -> Vec<&'static str> {
+    vec![POOL_RATE_TARGET, MY_DATAPOINT_TARGET]
+}
+
+/// Time series of the targets within the request time range
+pub fn query(
+    archive: &EpochArchive,
+    request: &QueryRequest,
+    clock: HeightClock,
+) -> Result<Vec<TimeSeries>, GrafanaError> {
+    let (from, to) = clock.heights(&request.range)?;
+    request
+        .targets
+        .iter()
+        .map(|target| {
+            let datapoints = match target.target.as_str() {
+                POOL_RATE_TARGET => archive
+                    .records_between(from, to)
+                    .iter()
+                    .map(|r| (i64::from(r.rate), clock.time_ms(r.height)))
+                    .collect(),
+                MY_DATAPOINT_TARGET => archive
+                    .datapoints_between(from, to)
+                    .iter()
+                    .map(|r| (i64::from(r.rate), clock.time_ms(r.height)))
+                    .collect(),
+                other => return Err(GrafanaError::UnknownTarget(other.to_string())),
+            };
+            Ok(TimeSeries {
+                target: target.target.clone(),
+                datapoints,
+            })
+        })
+        .collect()
+}
+
+/// Pool epochs within the request time range, or only the ones we posted no datapoint for with
+/// the `missed_datapoints` query
+pub fn annotations(
+    archive: &EpochArchive,
+    request: &AnnotationRequest,
+    clock: HeightClock,
+) -> Result<Vec<Annotation>, GrafanaError> {
+    let (from, to) = clock.heights(&request.range)?;
+    let missed_only = request.annotation.query == MISSED_DATAPOINTS_QUERY;
+    Ok(archive
+        .records_between(from, to)
+        .iter()
+        .filter(|r| !missed_only || !archive.has_datapoint(r.epoch_id))
+        .map(|r| Annotation {
+            annotation: request.annotation.clone(),
+            time: clock.time_ms(r.height),
+            title: format!("Epoch {}", r.epoch_id.0),
+            text: format!("pool rate {} at height {}", r.rate, r.height.0),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::epoch_archive::DatapointRecord;
+    use crate::epoch_archive::EpochRecord;
+    use crate::oracle_types::EpochCounter;
+    use crate::oracle_types::Rate;
+
+    use super::*;
+
+    fn range(from: &str, to: &str) -> TimeRange {
+        TimeRange {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamp_ms() {
+        assert_eq!(parse_timestamp_ms("1970-01-01T00:00:00Z"), Ok(0));
+        assert_eq!(
+            parse_timestamp_ms("2023-04-01T12:30:15.250Z"),
+            Ok(1_680_352_215_250)
+        );
+        assert_eq!(
+            parse_timestamp_ms("2024-02-29T00:00:00.5Z"),
+            Ok(1_709_164_800_500)
+        );
+        assert!(parse_timestamp_ms("2023-04-01T12:30:15+02:00").is_err());
+        assert!(parse_timestamp_ms("2023-13-01T12:30:15Z").is_err());
+        assert!(parse_timestamp_ms("now-6h").is_err());
+    }
+
+    #[test]
+    fn test_height_clock() {
+        let clock = HeightClock {
+            height: BlockHeight(1000),
+            time_ms: 1_000_000_000,
+        };
+        assert_eq!(
+            clock.time_ms(BlockHeight(990)),
+            1_000_000_000 - 10 * 120_000
+        );
+        assert_eq!(clock.height(1_000_000_000 - 10 * 120_000), BlockHeight(990));
+        assert_eq!(clock.height(1_000_000_000 - 1), BlockHeight(999));
+        assert_eq!(clock.height(0), BlockHeight(0));
+    }
+
+    #[test]
+    fn test_query_and_annotations() {
+        let mut archive = EpochArchive::default();
+        for epoch_id in 1..=4 {
+            archive.insert(EpochRecord {
+                epoch_id: EpochCounter(epoch_id),
+                rate: Rate::from(1000 + epoch_id as i64),
+                height: BlockHeight(epoch_id * 30),
+                box_id: format!("box{}", epoch_id),
+                tx_id: format!("tx{}", epoch_id),
+                reward_tokens: None,
+            });
+        }
+        for epoch_id in [2, 4] {
+            archive.insert_datapoint(DatapointRecord {
+                epoch_id: EpochCounter(epoch_id),
+                rate: Rate::from(990 + epoch_id as i64),
+                height: BlockHeight(epoch_id * 30 - 5),
+            });
+        }
+        // height 120 is at 2023-04-01T12:00:00Z
+        let clock = HeightClock {
+            height: BlockHeight(120),
+            time_ms: 1_680_350_400_000,
+        };
+        // heights 60..=120
+        let request = QueryRequest {
+            range: range("2023-04-01T10:00:00.000Z", "2023-04-01T12:00:00.000Z"),
+            targets: vec![
+                QueryTarget {
+                    target: POOL_RATE_TARGET.to_string(),
+                },
+                QueryTarget {
+                    target: MY_DATAPOINT_TARGET.to_string(),
+                },
+            ],
+        };
+        let series = query(&archive, &request, clock).unwrap();
+        assert_eq!(
+            series[0].datapoints,
+            vec![
+                (1002, 1_680_350_400_000 - 60 * 120_000),
+                (1003, 1_680_350_400_000 - 30 * 120_000),
+                (1004, 1_680_350_400_000),
+            ]
+        );
+        assert_eq!(
+            series[1].datapoints,
+            vec![(994, 1_680_350_400_000 - 5 * 120_000)]
+        );
+        let unknown_target = QueryRequest {
+            targets: vec![QueryTarget {
+                target: "volume".to_string(),
+            }],
+            ..request
+        };
+        assert_eq!(
+            query(&archive, &unknown_target, clock),
+            Err(GrafanaError::UnknownTarget("volume".to_string()))
+        );
+
+        let request = AnnotationRequest {
+            range: range("2023-04-01T10:00:00.000Z", "2023-04-01T12:00:00.000Z"),
+            annotation: AnnotationQuery {
+                name: "missed".to_string(),
+                query: MISSED_DATAPOINTS_QUERY.to_string(),
+            },
+        };
+        let missed: Vec<String> = annotations(&archive, &request, clock)
+            .unwrap()
+            .into_iter()
+            .map(|a| a.title)
+            .collect();
+        assert_eq!(missed, vec!["Epoch 3".to_string()]);
+    }
+}
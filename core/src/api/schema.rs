@@ -0,0 +1,362 @@
+//! Stable JSON schema of the `/poolStatus` and `/oracleStatus` responses consumed by the
+//! frontend and third-party tools. The types are decoupled from the internal structs so that
+//! refactoring doesn't change the responses. Adding a field is a compatible change, while
+//! removing, renaming or retyping a field requires bumping the `schema_version` of the response.
+//! The heights and epoch parameters use the serde-transparent `oracle_types` newtypes, which
+//! serialize as the plain numbers.
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::monitor::HealthStatus;
+use crate::monitor::OracleBoxDetails;
+use crate::monitor::OracleDetails;
+use crate::monitor::OracleHealth;
+use crate::monitor::PoolHealth;
+use crate::oracle_state::LocalDatapointState;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+use crate::oracle_types::EpochLength;
+use crate::oracle_types::MinDatapoints;
+
+pub const POOL_STATUS_SCHEMA_VERSION: u32 = 1;
+pub const ORACLE_STATUS_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStatusResponse {
+    pub schema_version: u32,
+    pub latest_pool_datapoint: i64,
+    pub latest_pool_box_height: BlockHeight,
+    pub pool_box_epoch_id: EpochCounter,
+    pub current_block_height: BlockHeight,
+    pub epoch_end_height: BlockHeight,
+    pub reward_tokens_in_pool_box: u64,
+    /// Number of the active oracles
+    pub number_of_oracles: usize,
+    pub pool_health: PoolHealthSchema,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleStatusResponse {
+    pub schema_version: u32,
+    /// `null` if we have no datapoint box
+    pub local_datapoint_box_state: Option<LocalDatapointStateSchema>,
+    /// `null` if we have no datapoint box
+    pub oracle_health: Option<OracleHealthSchema>,
+    /// The oracle stopped submitting txs after repeated script failure rejections, only present
+    /// when `true`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub safe_mode: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthStatusSchema {
+    Ok,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LocalDatapointStateSchema {
+    Collected {
+        height: BlockHeight,
+    },
+    Posted {
+        epoch_id: EpochCounter,
+        height: BlockHeight,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolHealthSchema {
+    pub status: HealthStatusSchema,
+    pub details: PoolHealthDetailsSchema,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolHealthDetailsSchema {
+    pub pool_box_height: BlockHeight,
+    pub current_height: BlockHeight,
+    pub epoch_length: EpochLength,
+    pub all_oracle_boxes: Vec<OracleDetailsSchema>,
+    pub active_oracle_boxes: Vec<OracleDetailsSchema>,
+    pub min_data_points: MinDatapoints,
+    pub total_oracle_token_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleDetailsSchema {
+    /// Base58 encoded P2PK address of the oracle
+    pub address: String,
+    pub box_height: OracleBoxDetailsSchema,
+    pub reward_tokens: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OracleBoxDetailsSchema {
+    PostedBox(BlockHeight),
+    CollectedBox(BlockHeight),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleHealthSchema {
+    pub status: HealthStatusSchema,
+    pub details: OracleHealthDetailsSchema,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleHealthDetailsSchema {
+    pub pool_box_height: BlockHeight,
+    pub box_details: OracleBoxDetailsSchema,
+}
+
+impl From<HealthStatus> for HealthStatusSchema {
+    fn from(status: HealthStatus) -> Self {
+        match status {
+            HealthStatus::Ok => HealthStatusSchema::Ok,
+            HealthStatus::Down => HealthStatusSchema::Down,
+        }
+    }
+}
+
+impl From<LocalDatapointState> for LocalDatapointStateSchema {
+    fn from(state: LocalDatapointState) -> Self {
+        match state {
+            LocalDatapointState::Collected { height } => {
+                LocalDatapointStateSchema::Collected { height }
+            }
+            LocalDatapointState::Posted { epoch_id, height } => {
+                LocalDatapointStateSchema::Posted { epoch_id, height }
+            }
+        }
+    }
+}
+
+impl From<&OracleBoxDetails> for OracleBoxDetailsSchema {
+    fn from(details: &OracleBoxDetails) -> Self {
+        match details {
+            OracleBoxDetails::PostedBox(height) => OracleBoxDetailsSchema::PostedBox(*height),
+            OracleBoxDetails::CollectedBox(height) => OracleBoxDetailsSchema::CollectedBox(*height),
+        }
+    }
+}
+
+impl From<&OracleDetails> for OracleDetailsSchema {
+    fn from(details: &OracleDetails) -> Self {
+        OracleDetailsSchema {
+            address: details.address.to_base58(),
+            box_height: (&details.box_height).into(),
+            reward_tokens: details.reward_tokens,
+        }
+    }
+}
+
+impl From<&PoolHealth> for PoolHealthSchema {
+    fn from(health: &PoolHealth) -> Self {
+        let details = &health.details;
+        PoolHealthSchema {
+            status: health.status.into(),
+            details: PoolHealthDetailsSchema {
+                pool_box_height: details.pool_box_height,
+                current_height: details.current_height,
+                epoch_length: details.epoch_length,
+                all_oracle_boxes: details.all_oracle_boxes.iter().map(Into::into).collect(),
+                active_oracle_boxes: details.active_oracle_boxes.iter().map(Into::into).collect(),
+                min_data_points: details.min_data_points,
+                total_oracle_token_count: details.total_oracle_token_count,
+            },
+        }
+    }
+}
+
+impl From<&OracleHealth> for OracleHealthSchema {
+    fn from(health: &OracleHealth) -> Self {
+        OracleHealthSchema {
+            status: health.status.into(),
+            details: OracleHealthDetailsSchema {
+                pool_box_height: health.details.pool_box_height,
+                box_details: (&health.details.box_details).into(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn pool_status() -> PoolStatusResponse {
+        let oracle = OracleDetailsSchema {
+            address: "9iHyKxXs2ZNLMp9N9gbUT9V8gTbsV7HED1C1VhttMfBUMPDyF7r".to_string(),
+            box_height: OracleBoxDetailsSchema::PostedBox(BlockHeight(1010)),
+            reward_tokens: 42,
+        };
+        PoolStatusResponse {
+            schema_version: POOL_STATUS_SCHEMA_VERSION,
+            latest_pool_datapoint: 1668116,
+            latest_pool_box_height: BlockHeight(1000),
+            pool_box_epoch_id: EpochCounter(5),
+            current_block_height: BlockHeight(1015),
+            epoch_end_height: BlockHeight(1030),
+            reward_tokens_in_pool_box: 99000,
+            number_of_oracles: 1,
+            pool_health: PoolHealthSchema {
+                status: HealthStatusSchema::Ok,
+                details: PoolHealthDetailsSchema {
+                    pool_box_height: BlockHeight(1000),
+                    current_height: BlockHeight(1015),
+                    epoch_length: EpochLength(30),
+                    all_oracle_boxes: vec![oracle.clone()],
+                    active_oracle_boxes: vec![oracle],
+                    min_data_points: MinDatapoints(4),
+                    total_oracle_token_count: 15,
+                },
+            },
+        }
+    }
+
+    fn oracle_status() -> OracleStatusResponse {
+        OracleStatusResponse {
+            schema_version: ORACLE_STATUS_SCHEMA_VERSION,
+            local_datapoint_box_state: Some(LocalDatapointStateSchema::Posted {
+                epoch_id: EpochCounter(5),
+                height: BlockHeight(1010),
+            }),
+            oracle_health: Some(OracleHealthSchema {
+                status: HealthStatusSchema::Down,
+                details: OracleHealthDetailsSchema {
+                    pool_box_height: BlockHeight(1000),
+                    box_details: OracleBoxDetailsSchema::CollectedBox(BlockHeight(970)),
+                },
+            }),
+            safe_mode: false,
+        }
+    }
+
+    // Changing the expected JSON below breaks the consumers of the schema version
+    #[test]
+    fn test_pool_status_schema_v1() {
+        let json_str = serde_json::to_string_pretty(&pool_status()).unwrap();
+        expect![[r#"
+            {
+              "schema_version": 1,
+              "latest_pool_datapoint": 1668116,
+              "latest_pool_box_height": 1000,
+              "pool_box_epoch_id": 5,
+              "current_block_height": 1015,
+              "epoch_end_height": 1030,
+              "reward_tokens_in_pool_box": 99000,
+              "number_of_oracles": 1,
+              "pool_health": {
+                "status": "Ok",
+                "details": {
+                  "pool_box_height": 1000,
+                  "current_height": 1015,
+                  "epoch_length": 30,
+                  "all_oracle_boxes": [
+                    {
+                      "address": "9iHyKxXs2ZNLMp9N9gbUT9V8gTbsV7HED1C1VhttMfBUMPDyF7r",
+                      "box_height": {
+                        "PostedBox": 1010
+                      },
+                      "reward_tokens": 42
+                    }
+                  ],
+                  "active_oracle_boxes": [
+                    {
+                      "address": "9iHyKxXs2ZNLMp9N9gbUT9V8gTbsV7HED1C1VhttMfBUMPDyF7r",
+                      "box_height": {
+                        "PostedBox": 1010
+                      },
+                      "reward_tokens": 42
+                    }
+                  ],
+                  "min_data_points": 4,
+                  "total_oracle_token_count": 15
+                }
+              }
+            }"#]]
+        .assert_eq(&json_str);
+        let parsed: PoolStatusResponse = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(parsed, pool_status());
+    }
+
+    #[test]
+    fn test_oracle_status_schema_v1() {
+        let json_str = serde_json::to_string_pretty(&oracle_status()).unwrap();
+        expect![[r#"
+            {
+              "schema_version": 1,
+              "local_datapoint_box_state": {
+                "status": "posted",
+                "epoch_id": 5,
+                "height": 1010
+              },
+              "oracle_health": {
+                "status": "Down",
+                "details": {
+                  "pool_box_height": 1000,
+                  "box_details": {
+                    "CollectedBox": 970
+                  }
+                }
+              }
+            }"#]]
+        .assert_eq(&json_str);
+        let parsed: OracleStatusResponse = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(parsed, oracle_status());
+        let no_datapoint_box = OracleStatusResponse {
+            schema_version: ORACLE_STATUS_SCHEMA_VERSION,
+            local_datapoint_box_state: None,
+            oracle_health: None,
+            safe_mode: false,
+        };
+        expect![[r#"{"schema_version":1,"local_datapoint_box_state":null,"oracle_health":null}"#]]
+            .assert_eq(&serde_json::to_string(&no_datapoint_box).unwrap());
+        let safe_mode = OracleStatusResponse {
+            safe_mode: true,
+            ..no_datapoint_box
+        };
+        expect![[r#"{"schema_version":1,"local_datapoint_box_state":null,"oracle_health":null,"safe_mode":true}"#]]
+            .assert_eq(&serde_json::to_string(&safe_mode).unwrap());
+    }
+
+    #[test]
+    fn test_from_local_datapoint_state() {
+        assert_eq!(
+            LocalDatapointStateSchema::from(LocalDatapointState::Posted {
+                epoch_id: EpochCounter(5),
+                height: BlockHeight(1010),
+            }),
+            LocalDatapointStateSchema::Posted {
+                epoch_id: EpochCounter(5),
+                height: BlockHeight(1010),
+            }
+        );
+        assert_eq!(
+            OracleBoxDetailsSchema::from(&OracleBoxDetails::CollectedBox(BlockHeight(970))),
+            OracleBoxDetailsSchema::CollectedBox(BlockHeight(970))
+        );
+    }
+
+    // Consumers built against a version keep parsing the responses with fields added later
+    #[test]
+    fn test_parse_with_unknown_fields() {
+        let json_str = r#"{
+            "schema_version": 1,
+            "local_datapoint_box_state": { "status": "collected", "height": 1000 },
+            "oracle_health": null,
+            "added_later": true
+        }"#;
+        let parsed: OracleStatusResponse = serde_json::from_str(json_str).unwrap();
+        assert_eq!(
+            parsed.local_datapoint_box_state,
+            Some(LocalDatapointStateSchema::Collected {
+                height: BlockHeight(1000)
+            })
+        );
+    }
+}
@@ -115,7 +115,7 @@ impl BallotBoxWrapperInputs {
 pub struct CastBallotBoxVoteParameters {
     pub pool_box_address_hash: Digest32,
     pub reward_token_opt: Option<SpecToken<RewardTokenId>>,
-    pub update_box_creation_height: i32,
+    pub update_box_creation_height: BlockHeight,
 }
 
 /// A Ballot Box with vote parameters guaranteed to be set
@@ -147,10 +147,12 @@ impl VoteBallotBoxWrapper {
         {
             return Err(BallotBoxError::NoGroupElementInR4);
         }
-        let update_box_creation_height = ergo_box
-            .get_register(NonMandatoryRegisterId::R5.into())
-            .ok_or(BallotBoxError::NoUpdateBoxCreationHeightInR5)?
-            .try_extract_into::<i32>()?;
+        let update_box_creation_height = BlockHeight(
+            ergo_box
+                .get_register(NonMandatoryRegisterId::R5.into())
+                .ok_or(BallotBoxError::NoUpdateBoxCreationHeightInR5)?
+                .try_extract_into::<i32>()? as u32,
+        );
 
         let pool_box_address_hash = ergo_box
             .get_register(NonMandatoryRegisterId::R6.into())
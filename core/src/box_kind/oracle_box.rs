@@ -78,6 +78,16 @@ pub enum OracleBoxWrapper {
 
 impl OracleBoxWrapper {
     pub fn new(b: ErgoBox, inputs: &OracleBoxWrapperInputs) -> Result<Self, OracleBoxError> {
+        Self::new_with_known_contract(b, inputs, None)
+    }
+
+    /// Same as `new`, but skips parsing the contract if the box is guarded by `known_contract`,
+    /// which must be loaded from the same `inputs` (see `Contracts`)
+    pub fn new_with_known_contract(
+        b: ErgoBox,
+        inputs: &OracleBoxWrapperInputs,
+        known_contract: Option<&OracleContract>,
+    ) -> Result<Self, OracleBoxError> {
         let oracle_token_id = b
             .tokens
             .as_ref()
@@ -104,8 +114,12 @@ impl OracleBoxWrapper {
             .get_register(NonMandatoryRegisterId::R6.into())
             .and_then(|r| r.try_extract_into::<i64>().ok());
 
-        let contract =
-            OracleContract::from_ergo_tree(b.ergo_tree.clone(), &inputs.contract_inputs)?;
+        let contract = match known_contract {
+            Some(known_contract) if known_contract.ergo_tree() == b.ergo_tree => {
+                known_contract.clone()
+            }
+            _ => OracleContract::from_ergo_tree(b.ergo_tree.clone(), &inputs.contract_inputs)?,
+        };
 
         let collected_oracle_box = OracleBoxWrapper::Collected(CollectedOracleBox {
             ergo_box: b.clone(),
@@ -56,6 +56,16 @@ pub struct PoolBoxWrapper {
 
 impl PoolBoxWrapper {
     pub fn new(b: ErgoBox, inputs: &PoolBoxWrapperInputs) -> Result<Self, PoolBoxError> {
+        Self::new_with_known_contract(b, inputs, None)
+    }
+
+    /// Same as `new`, but skips parsing the contract if the box is guarded by `known_contract`,
+    /// which must be loaded from the same `inputs` (see `Contracts`)
+    pub fn new_with_known_contract(
+        b: ErgoBox,
+        inputs: &PoolBoxWrapperInputs,
+        known_contract: Option<&PoolContract>,
+    ) -> Result<Self, PoolBoxError> {
         if let Some(token) = b.tokens.as_ref().ok_or(PoolBoxError::NoTokens)?.get(0) {
             if token.token_id != inputs.pool_nft_token_id.token_id() {
                 return Err(PoolBoxError::UnknownPoolNftId);
@@ -90,7 +100,12 @@ impl PoolBoxWrapper {
         } else {
             return Err(PoolBoxError::NoRewardToken);
         }
-        let contract = PoolContract::from_ergo_tree(b.ergo_tree.clone(), &inputs.contract_inputs)?;
+        let contract = match known_contract {
+            Some(known_contract) if known_contract.ergo_tree() == b.ergo_tree => {
+                known_contract.clone()
+            }
+            _ => PoolContract::from_ergo_tree(b.ergo_tree.clone(), &inputs.contract_inputs)?,
+        };
         Ok(Self {
             ergo_box: b,
             contract,
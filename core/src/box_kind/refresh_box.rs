@@ -88,6 +88,16 @@ impl RefreshBoxWrapperInputs {
 
 impl RefreshBoxWrapper {
     pub fn new(b: ErgoBox, inputs: &RefreshBoxWrapperInputs) -> Result<Self, RefreshBoxError> {
+        Self::new_with_known_contract(b, inputs, None)
+    }
+
+    /// Same as `new`, but skips parsing the contract if the box is guarded by `known_contract`,
+    /// which must be loaded from the same `inputs` (see `Contracts`)
+    pub fn new_with_known_contract(
+        b: ErgoBox,
+        inputs: &RefreshBoxWrapperInputs,
+        known_contract: Option<&RefreshContract>,
+    ) -> Result<Self, RefreshBoxError> {
         let refresh_token_id = b
             .tokens
             .as_ref()
@@ -99,8 +109,12 @@ impl RefreshBoxWrapper {
             return Err(RefreshBoxError::IncorrectRefreshTokenId(refresh_token_id));
         }
 
-        let contract =
-            RefreshContract::from_ergo_tree(b.ergo_tree.clone(), &inputs.contract_inputs)?;
+        let contract = match known_contract {
+            Some(known_contract) if known_contract.ergo_tree() == b.ergo_tree => {
+                known_contract.clone()
+            }
+            _ => RefreshContract::from_ergo_tree(b.ergo_tree.clone(), &inputs.contract_inputs)?,
+        };
         Ok(Self {
             ergo_box: b,
             contract,
@@ -1,8 +1,15 @@
 pub mod bootstrap;
+pub mod config;
+pub mod doctor;
+pub mod export_frontend_config;
 pub mod extract_reward_tokens;
 pub mod import_pool_update;
 pub mod prepare_update;
+pub mod print_my_datapoint_box;
 pub mod print_reward_tokens;
+pub mod recover_pool;
+pub mod rotate_oracle_key;
+pub mod state;
 pub mod transfer_oracle_token;
 pub mod update_pool;
 pub mod vote_update_pool;
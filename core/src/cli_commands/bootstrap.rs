@@ -1,5 +1,5 @@
 //! Bootstrap a new oracle pool
-use std::{convert::TryInto, io::Write, path::Path};
+use std::{convert::TryInto, io::Write, path::Path, time::Duration};
 
 use ergo_lib::{
     chain::{
@@ -8,7 +8,7 @@ use ergo_lib::{
     },
     ergotree_ir::{
         chain::{
-            address::{Address, AddressEncoderError, NetworkAddress},
+            address::{Address, AddressEncoderError, NetworkAddress, NetworkPrefix},
             ergo_box::{
                 box_value::{BoxValue, BoxValueError},
                 ErgoBox,
@@ -19,12 +19,12 @@ use ergo_lib::{
         serialization::SigmaParsingError,
     },
     wallet::{
-        box_selector::{BoxSelector, BoxSelectorError, SimpleBoxSelector},
+        box_selector::{BoxSelector, BoxSelectorError},
         tx_builder::{TxBuilder, TxBuilderError},
     },
 };
 use ergo_node_interface::node_interface::NodeError;
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -41,9 +41,9 @@ use crate::{
             UpdateContract, UpdateContractError, UpdateContractInputs, UpdateContractParameters,
         },
     },
-    explorer_api::wait_for_txs_confirmation,
+    funding::wallet_balance,
     node_interface::{
-        node_api::{NodeApi, NodeApiError},
+        node_api::{NodeApi, NodeApiError, TxStatus},
         try_ensure_wallet_unlocked, SignTransactionWithInputs, SubmitTransaction,
     },
     oracle_config::{BASE_FEE, ORACLE_CONFIG, ORACLE_SECRETS},
@@ -57,7 +57,7 @@ use crate::{
         BallotTokenId, OracleTokenId, PoolTokenId, RefreshTokenId, RewardTokenId, SpecToken,
         TokenIdKind, UpdateTokenId,
     },
-    wallet::{WalletDataError, WalletDataSource},
+    wallet::{configured_box_selector, WalletDataError, WalletDataSource},
 };
 
 /// Loads bootstrap configuration file and performs the chain-transactions for minting of tokens and
@@ -77,6 +77,11 @@ pub fn bootstrap(config_file_name: String) -> Result<(), anyhow::Error> {
     let change_address = node_api.get_change_address()?;
     debug!("Change address: {:?}", change_address);
     let erg_value_per_box = config.oracle_contract_parameters.min_storage_rent;
+    ensure_bootstrap_funds(
+        &node_api,
+        bootstrap_required_balance(erg_value_per_box, *BASE_FEE)?,
+        &change_address,
+    )?;
     let input = BootstrapInput {
         oracle_address: oracle_config.oracle_address.clone(),
         config,
@@ -88,7 +93,7 @@ pub fn bootstrap(config_file_name: String) -> Result<(), anyhow::Error> {
         change_address: change_address.address(),
         height: BlockHeight(node_api.node.current_block_height()? as u32),
     };
-    let (oracle_config, submitted_tx_ids) = perform_bootstrap_chained_transaction(input)?;
+    let (oracle_config, steps) = perform_bootstrap_chained_transaction(input)?;
     info!("Bootstrap chain-transaction complete");
     let s = serde_yaml::to_string(&oracle_config)?;
     let mut file = std::fs::File::create(DEFAULT_POOL_CONFIG_FILE_NAME)?;
@@ -97,10 +102,85 @@ pub fn bootstrap(config_file_name: String) -> Result<(), anyhow::Error> {
         "Pool configuration file created: {}",
         DEFAULT_POOL_CONFIG_FILE_NAME
     );
-    wait_for_txs_confirmation(submitted_tx_ids);
+    if track_bootstrap_confirmations(&node_api, &steps) {
+        info!("All bootstrap transactions are confirmed");
+    } else {
+        println!("Timeout waiting for the bootstrap transactions to be confirmed, see their status above");
+    }
     Ok(())
 }
 
+/// Number of transactions in the bootstrap chain-transaction
+const BOOTSTRAP_TRANSACTIONS: u32 = 8;
+
+/// Attempts to submit a bootstrap transaction before giving up
+const BOOTSTRAP_SUBMIT_ATTEMPTS: u32 = 3;
+
+/// Delay between the attempts to submit a bootstrap transaction
+const BOOTSTRAP_SUBMIT_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Delay between the status checks of the submitted bootstrap transactions
+const BOOTSTRAP_CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time to wait for all bootstrap transactions to be confirmed
+const BOOTSTRAP_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(1200);
+
+/// Testnet faucet to request the ERG for the bootstrap from
+const TESTNET_FAUCET_URL: &str = "https://testnet.ergofaucet.org";
+
+/// Delay between the wallet balance checks while waiting for the faucet
+const BOOTSTRAP_FUNDS_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// ERG needed for the bootstrap chain-transaction (see `perform_bootstrap_chained_transaction`)
+fn bootstrap_required_balance(
+    erg_value_per_box: BoxValue,
+    tx_fee: BoxValue,
+) -> Result<BoxValue, BoxValueError> {
+    erg_value_per_box
+        .checked_add(&tx_fee)?
+        .checked_mul_u32(BOOTSTRAP_TRANSACTIONS)
+}
+
+/// Checks that the wallet holds enough ERG before any bootstrap transaction is submitted. On
+/// testnet prints the faucet URL and waits for the funds.
+fn ensure_bootstrap_funds(
+    wallet: &dyn WalletDataSource,
+    required_balance: BoxValue,
+    address: &NetworkAddress,
+) -> Result<(), BootstrapError> {
+    let required = *required_balance.as_u64();
+    let mut faucet_printed = false;
+    loop {
+        let balance = wallet_balance(&wallet.get_unspent_wallet_boxes()?);
+        if balance >= required {
+            return Ok(());
+        }
+        match address.network() {
+            NetworkPrefix::Mainnet => {
+                return Err(BootstrapError::InsufficientFunds { balance, required })
+            }
+            NetworkPrefix::Testnet => {
+                if !faucet_printed {
+                    println!(
+                        "The wallet holds {} nanoERG, the bootstrap needs {} nanoERG. \
+                        Request testnet ERG for {} at {}",
+                        balance,
+                        required,
+                        address.to_base58(),
+                        TESTNET_FAUCET_URL
+                    );
+                    faucet_printed = true;
+                }
+                info!(
+                    "Waiting for the bootstrap funds, wallet balance {} of {} nanoERG",
+                    balance, required
+                );
+                std::thread::sleep(BOOTSTRAP_FUNDS_POLL_INTERVAL);
+            }
+        }
+    }
+}
+
 pub fn generate_bootstrap_config_template(config_file_name: String) -> Result<(), BootstrapError> {
     if Path::new(&config_file_name).exists() {
         return Err(BootstrapError::ConfigFilenameAlreadyExists);
@@ -136,7 +216,7 @@ pub struct BootstrapInput<'a> {
 /// https://github.com/ergoplatform/eips/blob/eip23/eip-0023.md#tokens
 pub(crate) fn perform_bootstrap_chained_transaction(
     input: BootstrapInput,
-) -> Result<(PoolConfig, Vec<TxId>), BootstrapError> {
+) -> Result<(PoolConfig, Vec<BootstrapStep>), BootstrapError> {
     let BootstrapInput {
         oracle_address,
         config,
@@ -178,7 +258,7 @@ pub(crate) fn perform_bootstrap_chained_transaction(
     // And so on.
 
     // This variable represents the index `i` described above.
-    let mut num_transactions_left = 8;
+    let mut num_transactions_left = BOOTSTRAP_TRANSACTIONS;
 
     let wallet_pk_ergo_tree = oracle_address.address().script()?;
     let guard = wallet_pk_ergo_tree.clone();
@@ -211,7 +291,7 @@ pub(crate) fn perform_bootstrap_chained_transaction(
                       different_token_box_guard: Option<ErgoTree>|
      -> Result<(Token, Transaction), BootstrapError> {
         let target_balance = calc_target_balance(*num_transactions_left)?;
-        let box_selector = SimpleBoxSelector::new();
+        let box_selector = configured_box_selector();
         let box_selection = box_selector.select(input_boxes, target_balance, &[])?;
         let token = Token {
             token_id: box_selection.boxes.first().box_id().into(),
@@ -253,7 +333,7 @@ pub(crate) fn perform_bootstrap_chained_transaction(
     debug!("unspent boxes: {:?}", unspent_boxes);
     let target_balance = calc_target_balance(num_transactions_left)?;
     debug!("target_balance: {:?}", target_balance);
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let box_selection = box_selector.select(unspent_boxes.clone(), target_balance, &[])?;
     debug!("box selection: {:?}", box_selection);
 
@@ -423,7 +503,7 @@ pub(crate) fn perform_bootstrap_chained_transaction(
     output_candidates.push(builder.build()?);
 
     let target_balance = calc_target_balance(num_transactions_left)?;
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let mut inputs = filter_tx_outputs(signed_mint_reward_tokens_tx.outputs.clone());
 
     // Need to find the box containing the pool NFT, and transfer this token to the pool box.
@@ -480,7 +560,7 @@ pub(crate) fn perform_bootstrap_chained_transaction(
     let output_candidates = vec![refresh_box_candidate];
 
     let target_balance = calc_target_balance(num_transactions_left)?;
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let mut inputs = filter_tx_outputs(signed_pool_box_tx.outputs.clone());
 
     // Need to find the box containing the refresh NFT, and transfer this token to the refresh box.
@@ -516,35 +596,147 @@ pub(crate) fn perform_bootstrap_chained_transaction(
         wallet_sign.sign_transaction_with_inputs(&refresh_box_tx, inputs, None)?;
 
     // ---------------------------------------------------------------------------------------------
-    let mut submitted_tx_ids = vec![];
-    let tx_id = submit_tx.submit_transaction(&signed_mint_pool_nft_tx)?;
-    submitted_tx_ids.push(signed_mint_pool_nft_tx.id());
-    info!("Minted pool NFT TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_refresh_nft_tx)?;
-    submitted_tx_ids.push(signed_mint_refresh_nft_tx.id());
-    info!("Minted refresh NFT TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_ballot_tokens_tx)?;
-    submitted_tx_ids.push(signed_mint_ballot_tokens_tx.id());
-    info!("Minted ballot tokens TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_update_nft_tx)?;
-    submitted_tx_ids.push(signed_mint_update_nft_tx.id());
-    info!("Minted update NFT TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_oracle_tokens_tx)?;
-    submitted_tx_ids.push(signed_mint_oracle_tokens_tx.id());
-    info!("Minted oracle tokens TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_mint_reward_tokens_tx)?;
-    submitted_tx_ids.push(signed_mint_reward_tokens_tx.id());
-    info!("Minted reward tokens TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_pool_box_tx)?;
-    submitted_tx_ids.push(signed_pool_box_tx.id());
-    info!("Created initial pool box TxId: {}", tx_id);
-    let tx_id = submit_tx.submit_transaction(&signed_refresh_box_tx)?;
-    submitted_tx_ids.push(signed_refresh_box_tx.id());
-    info!("Created initial refresh box TxId: {}", tx_id);
+    let steps = vec![
+        BootstrapStep {
+            name: "Mint pool NFT",
+            tx: signed_mint_pool_nft_tx,
+        },
+        BootstrapStep {
+            name: "Mint refresh NFT",
+            tx: signed_mint_refresh_nft_tx,
+        },
+        BootstrapStep {
+            name: "Mint ballot tokens",
+            tx: signed_mint_ballot_tokens_tx,
+        },
+        BootstrapStep {
+            name: "Mint update NFT",
+            tx: signed_mint_update_nft_tx,
+        },
+        BootstrapStep {
+            name: "Mint oracle tokens",
+            tx: signed_mint_oracle_tokens_tx,
+        },
+        BootstrapStep {
+            name: "Mint reward tokens",
+            tx: signed_mint_reward_tokens_tx,
+        },
+        BootstrapStep {
+            name: "Create pool box",
+            tx: signed_pool_box_tx,
+        },
+        BootstrapStep {
+            name: "Create refresh box",
+            tx: signed_refresh_box_tx,
+        },
+    ];
+    // each tx spends an output of the previous one, so they are submitted in order
+    for step in &steps {
+        let tx_id = submit_with_retries(submit_tx, step)?;
+        info!("{} TxId: {}", step.name, tx_id);
+    }
 
     info!("Minted tokens: {:?}", token_ids);
 
-    Ok((PoolConfig::create(config, token_ids)?, submitted_tx_ids))
+    Ok((PoolConfig::create(config, token_ids)?, steps))
+}
+
+/// Submits the tx, retrying if the node fails to respond (but not if it rejects the tx)
+fn submit_with_retries(
+    submit_tx: &dyn SubmitTransaction,
+    step: &BootstrapStep,
+) -> Result<TxId, BootstrapError> {
+    let mut attempt = 1;
+    loop {
+        match submit_tx.submit_transaction(&step.tx) {
+            Ok(tx_id) => return Ok(tx_id),
+            Err(NodeError::BadRequest(msg)) => {
+                return Err(NodeError::BadRequest(msg).into());
+            }
+            Err(e) if attempt < BOOTSTRAP_SUBMIT_ATTEMPTS => {
+                warn!(
+                    "{}: failed to submit the tx (attempt {} of {}): {}",
+                    step.name, attempt, BOOTSTRAP_SUBMIT_ATTEMPTS, e
+                );
+                std::thread::sleep(BOOTSTRAP_SUBMIT_RETRY_DELAY);
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Waits for the bootstrap txs to be confirmed, resubmitting the ones dropped by the node and
+/// printing the status of every step. Returns `false` on timeout.
+fn track_bootstrap_confirmations(node_api: &NodeApi, steps: &[BootstrapStep]) -> bool {
+    let start_time = std::time::Instant::now();
+    let mut resubmissions = vec![0; steps.len()];
+    loop {
+        let statuses: Vec<Option<TxStatus>> = steps
+            .iter()
+            .map(|step| {
+                node_api
+                    .tx_status(&step.tx.id())
+                    .map_err(|e| warn!("{}: failed to get the tx status: {}", step.name, e))
+                    .ok()
+            })
+            .collect();
+        // in order, so that a resubmitted tx finds the outputs of the resubmitted previous one
+        for (i, step) in steps.iter().enumerate() {
+            if statuses[i] == Some(TxStatus::Unknown) {
+                match node_api.submit_transaction(&step.tx) {
+                    Ok(tx_id) => {
+                        resubmissions[i] += 1;
+                        info!("{}: resubmitted the tx {}", step.name, tx_id);
+                    }
+                    Err(e) => warn!("{}: failed to resubmit the tx: {}", step.name, e),
+                }
+            }
+        }
+        println!("{}", format_steps_status(steps, &statuses, &resubmissions));
+        if statuses.iter().all(|s| *s == Some(TxStatus::Confirmed)) {
+            return true;
+        }
+        if start_time.elapsed() > BOOTSTRAP_CONFIRMATION_TIMEOUT {
+            return false;
+        }
+        std::thread::sleep(BOOTSTRAP_CONFIRMATION_POLL_INTERVAL);
+    }
+}
+
+/// Table of the bootstrap steps with their tx ids, statuses and resubmission counts
+fn format_steps_status(
+    steps: &[BootstrapStep],
+    statuses: &[Option<TxStatus>],
+    resubmissions: &[u32],
+) -> String {
+    let mut table = format!(
+        "{:<20} {:<64} {:<12} {}\n",
+        "Step", "Tx id", "Status", "Resubmitted"
+    );
+    for ((step, status), resubmitted) in steps.iter().zip(statuses).zip(resubmissions) {
+        let status = match status {
+            Some(TxStatus::Confirmed) => "confirmed",
+            Some(TxStatus::Unconfirmed) => "in mempool",
+            Some(TxStatus::Unknown) => "dropped",
+            None => "unknown",
+        };
+        table.push_str(&format!(
+            "{:<20} {:<64} {:<12} {}\n",
+            step.name,
+            step.tx.id().to_string(),
+            status,
+            resubmitted
+        ));
+    }
+    table
+}
+
+/// A tx of the bootstrap chain-transaction
+#[derive(Debug, Clone)]
+pub struct BootstrapStep {
+    pub name: &'static str,
+    pub tx: Transaction,
 }
 
 /// An instance of this struct is created from an operator-provided YAML file.
@@ -665,6 +857,8 @@ pub enum BootstrapError {
     PoolContractError(#[from] PoolContractError),
     #[error("WalletData error: {0}")]
     WalletData(#[from] WalletDataError),
+    #[error("not enough ERG in the wallet for the bootstrap: {balance} nanoERG, {required} nanoERG needed")]
+    InsufficientFunds { balance: u64, required: u64 },
 }
 
 #[cfg(test)]
@@ -841,6 +1035,32 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_format_steps_status() {
+        let steps = vec![
+            BootstrapStep {
+                name: "Mint pool NFT",
+                tx: force_any_val::<Transaction>(),
+            },
+            BootstrapStep {
+                name: "Mint refresh NFT",
+                tx: force_any_val::<Transaction>(),
+            },
+        ];
+        let table = format_steps_status(
+            &steps,
+            &[Some(TxStatus::Confirmed), Some(TxStatus::Unknown)],
+            &[0, 1],
+        );
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("Mint pool NFT"));
+        assert!(lines[1].contains(&steps[0].tx.id().to_string()));
+        assert!(lines[1].contains("confirmed"));
+        assert!(lines[2].contains("dropped"));
+        assert!(lines[2].ends_with('1'));
+    }
+
     #[test]
     fn test_custom_contract_param() {
         let config: BootstrapConfig = serde_yaml::from_str("
@@ -0,0 +1,482 @@
+//! `doctor` command: runs a set of checks of the oracle setup and prints a suggested fix for each
+//! failed check.
+
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+
+use crate::box_kind::RefreshBox;
+use crate::contract_switch::pool_contract_status;
+use crate::contract_switch::PoolContractStatus;
+use crate::datapoint_source::DataPointSource;
+use crate::datapoint_source::RuntimeDataPointSource;
+use crate::explorer_api::explorer_url::default_explorer_api_url;
+use crate::explorer_api::ExplorerApi;
+use crate::node_interface::node_api::NodeApi;
+use crate::oracle_config::OracleConfig;
+use crate::oracle_config::BASE_FEE;
+use crate::oracle_config::NODE_API_KEY_ENV_VAR;
+use crate::oracle_config::ORACLE_CONFIG_OPT;
+use crate::oracle_config::ORACLE_SECRETS;
+use crate::oracle_state::OraclePool;
+use crate::pool_config::PoolConfig;
+use crate::pool_config::POOL_CONFIG_OPT;
+use crate::scans::NodeScanId;
+use crate::scans::NodeScanRegistry;
+use crate::spec_token::TokenIdKind;
+
+/// Max. number of blocks the node's full blocks height may lag behind the headers height
+const MAX_SYNC_LAG_BLOCKS: u64 = 2;
+/// The wallet is considered funded if it can pay fees for this many transactions
+const MIN_FUNDED_TX_COUNT: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub details: String,
+    /// What the operator should do to fix the failure
+    pub fix: Option<String>,
+}
+
+impl CheckResult {
+    fn ok(name: &'static str, details: String) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Ok,
+            details,
+            fix: None,
+        }
+    }
+
+    fn warning(name: &'static str, details: String, fix: &str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warning,
+            details,
+            fix: Some(fix.to_string()),
+        }
+    }
+
+    fn failed(name: &'static str, details: String, fix: &str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Failed,
+            details,
+            fix: Some(fix.to_string()),
+        }
+    }
+
+    /// Checks that can't be run because the check they depend on has failed
+    fn skipped(name: &'static str, reason: &str) -> Self {
+        Self::warning(
+            name,
+            format!("skipped, {reason}"),
+            "fix the failed checks above and run again",
+        )
+    }
+}
+
+impl std::fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.status {
+            CheckStatus::Ok => "[ OK ]",
+            CheckStatus::Warning => "[WARN]",
+            CheckStatus::Failed => "[FAIL]",
+        };
+        write!(f, "{} {}: {}", status, self.name, self.details)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n       fix: {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs all the checks and prints the results. Returns `false` if any check failed.
+pub fn doctor() -> bool {
+    let results = run_checks();
+    for result in &results {
+        println!("{}", result);
+    }
+    let failed = results
+        .iter()
+        .filter(|r| r.status == CheckStatus::Failed)
+        .count();
+    if failed == 0 {
+        println!("All checks passed");
+    } else {
+        println!("{} check(s) failed", failed);
+    }
+    failed == 0
+}
+
+fn run_checks() -> Vec<CheckResult> {
+    let mut results = vec![];
+    let oracle_config = match ORACLE_CONFIG_OPT.as_ref() {
+        Ok(config) => {
+            results.push(CheckResult::ok("oracle config", "loaded".to_string()));
+            Some(config)
+        }
+        Err(e) => {
+            results.push(CheckResult::failed(
+                "oracle config",
+                format!("{:#}", e),
+                "fix the oracle config file or remove it to generate the default one",
+            ));
+            None
+        }
+    };
+    let pool_config = match POOL_CONFIG_OPT.as_ref() {
+        Ok(config) => {
+            results.push(CheckResult::ok("pool config", "loaded".to_string()));
+            Some(config)
+        }
+        Err(e) => {
+            results.push(CheckResult::failed(
+                "pool config",
+                e.clone(),
+                "get the pool config file from the pool operator and pass it with --pool-config-file",
+            ));
+            None
+        }
+    };
+    let node_api = match oracle_config {
+        Some(oracle_config) => {
+            let node_api = check_node_api(oracle_config, &mut results);
+            if let Some(node_api) = &node_api {
+                results.push(check_wallet(node_api));
+            }
+            node_api
+        }
+        None => {
+            results.push(CheckResult::skipped("node", "no oracle config"));
+            None
+        }
+    };
+    match (oracle_config, pool_config) {
+        (Some(oracle_config), Some(pool_config)) => {
+            results.push(check_token_ids(oracle_config, pool_config));
+            results.push(check_datapoint_source(oracle_config, pool_config));
+        }
+        _ => {
+            results.push(CheckResult::skipped(
+                "token ids",
+                "no oracle or pool config",
+            ));
+            results.push(CheckResult::skipped(
+                "datapoint source",
+                "no oracle or pool config",
+            ));
+        }
+    }
+    match (node_api, pool_config) {
+        (Some(node_api), Some(pool_config)) => {
+            let scans_result = check_scans(&node_api);
+            let scans_ok = scans_result.status == CheckStatus::Ok;
+            results.push(scans_result);
+            if scans_ok {
+                results.push(check_contracts(pool_config));
+            } else {
+                results.push(CheckResult::skipped(
+                    "contracts",
+                    "scans are not registered",
+                ));
+            }
+        }
+        _ => {
+            results.push(CheckResult::skipped(
+                "scans",
+                "node or pool config check failed",
+            ));
+            results.push(CheckResult::skipped(
+                "contracts",
+                "node or pool config check failed",
+            ));
+        }
+    }
+    results
+}
+
+fn check_node_api(oracle_config: &OracleConfig, results: &mut Vec<CheckResult>) -> Option<NodeApi> {
+    if std::env::var(NODE_API_KEY_ENV_VAR).is_err() {
+        results.push(CheckResult::failed(
+            "node",
+            format!("{NODE_API_KEY_ENV_VAR} environment variable is not set"),
+            &format!("set {NODE_API_KEY_ENV_VAR} to the API key of your node"),
+        ));
+        return None;
+    }
+    let node_api = NodeApi::new(
+        ORACLE_SECRETS.node_api_key.clone(),
+        ORACLE_SECRETS.wallet_password.clone(),
+        &oracle_config.node_url,
+    );
+    let res = node_api.node.send_get_req("/info");
+    let info = match node_api.node.parse_response_to_json(res) {
+        Ok(info) => info,
+        Err(e) => {
+            results.push(CheckResult::failed(
+                "node",
+                format!("{} is not reachable: {}", oracle_config.node_url, e),
+                "make sure the node is running and node_url in the oracle config points to it",
+            ));
+            return None;
+        }
+    };
+    let headers_height = info["headersHeight"].as_u64().unwrap_or(0);
+    let full_height = info["fullHeight"].as_u64().unwrap_or(0);
+    if full_height == 0 || headers_height > full_height + MAX_SYNC_LAG_BLOCKS {
+        results.push(CheckResult::failed(
+            "node",
+            format!(
+                "node is not synced (headers height {headers_height}, full height {full_height})"
+            ),
+            "wait for the node to sync and run again",
+        ));
+    } else {
+        results.push(CheckResult::ok(
+            "node",
+            format!("reachable and synced at height {full_height}"),
+        ));
+    }
+    Some(node_api)
+}
+
+fn check_wallet(node_api: &NodeApi) -> CheckResult {
+    let wallet_status = match node_api.node.wallet_status() {
+        Ok(wallet_status) => wallet_status,
+        Err(e) => {
+            return CheckResult::failed(
+                "wallet",
+                format!("failed to get wallet status: {}", e),
+                "check the node API key",
+            )
+        }
+    };
+    if !wallet_status.unlocked {
+        return CheckResult::failed(
+            "wallet",
+            "wallet is locked".to_string(),
+            "unlock the node wallet or set ORACLE_NODE_WALLET_PASSWORD for automatic unlock",
+        );
+    }
+    if wallet_status.change_address.is_none() {
+        return CheckResult::failed(
+            "wallet",
+            "no change address is set in the node".to_string(),
+            "set the change address with the node's /wallet/updateChangeAddress endpoint",
+        );
+    }
+    let min_balance = *BASE_FEE.as_u64() * MIN_FUNDED_TX_COUNT;
+    match node_api.node.wallet_nano_ergs_balance() {
+        Ok(balance) if balance < min_balance => CheckResult::failed(
+            "wallet",
+            format!("unlocked, balance {balance} nanoERG is below {min_balance} nanoERG"),
+            "send some ERGs to the node wallet to pay the transaction fees",
+        ),
+        Ok(balance) => CheckResult::ok("wallet", format!("unlocked, balance {balance} nanoERG")),
+        Err(e) => CheckResult::failed(
+            "wallet",
+            format!("failed to get wallet balance: {}", e),
+            "check that the node wallet is initialized",
+        ),
+    }
+}
+
+fn check_token_ids(oracle_config: &OracleConfig, pool_config: &PoolConfig) -> CheckResult {
+    let explorer_url = oracle_config
+        .explorer_url
+        .clone()
+        .unwrap_or_else(|| default_explorer_api_url(oracle_config.network_prefix()));
+    let explorer_api = ExplorerApi::new(explorer_url);
+    let token_ids = &pool_config.token_ids;
+    let tokens: Vec<(&str, TokenId)> = vec![
+        ("pool NFT", token_ids.pool_nft_token_id.token_id()),
+        ("refresh NFT", token_ids.refresh_nft_token_id.token_id()),
+        ("update NFT", token_ids.update_nft_token_id.token_id()),
+        ("oracle token", token_ids.oracle_token_id.token_id()),
+        ("reward token", token_ids.reward_token_id.token_id()),
+        ("ballot token", token_ids.ballot_token_id.token_id()),
+    ];
+    let mut missing = vec![];
+    for (name, token_id) in tokens {
+        match explorer_api.token_exists(token_id) {
+            Ok(true) => (),
+            Ok(false) => missing.push(format!("{} {}", name, String::from(token_id))),
+            Err(e) => {
+                return CheckResult::warning(
+                    "token ids",
+                    format!("failed to query the explorer: {}", e),
+                    "check explorer_url in the oracle config",
+                )
+            }
+        }
+    }
+    if missing.is_empty() {
+        CheckResult::ok("token ids", "all pool tokens exist".to_string())
+    } else {
+        CheckResult::failed(
+            "token ids",
+            format!("not found on chain: {}", missing.join(", ")),
+            "check that the pool config matches the network (mainnet/testnet) and the pool you are joining",
+        )
+    }
+}
+
+fn check_scans(node_api: &NodeApi) -> CheckResult {
+    let registry = match NodeScanRegistry::load() {
+        Ok(registry) => registry,
+        Err(e) => {
+            return CheckResult::failed(
+                "scans",
+                format!("failed to load scan ids: {}", e),
+                "run the oracle once to register the scans",
+            )
+        }
+    };
+    let res = node_api.node.send_get_req("/scan/listAll");
+    let node_scan_ids: Vec<String> = match node_api.node.parse_response_to_json(res) {
+        Ok(json) => json
+            .members()
+            .map(|scan| scan["scanId"].to_string())
+            .collect(),
+        Err(e) => {
+            return CheckResult::failed(
+                "scans",
+                format!("failed to get registered scans from the node: {}", e),
+                "check the node API key",
+            )
+        }
+    };
+    let mut scan_ids = vec![
+        registry.oracle_token_scan.scan_id(),
+        registry.pool_token_scan.scan_id(),
+        registry.ballot_token_scan.scan_id(),
+        registry.refresh_token_scan.scan_id(),
+        registry.update_token_scan.scan_id(),
+    ];
+    if let Some(buyback_token_scan) = &registry.buyback_token_scan {
+        scan_ids.push(buyback_token_scan.scan_id());
+    }
+    let missing: Vec<String> = scan_ids
+        .into_iter()
+        .map(|id| id.to_string())
+        .filter(|id| !node_scan_ids.contains(id))
+        .collect();
+    if missing.is_empty() {
+        CheckResult::ok("scans", "all scans are registered in the node".to_string())
+    } else {
+        CheckResult::failed(
+            "scans",
+            format!(
+                "scans {} are not registered in the node",
+                missing.join(", ")
+            ),
+            "remove scanIDs.json and run the oracle to register the scans again",
+        )
+    }
+}
+
+fn check_contracts(pool_config: &PoolConfig) -> CheckResult {
+    let oracle_pool = match NodeScanRegistry::load().and_then(|r| Ok(OraclePool::new(&r)?)) {
+        Ok(oracle_pool) => oracle_pool,
+        Err(e) => {
+            return CheckResult::failed(
+                "contracts",
+                format!("failed to load the oracle pool: {}", e),
+                "remove scanIDs.json and run the oracle to register the scans again",
+            )
+        }
+    };
+    match oracle_pool.get_raw_pool_box() {
+        Ok(Some(pool_box)) => {
+            match pool_contract_status(pool_box, &pool_config.pool_box_wrapper_inputs, None) {
+                PoolContractStatus::Current => (),
+                PoolContractStatus::Staged | PoolContractStatus::Unknown => {
+                    return CheckResult::failed(
+                        "contracts",
+                        "the pool box contract does not match the pool config".to_string(),
+                        "the pool was probably updated, get the new pool config from the pool operator and import it with import-pool-update",
+                    )
+                }
+            }
+        }
+        Ok(None) => {
+            return CheckResult::failed(
+                "contracts",
+                "pool box is not found".to_string(),
+                "wait for the node to finish the scans, if it persists check scan_start_height in the oracle config and remove scanIDs.json",
+            )
+        }
+        Err(e) => {
+            return CheckResult::failed(
+                "contracts",
+                format!("failed to get the pool box: {}", e),
+                "check the node",
+            )
+        }
+    }
+    match oracle_pool.get_refresh_box_source().get_refresh_box() {
+        Ok(refresh_box) => CheckResult::ok(
+            "contracts",
+            format!(
+                "pool box and refresh box (epoch length {}) match the pool config",
+                refresh_box.contract().epoch_length().0
+            ),
+        ),
+        Err(e) => CheckResult::failed(
+            "contracts",
+            format!("refresh box does not match the pool config: {}", e),
+            "get the actual pool config from the pool operator",
+        ),
+    }
+}
+
+fn check_datapoint_source(oracle_config: &OracleConfig, pool_config: &PoolConfig) -> CheckResult {
+    let datapoint_source = match RuntimeDataPointSource::new(
+        pool_config.data_point_source,
+        oracle_config.data_point_source_custom_script.clone(),
+        oracle_config.data_point_source_http.clone(),
+        oracle_config.data_point_source_file.clone(),
+        oracle_config.data_point_source_rhai.clone(),
+    ) {
+        Ok(datapoint_source) => datapoint_source,
+        Err(e) => {
+            return CheckResult::failed(
+                "datapoint source",
+                e.to_string(),
+                "set data_point_source in the pool config or data_point_source_custom_script (or data_point_source_http, data_point_source_file, data_point_source_rhai) in the oracle config",
+            )
+        }
+    };
+    match datapoint_source.get_datapoint() {
+        Ok(rate) => CheckResult::ok("datapoint source", format!("responded with {}", rate)),
+        Err(e) => CheckResult::failed(
+            "datapoint source",
+            e.to_string(),
+            "check the network connection (and the custom script output, the data_point_source_http response, the data_point_source_file or the Rhai script if it is set)",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_result_display() {
+        assert_eq!(
+            CheckResult::ok("node", "reachable".to_string()).to_string(),
+            "[ OK ] node: reachable"
+        );
+        assert_eq!(
+            CheckResult::failed("wallet", "wallet is locked".to_string(), "unlock it").to_string(),
+            "[FAIL] wallet: wallet is locked\n       fix: unlock it"
+        );
+    }
+}
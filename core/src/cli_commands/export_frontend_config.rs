@@ -0,0 +1,218 @@
+//! `export-frontend-config` command: the JSON bundle of the oracle pool frontend website (token
+//! ids, contract addresses, parameters and the operators of the pool) generated from the pool
+//! config and the oracle boxes on chain, so the website needs no hand-written config.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::address::AddressEncoder;
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use serde::Serialize;
+
+use crate::oracle_state::OraclePool;
+use crate::oracle_types::EpochLength;
+use crate::oracle_types::MinDatapoints;
+use crate::pool_config::PoolConfig;
+use crate::spec_token::TokenIdKind;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FrontendTokenIds {
+    pub pool_nft: String,
+    pub refresh_nft: String,
+    pub update_nft: String,
+    pub oracle_token: String,
+    pub reward_token: String,
+    pub ballot_token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FrontendContractAddresses {
+    pub pool: String,
+    pub refresh: String,
+    pub oracle: String,
+    pub ballot: String,
+    pub update: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FrontendPoolParameters {
+    pub epoch_length: EpochLength,
+    pub min_data_points: MinDatapoints,
+    pub max_deviation_percent: i32,
+    pub min_votes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FrontendConfig {
+    pub network: String,
+    /// Predefined datapoint source of the pool, `null` for a custom source
+    pub data_point_source: Option<String>,
+    pub token_ids: FrontendTokenIds,
+    pub contract_addresses: FrontendContractAddresses,
+    pub parameters: FrontendPoolParameters,
+    /// Addresses of the oracle boxes on chain
+    pub operators: Vec<String>,
+    /// REST API endpoints of the known operators
+    pub operator_apis: Vec<String>,
+}
+
+fn p2s_address(encoder: &AddressEncoder, ergo_tree_bytes: Vec<u8>) -> String {
+    encoder.address_to_str(&Address::P2S(ergo_tree_bytes))
+}
+
+pub fn build_frontend_config(
+    pool_config: &PoolConfig,
+    network_prefix: NetworkPrefix,
+    operators: BTreeSet<String>,
+    operator_apis: Vec<String>,
+) -> FrontendConfig {
+    let encoder = AddressEncoder::new(network_prefix);
+    let token_ids = &pool_config.token_ids;
+    let refresh_parameters = pool_config
+        .refresh_box_wrapper_inputs
+        .contract_inputs
+        .contract_parameters();
+    let update_parameters = pool_config
+        .update_box_wrapper_inputs
+        .contract_inputs
+        .contract_parameters();
+    FrontendConfig {
+        network: match network_prefix {
+            NetworkPrefix::Mainnet => "mainnet".to_string(),
+            NetworkPrefix::Testnet => "testnet".to_string(),
+        },
+        data_point_source: pool_config
+            .data_point_source
+            .map(|source| format!("{:?}", source)),
+        token_ids: FrontendTokenIds {
+            pool_nft: String::from(token_ids.pool_nft_token_id.token_id()),
+            refresh_nft: String::from(token_ids.refresh_nft_token_id.token_id()),
+            update_nft: String::from(token_ids.update_nft_token_id.token_id()),
+            oracle_token: String::from(token_ids.oracle_token_id.token_id()),
+            reward_token: String::from(token_ids.reward_token_id.token_id()),
+            ballot_token: String::from(token_ids.ballot_token_id.token_id()),
+        },
+        contract_addresses: FrontendContractAddresses {
+            pool: p2s_address(
+                &encoder,
+                pool_config
+                    .pool_box_wrapper_inputs
+                    .contract_inputs
+                    .contract_parameters()
+                    .ergo_tree_bytes(),
+            ),
+            refresh: p2s_address(&encoder, refresh_parameters.ergo_tree_bytes()),
+            oracle: p2s_address(
+                &encoder,
+                pool_config
+                    .oracle_box_wrapper_inputs
+                    .contract_inputs
+                    .contract_parameters()
+                    .ergo_tree_bytes(),
+            ),
+            ballot: p2s_address(
+                &encoder,
+                pool_config
+                    .ballot_box_wrapper_inputs
+                    .contract_inputs
+                    .contract_parameters()
+                    .ergo_tree_bytes(),
+            ),
+            update: p2s_address(&encoder, update_parameters.ergo_tree_bytes()),
+        },
+        parameters: FrontendPoolParameters {
+            epoch_length: refresh_parameters.epoch_length(),
+            min_data_points: refresh_parameters.min_data_points(),
+            max_deviation_percent: refresh_parameters.max_deviation_percent(),
+            min_votes: update_parameters.min_votes(),
+        },
+        operators: operators.into_iter().collect(),
+        operator_apis,
+    }
+}
+
+/// Addresses of the posted and collected oracle boxes
+fn operator_addresses(
+    oracle_pool: &OraclePool,
+    network_prefix: NetworkPrefix,
+) -> Result<BTreeSet<String>, anyhow::Error> {
+    let mut public_keys: Vec<_> = oracle_pool
+        .get_posted_datapoint_boxes_source()
+        .get_posted_datapoint_boxes()?
+        .iter()
+        .map(|b| b.public_key())
+        .collect();
+    public_keys.extend(
+        oracle_pool
+            .get_collected_datapoint_boxes_source()
+            .get_collected_datapoint_boxes()?
+            .iter()
+            .map(|b| b.public_key()),
+    );
+    let encoder = AddressEncoder::new(network_prefix);
+    Ok(public_keys
+        .into_iter()
+        .map(|pk| encoder.address_to_str(&Address::P2Pk(pk.into())))
+        .collect())
+}
+
+pub fn export_frontend_config(
+    oracle_pool: &OraclePool,
+    pool_config: &PoolConfig,
+    network_prefix: NetworkPrefix,
+    operator_apis: Vec<String>,
+    output: &Path,
+) -> Result<(), anyhow::Error> {
+    let operators = operator_addresses(oracle_pool, network_prefix)?;
+    let config = build_frontend_config(pool_config, network_prefix, operators, operator_apis);
+    std::fs::write(output, serde_json::to_string_pretty(&config)?)?;
+    println!(
+        "Exported the frontend config with {} operators to {}",
+        config.operators.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_commands::bootstrap::BootstrapConfig;
+    use crate::pool_commands::test_utils::generate_token_ids;
+
+    #[test]
+    fn test_build_frontend_config() {
+        let token_ids = generate_token_ids();
+        let pool_config =
+            PoolConfig::create(BootstrapConfig::default(), token_ids.clone()).unwrap();
+        let operators = BTreeSet::from(["9f...b".to_string(), "9e...a".to_string()]);
+        let config = build_frontend_config(
+            &pool_config,
+            NetworkPrefix::Mainnet,
+            operators,
+            vec!["http://oracle.example.com:9010".to_string()],
+        );
+        assert_eq!(config.network, "mainnet");
+        assert_eq!(
+            config.token_ids.pool_nft,
+            String::from(token_ids.pool_nft_token_id.token_id())
+        );
+        assert_eq!(config.operators, vec!["9e...a", "9f...b"]);
+        let pool_address = AddressEncoder::unchecked_parse_network_address_from_str(
+            &config.contract_addresses.pool,
+        )
+        .unwrap();
+        assert_eq!(pool_address.network(), NetworkPrefix::Mainnet);
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(
+            json["parameters"]["epoch_length"],
+            pool_config
+                .refresh_box_wrapper_inputs
+                .contract_inputs
+                .contract_parameters()
+                .epoch_length()
+                .0
+        );
+    }
+}
@@ -14,7 +14,7 @@ use ergo_lib::{
         serialization::SigmaParsingError,
     },
     wallet::{
-        box_selector::{BoxSelection, BoxSelector, BoxSelectorError, SimpleBoxSelector},
+        box_selector::{BoxSelection, BoxSelector, BoxSelectorError},
         tx_builder::{TxBuilder, TxBuilderError},
     },
 };
@@ -31,7 +31,8 @@ use crate::{
     oracle_state::{DataSourceError, LocalDatapointBoxSource},
     oracle_types::BlockHeight,
     spec_token::SpecToken,
-    wallet::{WalletDataError, WalletDataSource},
+    tx_summary::format_tx_outputs,
+    wallet::{configured_box_selector, WalletDataError, WalletDataSource},
 };
 
 #[derive(Debug, Error)]
@@ -86,6 +87,10 @@ pub fn extract_reward_tokens(
         change_address.address(),
     )?;
 
+    println!(
+        "Transaction outputs:\n{}",
+        format_tx_outputs(&unsigned_tx, &change_address.address(), network_prefix)
+    );
     println!(
         "YOU WILL BE TRANSFERRING {} REWARD TOKENS TO {}. TYPE 'YES' TO INITIATE THE TRANSACTION.",
         num_reward_tokens, rewards_destination_str
@@ -169,7 +174,7 @@ fn build_extract_reward_tokens_tx(
         // `BASE_FEE` each for the fee and the box holding the extracted reward tokens.
         let target_balance = BASE_FEE.checked_mul_u32(2).unwrap();
 
-        let box_selector = SimpleBoxSelector::new();
+        let box_selector = configured_box_selector();
         let selection = box_selector.select(unspent_boxes, target_balance, &[])?;
         let mut input_boxes = vec![in_oracle_box.get_box().clone()];
         input_boxes.append(selection.boxes.as_vec().clone().as_mut());
@@ -26,7 +26,7 @@ use ergo_lib::{
         serialization::SigmaParsingError,
     },
     wallet::{
-        box_selector::{BoxSelector, BoxSelectorError, SimpleBoxSelector},
+        box_selector::{BoxSelector, BoxSelectorError},
         tx_builder::{TxBuilder, TxBuilderError},
     },
 };
@@ -64,7 +64,7 @@ use crate::{
     spec_token::{
         BallotTokenId, OracleTokenId, RefreshTokenId, RewardTokenId, TokenIdKind, UpdateTokenId,
     },
-    wallet::{WalletDataError, WalletDataSource},
+    wallet::{configured_box_selector, WalletDataError, WalletDataSource},
 };
 
 use super::bootstrap::{NftMintDetails, TokenMintDetails};
@@ -235,7 +235,7 @@ impl<'a> PrepareUpdate<'a> {
         different_token_box_guard: Option<ErgoTree>,
     ) -> Result<Token, PrepareUpdateError> {
         let target_balance = self.calc_target_balance(self.num_transactions_left)?;
-        let box_selector = SimpleBoxSelector::new();
+        let box_selector = configured_box_selector();
         let box_selection =
             box_selector.select(self.inputs_for_next_tx.clone(), target_balance, &[])?;
         let token = Token {
@@ -293,7 +293,7 @@ impl<'a> PrepareUpdate<'a> {
             self.input.height,
         )?;
         let target_balance = self.calc_target_balance(self.num_transactions_left)?;
-        let box_selection = SimpleBoxSelector::new().select(
+        let box_selection = configured_box_selector().select(
             self.inputs_for_next_tx.clone(),
             target_balance,
             &[refresh_nft_token.clone()],
@@ -347,7 +347,7 @@ impl<'a> PrepareUpdate<'a> {
         debug!("unspent boxes: {:?}", unspent_boxes);
         let target_balance = self.calc_target_balance(self.num_transactions_left)?;
         debug!("target_balance: {:?}", target_balance);
-        let box_selector = SimpleBoxSelector::new();
+        let box_selector = configured_box_selector();
         let box_selection = box_selector.select(unspent_boxes.clone(), target_balance, &[])?;
         debug!("box selection: {:?}", box_selection);
 
@@ -0,0 +1,145 @@
+use ergo_lib::ergotree_ir::chain::address::{Address, NetworkAddress, NetworkPrefix};
+
+use crate::{
+    box_kind::{OracleBox, OracleBoxWrapper},
+    oracle_state::LocalDatapointBoxSource,
+    oracle_types::Rate,
+    pool_config::PredefinedDataPointSource,
+};
+
+pub fn print_my_datapoint_box(
+    local_datapoint_box_source: &dyn LocalDatapointBoxSource,
+    data_point_source: Option<PredefinedDataPointSource>,
+    network_prefix: NetworkPrefix,
+) -> Result<(), anyhow::Error> {
+    if let Some(oracle_box) = local_datapoint_box_source.get_local_oracle_datapoint_box()? {
+        println!(
+            "{}",
+            describe_datapoint_box(&oracle_box, data_point_source, network_prefix)
+        );
+    } else {
+        println!("No datapoint box exists");
+    }
+    Ok(())
+}
+
+fn describe_datapoint_box(
+    oracle_box: &OracleBoxWrapper,
+    data_point_source: Option<PredefinedDataPointSource>,
+    network_prefix: NetworkPrefix,
+) -> String {
+    let ergo_box = oracle_box.get_box();
+    let public_key = oracle_box.public_key();
+    let address = NetworkAddress::new(network_prefix, &Address::P2Pk(public_key.clone().into()));
+    let mut lines = vec![
+        format!("Box id: {}", String::from(ergo_box.box_id())),
+        format!("Creation height: {}", ergo_box.creation_height),
+        format!("Value: {} nanoERG", ergo_box.value.as_u64()),
+        format!(
+            "Oracle token: {} (amount {})",
+            String::from(oracle_box.oracle_token().token_id()),
+            oracle_box.oracle_token().amount.as_u64()
+        ),
+        format!(
+            "Reward token: {} (amount {})",
+            String::from(oracle_box.reward_token().token_id()),
+            oracle_box.reward_token().amount.as_u64()
+        ),
+        format!("R4 public key: {}", String::from(public_key)),
+        format!("R4 address: {}", address.to_base58()),
+    ];
+    match oracle_box {
+        OracleBoxWrapper::Posted(posted_box) => {
+            lines.push(format!(
+                "R5 epoch counter: {}",
+                posted_box.epoch_counter().0
+            ));
+            lines.push(format!(
+                "R6 rate: {} ({})",
+                posted_box.rate(),
+                describe_rate(posted_box.rate(), data_point_source)
+            ));
+        }
+        OracleBoxWrapper::Collected(_) => {
+            lines.push("Datapoint was collected by the last refresh (no R5, R6)".to_string())
+        }
+    }
+    lines.join("\n")
+}
+
+/// Human-readable conversion of the datapoint for the predefined pairs
+fn describe_rate(rate: Rate, data_point_source: Option<PredefinedDataPointSource>) -> String {
+    let rate = i64::from(rate) as f64;
+    let (unit, per1) = match data_point_source {
+        Some(PredefinedDataPointSource::NanoAdaUsd) => {
+            return format!("{:.4} ADA per 1 USD", rate / 1_000_000.0)
+        }
+        Some(PredefinedDataPointSource::UsdCentSpx) => {
+            return format!("{:.2} USD per 1 S&P 500 index point", rate / 100.0)
+        }
+        Some(PredefinedDataPointSource::UsdCentBtc) => {
+            return format!("{:.2} USD per 1 BTC", rate / 100.0)
+        }
+        Some(PredefinedDataPointSource::UsCpi) => {
+            return format!("US CPI-U index level times us_cpi_scale: {}", rate)
+        }
+        Some(PredefinedDataPointSource::ErgoHashrate) => {
+            return format!("{:.2} TH/s Ergo network hashrate", rate / 1e12)
+        }
+        Some(PredefinedDataPointSource::NanoErgUsd) => ("ERG", "USD"),
+        Some(PredefinedDataPointSource::NanoErgXau) => ("ERG", "kg of gold"),
+        Some(PredefinedDataPointSource::NanoErgBTC) => ("ERG", "BTC"),
+        Some(PredefinedDataPointSource::NanoErgSigUsd) => ("ERG", "SigUSD"),
+        Some(PredefinedDataPointSource::NanoErgSigRsv) => ("ERG", "SigRSV"),
+        Some(PredefinedDataPointSource::NanoErgRsn) => ("ERG", "RSN"),
+        Some(PredefinedDataPointSource::NanoErgJpy) => ("ERG", "JPY"),
+        Some(PredefinedDataPointSource::NanoErgXpt) => ("ERG", "kg of platinum"),
+        Some(PredefinedDataPointSource::NanoErgXpd) => ("ERG", "kg of palladium"),
+        Some(PredefinedDataPointSource::NanoErgBrent) => ("ERG", "barrel of Brent crude oil"),
+        None => ("ERG", "unit"),
+    };
+    format!("{:.9} {} per 1 {}", rate / 1_000_000_000.0, unit, per1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::box_kind::OracleBoxWrapperInputs;
+    use crate::contracts::oracle::OracleContractParameters;
+    use crate::oracle_config::BASE_FEE;
+    use crate::oracle_types::{BlockHeight, EpochCounter};
+    use crate::pool_commands::test_utils::{generate_token_ids, make_datapoint_box};
+    use ergo_lib::ergo_chain_types::EcPoint;
+    use sigma_test_util::force_any_val;
+
+    #[test]
+    fn test_describe_datapoint_box() {
+        let token_ids = generate_token_ids();
+        let oracle_box_wrapper_inputs =
+            OracleBoxWrapperInputs::try_from((OracleContractParameters::default(), &token_ids))
+                .unwrap();
+        let oracle_box = OracleBoxWrapper::new(
+            make_datapoint_box(
+                force_any_val::<EcPoint>(),
+                600_000_000,
+                EpochCounter(7),
+                &token_ids,
+                *BASE_FEE,
+                BlockHeight(100),
+                3,
+            ),
+            &oracle_box_wrapper_inputs,
+        )
+        .unwrap();
+        let description = describe_datapoint_box(
+            &oracle_box,
+            Some(PredefinedDataPointSource::NanoErgUsd),
+            NetworkPrefix::Mainnet,
+        );
+        assert!(description.contains("R5 epoch counter: 7"));
+        assert!(description.contains("R6 rate: 600000000 (0.600000000 ERG per 1 USD)"));
+        assert!(description.contains("(amount 3)"));
+    }
+}
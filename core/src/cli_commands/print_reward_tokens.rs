@@ -1,7 +1,8 @@
-use crate::{box_kind::OracleBox, oracle_state::LocalDatapointBoxSource};
+use crate::{box_kind::OracleBox, fee_ledger::FeeLedger, oracle_state::LocalDatapointBoxSource};
 
 pub fn print_reward_tokens(
     local_datapoint_box_source: &dyn LocalDatapointBoxSource,
+    fee_ledger: &FeeLedger,
 ) -> Result<(), anyhow::Error> {
     if let Some(oracle_box) = local_datapoint_box_source.get_local_oracle_datapoint_box()? {
         let num_tokens = *oracle_box.reward_token().amount.as_u64();
@@ -13,5 +14,10 @@ pub fn print_reward_tokens(
     } else {
         println!("No datapoint box exists");
     }
+    // to compare the earned tokens against the costs
+    println!(
+        "Fees paid in the last 90 days: {} ERG",
+        fee_ledger.total_fees() as f64 / 1_000_000_000.0
+    );
     Ok(())
 }
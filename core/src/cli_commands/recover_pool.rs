@@ -0,0 +1,460 @@
+use thiserror::Error;
+
+use crate::{
+    actions::execute_action,
+    box_kind::{OracleBoxWrapper, PoolBox},
+    datapoint_source::RuntimeDataPointSource,
+    node_interface::node_api::NodeApi,
+    oracle_state::{
+        DataSourceError, LocalDatapointBoxSource, OraclePool, PoolBoxSource,
+        PostedDatapointBoxesSource,
+    },
+    oracle_types::{BlockHeight, EpochCounter, EpochLength, MinDatapoints},
+    pool_commands::{build_action, refresh::filtered_oracle_boxes_by_rate, PoolCommand},
+    pool_config::POOL_CONFIG,
+};
+
+#[derive(Debug, Error)]
+pub enum RecoverPoolError {
+    #[error("data source error: {0}")]
+    DataSourceError(#[from] DataSourceError),
+}
+
+/// State of our own datapoint box relative to the current pool epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MyDatapointStatus {
+    /// No datapoint box with our public key was found
+    Missing,
+    /// Our datapoint box was collected by the last refresh and a new datapoint was not posted yet
+    Collected,
+    /// Our datapoint was posted for another epoch or is outside the epoch window
+    Stale {
+        epoch_id: EpochCounter,
+        height: BlockHeight,
+    },
+    /// Our datapoint was posted for the current epoch and can be included in the refresh
+    InEpoch,
+}
+
+/// Summary of everything that decides whether the refresh can be built right now
+#[derive(Debug, Clone)]
+pub struct PoolDiagnosis {
+    pub current_height: BlockHeight,
+    pub pool_box_height: BlockHeight,
+    pub pool_box_epoch_id: EpochCounter,
+    pub epoch_length: EpochLength,
+    pub min_data_points: MinDatapoints,
+    pub max_deviation_percent: u32,
+    pub posted_total: usize,
+    pub posted_wrong_epoch: usize,
+    pub posted_too_old: usize,
+    pub posted_in_epoch: usize,
+    pub posted_within_deviation: usize,
+    pub reward_tokens_in_pool_box: u64,
+    pub my_datapoint: MyDatapointStatus,
+}
+
+impl PoolDiagnosis {
+    /// Number of reward tokens taken from the pool box by a refresh with the datapoints we have
+    /// (2 per collected datapoint box)
+    pub fn reward_tokens_required(&self) -> u64 {
+        let collected = std::cmp::max(
+            self.posted_within_deviation,
+            self.min_data_points.0.max(0) as usize,
+        );
+        collected as u64 * 2
+    }
+
+    /// Never over on a chain shorter than the epoch length (e.g. a fresh testnet)
+    pub fn is_epoch_over(&self) -> bool {
+        self.pool_box_height < self.current_height.saturating_sub(self.epoch_length)
+    }
+
+    /// Human readable list of the reasons the refresh is failing
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if (self.posted_in_epoch as i32) < self.min_data_points.0 {
+            problems.push(format!(
+                "only {} datapoint(s) are posted for epoch {} within the last {} blocks, {} required ({} posted for another epoch, {} are too old)",
+                self.posted_in_epoch,
+                self.pool_box_epoch_id.0,
+                self.epoch_length.0,
+                self.min_data_points.0,
+                self.posted_wrong_epoch,
+                self.posted_too_old,
+            ));
+        } else if (self.posted_within_deviation as i32) < self.min_data_points.0 {
+            problems.push(format!(
+                "only {} of {} datapoints are within {}% of each other, {} required",
+                self.posted_within_deviation,
+                self.posted_in_epoch,
+                self.max_deviation_percent,
+                self.min_data_points.0,
+            ));
+        }
+        if self.reward_tokens_in_pool_box < self.reward_tokens_required() {
+            problems.push(format!(
+                "pool box holds {} reward tokens, refresh needs at least {}",
+                self.reward_tokens_in_pool_box,
+                self.reward_tokens_required(),
+            ));
+        }
+        match &self.my_datapoint {
+            MyDatapointStatus::Missing => {
+                problems.push("no datapoint box with our public key is found".to_string())
+            }
+            MyDatapointStatus::Stale { epoch_id, height } => problems.push(format!(
+                "our datapoint is stale (epoch {}, height {})",
+                epoch_id.0, height
+            )),
+            MyDatapointStatus::Collected | MyDatapointStatus::InEpoch => (),
+        }
+        problems
+    }
+
+    /// The smallest transaction that moves the pool forward, if one can be built by us
+    pub fn corrective_command(&self) -> Option<PoolCommand> {
+        match self.my_datapoint {
+            MyDatapointStatus::Missing => Some(PoolCommand::PublishFirstDataPoint),
+            MyDatapointStatus::Collected | MyDatapointStatus::Stale { .. } => {
+                Some(PoolCommand::PublishSubsequentDataPoint { republish: true })
+            }
+            MyDatapointStatus::InEpoch => {
+                if self.is_epoch_over()
+                    && (self.posted_within_deviation as i32) >= self.min_data_points.0
+                    && self.reward_tokens_in_pool_box >= self.reward_tokens_required()
+                {
+                    Some(PoolCommand::Refresh)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+pub fn diagnose_pool(
+    pool_box_source: &dyn PoolBoxSource,
+    datapoint_boxes_source: &dyn PostedDatapointBoxesSource,
+    local_datapoint_box_source: &dyn LocalDatapointBoxSource,
+    epoch_length: EpochLength,
+    max_deviation_percent: u32,
+    min_data_points: MinDatapoints,
+    height: BlockHeight,
+) -> Result<PoolDiagnosis, RecoverPoolError> {
+    let pool_box = pool_box_source.get_pool_box()?;
+    let pool_box_epoch_id = pool_box.epoch_counter();
+    let min_start_height = height.saturating_sub(epoch_length);
+    let posted_boxes = datapoint_boxes_source.get_posted_datapoint_boxes()?;
+    let posted_total = posted_boxes.len();
+    let posted_wrong_epoch = posted_boxes
+        .iter()
+        .filter(|b| b.epoch_counter() != pool_box_epoch_id)
+        .count();
+    let posted_too_old = posted_boxes
+        .iter()
+        .filter(|b| {
+            b.epoch_counter() == pool_box_epoch_id
+                && b.get_box().creation_height <= min_start_height.0
+        })
+        .count();
+    let in_epoch_rates: Vec<_> = posted_boxes
+        .iter()
+        .filter(|b| {
+            b.get_box().creation_height > min_start_height.0
+                && b.epoch_counter() == pool_box_epoch_id
+        })
+        .map(|b| b.rate())
+        .collect();
+    let posted_in_epoch = in_epoch_rates.len();
+    // the refresh fails in the same way if too few datapoints remain after outlier removal
+    let posted_within_deviation =
+        filtered_oracle_boxes_by_rate(in_epoch_rates, max_deviation_percent).len();
+
+    let my_datapoint = match local_datapoint_box_source.get_local_oracle_datapoint_box()? {
+        None => MyDatapointStatus::Missing,
+        Some(OracleBoxWrapper::Collected(_)) => MyDatapointStatus::Collected,
+        Some(OracleBoxWrapper::Posted(posted_box)) => {
+            let box_height = BlockHeight(posted_box.get_box().creation_height);
+            if posted_box.epoch_counter() == pool_box_epoch_id && box_height > min_start_height {
+                MyDatapointStatus::InEpoch
+            } else {
+                MyDatapointStatus::Stale {
+                    epoch_id: posted_box.epoch_counter(),
+                    height: box_height,
+                }
+            }
+        }
+    };
+
+    Ok(PoolDiagnosis {
+        current_height: height,
+        pool_box_height: BlockHeight(pool_box.get_box().creation_height),
+        pool_box_epoch_id,
+        epoch_length,
+        min_data_points,
+        max_deviation_percent,
+        posted_total,
+        posted_wrong_epoch,
+        posted_too_old,
+        posted_in_epoch,
+        posted_within_deviation,
+        reward_tokens_in_pool_box: *pool_box.reward_token().amount.as_u64(),
+        my_datapoint,
+    })
+}
+
+pub fn recover_pool(
+    op: &OraclePool,
+    node_api: &NodeApi,
+    datapoint_source: &RuntimeDataPointSource,
+    height: BlockHeight,
+) -> Result<(), anyhow::Error> {
+    let refresh_contract_parameters = POOL_CONFIG
+        .refresh_box_wrapper_inputs
+        .contract_inputs
+        .contract_parameters();
+    let diagnosis = diagnose_pool(
+        op.get_pool_box_source(),
+        op.get_posted_datapoint_boxes_source(),
+        op.get_local_datapoint_box_source(),
+        refresh_contract_parameters.epoch_length(),
+        refresh_contract_parameters.max_deviation_percent() as u32,
+        refresh_contract_parameters.min_data_points(),
+        height,
+    )?;
+    print_diagnosis(&diagnosis);
+
+    let cmd = match diagnosis.corrective_command() {
+        Some(cmd) => cmd,
+        None => {
+            println!("No corrective transaction can be built by this oracle right now.");
+            return Ok(());
+        }
+    };
+    let change_address = node_api.get_change_address()?;
+    let (action, _) = build_action(
+        cmd,
+        op,
+        node_api,
+        height,
+        change_address.address(),
+        datapoint_source,
+    )?;
+    println!(
+        "Corrective transaction outputs:\n{}",
+        format_tx_outputs(
+            action.tx(),
+            &change_address.address(),
+            change_address.network()
+        )
+    );
+    println!("TYPE 'YES' TO SUBMIT THE CORRECTIVE TRANSACTION.");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    if input.trim() == "YES" {
+        execute_action(action, node_api, None, None)?;
+    } else {
+        println!("Aborting the transaction.")
+    }
+    Ok(())
+}
+
+fn print_diagnosis(diagnosis: &PoolDiagnosis) {
+    println!("Current height: {}", diagnosis.current_height);
+    println!(
+        "Pool box: epoch {}, height {} (epoch length {}, {})",
+        diagnosis.pool_box_epoch_id.0,
+        diagnosis.pool_box_height,
+        diagnosis.epoch_length.0,
+        if diagnosis.is_epoch_over() {
+            "refresh is due"
+        } else {
+            "epoch in progress"
+        }
+    );
+    println!(
+        "Posted datapoints: {} total, {} for the current epoch, {} within {}% deviation (min {})",
+        diagnosis.posted_total,
+        diagnosis.posted_in_epoch,
+        diagnosis.posted_within_deviation,
+        diagnosis.max_deviation_percent,
+        diagnosis.min_data_points.0
+    );
+    println!(
+        "Reward tokens in pool box: {} (refresh needs {})",
+        diagnosis.reward_tokens_in_pool_box,
+        diagnosis.reward_tokens_required()
+    );
+    println!("Our datapoint: {:?}", diagnosis.my_datapoint);
+    let problems = diagnosis.problems();
+    if problems.is_empty() {
+        println!("No problems found");
+    } else {
+        println!("Problems found:");
+        for problem in problems {
+            println!("  - {}", problem);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::box_kind::{OracleBoxWrapperInputs, PostedOracleBox};
+    use crate::contracts::oracle::OracleContractParameters;
+    use crate::contracts::pool::PoolContractParameters;
+    use crate::oracle_config::BASE_FEE;
+    use crate::pool_commands::test_utils::{
+        generate_token_ids, make_datapoint_box, make_pool_box, OracleBoxMock, PoolBoxMock,
+    };
+    use ergo_lib::ergo_chain_types::EcPoint;
+    use sigma_test_util::force_any_val;
+
+    struct DatapointSourceMock {
+        datapoints: Vec<PostedOracleBox>,
+    }
+
+    impl PostedDatapointBoxesSource for DatapointSourceMock {
+        fn get_posted_datapoint_boxes(
+            &self,
+        ) -> std::result::Result<Vec<PostedOracleBox>, DataSourceError> {
+            Ok(self.datapoints.clone())
+        }
+    }
+
+    #[test]
+    fn test_diagnose_pool() {
+        let height = BlockHeight(1000);
+        let token_ids = generate_token_ids();
+        let pool_box_epoch_id = EpochCounter(5);
+        let pool_box_mock = PoolBoxMock {
+            pool_box: make_pool_box(
+                200,
+                pool_box_epoch_id,
+                *BASE_FEE,
+                height - EpochLength(40),
+                &PoolContractParameters::default(),
+                &token_ids,
+            ),
+        };
+        let oracle_box_wrapper_inputs =
+            OracleBoxWrapperInputs::try_from((OracleContractParameters::default(), &token_ids))
+                .unwrap();
+        let my_pk = force_any_val::<EcPoint>();
+        let make_posted = |pk: EcPoint, rate: i64, epoch: EpochCounter, h: BlockHeight| {
+            PostedOracleBox::new(
+                make_datapoint_box(pk, rate, epoch, &token_ids, *BASE_FEE, h, 1),
+                &oracle_box_wrapper_inputs,
+            )
+            .unwrap()
+        };
+        let datapoints = vec![
+            make_posted(my_pk.clone(), 199, pool_box_epoch_id, height - 5),
+            make_posted(force_any_val(), 198, pool_box_epoch_id, height - 5),
+            make_posted(force_any_val(), 70, pool_box_epoch_id, height - 5),
+            make_posted(force_any_val(), 197, pool_box_epoch_id, height - 50),
+            make_posted(force_any_val(), 196, EpochCounter(4), height - 5),
+        ];
+        let datapoint_source = DatapointSourceMock {
+            datapoints: datapoints.clone(),
+        };
+        let local_box_mock = OracleBoxMock {
+            oracle_box: OracleBoxWrapper::Posted(datapoints[0].clone()),
+        };
+
+        let diagnosis = diagnose_pool(
+            &pool_box_mock,
+            &datapoint_source,
+            &local_box_mock,
+            EpochLength(30),
+            5,
+            MinDatapoints(3),
+            height,
+        )
+        .unwrap();
+        assert_eq!(diagnosis.posted_total, 5);
+        assert_eq!(diagnosis.posted_wrong_epoch, 1);
+        assert_eq!(diagnosis.posted_too_old, 1);
+        assert_eq!(diagnosis.posted_in_epoch, 3);
+        assert_eq!(diagnosis.posted_within_deviation, 2);
+        assert_eq!(diagnosis.my_datapoint, MyDatapointStatus::InEpoch);
+        assert!(diagnosis.is_epoch_over());
+        assert_eq!(diagnosis.problems().len(), 1);
+        assert!(diagnosis.corrective_command().is_none());
+
+        let stale_local_box_mock = OracleBoxMock {
+            oracle_box: OracleBoxWrapper::Posted(datapoints[4].clone()),
+        };
+        let diagnosis = diagnose_pool(
+            &pool_box_mock,
+            &datapoint_source,
+            &stale_local_box_mock,
+            EpochLength(30),
+            5,
+            MinDatapoints(3),
+            height,
+        )
+        .unwrap();
+        assert!(matches!(
+            diagnosis.my_datapoint,
+            MyDatapointStatus::Stale { .. }
+        ));
+        assert!(matches!(
+            diagnosis.corrective_command(),
+            Some(PoolCommand::PublishSubsequentDataPoint { republish: true })
+        ));
+    }
+
+    #[test]
+    fn test_diagnose_pool_on_chain_shorter_than_epoch() {
+        let height = BlockHeight(20);
+        let token_ids = generate_token_ids();
+        let pool_box_epoch_id = EpochCounter(1);
+        let pool_box_mock = PoolBoxMock {
+            pool_box: make_pool_box(
+                200,
+                pool_box_epoch_id,
+                *BASE_FEE,
+                BlockHeight(10),
+                &PoolContractParameters::default(),
+                &token_ids,
+            ),
+        };
+        let oracle_box_wrapper_inputs =
+            OracleBoxWrapperInputs::try_from((OracleContractParameters::default(), &token_ids))
+                .unwrap();
+        let my_box = PostedOracleBox::new(
+            make_datapoint_box(
+                force_any_val(),
+                199,
+                pool_box_epoch_id,
+                &token_ids,
+                *BASE_FEE,
+                BlockHeight(15),
+                1,
+            ),
+            &oracle_box_wrapper_inputs,
+        )
+        .unwrap();
+        let diagnosis = diagnose_pool(
+            &pool_box_mock,
+            &DatapointSourceMock {
+                datapoints: vec![my_box.clone()],
+            },
+            &OracleBoxMock {
+                oracle_box: OracleBoxWrapper::Posted(my_box),
+            },
+            EpochLength(30),
+            5,
+            MinDatapoints(3),
+            height,
+        )
+        .unwrap();
+        assert!(!diagnosis.is_epoch_over());
+        assert_eq!(diagnosis.posted_in_epoch, 1);
+        assert_eq!(diagnosis.my_datapoint, MyDatapointStatus::InEpoch);
+    }
+}
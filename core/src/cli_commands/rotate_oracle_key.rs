@@ -0,0 +1,158 @@
+//! Moves the oracle token into a new datapoint box tied to another key of the node wallet and
+//! switches the oracle config to it, so that a compromised oracle key can be replaced without
+//! the pool admin.
+
+use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::address::AddressEncoder;
+use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use thiserror::Error;
+
+use crate::cli_commands::transfer_oracle_token::build_transfer_oracle_token_tx;
+use crate::cli_commands::transfer_oracle_token::TransferRewards;
+use crate::explorer_api::ergo_explorer_transaction_link;
+use crate::node_interface::node_api::NodeApi;
+use crate::oracle_config::ORACLE_CONFIG;
+use crate::oracle_config::ORACLE_CONFIG_FILE_PATH;
+use crate::oracle_state::LocalBallotBoxSource;
+use crate::oracle_state::LocalDatapointBoxSource;
+use crate::oracle_types::BlockHeight;
+use crate::tx_summary::format_tx_outputs;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RotateOracleKeyError {
+    #[error("new oracle address must be P2PK")]
+    NotP2Pk,
+    #[error("new oracle address is the current oracle address")]
+    SameAddress,
+    #[error("new oracle address {0} is not in the node wallet")]
+    NotInWallet(String),
+}
+
+/// The new key must differ from the current one and its secret must be in the node wallet,
+/// otherwise the oracle couldn't spend its datapoint box anymore
+pub fn check_new_oracle_address(
+    current_address: &NetworkAddress,
+    new_address: &NetworkAddress,
+    wallet_addresses: &[String],
+) -> Result<(), RotateOracleKeyError> {
+    if !matches!(new_address.address(), Address::P2Pk(_)) {
+        return Err(RotateOracleKeyError::NotP2Pk);
+    }
+    if new_address.address() == current_address.address() {
+        return Err(RotateOracleKeyError::SameAddress);
+    }
+    let new_address_str = new_address.to_base58();
+    if !wallet_addresses.contains(&new_address_str) {
+        return Err(RotateOracleKeyError::NotInWallet(new_address_str));
+    }
+    Ok(())
+}
+
+pub fn rotate_oracle_key(
+    node_api: &NodeApi,
+    local_datapoint_box_source: &dyn LocalDatapointBoxSource,
+    local_ballot_box_source: &dyn LocalBallotBoxSource,
+    new_oracle_address_str: String,
+    height: BlockHeight,
+) -> Result<(), anyhow::Error> {
+    let new_oracle_address =
+        AddressEncoder::unchecked_parse_network_address_from_str(&new_oracle_address_str)?;
+    check_new_oracle_address(
+        &ORACLE_CONFIG.oracle_address,
+        &new_oracle_address,
+        &node_api.wallet_addresses()?,
+    )?;
+    let (change_address, network_prefix) = {
+        let net_address = node_api.get_change_address()?;
+        (net_address.address(), net_address.network())
+    };
+    let unsigned_tx = build_transfer_oracle_token_tx(
+        local_datapoint_box_source,
+        node_api,
+        new_oracle_address.address(),
+        TransferRewards::ToNewOperator,
+        height,
+        change_address.clone(),
+        None,
+    )?;
+    println!(
+        "Transaction outputs:\n{}",
+        format_tx_outputs(&unsigned_tx, &change_address, network_prefix)
+    );
+
+    if local_ballot_box_source.get_ballot_box()?.is_some() {
+        println!(
+            "WARNING: the ballot token stays with the current key. Vote with it (`vote-update-pool`) \
+            before the rotation if you want to keep voting."
+        );
+    }
+    println!(
+        "YOU WILL BE MOVING YOUR ORACLE TOKEN AND REWARD TOKENS TO {}. \
+        TYPE 'YES' TO INITIATE THE TRANSACTION.",
+        new_oracle_address_str
+    );
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    if input.trim() != "YES" {
+        println!("Aborting the transaction.");
+        return Ok(());
+    }
+    let tx_id = node_api.sign_and_submit_transaction(&unsigned_tx)?;
+    crate::explorer_api::wait_for_tx_confirmation(tx_id);
+    println!(
+        "Transaction made. Check status here: {}",
+        ergo_explorer_transaction_link(tx_id, network_prefix)
+    );
+
+    let mut new_oracle_config = ORACLE_CONFIG.clone();
+    new_oracle_config.oracle_address = new_oracle_address;
+    let oracle_config_path = ORACLE_CONFIG_FILE_PATH.get().unwrap();
+    new_oracle_config.save(oracle_config_path)?;
+    println!(
+        "oracle_address in {} is set to {}. Restart the oracle to use the new key.",
+        oracle_config_path.display(),
+        new_oracle_address_str
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInput;
+    use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+
+    fn p2pk_address() -> NetworkAddress {
+        let secret = force_any_val::<DlogProverInput>();
+        NetworkAddress::new(
+            NetworkPrefix::Mainnet,
+            &Address::P2Pk(secret.public_image()),
+        )
+    }
+
+    #[test]
+    fn test_check_new_oracle_address() {
+        let current = p2pk_address();
+        let new = p2pk_address();
+        let wallet_addresses = vec![current.to_base58(), new.to_base58()];
+        assert_eq!(
+            check_new_oracle_address(&current, &new, &wallet_addresses),
+            Ok(())
+        );
+        assert_eq!(
+            check_new_oracle_address(&current, &current, &wallet_addresses),
+            Err(RotateOracleKeyError::SameAddress)
+        );
+        let p2s = NetworkAddress::new(NetworkPrefix::Mainnet, &Address::P2S(vec![0x00, 0x7f]));
+        assert_eq!(
+            check_new_oracle_address(&current, &p2s, &wallet_addresses),
+            Err(RotateOracleKeyError::NotP2Pk)
+        );
+        assert_eq!(
+            check_new_oracle_address(&current, &new, &[current.to_base58()]),
+            Err(RotateOracleKeyError::NotInWallet(new.to_base58()))
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! `state export` / `state import` commands. The oracle's local state (the scan ids, epoch
+//! archive, fee ledger, datapoint audit log, retry queue and the other data dir files) is bundled
+//! with the checksums of the config files into a single JSON snapshot, to move the oracle to
+//! another host or to restore it from a backup.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use ergo_lib::ergo_chain_types::blake2b256_hash;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::datapoint_audit;
+use crate::fee_ledger::FeeLedger;
+use crate::oracle_config::ORACLE_CONFIG_FILE_PATH;
+use crate::pool_config::POOL_CONFIG_FILE_PATH;
+use crate::reconciliation::snapshot_file_path;
+use crate::refresh_debug::RefreshDebugBundle;
+use crate::scans::get_scans_file_path;
+use crate::state_store;
+use crate::state_store::StateKey;
+use crate::state_store::StateStore;
+use crate::state_store::StateStoreError;
+
+const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum StateSnapshotError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("state store error: {0}")]
+    StateStore(#[from] StateStoreError),
+    #[error("invalid state snapshot: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unsupported state snapshot format version {0}")]
+    UnsupportedFormatVersion(u32),
+    #[error("unknown file {0} in the state snapshot")]
+    UnknownFile(String),
+    #[error("{0} differs from the exported one, use --force to import anyway")]
+    ConfigMismatch(String),
+    #[error("{0} already exists in the data dir, use --force to overwrite it")]
+    FileExists(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub format_version: u32,
+    /// oracle-core version that exported the state
+    pub app_version: String,
+    /// Contents of the data dir files by file name
+    pub files: BTreeMap<String, String>,
+    /// Base16-encoded blake2b256 hashes of the config files by the config name
+    pub config_checksums: BTreeMap<String, String>,
+}
+
+/// Data dir files holding the oracle's state besides the state store values, the missing ones are
+/// skipped
+fn state_file_paths() -> Vec<PathBuf> {
+    vec![
+        get_scans_file_path(),
+        FeeLedger::file_path(),
+        datapoint_audit::file_path(),
+        snapshot_file_path(),
+        RefreshDebugBundle::file_path(),
+    ]
+}
+
+fn config_paths() -> Vec<(&'static str, PathBuf)> {
+    vec![
+        (
+            "oracle_config",
+            ORACLE_CONFIG_FILE_PATH.get().unwrap().clone(),
+        ),
+        ("pool_config", POOL_CONFIG_FILE_PATH.get().unwrap().clone()),
+    ]
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn checksum(path: &Path) -> Result<String, std::io::Error> {
+    Ok(base16::encode_lower(&blake2b256_hash(&std::fs::read(
+        path,
+    )?)))
+}
+
+/// Bundles the state files and the state store values (under their `files` backend file names)
+fn build_snapshot(
+    state_files: &[PathBuf],
+    state_store: &dyn StateStore,
+    configs: &[(&str, PathBuf)],
+) -> Result<StateSnapshot, StateSnapshotError> {
+    let mut files = BTreeMap::new();
+    for path in state_files.iter().filter(|path| path.exists()) {
+        files.insert(file_name(path), std::fs::read_to_string(path)?);
+    }
+    for key in StateKey::ALL {
+        if let Some(value) = state_store.get(key)? {
+            files.insert(key.file_name().to_string(), value);
+        }
+    }
+    let mut config_checksums = BTreeMap::new();
+    for (name, path) in configs {
+        config_checksums.insert(name.to_string(), checksum(path)?);
+    }
+    Ok(StateSnapshot {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        files,
+        config_checksums,
+    })
+}
+
+/// Writes the snapshot files to their paths and the state store values to the store. Refuses to
+/// overwrite the existing files (or values) or to import under the configs different from the
+/// exported ones unless `force` is set.
+fn restore_snapshot(
+    snapshot: &StateSnapshot,
+    state_files: &[PathBuf],
+    state_store: &dyn StateStore,
+    configs: &[(&str, PathBuf)],
+    force: bool,
+) -> Result<usize, StateSnapshotError> {
+    if snapshot.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(StateSnapshotError::UnsupportedFormatVersion(
+            snapshot.format_version,
+        ));
+    }
+    let mut targets = Vec::new();
+    let mut store_values = Vec::new();
+    for (name, content) in &snapshot.files {
+        if let Some(key) = StateKey::from_file_name(name) {
+            if state_store.get(key)?.is_some() && !force {
+                return Err(StateSnapshotError::FileExists(name.clone()));
+            }
+            store_values.push((key, content));
+            continue;
+        }
+        let path = state_files
+            .iter()
+            .find(|path| file_name(path) == *name)
+            .ok_or_else(|| StateSnapshotError::UnknownFile(name.clone()))?;
+        if path.exists() && !force {
+            return Err(StateSnapshotError::FileExists(name.clone()));
+        }
+        targets.push((path, content));
+    }
+    if !force {
+        for (name, path) in configs {
+            let exported = snapshot.config_checksums.get(*name);
+            if exported != Some(&checksum(path)?) {
+                return Err(StateSnapshotError::ConfigMismatch(file_name(path)));
+            }
+        }
+    }
+    for (path, content) in &targets {
+        std::fs::write(path, content)?;
+    }
+    for (key, content) in &store_values {
+        state_store.put(*key, content)?;
+    }
+    Ok(targets.len() + store_values.len())
+}
+
+pub fn export_state(output: &Path) -> Result<(), anyhow::Error> {
+    let state_store = state_store::open_configured()?;
+    let snapshot = build_snapshot(&state_file_paths(), state_store.as_ref(), &config_paths())?;
+    std::fs::write(output, serde_json::to_string_pretty(&snapshot)?)?;
+    println!(
+        "Exported {} state files to {}",
+        snapshot.files.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Restores the exported state into the data dir. Should be run while the oracle is stopped.
+pub fn import_state(input: &Path, force: bool) -> Result<(), anyhow::Error> {
+    let snapshot: StateSnapshot = serde_json::from_str(&std::fs::read_to_string(input)?)?;
+    let state_store = state_store::open_configured()?;
+    let imported = restore_snapshot(
+        &snapshot,
+        &state_file_paths(),
+        state_store.as_ref(),
+        &config_paths(),
+        force,
+    )?;
+    println!(
+        "Imported {} state files exported by oracle-core v{}",
+        imported, snapshot.app_version
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_store::InMemoryStateStore;
+
+    #[test]
+    fn test_build_and_restore_snapshot() {
+        let dir = std::env::temp_dir().join("oracle_core_test_state_snapshot");
+        std::fs::create_dir_all(&dir).unwrap();
+        let scans = dir.join("scanIDs.json");
+        let ledger = dir.join("fee_ledger.json");
+        let config = dir.join("oracle_config.yaml");
+        std::fs::write(&scans, r#"{"instance": "erg-usd"}"#).unwrap();
+        std::fs::write(&config, "node_url: http://127.0.0.1:9053").unwrap();
+        let state_files = vec![scans.clone(), ledger.clone()];
+        let configs = vec![("oracle_config", config.clone())];
+        let exported_store = InMemoryStateStore::default();
+        exported_store
+            .put(StateKey::LastPostedEpoch, r#"{"epoch_id":42}"#)
+            .unwrap();
+
+        let snapshot = build_snapshot(&state_files, &exported_store, &configs).unwrap();
+        // missing files are skipped, the store values are bundled under their file names
+        assert_eq!(snapshot.files.len(), 2);
+        assert!(snapshot.files.contains_key("last_posted_epoch.json"));
+
+        let store = InMemoryStateStore::default();
+        assert!(matches!(
+            restore_snapshot(&snapshot, &state_files, &store, &configs, false),
+            Err(StateSnapshotError::FileExists(_))
+        ));
+        std::fs::remove_file(&scans).unwrap();
+        assert_eq!(
+            restore_snapshot(&snapshot, &state_files, &store, &configs, false).unwrap(),
+            2
+        );
+        assert_eq!(
+            std::fs::read_to_string(&scans).unwrap(),
+            r#"{"instance": "erg-usd"}"#
+        );
+        assert_eq!(
+            store.get(StateKey::LastPostedEpoch).unwrap().as_deref(),
+            Some(r#"{"epoch_id":42}"#)
+        );
+
+        // the store value is not overwritten either
+        std::fs::remove_file(&scans).unwrap();
+        assert!(matches!(
+            restore_snapshot(&snapshot, &state_files, &store, &configs, false),
+            Err(StateSnapshotError::FileExists(_))
+        ));
+
+        std::fs::write(&config, "node_url: http://10.0.0.1:9053").unwrap();
+        assert!(matches!(
+            restore_snapshot(
+                &snapshot,
+                &state_files,
+                &InMemoryStateStore::default(),
+                &configs,
+                false
+            ),
+            Err(StateSnapshotError::ConfigMismatch(_))
+        ));
+        assert!(restore_snapshot(&snapshot, &state_files, &store, &configs, true).is_ok());
+
+        let unknown_file = StateSnapshot {
+            files: BTreeMap::from([("../id_rsa".to_string(), String::new())]),
+            ..snapshot
+        };
+        assert!(matches!(
+            restore_snapshot(&unknown_file, &state_files, &store, &configs, true),
+            Err(StateSnapshotError::UnknownFile(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
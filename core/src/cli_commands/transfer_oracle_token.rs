@@ -2,16 +2,20 @@ use std::convert::TryInto;
 
 use ergo_lib::{
     chain::{
-        ergo_box::box_builder::ErgoBoxCandidateBuilderError,
+        ergo_box::box_builder::{ErgoBoxCandidateBuilder, ErgoBoxCandidateBuilderError},
+        ergo_state_context::ErgoStateContext,
         transaction::unsigned::UnsignedTransaction,
     },
     ergotree_interpreter::sigma_protocol::prover::ContextExtension,
     ergotree_ir::{
-        chain::address::{Address, AddressEncoder, AddressEncoderError},
+        chain::{
+            address::{Address, AddressEncoder, AddressEncoderError},
+            token::Token,
+        },
         serialization::SigmaParsingError,
     },
     wallet::{
-        box_selector::{BoxSelection, BoxSelector, BoxSelectorError, SimpleBoxSelector},
+        box_selector::{BoxSelection, BoxSelector, BoxSelectorError},
         tx_builder::{TxBuilder, TxBuilderError},
     },
 };
@@ -22,14 +26,29 @@ use crate::{
     box_kind::{
         make_collected_oracle_box_candidate, make_oracle_box_candidate, OracleBox, OracleBoxWrapper,
     },
+    ergopay::print_ergopay_request,
     explorer_api::ergo_explorer_transaction_link,
     node_interface::{SignTransaction, SubmitTransaction},
-    oracle_config::BASE_FEE,
+    oracle_config::{BASE_FEE, ORACLE_CONFIG},
     oracle_state::{DataSourceError, LocalDatapointBoxSource},
     oracle_types::BlockHeight,
-    wallet::{WalletDataError, WalletDataSource},
+    spec_token::SpecToken,
+    tx_summary::format_tx_outputs,
+    wallet::{configured_box_selector, WalletDataError, WalletDataSource},
 };
 
+/// What to do with the reward tokens accumulated in the oracle box on transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferRewards {
+    /// Oracle box must hold exactly 1 reward token (extract them beforehand)
+    RequireExtracted,
+    /// Move all accumulated reward tokens along with the oracle token to the new operator
+    ToNewOperator,
+    /// Send accumulated reward tokens (all but 1) to the old operator's reward beneficiary
+    /// address, or to its change address if it's not set
+    RetainToOldOperator,
+}
+
 #[derive(Debug, Error)]
 pub enum TransferOracleTokenActionError {
     #[error(
@@ -63,13 +82,16 @@ pub enum TransferOracleTokenActionError {
     WalletData(#[from] WalletDataError),
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn transfer_oracle_token(
     wallet: &dyn WalletDataSource,
     tx_signer: &dyn SignTransaction,
     tx_submit: &dyn SubmitTransaction,
     local_datapoint_box_source: &dyn LocalDatapointBoxSource,
     rewards_destination_str: String,
+    transfer_rewards: TransferRewards,
     height: BlockHeight,
+    ergopay_state_context: Option<ErgoStateContext>,
 ) -> Result<(), anyhow::Error> {
     let rewards_destination =
         AddressEncoder::unchecked_parse_network_address_from_str(&rewards_destination_str)?;
@@ -77,14 +99,49 @@ pub fn transfer_oracle_token(
         let net_address = wallet.get_change_address()?;
         (net_address.address(), net_address.network())
     };
+    let reward_beneficiary = ORACLE_CONFIG
+        .reward_beneficiary_address
+        .as_ref()
+        .map(|a| a.address());
     let unsigned_tx = build_transfer_oracle_token_tx(
         local_datapoint_box_source,
         wallet,
         rewards_destination.address(),
+        transfer_rewards,
         height,
-        change_address,
+        change_address.clone(),
+        reward_beneficiary.clone(),
     )?;
 
+    println!(
+        "Transaction outputs:\n{}",
+        format_tx_outputs(&unsigned_tx, &change_address, network_prefix)
+    );
+    match transfer_rewards {
+        TransferRewards::RequireExtracted => (),
+        TransferRewards::ToNewOperator => {
+            println!("Accumulated reward tokens will be transferred to the new operator.")
+        }
+        TransferRewards::RetainToOldOperator => match &ORACLE_CONFIG.reward_beneficiary_address {
+            Some(address) => println!(
+                "Accumulated reward tokens will be sent to your reward beneficiary address {}.",
+                address.to_base58()
+            ),
+            None => println!("Accumulated reward tokens will be sent to your change address."),
+        },
+    }
+    if let Some(state_context) = ergopay_state_context {
+        let mut available_boxes = wallet.get_unspent_wallet_boxes()?;
+        if let Some(oracle_box) = local_datapoint_box_source.get_local_oracle_datapoint_box()? {
+            available_boxes.push(oracle_box.get_box().clone());
+        }
+        println!(
+            "The transaction transfers your oracle token to {}.",
+            rewards_destination_str
+        );
+        print_ergopay_request(&unsigned_tx, &available_boxes, &state_context)?;
+        return Ok(());
+    }
     println!(
         "YOU WILL BE TRANSFERRING YOUR ORACLE TOKEN TO {}. TYPE 'YES' TO INITIATE THE TRANSACTION.",
         rewards_destination_str
@@ -104,24 +161,38 @@ pub fn transfer_oracle_token(
     }
     Ok(())
 }
-fn build_transfer_oracle_token_tx(
+
+pub(crate) fn build_transfer_oracle_token_tx(
     local_datapoint_box_source: &dyn LocalDatapointBoxSource,
     wallet: &dyn WalletDataSource,
     oracle_token_destination: Address,
+    transfer_rewards: TransferRewards,
     height: BlockHeight,
     change_address: Address,
+    reward_beneficiary: Option<Address>,
 ) -> Result<UnsignedTransaction, TransferOracleTokenActionError> {
     let in_oracle_box = local_datapoint_box_source
         .get_local_oracle_datapoint_box()?
         .ok_or(TransferOracleTokenActionError::NoLocalDatapointBox)?;
     let num_reward_tokens = *in_oracle_box.reward_token().amount.as_u64();
-    if num_reward_tokens != 1 {
+    if num_reward_tokens != 1 && transfer_rewards == TransferRewards::RequireExtracted {
         return Err(
             TransferOracleTokenActionError::IncorrectNumberOfRewardTokensInOracleBox(
                 num_reward_tokens as usize,
             ),
         );
     }
+    // Reward tokens kept by the old operator, the oracle box must always hold at least 1
+    let retained_reward_tokens = match transfer_rewards {
+        TransferRewards::RetainToOldOperator => num_reward_tokens - 1,
+        TransferRewards::RequireExtracted | TransferRewards::ToNewOperator => 0,
+    };
+    let out_reward_token = SpecToken {
+        token_id: in_oracle_box.reward_token().token_id,
+        amount: (num_reward_tokens - retained_reward_tokens)
+            .try_into()
+            .unwrap(),
+    };
     if let Address::P2Pk(p2pk_dest) = &oracle_token_destination {
         let oracle_box_candidate =
             if let OracleBoxWrapper::Posted(ref posted_oracle_box) = in_oracle_box {
@@ -131,7 +202,7 @@ fn build_transfer_oracle_token_tx(
                     posted_oracle_box.rate(),
                     posted_oracle_box.epoch_counter(),
                     posted_oracle_box.oracle_token(),
-                    posted_oracle_box.reward_token(),
+                    out_reward_token,
                     posted_oracle_box.get_box().value,
                     height,
                 )?
@@ -140,17 +211,35 @@ fn build_transfer_oracle_token_tx(
                     in_oracle_box.contract(),
                     *p2pk_dest.h.clone(),
                     in_oracle_box.oracle_token(),
-                    in_oracle_box.reward_token(),
+                    out_reward_token,
                     in_oracle_box.get_box().value,
                     height,
                 )?
             };
 
-        let unspent_boxes = wallet.get_unspent_wallet_boxes()?;
+        let mut output_candidates = vec![oracle_box_candidate];
+        if retained_reward_tokens > 0 {
+            // Box to hold the reward tokens retained by the old operator
+            let retained_rewards_address = reward_beneficiary.unwrap_or(change_address.clone());
+            let mut builder = ErgoBoxCandidateBuilder::new(
+                *BASE_FEE,
+                retained_rewards_address.script()?,
+                height.0,
+            );
+            builder.add_token(Token {
+                token_id: in_oracle_box.reward_token().token_id(),
+                amount: retained_reward_tokens.try_into().unwrap(),
+            });
+            output_candidates.push(builder.build()?);
+        }
 
-        let target_balance = *BASE_FEE;
+        let unspent_boxes = wallet.get_unspent_wallet_boxes()?;
+        // `BASE_FEE` for the fee and for the box holding the retained reward tokens (if any).
+        let target_balance = BASE_FEE
+            .checked_mul_u32(output_candidates.len() as u32)
+            .unwrap();
 
-        let box_selector = SimpleBoxSelector::new();
+        let box_selector = configured_box_selector();
         let selection = box_selector.select(unspent_boxes, target_balance, &[])?;
         let mut input_boxes = vec![in_oracle_box.get_box().clone()];
         input_boxes.append(selection.boxes.as_vec().clone().as_mut());
@@ -160,9 +249,9 @@ fn build_transfer_oracle_token_tx(
         };
         let mut tx_builder = TxBuilder::new(
             box_selection,
-            vec![oracle_box_candidate],
+            output_candidates,
             height.0,
-            target_balance,
+            *BASE_FEE,
             change_address,
         );
         // The following context value ensures that `outIndex` in the oracle contract is properly set.
@@ -242,8 +331,10 @@ mod tests {
             &local_datapoint_box_source,
             &wallet_mock,
             change_address.address(),
+            TransferRewards::RequireExtracted,
             height,
             change_address.address(),
+            None,
         )
         .unwrap();
 
@@ -264,4 +355,130 @@ mod tests {
 
         let _signed_tx = wallet.sign_transaction(tx_context, &ctx, None).unwrap();
     }
+
+    #[test]
+    fn test_transfer_oracle_datapoint_with_rewards() {
+        let ctx = force_any_val::<ErgoStateContext>();
+        let height = BlockHeight(ctx.pre_header.height);
+        let token_ids = generate_token_ids();
+        let secret = force_any_val::<DlogProverInput>();
+        let wallet = Wallet::from_secrets(vec![secret.clone().into()]);
+        let oracle_pub_key = secret.public_image().h;
+
+        let parameters = OracleContractParameters::default();
+        let oracle_box_wrapper_inputs =
+            OracleBoxWrapperInputs::try_from((parameters, &token_ids)).unwrap();
+        let oracle_box = OracleBoxWrapper::new(
+            make_datapoint_box(
+                *oracle_pub_key,
+                200,
+                EpochCounter(1),
+                &token_ids,
+                BASE_FEE.checked_mul_u32(100).unwrap(),
+                BlockHeight(height.0) - 9,
+                5,
+            ),
+            &oracle_box_wrapper_inputs,
+        )
+        .unwrap();
+        let local_datapoint_box_source = OracleBoxMock { oracle_box };
+
+        let change_address = AddressEncoder::unchecked_parse_network_address_from_str(
+            "9iHyKxXs2ZNLMp9N9gbUT9V8gTbsV7HED1C1VhttMfBUMPDyF7r",
+        )
+        .unwrap();
+
+        let wallet_unspent_box = make_wallet_unspent_box(
+            secret.public_image(),
+            BASE_FEE.checked_mul_u32(10000).unwrap(),
+            None,
+        );
+        let wallet_mock = WalletDataMock {
+            unspent_boxes: vec![wallet_unspent_box],
+            change_address: change_address.clone(),
+        };
+
+        assert!(matches!(
+            build_transfer_oracle_token_tx(
+                &local_datapoint_box_source,
+                &wallet_mock,
+                change_address.address(),
+                TransferRewards::RequireExtracted,
+                height,
+                change_address.address(),
+                None,
+            ),
+            Err(TransferOracleTokenActionError::IncorrectNumberOfRewardTokensInOracleBox(5))
+        ));
+
+        let reward_token_id = token_ids.reward_token_id.token_id();
+        let reward_tokens_in_output = |tx: &UnsignedTransaction, index: usize| {
+            tx.output_candidates.as_vec()[index]
+                .tokens
+                .as_ref()
+                .and_then(|tokens| {
+                    tokens
+                        .iter()
+                        .find(|t| t.token_id == reward_token_id)
+                        .cloned()
+                })
+                .map(|t| *t.amount.as_u64())
+                .unwrap_or(0)
+        };
+
+        for (transfer_rewards, expected_in_oracle_box, expected_retained) in [
+            (TransferRewards::ToNewOperator, 5, None),
+            (TransferRewards::RetainToOldOperator, 1, Some(4)),
+        ] {
+            let tx = build_transfer_oracle_token_tx(
+                &local_datapoint_box_source,
+                &wallet_mock,
+                change_address.address(),
+                transfer_rewards,
+                height,
+                change_address.address(),
+                None,
+            )
+            .unwrap();
+            assert_eq!(reward_tokens_in_output(&tx, 0), expected_in_oracle_box);
+            if let Some(expected_retained) = expected_retained {
+                assert_eq!(reward_tokens_in_output(&tx, 1), expected_retained);
+            }
+
+            let mut possible_input_boxes = vec![local_datapoint_box_source
+                .get_local_oracle_datapoint_box()
+                .unwrap()
+                .unwrap()
+                .get_box()
+                .clone()];
+            possible_input_boxes.append(&mut wallet_mock.get_unspent_wallet_boxes().unwrap());
+
+            let tx_context = TransactionContext::new(
+                tx.clone(),
+                find_input_boxes(tx, possible_input_boxes),
+                Vec::new(),
+            )
+            .unwrap();
+
+            let _signed_tx = wallet.sign_transaction(tx_context, &ctx, None).unwrap();
+        }
+
+        // retained reward tokens go to the reward beneficiary address
+        let beneficiary = Address::P2Pk(force_any_val::<DlogProverInput>().public_image());
+        let tx = build_transfer_oracle_token_tx(
+            &local_datapoint_box_source,
+            &wallet_mock,
+            change_address.address(),
+            TransferRewards::RetainToOldOperator,
+            height,
+            change_address.address(),
+            Some(beneficiary.clone()),
+        )
+        .unwrap();
+        assert_eq!(reward_tokens_in_output(&tx, 1), 4);
+        assert_eq!(
+            tx.output_candidates.as_vec()[1].ergo_tree,
+            beneficiary.script().unwrap()
+        );
+    }
 }
@@ -12,7 +12,7 @@ use ergo_lib::{
     },
     ergotree_ir::serialization::SigmaSerializable,
     wallet::{
-        box_selector::{BoxSelection, BoxSelector, BoxSelectorError, SimpleBoxSelector},
+        box_selector::{BoxSelection, BoxSelector, BoxSelectorError},
         signing::{TransactionContext, TxSigningError},
         tx_builder::{TxBuilder, TxBuilderError},
     },
@@ -36,7 +36,8 @@ use crate::{
     oracle_types::BlockHeight,
     pool_config::{PoolConfig, POOL_CONFIG},
     spec_token::{RewardTokenId, SpecToken, TokenIdKind},
-    wallet::{WalletDataError, WalletDataSource},
+    tx_summary::format_tx_outputs,
+    wallet::{configured_box_selector, WalletDataError, WalletDataSource},
 };
 use thiserror::Error;
 
@@ -118,13 +119,17 @@ pub fn update_pool(
         op.get_update_box_source(),
         new_reward_tokens.clone(),
         height,
-        change_address,
+        change_address.clone(),
         new_pool_contract,
     )?;
 
     log::debug!("Signing update pool box tx: {:#?}", tx);
     let signed_tx = tx_signer.sign_transaction(&tx.spending_tx)?;
 
+    println!(
+        "Transaction outputs:\n{}",
+        format_tx_outputs(&tx.spending_tx, &change_address, network_prefix)
+    );
     println!(
         "YOU WILL BE SUBMITTING AN UPDATE TO THE POOL CONTRACT:\
            - Hash of new pool box contract: {}",
@@ -286,7 +291,7 @@ fn build_update_pool_box_tx(
     let vote_parameters = CastBallotBoxVoteParameters {
         pool_box_address_hash: pool_box_hash,
         reward_token_opt: new_reward_tokens.clone(),
-        update_box_creation_height: update_box.get_box().creation_height as i32,
+        update_box_creation_height: BlockHeight(update_box.get_box().creation_height),
     };
     let reward_tokens = new_reward_tokens.unwrap_or_else(|| old_pool_box.reward_token());
     // Find ballot boxes that are voting for the new pool hash
@@ -364,7 +369,7 @@ fn build_update_pool_box_tx(
         } else {
             vec![]
         };
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let selection = box_selector.select(unspent_boxes, target_balance, &target_tokens)?;
     let mut input_boxes = vec![old_pool_box.get_box().clone(), update_box.get_box().clone()];
     input_boxes.extend(
@@ -2,14 +2,14 @@ use std::convert::{TryFrom, TryInto};
 
 use ergo_lib::{
     chain::{
-        ergo_box::box_builder::ErgoBoxCandidateBuilderError,
+        ergo_box::box_builder::ErgoBoxCandidateBuilderError, ergo_state_context::ErgoStateContext,
         transaction::unsigned::UnsignedTransaction,
     },
     ergo_chain_types::{Digest32, DigestNError, EcPoint},
     ergotree_interpreter::sigma_protocol::prover::ContextExtension,
     ergotree_ir::chain::address::Address,
     wallet::{
-        box_selector::{BoxSelection, BoxSelector, BoxSelectorError, SimpleBoxSelector},
+        box_selector::{BoxSelection, BoxSelector, BoxSelectorError},
         tx_builder::{TxBuilder, TxBuilderError},
     },
 };
@@ -20,6 +20,7 @@ use crate::{
     contracts::ballot::{
         BallotContract, BallotContractError, BallotContractInputs, BallotContractParameters,
     },
+    ergopay::print_ergopay_request,
     explorer_api::ergo_explorer_transaction_link,
     node_interface::{SignTransaction, SubmitTransaction},
     oracle_config::{BASE_FEE, ORACLE_CONFIG},
@@ -27,7 +28,8 @@ use crate::{
     oracle_types::BlockHeight,
     pool_config::{TokenIds, POOL_CONFIG},
     spec_token::{RewardTokenId, SpecToken, TokenIdKind},
-    wallet::{WalletDataError, WalletDataSource},
+    tx_summary::format_tx_outputs,
+    wallet::{configured_box_selector, WalletDataError, WalletDataSource},
 };
 use thiserror::Error;
 
@@ -68,6 +70,7 @@ pub fn vote_update_pool(
     update_box_creation_height: BlockHeight,
     height: BlockHeight,
     ballot_contract: &BallotContract,
+    ergopay_state_context: Option<ErgoStateContext>,
 ) -> Result<(), anyhow::Error> {
     let change_network_address = wallet.get_change_address()?;
     let network_prefix = change_network_address.network();
@@ -112,6 +115,14 @@ pub fn vote_update_pool(
             change_network_address.address(),
         )?
     };
+    println!(
+        "Transaction outputs:\n{}",
+        format_tx_outputs(
+            &unsigned_tx,
+            &change_network_address.address(),
+            network_prefix
+        )
+    );
     println!(
         "YOU WILL BE CASTING A VOTE FOR THE FOLLOWING ITEMS:\
            - Hash of new pool box contract: {}",
@@ -125,6 +136,14 @@ pub fn vote_update_pool(
             reward_token.amount.as_u64(),
         );
     }
+    if let Some(state_context) = ergopay_state_context {
+        let mut available_boxes = wallet.get_unspent_wallet_boxes()?;
+        if let Some(ballot_box) = local_ballot_box_source.get_ballot_box()? {
+            available_boxes.push(ballot_box.get_box().clone());
+        }
+        print_ergopay_request(&unsigned_tx, &available_boxes, &state_context)?;
+        return Ok(());
+    }
     println!("TYPE 'YES' TO INITIATE THE TRANSACTION.");
     let mut input = String::new();
     std::io::stdin().read_line(&mut input)?;
@@ -174,7 +193,7 @@ fn build_tx_with_existing_ballot_box(
         in_ballot_box.get_box().value,
         height,
     )?;
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let selection = box_selector.select(unspent_boxes, *BASE_FEE, &[])?;
     let mut input_boxes = vec![in_ballot_box.get_box().clone()];
     input_boxes.append(selection.boxes.as_vec().clone().as_mut());
@@ -231,7 +250,7 @@ fn build_tx_for_first_ballot_box(
         out_ballot_box_value,
         height,
     )?;
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let selection_target_balance = out_ballot_box_value.checked_add(&BASE_FEE).unwrap();
     let selection = box_selector.select(
         unspent_boxes,
@@ -0,0 +1,194 @@
+//! Detects that the pool box has moved to a new contract after an on-chain pool update and
+//! switches to the staged pool config (`pool_config_updated.yaml` next to the pool config file)
+//! without operator's intervention.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+
+use crate::box_kind::PoolBoxWrapper;
+use crate::box_kind::PoolBoxWrapperInputs;
+use crate::cli_commands::import_pool_update::import_pool_update;
+use crate::error_code::ErrorCode;
+use crate::metrics::set_pool_contract_unknown;
+use crate::node_interface::node_api::NodeApi;
+use crate::oracle_state::OraclePool;
+use crate::pool_config::PoolConfig;
+use crate::pool_config::POOL_CONFIG;
+use crate::pool_config::POOL_CONFIG_FILE_PATH;
+use crate::scans::get_scans_file_path;
+use crate::scans::NodeScanRegistry;
+
+/// Pool config file created by `prepare-update` command
+pub const STAGED_POOL_CONFIG_FILE_NAME: &str = "pool_config_updated.yaml";
+
+/// Staged pool config file, in the folder of the pool config file
+pub fn staged_pool_config_path() -> PathBuf {
+    POOL_CONFIG_FILE_PATH
+        .get()
+        .unwrap()
+        .with_file_name(STAGED_POOL_CONFIG_FILE_NAME)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolContractStatus {
+    /// Pool box is guarded by the contract from the active pool config
+    Current,
+    /// Pool box is guarded by the contract from the staged pool config
+    Staged,
+    /// Pool box is guarded by a contract we know nothing about
+    Unknown,
+}
+
+pub fn pool_contract_status(
+    pool_box: ErgoBox,
+    current: &PoolBoxWrapperInputs,
+    staged: Option<&PoolBoxWrapperInputs>,
+) -> PoolContractStatus {
+    if PoolBoxWrapper::new(pool_box.clone(), current).is_ok() {
+        PoolContractStatus::Current
+    } else if staged.map_or(false, |staged| {
+        PoolBoxWrapper::new(pool_box, staged).is_ok()
+    }) {
+        PoolContractStatus::Staged
+    } else {
+        PoolContractStatus::Unknown
+    }
+}
+
+/// Imports the staged pool config if the pool box is found under its contract.
+/// Returns `true` if the pool config was switched and the oracle needs to be restarted to pick it up.
+pub fn check_pool_contract_switch(
+    oracle_pool: &OraclePool,
+    node_api: &NodeApi,
+    staged_pool_config_path: &Path,
+) -> Result<bool, anyhow::Error> {
+    let pool_box = match oracle_pool.get_raw_pool_box()? {
+        Some(pool_box) => pool_box,
+        None => return Ok(false),
+    };
+    let staged_pool_config = if staged_pool_config_path.exists() {
+        let staged_pool_config_str = std::fs::read_to_string(staged_pool_config_path)?;
+        Some(PoolConfig::load_from_str(&staged_pool_config_str)?)
+    } else {
+        None
+    };
+    let status = pool_contract_status(
+        pool_box.clone(),
+        &POOL_CONFIG.pool_box_wrapper_inputs,
+        staged_pool_config
+            .as_ref()
+            .map(|c| &c.pool_box_wrapper_inputs),
+    );
+    set_pool_contract_unknown(status == PoolContractStatus::Unknown);
+    match status {
+        PoolContractStatus::Current => Ok(false),
+        PoolContractStatus::Staged => {
+            log::info!(
+                "Pool box {:?} is found under the contract from {}, switching to it",
+                pool_box.box_id(),
+                staged_pool_config_path.display()
+            );
+            import_pool_update(
+                staged_pool_config_path.to_string_lossy().to_string(),
+                &POOL_CONFIG.token_ids.oracle_token_id,
+                &POOL_CONFIG.token_ids.reward_token_id,
+                POOL_CONFIG_FILE_PATH.get().unwrap(),
+                oracle_pool.get_local_datapoint_box_source(),
+                &get_scans_file_path(),
+                NodeScanRegistry::load()?,
+                node_api,
+            )?;
+            Ok(true)
+        }
+        PoolContractStatus::Unknown => {
+            log::error!(
+                "[{}] Pool box {:?} is guarded by an unknown contract (ergo tree {}). \
+                The pool was probably updated, import the new pool config with import-pool-update command",
+                ErrorCode::PoolContractUnknown.code(),
+                pool_box.box_id(),
+                base16::encode_lower(&pool_box.ergo_tree.sigma_serialize_bytes()?)
+            );
+            Ok(false)
+        }
+    }
+}
+
+/// Replaces the running process with a fresh one started with the same arguments
+#[cfg(unix)]
+pub fn restart() -> anyhow::Error {
+    use std::os::unix::process::CommandExt;
+    match std::env::current_exe() {
+        Ok(exe) => std::process::Command::new(exe)
+            .args(std::env::args().skip(1))
+            .exec()
+            .into(),
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn restart() -> anyhow::Error {
+    anyhow::anyhow!("automatic restart is not supported on this platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::box_kind::PoolBox;
+    use crate::contracts::pool::PoolContractInputs;
+    use crate::contracts::pool::PoolContractParameters;
+    use crate::oracle_config::BASE_FEE;
+    use crate::oracle_types::{BlockHeight, EpochCounter};
+    use crate::pool_commands::test_utils::{generate_token_ids, make_pool_box};
+    use crate::pool_config::TokenIds;
+
+    fn make_pool_box_wrapper_inputs(token_ids: &TokenIds) -> PoolBoxWrapperInputs {
+        PoolBoxWrapperInputs {
+            contract_inputs: PoolContractInputs::build_with(
+                PoolContractParameters::default(),
+                token_ids.refresh_nft_token_id.clone(),
+                token_ids.update_nft_token_id.clone(),
+            )
+            .unwrap(),
+            pool_nft_token_id: token_ids.pool_nft_token_id.clone(),
+            reward_token_id: token_ids.reward_token_id.clone(),
+        }
+    }
+
+    #[test]
+    fn test_pool_contract_status() {
+        let old_token_ids = generate_token_ids();
+        let new_token_ids = generate_token_ids();
+        let pool_box = make_pool_box(
+            200,
+            EpochCounter(1),
+            *BASE_FEE,
+            BlockHeight(100),
+            &PoolContractParameters::default(),
+            &new_token_ids,
+        )
+        .get_box()
+        .clone();
+        let old_inputs = make_pool_box_wrapper_inputs(&old_token_ids);
+        let new_inputs = make_pool_box_wrapper_inputs(&new_token_ids);
+        assert_eq!(
+            pool_contract_status(pool_box.clone(), &new_inputs, None),
+            PoolContractStatus::Current
+        );
+        assert_eq!(
+            pool_contract_status(pool_box.clone(), &old_inputs, Some(&new_inputs)),
+            PoolContractStatus::Staged
+        );
+        assert_eq!(
+            pool_contract_status(pool_box.clone(), &old_inputs, None),
+            PoolContractStatus::Unknown
+        );
+        assert_eq!(
+            pool_contract_status(pool_box, &old_inputs, Some(&old_inputs)),
+            PoolContractStatus::Unknown
+        );
+    }
+}
@@ -1,5 +1,65 @@
+use thiserror::Error;
+
+use crate::pool_config::PoolConfig;
+
+use self::ballot::BallotContract;
+use self::ballot::BallotContractError;
+use self::oracle::OracleContract;
+use self::oracle::OracleContractError;
+use self::pool::PoolContract;
+use self::pool::PoolContractError;
+use self::refresh::RefreshContract;
+use self::refresh::RefreshContractError;
+use self::update::UpdateContract;
+use self::update::UpdateContractError;
+
 pub mod ballot;
 pub mod oracle;
 pub mod pool;
 pub mod refresh;
 pub mod update;
+
+#[derive(Debug, Error)]
+pub enum ContractsError {
+    #[error("pool contract error: {0}")]
+    Pool(#[from] PoolContractError),
+    #[error("refresh contract error: {0}")]
+    Refresh(#[from] RefreshContractError),
+    #[error("oracle contract error: {0}")]
+    Oracle(#[from] OracleContractError),
+    #[error("ballot contract error: {0}")]
+    Ballot(#[from] BallotContractError),
+    #[error("update contract error: {0}")]
+    Update(#[from] UpdateContractError),
+}
+
+/// All the pool contracts built and checked once from the pool config, so that the ergo trees
+/// are not parsed again on every box fetched from the scans
+#[derive(Debug, Clone)]
+pub struct Contracts {
+    pub pool: PoolContract,
+    pub refresh: RefreshContract,
+    pub oracle: OracleContract,
+    pub ballot: BallotContract,
+    pub update: UpdateContract,
+}
+
+impl Contracts {
+    pub fn checked_load(pool_config: &PoolConfig) -> Result<Self, ContractsError> {
+        Ok(Self {
+            pool: PoolContract::checked_load(&pool_config.pool_box_wrapper_inputs.contract_inputs)?,
+            refresh: RefreshContract::checked_load(
+                &pool_config.refresh_box_wrapper_inputs.contract_inputs,
+            )?,
+            oracle: OracleContract::checked_load(
+                &pool_config.oracle_box_wrapper_inputs.contract_inputs,
+            )?,
+            ballot: BallotContract::checked_load(
+                &pool_config.ballot_box_wrapper_inputs.contract_inputs,
+            )?,
+            update: UpdateContract::checked_load(
+                &pool_config.update_box_wrapper_inputs.contract_inputs,
+            )?,
+        })
+    }
+}
@@ -15,7 +15,7 @@ use crate::spec_token::OracleTokenId;
 use crate::spec_token::PoolTokenId;
 use crate::spec_token::TokenIdKind;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct RefreshContract {
     ergo_tree: ErgoTree,
     pool_nft_index: usize,
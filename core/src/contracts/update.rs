@@ -14,7 +14,7 @@ use crate::spec_token::BallotTokenId;
 use crate::spec_token::PoolTokenId;
 use crate::spec_token::TokenIdKind;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct UpdateContract {
     ergo_tree: ErgoTree,
     pool_nft_index: usize,
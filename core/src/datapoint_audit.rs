@@ -0,0 +1,201 @@
+//! Audit log of the datapoints we posted, to answer "what did you post and why". Every datapoint
+//! is tagged with the height and time it was computed at and the outcome of each source it was
+//! aggregated from. The snapshots are appended to a JSON lines file in the data dir (one snapshot
+//! per line) and served by the REST API (`/datapointAudit`). Each line carries the version of its
+//! format, the lines appended by an older oracle-core are migrated on startup.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::datapoint_source::SourceRate;
+use crate::format_version::Format;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::Rate;
+use crate::scans::SCANS_DIR_PATH;
+
+const AUDIT_LOG_FILE_NAME: &str = "datapoint_audit.jsonl";
+
+pub const DATAPOINT_SNAPSHOT_FORMAT: Format = Format {
+    name: "datapoint audit log record",
+    migrations: &[],
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatapointSnapshot {
+    /// Height the datapoint was computed at
+    pub height: BlockHeight,
+    /// Unix time (in seconds) the datapoint was computed at
+    pub timestamp: u64,
+    pub datapoint: Rate,
+    /// Outcome of each source, empty for the sources not aggregating several sources
+    pub sources: Vec<SourceRate>,
+}
+
+impl DatapointSnapshot {
+    /// Tags the datapoint computed now at the given height
+    pub fn new(height: BlockHeight, datapoint: Rate, sources: Vec<SourceRate>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        DatapointSnapshot {
+            height,
+            timestamp,
+            datapoint,
+            sources,
+        }
+    }
+}
+
+pub fn file_path() -> PathBuf {
+    SCANS_DIR_PATH.get().unwrap().join(AUDIT_LOG_FILE_NAME)
+}
+
+pub fn append(path: &Path, snapshot: &DatapointSnapshot) -> Result<(), anyhow::Error> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{}",
+        DATAPOINT_SNAPSHOT_FORMAT.to_json_string(snapshot)?
+    )?;
+    Ok(())
+}
+
+/// Rewrites the log in the current format if any of its lines were appended by an older
+/// oracle-core, returns the number of migrated lines. The log is replaced atomically, so an
+/// interrupted migration leaves the old log intact.
+pub fn migrate(path: &Path) -> Result<usize, anyhow::Error> {
+    migrate_lines(path, &DATAPOINT_SNAPSHOT_FORMAT)
+}
+
+fn migrate_lines(path: &Path, format: &Format) -> Result<usize, anyhow::Error> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    let mut migrated = 0;
+    let mut lines = Vec::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match format.upgrade_json_str(line)? {
+            Some(upgraded) => {
+                migrated += 1;
+                lines.push(upgraded);
+            }
+            None => lines.push(line.to_string()),
+        }
+    }
+    if migrated > 0 {
+        let tmp_path = path.with_extension("jsonl.tmp");
+        let mut content = lines.join("\n");
+        content.push('\n');
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, path)?;
+    }
+    Ok(migrated)
+}
+
+/// Snapshots computed at or after `from_height`, oldest first, at most `limit` of the latest ones
+pub fn load(
+    path: &Path,
+    from_height: BlockHeight,
+    limit: usize,
+) -> Result<Vec<DatapointSnapshot>, anyhow::Error> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let snapshot: DatapointSnapshot = DATAPOINT_SNAPSHOT_FORMAT.from_json_str(line)?;
+        if snapshot.height >= from_height {
+            snapshots.push(snapshot);
+        }
+    }
+    let skipped = snapshots.len().saturating_sub(limit);
+    Ok(snapshots.split_off(skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_load() {
+        let path = std::env::temp_dir().join("oracle_core_test_datapoint_audit.jsonl");
+        let _ = std::fs::remove_file(&path);
+        assert!(load(&path, BlockHeight(0), 10).unwrap().is_empty());
+        let sources = vec![
+            SourceRate {
+                source: "coingecko".to_string(),
+                rate: Some(600_000_000.0),
+                error: None,
+            },
+            SourceRate {
+                source: "htx".to_string(),
+                rate: None,
+                error: Some("timeout".to_string()),
+            },
+        ];
+        for height in [100, 130, 160] {
+            let snapshot = DatapointSnapshot::new(
+                BlockHeight(height),
+                Rate::from(600_000_000),
+                sources.clone(),
+            );
+            append(&path, &snapshot).unwrap();
+        }
+        let loaded = load(&path, BlockHeight(0), 10).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[0].sources, sources);
+        assert!(loaded[0].timestamp > 0);
+        let heights = |snapshots: Vec<DatapointSnapshot>| {
+            snapshots.iter().map(|s| s.height.0).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            heights(load(&path, BlockHeight(130), 10).unwrap()),
+            vec![130, 160]
+        );
+        assert_eq!(
+            heights(load(&path, BlockHeight(0), 2).unwrap()),
+            vec![130, 160]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_lines() {
+        let path = std::env::temp_dir().join("oracle_core_test_datapoint_audit_migrate.jsonl");
+        std::fs::write(
+            &path,
+            "{\"height\":100,\"timestamp\":1,\"datapoint\":5,\"sources\":[]}\n",
+        )
+        .unwrap();
+        assert_eq!(migrate(&path).unwrap(), 0);
+        // the unversioned lines are of version 1, migrated by a format with one more version
+        let format = Format {
+            name: "test record",
+            migrations: &[|mut value| {
+                value["datapoint"] = serde_json::json!(6);
+                Ok(value)
+            }],
+        };
+        assert_eq!(migrate_lines(&path, &format).unwrap(), 1);
+        assert_eq!(migrate_lines(&path, &format).unwrap(), 0);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let value: serde_json::Value = serde_json::from_str(content.trim()).unwrap();
+        assert_eq!(Format::version_of(&value), 2);
+        assert_eq!(value["datapoint"], 6);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
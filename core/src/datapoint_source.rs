@@ -2,39 +2,134 @@
 mod ada_usd;
 mod aggregator;
 mod assets_exchange_rate;
+mod basket;
+mod bitfinex;
 mod bitpanda;
+mod bls;
+mod coinbase;
 mod coincap;
 mod coingecko;
+mod cryptocompare;
 mod custom_ext_script;
+mod erg_ageusd;
+mod erg_brent;
 mod erg_btc;
+mod erg_jpy;
+mod erg_rsn;
 mod erg_usd;
 mod erg_xau;
+mod erg_xpd;
+mod erg_xpt;
+mod ergo_hashrate;
+mod exchangerate_host;
+mod file_source;
+mod gateio;
+mod goldapi;
+mod http_client;
+mod http_json;
+mod htx;
+mod kraken;
+mod kraken_ws;
+mod kucoin;
+mod messari;
+mod oilpriceapi;
+mod okx;
 mod predef;
+mod price_method;
+mod rates_proxy;
+mod response;
+mod rhai_script;
+mod sigmausd;
+mod spectrum;
+mod spx_usd;
+mod stooq;
+mod us_cpi;
+mod usd_btc;
+mod yahoo;
 
+use crate::alerts;
+use crate::alerts::AlertCategory;
+use crate::oracle_config::ORACLE_CONFIG_OPT;
 use crate::oracle_types::Rate;
 use crate::pool_config::PredefinedDataPointSource;
 
+use self::basket::SourceBasketError;
+use self::coingecko::CoinGeckoError;
 use self::custom_ext_script::ExternalScript;
 use self::custom_ext_script::ExternalScriptError;
+use self::file_source::FileSourceError;
+use self::http_client::HttpClientError;
+use self::htx::HtxError;
+use self::kraken_ws::KrakenWsError;
 use self::predef::sync_fetch_predef_source_aggregated;
+use self::response::ResponseError;
+use self::rhai_script::RhaiScriptError;
+
+pub use self::aggregator::AggregationStrategy;
+pub use self::aggregator::SourceRate;
+pub use self::basket::validate_source_baskets;
+pub use self::basket::validate_source_weights;
+pub use self::bitpanda::validate_bitpanda_api_key;
+pub use self::coingecko::validate_coingecko_api_key;
+pub use self::file_source::FileSource;
+pub use self::http_client::redacted_headers;
+pub use self::http_client::validate_http_headers;
+pub use self::http_json::HttpJsonSource;
+pub use self::predef::start_rates_proxy_server;
+pub use self::price_method::validate_source_price_methods;
+pub use self::price_method::PriceMethod;
+pub use self::rates_proxy::RatesProxyConfig;
+pub use self::rhai_script::RhaiScriptSource;
+pub use self::spectrum::validate_pool_ids;
+pub use self::spectrum::validate_token_id;
+use self::spectrum::DexLiquidityError;
+use self::spectrum::SpectrumError;
 
 use anyhow::anyhow;
 use thiserror::Error;
 
 pub trait DataPointSource {
     fn get_datapoint(&self) -> Result<Rate, DataPointSourceError>;
+
+    /// Datapoint along with the outcome of each aggregated source, the breakdown is empty for
+    /// the sources not aggregating several sources
+    fn get_datapoint_with_breakdown(
+        &self,
+    ) -> Result<(Rate, Vec<SourceRate>), DataPointSourceError> {
+        Ok((self.get_datapoint()?, Vec::new()))
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum DataPointSourceError {
     #[error("external script error: {0}")]
     ExternalScript(#[from] ExternalScriptError),
+    #[error("datapoint file error: {0}")]
+    File(#[from] FileSourceError),
+    #[error("Rhai script source error: {0}")]
+    RhaiScript(#[from] RhaiScriptError),
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
     #[error("JSON parse error: {0}")]
-    JsonParse(#[from] json::Error),
+    JsonParse(#[from] serde_json::Error),
+    #[error("invalid response: {0}")]
+    Response(#[from] ResponseError),
     #[error("Missing JSON field {field} in {json}")]
     JsonMissingField { field: String, json: String },
+    #[error("HTTP client error: {0}")]
+    HttpClient(#[from] HttpClientError),
+    #[error("CoinGecko error: {0}")]
+    CoinGecko(#[from] CoinGeckoError),
+    #[error("HTX error: {0}")]
+    Htx(#[from] HtxError),
+    #[error("Kraken stream error: {0}")]
+    KrakenWs(#[from] KrakenWsError),
+    #[error("DEX liquidity check failed: {0}")]
+    DexLiquidity(#[from] DexLiquidityError),
+    #[error("Spectrum error: {0}")]
+    Spectrum(#[from] SpectrumError),
+    #[error("source basket error: {0}")]
+    SourceBasket(#[from] SourceBasketError),
     #[error("No datapoints from any source")]
     NoDataPoints,
 }
@@ -42,35 +137,89 @@ pub enum DataPointSourceError {
 pub enum RuntimeDataPointSource {
     Predefined(PredefinedDataPointSource),
     ExternalScript(ExternalScript),
+    HttpJson(HttpJsonSource),
+    File(FileSource),
+    RhaiScript(RhaiScriptSource),
 }
 
 impl RuntimeDataPointSource {
     pub fn new(
         predef_datapoint_source: Option<PredefinedDataPointSource>,
         custom_datapoint_source_shell_cmd: Option<String>,
+        http_datapoint_source: Option<HttpJsonSource>,
+        file_datapoint_source: Option<FileSource>,
+        rhai_datapoint_source: Option<RhaiScriptSource>,
     ) -> Result<RuntimeDataPointSource, anyhow::Error> {
         if let Some(external_script_name) = custom_datapoint_source_shell_cmd.clone() {
             Ok(RuntimeDataPointSource::ExternalScript(ExternalScript::new(
                 external_script_name.clone(),
             )))
+        } else if let Some(http_source) = http_datapoint_source {
+            Ok(RuntimeDataPointSource::HttpJson(http_source))
+        } else if let Some(file_source) = file_datapoint_source {
+            Ok(RuntimeDataPointSource::File(file_source))
+        } else if let Some(rhai_source) = rhai_datapoint_source {
+            if !cfg!(feature = "rhai") {
+                return Err(RhaiScriptError::FeatureDisabled.into());
+            }
+            Ok(RuntimeDataPointSource::RhaiScript(rhai_source))
         } else {
             match predef_datapoint_source {
                 Some(predef_datasource) => Ok(RuntimeDataPointSource::Predefined(predef_datasource)),
                 _ => Err(anyhow!(
-                    "pool config data_point_source is empty along with data_point_source_custom_script in the oracle config"
+                    "pool config data_point_source is empty along with data_point_source_custom_script, data_point_source_http, data_point_source_file and data_point_source_rhai in the oracle config"
                 )),
             }
         }
     }
+
+    /// Starts the streaming sources listed in the source basket of the pair, so that their
+    /// prices are ready by the first datapoint
+    pub fn start_streams(&self) {
+        let RuntimeDataPointSource::Predefined(pair) = self else {
+            return;
+        };
+        let basket = ORACLE_CONFIG_OPT
+            .as_ref()
+            .ok()
+            .and_then(|c| c.source_baskets.get(pair));
+        if *pair == PredefinedDataPointSource::NanoErgUsd
+            && basket.map_or(false, |names| {
+                names.iter().any(|n| n == kraken_ws::SOURCE_NAME)
+            })
+        {
+            kraken_ws::start();
+        }
+    }
 }
 
 impl DataPointSource for RuntimeDataPointSource {
     fn get_datapoint(&self) -> Result<Rate, DataPointSourceError> {
-        match self {
+        self.get_datapoint_with_breakdown().map(|(rate, _)| rate)
+    }
+
+    fn get_datapoint_with_breakdown(
+        &self,
+    ) -> Result<(Rate, Vec<SourceRate>), DataPointSourceError> {
+        let res = match self {
             RuntimeDataPointSource::Predefined(predef) => {
                 sync_fetch_predef_source_aggregated(predef)
             }
-            RuntimeDataPointSource::ExternalScript(script) => script.get_datapoint(),
-        }
+            RuntimeDataPointSource::ExternalScript(script) => script.get_datapoint_with_breakdown(),
+            RuntimeDataPointSource::HttpJson(http_source) => {
+                http_source.get_datapoint_with_breakdown()
+            }
+            RuntimeDataPointSource::File(file_source) => file_source.get_datapoint_with_breakdown(),
+            RuntimeDataPointSource::RhaiScript(rhai_source) => {
+                rhai_source.get_datapoint_with_breakdown()
+            }
+        };
+        let error = res
+            .as_ref()
+            .err()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        alerts::update(AlertCategory::SourceDegraded, res.is_err(), &error);
+        res
     }
 }
@@ -4,11 +4,20 @@ use std::pin::Pin;
 
 use futures::Future;
 
+use super::aggregator::fetch_aggregated_with_breakdown;
+use super::assets_exchange_rate::convert_rate;
 use super::assets_exchange_rate::Asset;
 use super::assets_exchange_rate::AssetsExchangeRate;
 use super::assets_exchange_rate::Usd;
+use super::basket::select_sources;
+use super::basket::source_weights;
+use super::bitfinex;
+use super::coincap;
 use super::coingecko;
+use super::erg_usd::nanoerg_usd_source;
+use super::spectrum;
 use super::DataPointSourceError;
+use super::PredefinedDataPointSource;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ada {}
@@ -25,9 +34,33 @@ impl Lovelace {
     }
 }
 
+pub const USD_LOVELACE_SOURCE_NAMES: &[&str] = &["coingecko", "coincap", "bitfinex", "spectrum"];
+
 #[allow(clippy::type_complexity)]
-pub fn usd_lovelace_sources() -> Vec<
+pub fn usd_lovelace_source(
+    name: &str,
+) -> Option<
     Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Usd, Lovelace>, DataPointSourceError>>>>,
 > {
-    vec![Box::pin(coingecko::get_usd_lovelace())]
+    match name {
+        "coingecko" => Some(Box::pin(coingecko::get_usd_lovelace())),
+        "coincap" => Some(Box::pin(coincap::get_usd_lovelace())),
+        "bitfinex" => Some(Box::pin(bitfinex::get_usd_lovelace())),
+        "spectrum" => Some(Box::pin(get_usd_lovelace_spectrum())),
+        _ => None,
+    }
+}
+
+// Calculate ADA/USD through rsADA/ERG and ERG/USD, aggregated from the `NanoErgUsd` basket (and
+// weights) of the oracle config, so that a single ERG/USD provider can't skew it
+async fn get_usd_lovelace_spectrum(
+) -> Result<AssetsExchangeRate<Usd, Lovelace>, DataPointSourceError> {
+    let usd_pair = PredefinedDataPointSource::NanoErgUsd;
+    let usd_sources = select_sources(usd_pair, nanoerg_usd_source)?;
+    let (usd_nanoerg, _) =
+        fetch_aggregated_with_breakdown(usd_sources, &source_weights(usd_pair)).await?;
+    Ok(convert_rate(
+        spectrum::get_nanoerg_lovelace().await?,
+        usd_nanoerg,
+    ))
 }
@@ -1,20 +1,123 @@
+use std::collections::HashMap;
 use std::pin::Pin;
 
 use futures::Future;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::oracle_config::ORACLE_CONFIG_OPT;
 
 use super::assets_exchange_rate::Asset;
 use super::assets_exchange_rate::AssetsExchangeRate;
 use super::DataPointSourceError;
 
+/// Outcome of one source of the aggregated datapoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceRate {
+    pub source: String,
+    /// Rate in the units of the pair, `None` if the source failed
+    pub rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[allow(clippy::type_complexity)]
+pub type NamedSource<PER1, GET> = (
+    String,
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<PER1, GET>, DataPointSourceError>>>>,
+);
+
+/// Weight of a source not listed in `source_weights` of the pair in the oracle config
+pub const DEFAULT_SOURCE_WEIGHT: f64 = 1.0;
+
+/// How the rates of the sources are combined, `aggregation_strategy` in the oracle config. The
+/// median and the trimmed mean keep a single wildly wrong source from dragging the datapoint away
+/// from the other oracles' (and getting it filtered out of the refresh).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationStrategy {
+    /// Weighted mean
+    #[default]
+    Mean,
+    /// Weighted median, the mean of the two middle rates if the weights split evenly between them
+    Median,
+    /// Weighted mean of the rates without the lowest and the highest one (the mean of up to 2
+    /// rates)
+    TrimmedMean,
+}
+
+impl AggregationStrategy {
+    /// Aggregated rate of the (rate, weight) pairs, the weights are positive
+    pub fn apply(&self, mut rates: Vec<(f64, f64)>) -> f64 {
+        rates.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        match self {
+            AggregationStrategy::Mean => weighted_mean(&rates),
+            AggregationStrategy::Median => weighted_median(&rates),
+            AggregationStrategy::TrimmedMean if rates.len() >= 3 => {
+                weighted_mean(&rates[1..rates.len() - 1])
+            }
+            AggregationStrategy::TrimmedMean => weighted_mean(&rates),
+        }
+    }
+}
+
+fn weighted_mean(rates: &[(f64, f64)]) -> f64 {
+    let total_weight = rates.iter().map(|(_, weight)| weight).sum::<f64>();
+    rates
+        .iter()
+        .map(|(rate, weight)| rate * weight)
+        .sum::<f64>()
+        / total_weight
+}
+
+/// Median of the rates sorted ascending
+fn weighted_median(rates: &[(f64, f64)]) -> f64 {
+    let half = rates.iter().map(|(_, weight)| weight).sum::<f64>() / 2.0;
+    let mut cumulative = 0.0;
+    for (i, (rate, weight)) in rates.iter().enumerate() {
+        cumulative += weight;
+        if cumulative == half {
+            if let Some((next, _)) = rates.get(i + 1) {
+                return (rate + next) / 2.0;
+            }
+        }
+        if cumulative >= half {
+            return *rate;
+        }
+    }
+    rates.last().map_or(f64::NAN, |(rate, _)| *rate)
+}
+
+/// Strategy set in the oracle config
+pub fn aggregation_strategy() -> AggregationStrategy {
+    ORACLE_CONFIG_OPT
+        .as_ref()
+        .map(|c| c.aggregation_strategy)
+        .unwrap_or_default()
+}
+
 pub fn aggregate<PER1: Asset, GET: Asset>(
     rates: Vec<AssetsExchangeRate<PER1, GET>>,
 ) -> AssetsExchangeRate<PER1, GET> {
-    // TODO: filter out outliers if > 2 datapoints?
-    let average = rates.iter().map(|r| r.rate).sum::<f64>() / rates.len() as f64;
-    AssetsExchangeRate {
-        rate: average,
-        ..rates[0]
-    }
+    aggregate_weighted(
+        rates
+            .into_iter()
+            .map(|rate| (rate, DEFAULT_SOURCE_WEIGHT))
+            .collect(),
+    )
+}
+
+/// Rates of the sources aggregated by the configured strategy, the weights are positive
+pub fn aggregate_weighted<PER1: Asset, GET: Asset>(
+    rates: Vec<(AssetsExchangeRate<PER1, GET>, f64)>,
+) -> AssetsExchangeRate<PER1, GET> {
+    let rate = aggregation_strategy().apply(
+        rates
+            .iter()
+            .map(|(rate, weight)| (rate.rate, *weight))
+            .collect(),
+    );
+    AssetsExchangeRate { rate, ..rates[0].0 }
 }
 
 #[allow(clippy::type_complexity)]
@@ -38,7 +141,147 @@ pub async fn fetch<PER1: Asset, GET: Asset>(
     >,
 ) -> Result<Vec<AssetsExchangeRate<PER1, GET>>, DataPointSourceError> {
     let results = futures::future::join_all(sources).await;
-    let ok_results: Vec<AssetsExchangeRate<PER1, GET>> =
-        results.into_iter().flat_map(|res| res.ok()).collect();
+    // a failed source is left out of the average
+    let ok_results: Vec<AssetsExchangeRate<PER1, GET>> = results
+        .into_iter()
+        .filter_map(|res| {
+            res.map_err(|e| log::warn!("Datapoint source failed: {}", e))
+                .ok()
+        })
+        .collect();
     Ok(ok_results)
 }
+
+/// Aggregated rate of the sources, weighted by their `weights` (or [`DEFAULT_SOURCE_WEIGHT`]),
+/// along with the outcome of each source
+pub async fn fetch_aggregated_with_breakdown<PER1: Asset, GET: Asset>(
+    sources: Vec<NamedSource<PER1, GET>>,
+    weights: &HashMap<String, f64>,
+) -> Result<(AssetsExchangeRate<PER1, GET>, Vec<SourceRate>), DataPointSourceError> {
+    let (names, futures): (Vec<String>, Vec<_>) = sources.into_iter().unzip();
+    let results = futures::future::join_all(futures).await;
+    let mut ok_results = Vec::new();
+    let mut breakdown = Vec::new();
+    for (source, res) in names.into_iter().zip(results) {
+        match res {
+            Ok(rate) => {
+                let weight = weights
+                    .get(&source)
+                    .copied()
+                    .unwrap_or(DEFAULT_SOURCE_WEIGHT);
+                breakdown.push(SourceRate {
+                    source,
+                    rate: Some(rate.rate),
+                    error: None,
+                });
+                ok_results.push((rate, weight));
+            }
+            Err(e) => {
+                // a failed source is left out of the average
+                log::warn!("Datapoint source {} failed: {}", source, e);
+                breakdown.push(SourceRate {
+                    source,
+                    rate: None,
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+    if ok_results.is_empty() {
+        return Err(DataPointSourceError::NoDataPoints);
+    }
+    Ok((aggregate_weighted(ok_results), breakdown))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assets_exchange_rate::NanoErg;
+    use super::super::assets_exchange_rate::Usd;
+    use super::*;
+
+    fn source(name: &str, rate: Option<f64>) -> NamedSource<Usd, NanoErg> {
+        (
+            name.to_string(),
+            Box::pin(async move {
+                rate.map(|rate| AssetsExchangeRate {
+                    per1: Usd {},
+                    get: NanoErg {},
+                    rate,
+                })
+                .ok_or(DataPointSourceError::NoDataPoints)
+            }),
+        )
+    }
+
+    #[test]
+    fn test_fetch_aggregated_with_breakdown() {
+        let (rate, breakdown) = tokio_test::block_on(fetch_aggregated_with_breakdown(
+            vec![
+                source("a", Some(100.0)),
+                source("b", None),
+                source("c", Some(200.0)),
+            ],
+            &HashMap::new(),
+        ))
+        .unwrap();
+        assert_eq!(rate.rate, 150.0);
+        assert_eq!(
+            breakdown.iter().map(|s| s.rate).collect::<Vec<_>>(),
+            vec![Some(100.0), None, Some(200.0)]
+        );
+        assert_eq!(breakdown[1].source, "b");
+        assert!(breakdown[1].error.is_some());
+        assert!(matches!(
+            tokio_test::block_on(fetch_aggregated_with_breakdown(
+                vec![source("b", None)],
+                &HashMap::new()
+            )),
+            Err(DataPointSourceError::NoDataPoints)
+        ));
+    }
+
+    #[test]
+    fn test_aggregation_strategies() {
+        // one wildly wrong source
+        let rates = vec![
+            (100.0, 1.0),
+            (1000.0, 1.0),
+            (102.0, 1.0),
+            (98.0, 1.0),
+            (101.0, 1.0),
+        ];
+        assert_eq!(AggregationStrategy::Mean.apply(rates.clone()), 280.2);
+        assert_eq!(AggregationStrategy::Median.apply(rates.clone()), 101.0);
+        assert_eq!(AggregationStrategy::TrimmedMean.apply(rates), 101.0);
+
+        let even = vec![(100.0, 1.0), (104.0, 1.0), (101.0, 1.0), (1000.0, 1.0)];
+        assert_eq!(AggregationStrategy::Median.apply(even.clone()), 102.5);
+        assert_eq!(AggregationStrategy::TrimmedMean.apply(even), 102.5);
+
+        // the weights shift the median
+        let weighted = vec![(100.0, 1.0), (101.0, 1.0), (110.0, 3.0)];
+        assert_eq!(AggregationStrategy::Median.apply(weighted), 110.0);
+
+        let two = vec![(100.0, 1.0), (110.0, 1.0)];
+        assert_eq!(AggregationStrategy::Median.apply(two.clone()), 105.0);
+        assert_eq!(AggregationStrategy::TrimmedMean.apply(two), 105.0);
+        assert_eq!(AggregationStrategy::Median.apply(vec![(100.0, 2.0)]), 100.0);
+    }
+
+    #[test]
+    fn test_weighted_aggregation() {
+        // the DEX source weighted at a quarter of the exchanges, the failed source is left out
+        let weights = HashMap::from([("spectrum".to_string(), 0.5), ("b".to_string(), 3.0)]);
+        let (rate, _) = tokio_test::block_on(fetch_aggregated_with_breakdown(
+            vec![
+                source("a", Some(100.0)),
+                source("b", None),
+                source("c", Some(200.0)),
+                source("spectrum", Some(400.0)),
+            ],
+            &weights,
+        ))
+        .unwrap();
+        assert_eq!(rate.rate, (100.0 + 200.0 + 400.0 * 0.5) / 2.5);
+    }
+}
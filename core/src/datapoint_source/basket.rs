@@ -0,0 +1,262 @@
+//! Operator-selected baskets of the sources aggregated for the predefined pairs, and the weights
+//! of the sources in the average
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::oracle_config::ORACLE_CONFIG_OPT;
+use crate::pool_config::PredefinedDataPointSource;
+
+use super::ada_usd::USD_LOVELACE_SOURCE_NAMES;
+use super::erg_ageusd::NANOERG_AGEUSD_SOURCE_NAMES;
+use super::erg_brent::NANOERG_BBL_SOURCE_NAMES;
+use super::erg_btc::NANOERG_BTC_SOURCE_NAMES;
+use super::erg_jpy::NANOERG_JPY_SOURCE_NAMES;
+use super::erg_rsn::NANOERG_RSN_SOURCE_NAMES;
+use super::erg_usd::NANOERG_USD_SOURCE_NAMES;
+use super::erg_xau::NANOERG_KGAU_SOURCE_NAMES;
+use super::erg_xpd::NANOERG_KGPD_SOURCE_NAMES;
+use super::erg_xpt::NANOERG_KGPT_SOURCE_NAMES;
+use super::ergo_hashrate::ERGO_HASHRATE_SOURCE_NAMES;
+use super::spectrum;
+use super::spx_usd::USDCENT_SPX_SOURCE_NAMES;
+use super::us_cpi::US_CPI_SOURCE_NAMES;
+use super::usd_btc::USDCENT_BTC_SOURCE_NAMES;
+
+/// Sources left out of the default baskets and used only when listed in a basket, as they keep a
+/// connection open (`kraken_ws`), need an API key (`goldapi`) or price USD by a stablecoin that can
+/// trade off its peg (`spectrum_sigusd`)
+pub const OPT_IN_SOURCE_NAMES: &[&str] = &["kraken_ws", "goldapi", "spectrum_sigusd"];
+
+/// Whether the source of the pair is left out of the default basket. Besides the opt-in sources,
+/// the `spectrum` source of `NanoErgUsd` is used only once the USDT token id is configured.
+pub fn is_opt_in(pair: PredefinedDataPointSource, source_name: &str) -> bool {
+    OPT_IN_SOURCE_NAMES.contains(&source_name)
+        || (pair == PredefinedDataPointSource::NanoErgUsd
+            && source_name == "spectrum"
+            && spectrum::usdt_token_id().is_none())
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SourceBasketError {
+    #[error("source basket for {pair:?} is empty")]
+    EmptyBasket { pair: PredefinedDataPointSource },
+    #[error("source {source_name} does not support {pair:?}, supported sources are {supported:?}")]
+    UnsupportedSource {
+        pair: PredefinedDataPointSource,
+        source_name: String,
+        supported: &'static [&'static str],
+    },
+    #[error("weight of source {source_name} of {pair:?} must be a positive number")]
+    InvalidWeight {
+        pair: PredefinedDataPointSource,
+        source_name: String,
+    },
+}
+
+/// Names of the sources that can be used in the basket of the pair
+pub fn supported_source_names(pair: PredefinedDataPointSource) -> &'static [&'static str] {
+    match pair {
+        PredefinedDataPointSource::NanoErgUsd => NANOERG_USD_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgXau => NANOERG_KGAU_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoAdaUsd => USD_LOVELACE_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgBTC => NANOERG_BTC_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgSigUsd => NANOERG_AGEUSD_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgSigRsv => NANOERG_AGEUSD_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgRsn => NANOERG_RSN_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgJpy => NANOERG_JPY_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgXpt => NANOERG_KGPT_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgXpd => NANOERG_KGPD_SOURCE_NAMES,
+        PredefinedDataPointSource::NanoErgBrent => NANOERG_BBL_SOURCE_NAMES,
+        PredefinedDataPointSource::UsdCentSpx => USDCENT_SPX_SOURCE_NAMES,
+        PredefinedDataPointSource::UsdCentBtc => USDCENT_BTC_SOURCE_NAMES,
+        PredefinedDataPointSource::ErgoHashrate => ERGO_HASHRATE_SOURCE_NAMES,
+        PredefinedDataPointSource::UsCpi => US_CPI_SOURCE_NAMES,
+    }
+}
+
+/// Checks that every basket is non-empty and lists only sources supporting its pair
+pub fn validate_source_baskets(
+    baskets: &HashMap<PredefinedDataPointSource, Vec<String>>,
+) -> Result<(), SourceBasketError> {
+    for (pair, source_names) in baskets {
+        if source_names.is_empty() {
+            return Err(SourceBasketError::EmptyBasket { pair: *pair });
+        }
+        let supported = supported_source_names(*pair);
+        if let Some(unsupported) = source_names
+            .iter()
+            .find(|name| !supported.contains(&name.as_str()))
+        {
+            return Err(SourceBasketError::UnsupportedSource {
+                pair: *pair,
+                source_name: unsupported.clone(),
+                supported,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the weights are positive and set for the sources supporting their pair
+pub fn validate_source_weights(
+    weights: &HashMap<PredefinedDataPointSource, HashMap<String, f64>>,
+) -> Result<(), SourceBasketError> {
+    for (pair, source_weights) in weights {
+        let supported = supported_source_names(*pair);
+        for (source_name, weight) in source_weights {
+            if !supported.contains(&source_name.as_str()) {
+                return Err(SourceBasketError::UnsupportedSource {
+                    pair: *pair,
+                    source_name: source_name.clone(),
+                    supported,
+                });
+            }
+            if !(weight.is_finite() && *weight > 0.0) {
+                return Err(SourceBasketError::InvalidWeight {
+                    pair: *pair,
+                    source_name: source_name.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Weights of the pair's sources from the oracle config, the sources not listed are weighted
+/// equally
+pub fn source_weights(pair: PredefinedDataPointSource) -> HashMap<String, f64> {
+    ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.source_weights.get(&pair).cloned())
+        .unwrap_or_default()
+}
+
+/// Sources of the pair's basket from the oracle config by name, or all sources of the pair
+/// except the opt-in ones if the basket is not configured
+pub fn select_sources<S>(
+    pair: PredefinedDataPointSource,
+    source_by_name: impl Fn(&str) -> Option<S>,
+) -> Result<Vec<(String, S)>, SourceBasketError> {
+    let basket = ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.source_baskets.get(&pair));
+    match basket {
+        Some(source_names) => source_names
+            .iter()
+            .map(|name| {
+                source_by_name(name)
+                    .map(|source| (name.clone(), source))
+                    .ok_or_else(|| SourceBasketError::UnsupportedSource {
+                        pair,
+                        source_name: name.clone(),
+                        supported: supported_source_names(pair),
+                    })
+            })
+            .collect(),
+        None => Ok(supported_source_names(pair)
+            .iter()
+            .filter(|name| !is_opt_in(pair, name))
+            .filter_map(|name| source_by_name(name).map(|source| (name.to_string(), source)))
+            .collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_source_baskets() {
+        let valid = HashMap::from([
+            (
+                PredefinedDataPointSource::NanoErgUsd,
+                vec!["coingecko".to_string(), "htx".to_string()],
+            ),
+            (
+                PredefinedDataPointSource::NanoErgSigUsd,
+                vec!["sigmausd".to_string()],
+            ),
+        ]);
+        assert_eq!(validate_source_baskets(&valid), Ok(()));
+
+        let empty = HashMap::from([(PredefinedDataPointSource::NanoErgUsd, vec![])]);
+        assert_eq!(
+            validate_source_baskets(&empty),
+            Err(SourceBasketError::EmptyBasket {
+                pair: PredefinedDataPointSource::NanoErgUsd
+            })
+        );
+
+        // htx has no ERG/XAU market
+        let unsupported = HashMap::from([(
+            PredefinedDataPointSource::NanoErgXau,
+            vec!["coingecko".to_string(), "htx".to_string()],
+        )]);
+        assert!(matches!(
+            validate_source_baskets(&unsupported),
+            Err(SourceBasketError::UnsupportedSource { source_name, .. }) if source_name == "htx"
+        ));
+    }
+
+    #[test]
+    fn test_validate_source_weights() {
+        let weights = |source_name: &str, weight: f64| {
+            HashMap::from([(
+                PredefinedDataPointSource::NanoErgUsd,
+                HashMap::from([(source_name.to_string(), weight)]),
+            )])
+        };
+        assert_eq!(validate_source_weights(&weights("spectrum", 0.25)), Ok(()));
+        assert!(matches!(
+            validate_source_weights(&weights("goldapi", 1.0)),
+            Err(SourceBasketError::UnsupportedSource { source_name, .. }) if source_name == "goldapi"
+        ));
+        for invalid in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                validate_source_weights(&weights("spectrum", invalid)),
+                Err(SourceBasketError::InvalidWeight {
+                    pair: PredefinedDataPointSource::NanoErgUsd,
+                    source_name: "spectrum".to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_opt_in() {
+        assert!(is_opt_in(
+            PredefinedDataPointSource::NanoErgUsd,
+            "kraken_ws"
+        ));
+        assert!(!is_opt_in(
+            PredefinedDataPointSource::NanoErgUsd,
+            "coingecko"
+        ));
+        assert!(is_opt_in(
+            PredefinedDataPointSource::NanoErgUsd,
+            "spectrum_sigusd"
+        ));
+        // no USDT token id in the test config
+        assert!(is_opt_in(PredefinedDataPointSource::NanoErgUsd, "spectrum"));
+        assert!(!is_opt_in(
+            PredefinedDataPointSource::NanoErgSigUsd,
+            "spectrum"
+        ));
+    }
+
+    #[test]
+    fn test_supported_source_names_resolve() {
+        use super::super::erg_usd::nanoerg_usd_source;
+        use super::super::erg_xau::nanoerg_kgau_source;
+        assert!(NANOERG_USD_SOURCE_NAMES
+            .iter()
+            .all(|name| nanoerg_usd_source(name).is_some()));
+        assert!(NANOERG_KGAU_SOURCE_NAMES
+            .iter()
+            .all(|name| nanoerg_kgau_source(name).is_some()));
+    }
+}
@@ -0,0 +1,188 @@
+use super::ada_usd::Lovelace;
+use super::assets_exchange_rate::convert_rate;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::Btc;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::erg_xau::KgAu;
+use super::DataPointSourceError;
+
+/// Returns the price of the given Bitfinex trading pair (e.g. `tBTCUSD`), the middle of the
+/// bid/ask spread unless `source_price_methods` sets the last trade
+#[cfg(not(test))]
+async fn get_ticker_price(
+    symbol: &str,
+    range: super::response::PlausibleRange,
+) -> Result<f64, DataPointSourceError> {
+    // see https://docs.bitfinex.com/reference/rest-public-ticker
+    let url = format!("https://api-pub.bitfinex.com/v2/ticker/{}", symbol);
+    let resp = super::http_client::get("bitfinex", url).await?;
+    // [BID, BID_SIZE, ASK, ASK_SIZE, DAILY_CHANGE, DAILY_CHANGE_RELATIVE, LAST_PRICE, ...]
+    let ticker: Vec<super::response::Quote> = super::response::parse_body("bitfinex", resp).await?;
+    Ok(super::price_method::price(
+        "bitfinex",
+        ("$[6]", ticker.get(6)),
+        ("$[0]", ticker.first()),
+        ("$[2]", ticker.get(2)),
+        range,
+    )?)
+}
+
+#[cfg(not(test))]
+pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
+    // Tether Gold (1 XAUT = 1 troy ounce of gold) priced in USDT, USDT is taken as USD
+    let usd_per_troy_ounce =
+        get_ticker_price("tXAUT:UST", super::response::USD_PER_TROY_OUNCE).await?;
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: Usd {},
+        rate: KgAu::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
+    let usd_per_troy_ounce = (2049.0 + 2051.0) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: Usd {},
+        rate: KgAu::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_lovelace() -> Result<AssetsExchangeRate<Usd, Lovelace>, DataPointSourceError> {
+    let usd_per_ada = get_ticker_price("tADAUSD", super::response::USD_PER_ADA).await?;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: Lovelace {},
+        rate: Lovelace::from_ada(1.0 / usd_per_ada),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_lovelace() -> Result<AssetsExchangeRate<Usd, Lovelace>, DataPointSourceError> {
+    let usd_per_ada = (0.60601 + 0.60672) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: Lovelace {},
+        rate: Lovelace::from_ada(1.0 / usd_per_ada),
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_btc_usd() -> Result<AssetsExchangeRate<Btc, Usd>, DataPointSourceError> {
+    let usd_per_btc = get_ticker_price("tBTCUSD", super::response::USD_PER_BTC).await?;
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: Usd {},
+        rate: usd_per_btc,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_btc_usd() -> Result<AssetsExchangeRate<Btc, Usd>, DataPointSourceError> {
+    let usd_per_btc = (43_705.0 + 43_712.0) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: Usd {},
+        rate: usd_per_btc,
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let usd_per_erg = get_ticker_price("tERGUSD", super::response::USD_PER_ERG).await?;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let usd_per_erg = (1.6612 + 1.6688) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+// Calculate ERG/XAU through ERG/USD and XAUT/USD, both from Bitfinex
+pub async fn get_kgau_nanoerg() -> Result<AssetsExchangeRate<KgAu, NanoErg>, DataPointSourceError> {
+    Ok(convert_rate(
+        get_usd_nanoerg().await?,
+        get_kgau_usd().await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coincap;
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_kgau_usd_price() {
+        let pair: AssetsExchangeRate<KgAu, Usd> = tokio_test::block_on(get_kgau_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+
+    #[test]
+    fn test_usd_nanoerg_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_kgau_nanoerg_price() {
+        let pair = tokio_test::block_on(get_kgau_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_kgau_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_usd_lovelace_price() {
+        let pair = tokio_test::block_on(get_usd_lovelace()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_lovelace()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_btc_usd_price() {
+        let pair = tokio_test::block_on(get_btc_usd()).unwrap();
+        let coincap = tokio_test::block_on(coincap::get_btc_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coincap = (pair.rate - coincap.rate).abs() / coincap.rate;
+        assert!(
+            deviation_from_coincap < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+}
@@ -1,38 +1,99 @@
+//! Gold, platinum, palladium and BTC prices from the Bitpanda ticker. With `bitpanda_api_key` in the oracle config the
+//! ticker is requested with the key, so the requests count against the higher rate limit of the
+//! key instead of the per-IP limit of the public endpoint.
+
+use reqwest::header::HeaderValue;
+use serde::Deserialize;
+
 use super::assets_exchange_rate::AssetsExchangeRate;
 use super::assets_exchange_rate::Btc;
 use super::assets_exchange_rate::Usd;
 use super::erg_xau::KgAu;
+use super::erg_xpd::KgPd;
+use super::erg_xpt::KgPt;
+use super::http_client::HttpClientError;
+use super::response::Quote;
 use super::DataPointSourceError;
 
+#[cfg(not(test))]
+const TICKER_URL: &str = "https://api.bitpanda.com/v1/ticker";
+const API_KEY_HEADER: &str = "X-Api-Key";
+
 #[derive(Debug, Clone)]
 pub struct BitPanda {}
 
+/// The assets of the ticker we use, the rest of the ticker is skipped while parsing
+#[derive(Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct Ticker {
+    xau: Option<Prices>,
+    xpt: Option<Prices>,
+    xpd: Option<Prices>,
+    btc: Option<Prices>,
+}
+
+#[derive(Deserialize)]
+struct Prices {
+    #[serde(rename = "USD")]
+    usd: Option<Quote>,
+}
+
+fn usd_price(prices: &Option<Prices>) -> Option<&Quote> {
+    prices.as_ref().and_then(|p| p.usd.as_ref())
+}
+
+/// Checks that the key can be sent in the request header
+pub fn validate_bitpanda_api_key(key: &str) -> Result<(), HttpClientError> {
+    HeaderValue::from_str(key)
+        .map(|_| ())
+        .map_err(|_| HttpClientError::InvalidHeaderValue(API_KEY_HEADER.to_string()))
+}
+
+/// Ticker of all the assets, requested with the API key if it's configured. A rejected key falls
+/// back to the public endpoint.
 #[cfg(not(test))]
-pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
-    let url = "https://api.bitpanda.com/v1/ticker";
-    let resp = reqwest::get(url).await?;
-    let json = json::parse(&resp.text().await?)?;
-    if let Some(p) = json["XAU"]["USD"].as_str() {
-        // USD price of 1 gram of gold
-        let p_float = p
-            .parse::<f64>()
-            .map_err(|_| DataPointSourceError::JsonMissingField {
-                field: "XAU.USD as f64".to_string(),
-                json: json.dump(),
-            })?;
-        let usd_per_kgau = KgAu::from_gram(p_float);
-        let rate = AssetsExchangeRate {
-            per1: KgAu {},
-            get: Usd {},
-            rate: usd_per_kgau,
-        };
-        Ok(rate)
-    } else {
-        Err(DataPointSourceError::JsonMissingField {
-            field: "XAU.USD".to_string(),
-            json: json.dump(),
-        })
+async fn get_ticker() -> Result<Ticker, DataPointSourceError> {
+    let api_key = crate::oracle_config::ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.bitpanda_api_key.clone());
+    if let Some(key) = api_key {
+        let resp = super::http_client::client_builder_for("bitpanda")?
+            .build()?
+            .get(TICKER_URL)
+            .header(API_KEY_HEADER, key)
+            .send()
+            .await?;
+        let status = resp.status();
+        if status != reqwest::StatusCode::UNAUTHORIZED && status != reqwest::StatusCode::FORBIDDEN {
+            return super::response::parse_body("bitpanda", resp.error_for_status()?).await;
+        }
+        log::warn!(
+            "Bitpanda rejected the API key ({}), using the public endpoint",
+            status
+        );
     }
+    let resp = super::http_client::get("bitpanda", TICKER_URL).await?;
+    super::response::parse_body("bitpanda", resp).await
+}
+
+#[cfg(not(test))]
+pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
+    let ticker = get_ticker().await?;
+    // USD price of 1 gram of gold
+    let p_float = super::response::price(
+        "bitpanda",
+        "$.XAU.USD",
+        usd_price(&ticker.xau),
+        super::response::USD_PER_GRAM_OF_GOLD,
+    )?;
+    let usd_per_kgau = KgAu::from_gram(p_float);
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: Usd {},
+        rate: usd_per_kgau,
+    };
+    Ok(rate)
 }
 
 #[cfg(test)]
@@ -48,32 +109,82 @@ pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSo
     Ok(rate)
 }
 
+#[cfg(not(test))]
+pub async fn get_kgpt_usd() -> Result<AssetsExchangeRate<KgPt, Usd>, DataPointSourceError> {
+    let ticker = get_ticker().await?;
+    // USD price of 1 gram of platinum
+    let p_float = super::response::price(
+        "bitpanda",
+        "$.XPT.USD",
+        usd_price(&ticker.xpt),
+        super::response::USD_PER_GRAM_OF_PLATINUM,
+    )?;
+    let rate = AssetsExchangeRate {
+        per1: KgPt {},
+        get: Usd {},
+        rate: KgPt::from_gram(p_float),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_kgpt_usd() -> Result<AssetsExchangeRate<KgPt, Usd>, DataPointSourceError> {
+    // USD price of 1 gram of platinum
+    let p_float = 31.55;
+    let rate = AssetsExchangeRate {
+        per1: KgPt {},
+        get: Usd {},
+        rate: KgPt::from_gram(p_float),
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_kgpd_usd() -> Result<AssetsExchangeRate<KgPd, Usd>, DataPointSourceError> {
+    let ticker = get_ticker().await?;
+    // USD price of 1 gram of palladium
+    let p_float = super::response::price(
+        "bitpanda",
+        "$.XPD.USD",
+        usd_price(&ticker.xpd),
+        super::response::USD_PER_GRAM_OF_PALLADIUM,
+    )?;
+    let rate = AssetsExchangeRate {
+        per1: KgPd {},
+        get: Usd {},
+        rate: KgPd::from_gram(p_float),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_kgpd_usd() -> Result<AssetsExchangeRate<KgPd, Usd>, DataPointSourceError> {
+    // USD price of 1 gram of palladium
+    let p_float = 32.98;
+    let rate = AssetsExchangeRate {
+        per1: KgPd {},
+        get: Usd {},
+        rate: KgPd::from_gram(p_float),
+    };
+    Ok(rate)
+}
+
 #[cfg(not(test))]
 // Get USD/BTC. Can be used as a redundant source for ERG/BTC through ERG/USD and USD/BTC
 pub(crate) async fn get_btc_usd() -> Result<AssetsExchangeRate<Btc, Usd>, DataPointSourceError> {
-    let url = "https://api.bitpanda.com/v1/ticker";
-    let resp = reqwest::get(url).await?;
-    let json = json::parse(&resp.text().await?)?;
-    if let Some(p) = json["BTC"]["USD"].as_str() {
-        // USD price of BTC
-        let usd_per_btc = p
-            .parse::<f64>()
-            .map_err(|_| DataPointSourceError::JsonMissingField {
-                field: "BTC.USD as f64".to_string(),
-                json: json.dump(),
-            })?;
-        let rate = AssetsExchangeRate {
-            per1: Btc {},
-            get: Usd {},
-            rate: usd_per_btc,
-        };
-        Ok(rate)
-    } else {
-        Err(DataPointSourceError::JsonMissingField {
-            field: "BTC.USD".to_string(),
-            json: json.dump(),
-        })
-    }
+    let ticker = get_ticker().await?;
+    let usd_per_btc = super::response::price(
+        "bitpanda",
+        "$.BTC.USD",
+        usd_price(&ticker.btc),
+        super::response::USD_PER_BTC,
+    )?;
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: Usd {},
+        rate: usd_per_btc,
+    };
+    Ok(rate)
 }
 
 #[cfg(test)]
@@ -98,8 +209,29 @@ mod tests {
         assert!(pair.rate > 0.0);
     }
     #[test]
+    fn test_kgpt_usd_price() {
+        let pair: AssetsExchangeRate<KgPt, Usd> = tokio_test::block_on(get_kgpt_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+    #[test]
+    fn test_kgpd_usd_price() {
+        let pair: AssetsExchangeRate<KgPd, Usd> = tokio_test::block_on(get_kgpd_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+    #[test]
     fn test_btc_usd_price() {
         let pair: AssetsExchangeRate<Btc, Usd> = tokio_test::block_on(get_btc_usd()).unwrap();
         assert!(pair.rate > 0.0);
     }
+
+    #[test]
+    fn test_validate_api_key() {
+        assert!(validate_bitpanda_api_key("key").is_ok());
+        assert_eq!(
+            validate_bitpanda_api_key("bad\nkey"),
+            Err(HttpClientError::InvalidHeaderValue(
+                API_KEY_HEADER.to_string()
+            ))
+        );
+    }
 }
@@ -0,0 +1,116 @@
+//! US consumer price index (CPI-U, all items, not seasonally adjusted, series `CUUR0000SA0`) from
+//! the public API of the US Bureau of Labor Statistics. The index is published monthly and the
+//! keyless API allows 25 requests a day, so the last value is reused for 6 hours.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use super::response::Quote;
+use super::DataPointSourceError;
+
+const CPI_U_URL: &str = "https://api.bls.gov/publicAPI/v2/timeseries/data/CUUR0000SA0";
+
+const CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TimeseriesResponse {
+    results: Option<Results>,
+}
+
+#[derive(Deserialize)]
+struct Results {
+    #[serde(default)]
+    series: Vec<Series>,
+}
+
+#[derive(Deserialize)]
+struct Series {
+    /// Most recent month first
+    #[serde(default)]
+    data: Vec<Observation>,
+}
+
+#[derive(Deserialize)]
+struct Observation {
+    /// Index level, "-" for the months not collected
+    value: Option<Quote>,
+}
+
+impl TimeseriesResponse {
+    /// Most recent published value of the series
+    fn latest_value(&self) -> Option<&Quote> {
+        let data = &self.results.as_ref()?.series.first()?.data;
+        data.iter()
+            .filter_map(|observation| observation.value.as_ref())
+            .find(|value| match value {
+                Quote::Number(_) => true,
+                Quote::Text(text) => text.parse::<f64>().is_ok(),
+            })
+            .or_else(|| data.first()?.value.as_ref())
+    }
+}
+
+lazy_static! {
+    static ref LAST_CPI_U: tokio::sync::Mutex<Option<(f64, Instant)>> =
+        tokio::sync::Mutex::new(None);
+}
+
+/// CPI-U index level (1982-84 = 100)
+#[cfg(not(test))]
+pub async fn get_cpi_u() -> Result<f64, DataPointSourceError> {
+    let mut last = LAST_CPI_U.lock().await;
+    if let Some((cpi_u, fetched)) = *last {
+        if fetched.elapsed() < CACHE_TTL {
+            return Ok(cpi_u);
+        }
+    }
+    let resp = super::http_client::get("bls", CPI_U_URL).await?;
+    let timeseries: TimeseriesResponse = super::response::parse_body("bls", resp).await?;
+    let cpi_u = super::response::price(
+        "bls",
+        "$.Results.series[0].data[0].value",
+        timeseries.latest_value(),
+        super::response::CPI_INDEX,
+    )?;
+    *last = Some((cpi_u, Instant::now()));
+    Ok(cpi_u)
+}
+
+#[cfg(test)]
+pub async fn get_cpi_u() -> Result<f64, DataPointSourceError> {
+    Ok(312.332)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_value() {
+        let timeseries: TimeseriesResponse = super::super::response::parse(
+            "bls",
+            br#"{"status":"REQUEST_SUCCEEDED","Results":{"series":[{"seriesID":"CUUR0000SA0",
+                "data":[{"year":"2025","period":"M10","value":"-"},
+                {"year":"2025","period":"M09","latest":"true","value":"324.800"},
+                {"year":"2025","period":"M08","value":"323.976"}]}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            timeseries.latest_value(),
+            Some(&Quote::Text("324.800".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_request_not_processed() {
+        let timeseries: TimeseriesResponse = super::super::response::parse(
+            "bls",
+            br#"{"status":"REQUEST_NOT_PROCESSED","message":["daily threshold reached"],"Results":{}}"#,
+        )
+        .unwrap();
+        assert_eq!(timeseries.latest_value(), None);
+    }
+}
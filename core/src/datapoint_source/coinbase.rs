@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct SpotPrice {
+    data: Option<SpotPriceData>,
+}
+
+#[derive(Deserialize)]
+struct SpotPriceData {
+    amount: Option<Quote>,
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    // see https://docs.cdp.coinbase.com/coinbase-app/docs/api-prices
+    let url = "https://api.coinbase.com/v2/prices/ERG-USD/spot";
+    let resp = super::http_client::get("coinbase", url).await?;
+    let spot_price: SpotPrice = super::response::parse_body("coinbase", resp).await?;
+    let p_float = super::response::price(
+        "coinbase",
+        "$.data.amount",
+        spot_price.data.and_then(|d| d.amount).as_ref(),
+        super::response::USD_PER_ERG,
+    )?;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let p_float = 1.664;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_erg_usd_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+}
@@ -1,38 +1,55 @@
+use serde::Deserialize;
+
+use super::ada_usd::Lovelace;
 use super::assets_exchange_rate::AssetsExchangeRate;
 use super::assets_exchange_rate::Btc;
 use super::assets_exchange_rate::NanoErg;
 use super::assets_exchange_rate::Usd;
+use super::response::Quote;
 use super::DataPointSourceError;
 
 #[derive(Debug, Clone)]
 pub struct CoinCap;
 
+#[derive(Deserialize)]
+struct Asset {
+    data: Option<AssetData>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AssetData {
+    price_usd: Option<Quote>,
+}
+
+/// USD price of the CoinCap asset (e.g. `ergo`)
 #[cfg(not(test))]
-pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+async fn get_price_usd(
+    asset_id: &str,
+    range: super::response::PlausibleRange,
+) -> Result<f64, DataPointSourceError> {
     // see https://coincap.io/assets/ergo
-    let url = "https://api.coincap.io/v2/assets/ergo";
-    let resp = reqwest::get(url).await?;
-    let price_json = json::parse(&resp.text().await?)?;
-    if let Some(p) = price_json["data"]["priceUsd"].as_str() {
-        let p_float = p
-            .parse::<f64>()
-            .map_err(|_| DataPointSourceError::JsonMissingField {
-                field: "data.priceUsd as f64".to_string(),
-                json: price_json.dump(),
-            })?;
-        let nanoerg_per_usd = NanoErg::from_erg(1.0 / p_float);
-        let rate = AssetsExchangeRate {
-            per1: Usd {},
-            get: NanoErg {},
-            rate: nanoerg_per_usd,
-        };
-        Ok(rate)
-    } else {
-        Err(DataPointSourceError::JsonMissingField {
-            field: "ergo.priceUsd as string".to_string(),
-            json: price_json.dump(),
-        })
-    }
+    let url = format!("https://api.coincap.io/v2/assets/{}", asset_id);
+    let resp = super::http_client::get("coincap", url).await?;
+    let asset: Asset = super::response::parse_body("coincap", resp).await?;
+    Ok(super::response::price(
+        "coincap",
+        "$.data.priceUsd",
+        asset.data.and_then(|d| d.price_usd).as_ref(),
+        range,
+    )?)
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let p_float = get_price_usd("ergo", super::response::USD_PER_ERG).await?;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
 }
 
 #[cfg(test)]
@@ -50,29 +67,13 @@ pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataP
 #[cfg(not(test))]
 // Get USD/BTC. Can be used as a redundant source for ERG/BTC through ERG/USD and USD/BTC
 pub async fn get_btc_usd() -> Result<AssetsExchangeRate<Btc, Usd>, DataPointSourceError> {
-    // see https://coincap.io/assets/ergo
-    let url = "https://api.coincap.io/v2/assets/bitcoin";
-    let resp = reqwest::get(url).await?;
-    let price_json = json::parse(&resp.text().await?)?;
-    if let Some(p) = price_json["data"]["priceUsd"].as_str() {
-        let usd_per_btc = p
-            .parse::<f64>()
-            .map_err(|_| DataPointSourceError::JsonMissingField {
-                field: "data.priceUsd as f64".to_string(),
-                json: price_json.dump(),
-            })?;
-        let rate = AssetsExchangeRate {
-            per1: Btc {},
-            get: Usd {},
-            rate: usd_per_btc,
-        };
-        Ok(rate)
-    } else {
-        Err(DataPointSourceError::JsonMissingField {
-            field: "btc.priceUsd as string".to_string(),
-            json: price_json.dump(),
-        })
-    }
+    let usd_per_btc = get_price_usd("bitcoin", super::response::USD_PER_BTC).await?;
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: Usd {},
+        rate: usd_per_btc,
+    };
+    Ok(rate)
 }
 
 #[cfg(test)]
@@ -86,6 +87,30 @@ pub async fn get_btc_usd() -> Result<AssetsExchangeRate<Btc, Usd>, DataPointSour
     Ok(rate)
 }
 
+#[cfg(not(test))]
+pub async fn get_usd_lovelace() -> Result<AssetsExchangeRate<Usd, Lovelace>, DataPointSourceError> {
+    let p_float = get_price_usd("cardano", super::response::USD_PER_ADA).await?;
+    let lovelace_per_usd = Lovelace::from_ada(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: Lovelace {},
+        rate: lovelace_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_lovelace() -> Result<AssetsExchangeRate<Usd, Lovelace>, DataPointSourceError> {
+    let p_float = 0.604_218_417_59;
+    let lovelace_per_usd = Lovelace::from_ada(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: Lovelace {},
+        rate: lovelace_per_usd,
+    };
+    Ok(rate)
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::bitpanda;
@@ -103,6 +128,18 @@ mod tests {
             "up to 5% deviation is allowed"
         );
     }
+    #[test]
+    fn test_ada_usd_price() {
+        let pair = tokio_test::block_on(get_usd_lovelace()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_lovelace()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
     #[test]
     fn test_usd_btc_price() {
         let pair = tokio_test::block_on(get_btc_usd()).unwrap();
@@ -1,3 +1,16 @@
+//! CoinGecko prices. All the pairs are read from a single `simple/price` request, which is cached
+//! for a minute, so the pairs fetched in one oracle loop cost one request of the free-tier quota.
+//! After a 429 (Too Many Requests) response no request is sent until the backoff ends. With
+//! `coingecko_pro_api_key` in the oracle config the requests go to the paid (pro) API.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use reqwest::header::HeaderValue;
+use serde::Deserialize;
+use thiserror::Error;
+
 use crate::datapoint_source::assets_exchange_rate::AssetsExchangeRate;
 use crate::datapoint_source::assets_exchange_rate::NanoErg;
 use crate::datapoint_source::DataPointSourceError;
@@ -5,29 +18,183 @@ use crate::datapoint_source::DataPointSourceError;
 use super::ada_usd::Lovelace;
 use super::assets_exchange_rate::Btc;
 use super::assets_exchange_rate::Usd;
+use super::erg_jpy::Jpy;
 use super::erg_xau::KgAu;
+use super::http_client::HttpClientError;
+use super::response::Quote;
+
+const PUBLIC_API_URL: &str = "https://api.coingecko.com/api/v3";
+const PRO_API_URL: &str = "https://pro-api.coingecko.com/api/v3";
+const PRO_API_KEY_HEADER: &str = "x-cg-pro-api-key";
+/// Prices of all the pairs of the predefined sources
+const SIMPLE_PRICE_ENDPOINT: &str = "/simple/price?ids=ergo,cardano&vs_currencies=usd,xau,btc,jpy";
+/// Responses are reused for this long
+const CACHE_TTL: Duration = Duration::from_secs(60);
+/// Backoff after a 429 response without `Retry-After`, doubled by consecutive 429 responses
+const MIN_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// `simple/price` response, the prices of each coin in the requested vs currencies
+#[derive(Deserialize)]
+struct SimplePrice {
+    ergo: Option<CoinPrices>,
+    cardano: Option<CoinPrices>,
+}
+
+#[derive(Deserialize)]
+struct CoinPrices {
+    usd: Option<Quote>,
+    xau: Option<Quote>,
+    btc: Option<Quote>,
+    jpy: Option<Quote>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CoinGeckoError {
+    #[error("rate limited by CoinGecko, next request in {retry_in_secs}s")]
+    RateLimited { retry_in_secs: u64 },
+}
+
+struct CachedResponse {
+    body: String,
+    fetched: Instant,
+}
+
+/// Responses by endpoint and the rate limit backoff, shared by all the CoinGecko pairs
+#[derive(Default)]
+struct ResponseCache {
+    responses: HashMap<&'static str, CachedResponse>,
+    /// No request is sent before this
+    backoff_until: Option<Instant>,
+    last_backoff: Option<Duration>,
+}
+
+impl ResponseCache {
+    /// Cached response of the endpoint if it's not older than [`CACHE_TTL`]
+    fn fresh(&self, endpoint: &str, now: Instant) -> Option<&str> {
+        self.responses
+            .get(endpoint)
+            .filter(|cached| now.saturating_duration_since(cached.fetched) < CACHE_TTL)
+            .map(|cached| cached.body.as_str())
+    }
+
+    fn check_backoff(&self, now: Instant) -> Result<(), CoinGeckoError> {
+        match self.backoff_until {
+            Some(until) if until > now => Err(CoinGeckoError::RateLimited {
+                retry_in_secs: until.saturating_duration_since(now).as_secs(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn store(&mut self, endpoint: &'static str, body: String, now: Instant) {
+        self.responses
+            .insert(endpoint, CachedResponse { body, fetched: now });
+        self.backoff_until = None;
+        self.last_backoff = None;
+    }
+
+    /// Starts the backoff after a 429 response, returns its length
+    fn rate_limited(&mut self, now: Instant, retry_after: Option<Duration>) -> Duration {
+        let backoff = retry_after.unwrap_or_else(|| {
+            self.last_backoff
+                .map_or(MIN_BACKOFF, |last| (last * 2).min(MAX_BACKOFF))
+        });
+        self.backoff_until = Some(now + backoff);
+        self.last_backoff = Some(backoff);
+        backoff
+    }
+}
+
+lazy_static! {
+    // held during the request, so the pairs fetched concurrently wait for a single response
+    static ref CACHE: tokio::sync::Mutex<ResponseCache> =
+        tokio::sync::Mutex::new(ResponseCache::default());
+}
 
 #[cfg(not(test))]
-pub async fn get_kgau_nanoerg() -> Result<AssetsExchangeRate<KgAu, NanoErg>, DataPointSourceError> {
-    let url = "https://api.coingecko.com/api/v3/simple/price?ids=ergo&vs_currencies=XAU";
-    let resp = reqwest::get(url).await?;
-    let price_json = json::parse(&resp.text().await?)?;
-    if let Some(p) = price_json["ergo"]["xau"].as_f64() {
-        // Convert from price Erg/XAU to nanoErgs per 1 XAU
-        let nanoerg_per_troy_ounce = NanoErg::from_erg(1.0 / p);
-        let nanoerg_per_kg = KgAu::from_troy_ounce(nanoerg_per_troy_ounce);
-        let rate = AssetsExchangeRate {
-            per1: KgAu {},
-            get: NanoErg {},
-            rate: nanoerg_per_kg,
-        };
-        Ok(rate)
-    } else {
-        Err(DataPointSourceError::JsonMissingField {
-            field: "ergo.xau as f64".to_string(),
-            json: price_json.dump(),
-        })
+static CLIENT: once_cell::sync::OnceCell<reqwest::Client> = once_cell::sync::OnceCell::new();
+
+/// Checks that the key can be sent in the request header
+pub fn validate_coingecko_api_key(key: &str) -> Result<(), HttpClientError> {
+    HeaderValue::from_str(key)
+        .map(|_| ())
+        .map_err(|_| HttpClientError::InvalidHeaderValue(PRO_API_KEY_HEADER.to_string()))
+}
+
+/// `Retry-After` of a 429 response, in seconds
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(not(test))]
+async fn get_cached(endpoint: &'static str) -> Result<SimplePrice, DataPointSourceError> {
+    let mut cache = CACHE.lock().await;
+    if let Some(body) = cache.fresh(endpoint, Instant::now()) {
+        return Ok(super::response::parse("coingecko", body.as_bytes())?);
+    }
+    cache.check_backoff(Instant::now())?;
+    // every datapoint fetch runs on a new tokio runtime, pooled connections would not outlive it
+    let client = CLIENT.get_or_try_init(|| {
+        Ok::<_, DataPointSourceError>(
+            super::http_client::client_builder_for("coingecko")?
+                .pool_max_idle_per_host(0)
+                .build()?,
+        )
+    })?;
+    let pro_api_key = crate::oracle_config::ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.coingecko_pro_api_key.clone());
+    let request = match pro_api_key {
+        Some(key) => client
+            .get(format!("{}{}", PRO_API_URL, endpoint))
+            .header(PRO_API_KEY_HEADER, key),
+        None => client.get(format!("{}{}", PUBLIC_API_URL, endpoint)),
+    };
+    let resp = request.send().await?;
+    if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let backoff = cache.rate_limited(Instant::now(), retry_after(resp.headers()));
+        log::warn!(
+            "Rate limited by CoinGecko, backing off for {}s",
+            backoff.as_secs()
+        );
+        return Err(CoinGeckoError::RateLimited {
+            retry_in_secs: backoff.as_secs(),
+        }
+        .into());
     }
+    let body = resp.error_for_status()?.text().await?;
+    let simple_price = super::response::parse("coingecko", body.as_bytes())?;
+    cache.store(endpoint, body, Instant::now());
+    Ok(simple_price)
+}
+
+#[cfg(not(test))]
+pub async fn get_kgau_nanoerg() -> Result<AssetsExchangeRate<KgAu, NanoErg>, DataPointSourceError> {
+    let simple_price = get_cached(SIMPLE_PRICE_ENDPOINT).await?;
+    let p = super::response::price(
+        "coingecko",
+        "$.ergo.xau",
+        simple_price.ergo.as_ref().and_then(|c| c.xau.as_ref()),
+        super::response::XAU_PER_ERG,
+    )?;
+    // Convert from price Erg/XAU to nanoErgs per 1 XAU
+    let nanoerg_per_troy_ounce = NanoErg::from_erg(1.0 / p);
+    let nanoerg_per_kg = KgAu::from_troy_ounce(nanoerg_per_troy_ounce);
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: NanoErg {},
+        rate: nanoerg_per_kg,
+    };
+    Ok(rate)
 }
 
 #[cfg(test)]
@@ -44,24 +211,21 @@ pub async fn get_kgau_nanoerg() -> Result<AssetsExchangeRate<KgAu, NanoErg>, Dat
 
 #[cfg(not(test))]
 pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
-    let url = "https://api.coingecko.com/api/v3/simple/price?ids=ergo&vs_currencies=USD";
-    let resp = reqwest::get(url).await?;
-    let price_json = json::parse(&resp.text().await?)?;
-    if let Some(p) = price_json["ergo"]["usd"].as_f64() {
-        // Convert from price Erg/USD to nanoErgs per 1 USD
-        let nanoerg_per_usd = NanoErg::from_erg(1.0 / p);
-        let rate = AssetsExchangeRate {
-            per1: Usd {},
-            get: NanoErg {},
-            rate: nanoerg_per_usd,
-        };
-        Ok(rate)
-    } else {
-        Err(DataPointSourceError::JsonMissingField {
-            field: "ergo.usd as f64".to_string(),
-            json: price_json.dump(),
-        })
-    }
+    let simple_price = get_cached(SIMPLE_PRICE_ENDPOINT).await?;
+    let p = super::response::price(
+        "coingecko",
+        "$.ergo.usd",
+        simple_price.ergo.as_ref().and_then(|c| c.usd.as_ref()),
+        super::response::USD_PER_ERG,
+    )?;
+    // Convert from price Erg/USD to nanoErgs per 1 USD
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
 }
 
 #[cfg(test)]
@@ -78,24 +242,21 @@ pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataP
 
 #[cfg(not(test))]
 pub async fn get_usd_lovelace() -> Result<AssetsExchangeRate<Usd, Lovelace>, DataPointSourceError> {
-    let url = "https://api.coingecko.com/api/v3/simple/price?ids=cardano&vs_currencies=USD";
-    let resp = reqwest::get(url).await?;
-    let price_json = json::parse(&resp.text().await?)?;
-    if let Some(p) = price_json["cardano"]["usd"].as_f64() {
-        // Convert from price Erg/USD to nanoErgs per 1 USD
-        let lovelace_price = Lovelace::from_ada(1.0 / p);
-        let rate = AssetsExchangeRate {
-            per1: Usd {},
-            get: Lovelace {},
-            rate: lovelace_price,
-        };
-        Ok(rate)
-    } else {
-        Err(DataPointSourceError::JsonMissingField {
-            field: "cardano.usd as f64".to_string(),
-            json: price_json.dump(),
-        })
-    }
+    let simple_price = get_cached(SIMPLE_PRICE_ENDPOINT).await?;
+    let p = super::response::price(
+        "coingecko",
+        "$.cardano.usd",
+        simple_price.cardano.as_ref().and_then(|c| c.usd.as_ref()),
+        super::response::USD_PER_ADA,
+    )?;
+    // Convert from price Erg/USD to nanoErgs per 1 USD
+    let lovelace_price = Lovelace::from_ada(1.0 / p);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: Lovelace {},
+        rate: lovelace_price,
+    };
+    Ok(rate)
 }
 
 #[cfg(test)]
@@ -112,24 +273,21 @@ pub async fn get_usd_lovelace() -> Result<AssetsExchangeRate<Usd, Lovelace>, Dat
 
 #[cfg(not(test))]
 pub async fn get_btc_nanoerg() -> Result<AssetsExchangeRate<Btc, NanoErg>, DataPointSourceError> {
-    let url = "https://api.coingecko.com/api/v3/simple/price?ids=ergo&vs_currencies=BTC";
-    let resp = reqwest::get(url).await?;
-    let price_json = json::parse(&resp.text().await?)?;
-    if let Some(p) = price_json["ergo"]["btc"].as_f64() {
-        // Convert from price BTC/ERG to nanoERG/BTC
-        let erg_per_usd = NanoErg::from_erg(1.0 / p);
-        let rate = AssetsExchangeRate {
-            per1: Btc {},
-            get: NanoErg {},
-            rate: erg_per_usd,
-        };
-        Ok(rate)
-    } else {
-        Err(DataPointSourceError::JsonMissingField {
-            field: "ergo.btc as f64".to_string(),
-            json: price_json.dump(),
-        })
-    }
+    let simple_price = get_cached(SIMPLE_PRICE_ENDPOINT).await?;
+    let p = super::response::price(
+        "coingecko",
+        "$.ergo.btc",
+        simple_price.ergo.as_ref().and_then(|c| c.btc.as_ref()),
+        super::response::BTC_PER_ERG,
+    )?;
+    // Convert from price BTC/ERG to nanoERG/BTC
+    let erg_per_usd = NanoErg::from_erg(1.0 / p);
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: NanoErg {},
+        rate: erg_per_usd,
+    };
+    Ok(rate)
 }
 
 #[cfg(test)]
@@ -144,10 +302,90 @@ pub async fn get_btc_nanoerg() -> Result<AssetsExchangeRate<Btc, NanoErg>, DataP
     Ok(rate)
 }
 
+#[cfg(not(test))]
+pub async fn get_jpy_nanoerg() -> Result<AssetsExchangeRate<Jpy, NanoErg>, DataPointSourceError> {
+    let simple_price = get_cached(SIMPLE_PRICE_ENDPOINT).await?;
+    let p = super::response::price(
+        "coingecko",
+        "$.ergo.jpy",
+        simple_price.ergo.as_ref().and_then(|c| c.jpy.as_ref()),
+        super::response::JPY_PER_ERG,
+    )?;
+    // Convert from price JPY/ERG to nanoERG/JPY
+    let nanoerg_per_jpy = NanoErg::from_erg(1.0 / p);
+    let rate = AssetsExchangeRate {
+        per1: Jpy {},
+        get: NanoErg {},
+        rate: nanoerg_per_jpy,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_jpy_nanoerg() -> Result<AssetsExchangeRate<Jpy, NanoErg>, DataPointSourceError> {
+    let nanoerg_per_jpy = NanoErg::from_erg(1.0 / 250.3);
+    let rate = AssetsExchangeRate {
+        per1: Jpy {},
+        get: NanoErg {},
+        rate: nanoerg_per_jpy,
+    };
+    Ok(rate)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_response_cache() {
+        let now = Instant::now();
+        let mut cache = ResponseCache::default();
+        assert_eq!(cache.fresh(SIMPLE_PRICE_ENDPOINT, now), None);
+        cache.store(SIMPLE_PRICE_ENDPOINT, "{}".to_string(), now);
+        assert_eq!(
+            cache.fresh(SIMPLE_PRICE_ENDPOINT, now + Duration::from_secs(30)),
+            Some("{}")
+        );
+        assert_eq!(cache.fresh(SIMPLE_PRICE_ENDPOINT, now + CACHE_TTL), None);
+    }
+
+    #[test]
+    fn test_rate_limit_backoff() {
+        let now = Instant::now();
+        let mut cache = ResponseCache::default();
+        assert_eq!(cache.check_backoff(now), Ok(()));
+        assert_eq!(cache.rate_limited(now, None), MIN_BACKOFF);
+        assert_eq!(
+            cache.check_backoff(now + Duration::from_secs(10)),
+            Err(CoinGeckoError::RateLimited { retry_in_secs: 50 })
+        );
+        assert_eq!(cache.check_backoff(now + MIN_BACKOFF), Ok(()));
+        // consecutive 429 responses
+        assert_eq!(cache.rate_limited(now, None), MIN_BACKOFF * 2);
+        assert_eq!(
+            cache.rate_limited(now, Some(Duration::from_secs(5))),
+            Duration::from_secs(5)
+        );
+        for _ in 0..10 {
+            cache.rate_limited(now, None);
+        }
+        assert_eq!(cache.last_backoff, Some(MAX_BACKOFF));
+        // a successful response ends the backoff
+        cache.store(SIMPLE_PRICE_ENDPOINT, "{}".to_string(), now);
+        assert_eq!(cache.check_backoff(now), Ok(()));
+        assert_eq!(cache.rate_limited(now, None), MIN_BACKOFF);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(reqwest::header::RETRY_AFTER, HeaderValue::from_static("30"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(30)));
+        assert!(validate_coingecko_api_key("CG-key").is_ok());
+        assert!(validate_coingecko_api_key("bad\nkey").is_err());
+    }
+
     #[test]
     fn test_erg_xau_price() {
         let pair: AssetsExchangeRate<KgAu, NanoErg> =
@@ -174,4 +412,11 @@ mod tests {
             tokio_test::block_on(get_btc_nanoerg()).unwrap();
         assert!(pair.rate > 0.0);
     }
+
+    #[test]
+    fn test_erg_jpy_price() {
+        let pair: AssetsExchangeRate<Jpy, NanoErg> =
+            tokio_test::block_on(get_jpy_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
 }
@@ -0,0 +1,66 @@
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct Price {
+    #[serde(rename = "USD")]
+    usd: Option<Quote>,
+}
+
+/// ERG/USD of the CryptoCompare aggregated index (CCCAGG), already averaged across exchanges.
+/// The API key (optional) is sent as the `authorization: Apikey <key>` header set in
+/// `source_http_headers`.
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    // see https://min-api.cryptocompare.com/documentation?key=Price&cat=SingleSymbolPriceEndpoint
+    let url = "https://min-api.cryptocompare.com/data/price?fsym=ERG&tsyms=USD&e=CCCAGG";
+    let resp = super::http_client::get("cryptocompare", url).await?;
+    let price: Price = super::response::parse_body("cryptocompare", resp).await?;
+    let p = super::response::price(
+        "cryptocompare",
+        "$.USD",
+        price.usd.as_ref(),
+        super::response::USD_PER_ERG,
+    )?;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let usd_per_erg = 1.668;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_erg_usd_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+}
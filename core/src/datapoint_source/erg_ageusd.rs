@@ -0,0 +1,77 @@
+//! Obtains the nanoErg per 1 SigUSD and per 1 SigRSV (SigmaUSD ageUSD tokens) rates
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::sigmausd;
+use super::spectrum;
+use super::DataPointSourceError;
+
+/// SigmaUSD stablecoin (1 SigUSD = 100 cents)
+#[derive(Debug, Clone, Copy)]
+pub struct SigUsd {}
+
+/// SigmaUSD reserve coin
+#[derive(Debug, Clone, Copy)]
+pub struct SigRsv {}
+
+impl Asset for SigUsd {}
+impl Asset for SigRsv {}
+
+/// Sources of both the SigUSD and SigRSV pairs
+pub const NANOERG_AGEUSD_SOURCE_NAMES: &[&str] = &["sigmausd", "spectrum"];
+
+#[allow(clippy::type_complexity)]
+pub fn nanoerg_sigusd_source(
+    name: &str,
+) -> Option<
+    Pin<
+        Box<dyn Future<Output = Result<AssetsExchangeRate<SigUsd, NanoErg>, DataPointSourceError>>>,
+    >,
+> {
+    match name {
+        "sigmausd" => Some(Box::pin(sigmausd::get_sigusd_nanoerg())),
+        "spectrum" => Some(Box::pin(spectrum::get_sigusd_nanoerg())),
+        _ => None,
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn nanoerg_sigrsv_source(
+    name: &str,
+) -> Option<
+    Pin<
+        Box<dyn Future<Output = Result<AssetsExchangeRate<SigRsv, NanoErg>, DataPointSourceError>>>,
+    >,
+> {
+    match name {
+        "sigmausd" => Some(Box::pin(sigmausd::get_sigrsv_nanoerg())),
+        "spectrum" => Some(Box::pin(spectrum::get_sigrsv_nanoerg())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sigusd_nanoerg_bank_vs_dex() {
+        let bank = tokio_test::block_on(sigmausd::get_sigusd_nanoerg()).unwrap();
+        let dex = tokio_test::block_on(spectrum::get_sigusd_nanoerg()).unwrap();
+        let deviation_from_bank = (dex.rate - bank.rate).abs() / bank.rate;
+        assert!(deviation_from_bank < 0.05, "up to 5% deviation is allowed");
+    }
+
+    #[test]
+    fn test_sigrsv_nanoerg_bank_vs_dex() {
+        let bank = tokio_test::block_on(sigmausd::get_sigrsv_nanoerg()).unwrap();
+        let dex = tokio_test::block_on(spectrum::get_sigrsv_nanoerg()).unwrap();
+        let deviation_from_bank = (dex.rate - bank.rate).abs() / bank.rate;
+        assert!(deviation_from_bank < 0.05, "up to 5% deviation is allowed");
+    }
+}
@@ -0,0 +1,74 @@
+//! Obtains the nanoErg per 1 barrel of Brent crude oil rate, the Brent USD price of a commodities
+//! API crossed with the ERG/USD rate aggregated from the `NanoErgUsd` sources
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::aggregator::fetch_aggregated;
+use super::assets_exchange_rate::convert_rate;
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::erg_usd::nanoerg_usd_sources;
+use super::oilpriceapi;
+use super::DataPointSourceError;
+
+/// One barrel (42 US gallons, about 159 liters) of Brent crude oil. The datapoint of the pair is
+/// the nanoErg per 1 barrel rate, unscaled: Brent at 80 USD with ERG at 1 USD gives
+/// 80_000_000_000. A contract reading the price per liter divides it by [`Bbl::LITERS`].
+#[derive(Debug, Clone, Copy)]
+pub struct Bbl {}
+
+impl Asset for Bbl {}
+
+impl Bbl {
+    /// US gallons per barrel
+    pub const US_GALLONS: f64 = 42.0;
+    /// Liters per barrel (42 US gallons of 3.785411784 liters)
+    pub const LITERS: f64 = Self::US_GALLONS * 3.785_411_784;
+}
+
+pub const NANOERG_BBL_SOURCE_NAMES: &[&str] = &["oilpriceapi"];
+
+#[allow(clippy::type_complexity)]
+pub fn nanoerg_bbl_source(
+    name: &str,
+) -> Option<
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Bbl, NanoErg>, DataPointSourceError>>>>,
+> {
+    match name {
+        "oilpriceapi" => Some(Box::pin(oilpriceapi_bbl_nanoerg())),
+        _ => None,
+    }
+}
+
+/// Brent spot price from oilpriceapi.com
+pub async fn oilpriceapi_bbl_nanoerg(
+) -> Result<AssetsExchangeRate<Bbl, NanoErg>, DataPointSourceError> {
+    let bbl_usd_rate = oilpriceapi::get_bbl_usd().await?;
+    let aggregated_usd_nanoerg_rate = fetch_aggregated(nanoerg_usd_sources()).await?;
+    Ok(convert_rate(aggregated_usd_nanoerg_rate, bbl_usd_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_bbl_nanoerg() {
+        let pair = tokio_test::block_on(oilpriceapi_bbl_nanoerg()).unwrap();
+        let expected = convert_rate(
+            tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap(),
+            tokio_test::block_on(oilpriceapi::get_bbl_usd()).unwrap(),
+        );
+        let deviation = (pair.rate - expected.rate).abs() / expected.rate;
+        assert!(deviation < 0.05, "up to 5% deviation is allowed");
+    }
+
+    #[test]
+    fn test_liters_per_barrel() {
+        assert!((Bbl::LITERS - 158.987_294_928).abs() < 1e-9);
+    }
+}
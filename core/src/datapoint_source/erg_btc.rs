@@ -4,18 +4,35 @@ use futures::Future;
 
 use super::{
     assets_exchange_rate::{convert_rate, AssetsExchangeRate, Btc, NanoErg},
-    bitpanda, coincap, coingecko, DataPointSourceError,
+    bitfinex, bitpanda, coincap, coingecko, kucoin, okx, spectrum, DataPointSourceError,
 };
 
+pub const NANOERG_BTC_SOURCE_NAMES: &[&str] = &[
+    "coingecko",
+    "coincap",
+    "bitpanda",
+    "bitfinex",
+    "kucoin",
+    "okx",
+    "spectrum",
+];
+
 #[allow(clippy::type_complexity)]
-pub fn nanoerg_btc_sources() -> Vec<
+pub fn nanoerg_btc_source(
+    name: &str,
+) -> Option<
     Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Btc, NanoErg>, DataPointSourceError>>>>,
 > {
-    vec![
-        Box::pin(coingecko::get_btc_nanoerg()),
-        Box::pin(get_btc_nanoerg_coincap()),
-        Box::pin(get_btc_nanoerg_bitpanda()),
-    ]
+    match name {
+        "coingecko" => Some(Box::pin(coingecko::get_btc_nanoerg())),
+        "coincap" => Some(Box::pin(get_btc_nanoerg_coincap())),
+        "bitpanda" => Some(Box::pin(get_btc_nanoerg_bitpanda())),
+        "bitfinex" => Some(Box::pin(get_btc_nanoerg_bitfinex())),
+        "kucoin" => Some(Box::pin(kucoin::get_btc_nanoerg())),
+        "okx" => Some(Box::pin(get_btc_nanoerg_okx())),
+        "spectrum" => Some(Box::pin(spectrum::get_btc_nanoerg())),
+        _ => None,
+    }
 }
 
 // Calculate ERG/BTC through ERG/USD and USD/BTC
@@ -35,9 +52,25 @@ async fn get_btc_nanoerg_bitpanda() -> Result<AssetsExchangeRate<Btc, NanoErg>,
     ))
 }
 
+async fn get_btc_nanoerg_bitfinex() -> Result<AssetsExchangeRate<Btc, NanoErg>, DataPointSourceError>
+{
+    Ok(convert_rate(
+        coincap::get_usd_nanoerg().await?,
+        bitfinex::get_btc_usd().await?,
+    ))
+}
+
+async fn get_btc_nanoerg_okx() -> Result<AssetsExchangeRate<Btc, NanoErg>, DataPointSourceError> {
+    Ok(convert_rate(
+        okx::get_usd_nanoerg().await?,
+        okx::get_btc_usd().await?,
+    ))
+}
+
 #[cfg(test)]
 mod test {
     use super::coingecko;
+    use super::get_btc_nanoerg_bitfinex;
     use super::get_btc_nanoerg_bitpanda;
     use super::get_btc_nanoerg_coincap;
     #[test]
@@ -61,5 +94,12 @@ mod test {
             deviation_from_bitpanda < 0.05,
             "up to 5% deviation is allowed"
         );
+        let bitfinex = tokio_test::block_on(get_btc_nanoerg_bitfinex()).unwrap();
+        let bitfinex_deviation_from_coingecko =
+            (bitfinex.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            bitfinex_deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
     }
 }
@@ -0,0 +1,74 @@
+//! Obtains the nanoErg per 1 JPY rate. Besides the direct CoinGecko quote the ERG/USD rate is
+//! crossed with the USD/JPY forex rate of an independent provider.
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::assets_exchange_rate::convert_rate;
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::coincap;
+use super::coingecko;
+use super::exchangerate_host;
+use super::kraken;
+use super::DataPointSourceError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Jpy {}
+
+impl Asset for Jpy {}
+
+pub const NANOERG_JPY_SOURCE_NAMES: &[&str] = &["coingecko", "kraken", "exchangerate_host"];
+
+#[allow(clippy::type_complexity)]
+pub fn nanoerg_jpy_source(
+    name: &str,
+) -> Option<
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Jpy, NanoErg>, DataPointSourceError>>>>,
+> {
+    match name {
+        "coingecko" => Some(Box::pin(coingecko::get_jpy_nanoerg())),
+        "kraken" => Some(Box::pin(get_jpy_nanoerg_kraken())),
+        "exchangerate_host" => Some(Box::pin(get_jpy_nanoerg_exchangerate_host())),
+        _ => None,
+    }
+}
+
+// Calculate ERG/JPY through ERG/USD and USD/JPY
+async fn get_jpy_nanoerg_kraken() -> Result<AssetsExchangeRate<Jpy, NanoErg>, DataPointSourceError>
+{
+    Ok(convert_rate(
+        kraken::get_usd_nanoerg().await?,
+        kraken::get_jpy_usd().await?,
+    ))
+}
+
+async fn get_jpy_nanoerg_exchangerate_host(
+) -> Result<AssetsExchangeRate<Jpy, NanoErg>, DataPointSourceError> {
+    Ok(convert_rate(
+        coincap::get_usd_nanoerg().await?,
+        exchangerate_host::get_jpy_usd().await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jpy_nanoerg_combined() {
+        let coingecko = tokio_test::block_on(coingecko::get_jpy_nanoerg()).unwrap();
+        for combined in [
+            tokio_test::block_on(get_jpy_nanoerg_kraken()).unwrap(),
+            tokio_test::block_on(get_jpy_nanoerg_exchangerate_host()).unwrap(),
+        ] {
+            let deviation_from_coingecko = (combined.rate - coingecko.rate).abs() / coingecko.rate;
+            assert!(
+                deviation_from_coingecko < 0.05,
+                "up to 5% deviation is allowed"
+            );
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Obtains the nanoErg per 1 RSN (Rosen Bridge token) rate
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::spectrum;
+use super::DataPointSourceError;
+
+/// Rosen Bridge token
+#[derive(Debug, Clone, Copy)]
+pub struct Rsn {}
+
+impl Asset for Rsn {}
+
+pub const NANOERG_RSN_SOURCE_NAMES: &[&str] = &["spectrum"];
+
+#[allow(clippy::type_complexity)]
+pub fn nanoerg_rsn_source(
+    name: &str,
+) -> Option<
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Rsn, NanoErg>, DataPointSourceError>>>>,
+> {
+    match name {
+        "spectrum" => Some(Box::pin(spectrum::get_rsn_nanoerg())),
+        _ => None,
+    }
+}
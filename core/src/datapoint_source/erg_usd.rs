@@ -7,16 +7,73 @@ use futures::Future;
 use super::assets_exchange_rate::AssetsExchangeRate;
 use super::assets_exchange_rate::NanoErg;
 use super::assets_exchange_rate::Usd;
+use super::basket::is_opt_in;
+use super::bitfinex;
+use super::coinbase;
 use super::coincap;
 use super::coingecko;
+use super::cryptocompare;
+use super::gateio;
+use super::htx;
+use super::kraken;
+use super::kraken_ws;
+use super::kucoin;
+use super::messari;
+use super::okx;
+use super::spectrum;
 use super::DataPointSourceError;
+use super::PredefinedDataPointSource;
+
+pub const NANOERG_USD_SOURCE_NAMES: &[&str] = &[
+    "bitfinex",
+    "coinbase",
+    "coincap",
+    "coingecko",
+    "cryptocompare",
+    "gateio",
+    "htx",
+    "kraken",
+    "kraken_ws",
+    "kucoin",
+    "messari",
+    "okx",
+    "spectrum",
+    "spectrum_sigusd",
+];
+
+#[allow(clippy::type_complexity)]
+pub fn nanoerg_usd_source(
+    name: &str,
+) -> Option<
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError>>>>,
+> {
+    match name {
+        "bitfinex" => Some(Box::pin(bitfinex::get_usd_nanoerg())),
+        "coinbase" => Some(Box::pin(coinbase::get_usd_nanoerg())),
+        "coincap" => Some(Box::pin(coincap::get_usd_nanoerg())),
+        "coingecko" => Some(Box::pin(coingecko::get_usd_nanoerg())),
+        "cryptocompare" => Some(Box::pin(cryptocompare::get_usd_nanoerg())),
+        "gateio" => Some(Box::pin(gateio::get_usd_nanoerg())),
+        "htx" => Some(Box::pin(htx::get_usd_nanoerg())),
+        "kraken" => Some(Box::pin(kraken::get_usd_nanoerg())),
+        kraken_ws::SOURCE_NAME => Some(Box::pin(kraken_ws::get_usd_nanoerg())),
+        "kucoin" => Some(Box::pin(kucoin::get_usd_nanoerg())),
+        "messari" => Some(Box::pin(messari::get_usd_nanoerg())),
+        "okx" => Some(Box::pin(okx::get_usd_nanoerg())),
+        "spectrum" => Some(Box::pin(spectrum::get_usd_nanoerg())),
+        "spectrum_sigusd" => Some(Box::pin(spectrum::get_usd_nanoerg_from_sigusd())),
+        _ => None,
+    }
+}
 
 #[allow(clippy::type_complexity)]
 pub fn nanoerg_usd_sources() -> Vec<
     Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError>>>>,
 > {
-    vec![
-        Box::pin(coincap::get_usd_nanoerg()),
-        Box::pin(coingecko::get_usd_nanoerg()),
-    ]
+    NANOERG_USD_SOURCE_NAMES
+        .iter()
+        .copied()
+        .filter(|name| !is_opt_in(PredefinedDataPointSource::NanoErgUsd, name))
+        .filter_map(nanoerg_usd_source)
+        .collect()
 }
@@ -9,9 +9,14 @@ use super::assets_exchange_rate::convert_rate;
 use super::assets_exchange_rate::Asset;
 use super::assets_exchange_rate::AssetsExchangeRate;
 use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::bitfinex;
 use super::bitpanda;
 use super::coingecko;
 use super::erg_usd::nanoerg_usd_sources;
+use super::exchangerate_host;
+use super::goldapi;
+use super::okx;
 use super::DataPointSourceError;
 
 #[derive(Debug, Clone, Copy)]
@@ -35,19 +40,65 @@ impl KgAu {
     }
 }
 
+pub const NANOERG_KGAU_SOURCE_NAMES: &[&str] =
+    &["coingecko", "bitpanda", "xaut", "bitfinex", "goldapi"];
+
 #[allow(clippy::type_complexity)]
-pub fn nanoerg_kgau_sources() -> Vec<
+pub fn nanoerg_kgau_source(
+    name: &str,
+) -> Option<
     Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<KgAu, NanoErg>, DataPointSourceError>>>>,
 > {
+    match name {
+        "coingecko" => Some(Box::pin(coingecko::get_kgau_nanoerg())),
+        "bitpanda" => Some(Box::pin(combined_kgau_nanoerg())),
+        "xaut" => Some(Box::pin(xaut_kgau_nanoerg())),
+        "bitfinex" => Some(Box::pin(bitfinex::get_kgau_nanoerg())),
+        "goldapi" => Some(Box::pin(goldapi_kgau_nanoerg())),
+        _ => None,
+    }
+}
+
+/// XAU/USD legs derived from Tether Gold (XAUT/USDT) order books
+#[allow(clippy::type_complexity)]
+pub fn xaut_kgau_usd_sources(
+) -> Vec<Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError>>>>>
+{
     vec![
-        Box::pin(coingecko::get_kgau_nanoerg()),
-        Box::pin(combined_kgau_nanoerg()),
+        Box::pin(bitfinex::get_kgau_usd()),
+        Box::pin(okx::get_kgau_usd()),
     ]
 }
 
+/// Gold priced by bitpanda, or by the exchangerate.host forex rates if bitpanda is unreachable
 pub async fn combined_kgau_nanoerg(
 ) -> Result<AssetsExchangeRate<KgAu, NanoErg>, DataPointSourceError> {
-    let kgau_usd_rate = bitpanda::get_kgau_usd().await?;
+    let kgau_usd_rate = match bitpanda::get_kgau_usd().await {
+        Ok(rate) => rate,
+        Err(e) => {
+            log::warn!(
+                "bitpanda XAU/USD failed, falling back to exchangerate.host: {}",
+                e
+            );
+            exchangerate_host::get_kgau_usd().await?
+        }
+    };
+    let aggregated_usd_nanoerg_rate = fetch_aggregated(nanoerg_usd_sources()).await?;
+    Ok(convert_rate(aggregated_usd_nanoerg_rate, kgau_usd_rate))
+}
+
+/// Gold spot price from goldapi.io, an alternative to bitpanda's
+pub async fn goldapi_kgau_nanoerg(
+) -> Result<AssetsExchangeRate<KgAu, NanoErg>, DataPointSourceError> {
+    let kgau_usd_rate = goldapi::get_kgau_usd().await?;
+    let aggregated_usd_nanoerg_rate = fetch_aggregated(nanoerg_usd_sources()).await?;
+    Ok(convert_rate(aggregated_usd_nanoerg_rate, kgau_usd_rate))
+}
+
+/// Gold priced through XAUT on crypto exchanges, independent of the metals data vendors
+pub async fn xaut_kgau_nanoerg() -> Result<AssetsExchangeRate<KgAu, NanoErg>, DataPointSourceError>
+{
+    let kgau_usd_rate = fetch_aggregated(xaut_kgau_usd_sources()).await?;
     let aggregated_usd_nanoerg_rate = fetch_aggregated(nanoerg_usd_sources()).await?;
     Ok(convert_rate(aggregated_usd_nanoerg_rate, kgau_usd_rate))
 }
@@ -67,4 +118,15 @@ mod tests {
             "up to 5% deviation is allowed"
         );
     }
+
+    #[test]
+    fn test_kgau_nanoerg_xaut() {
+        let xaut = tokio_test::block_on(xaut_kgau_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_kgau_nanoerg()).unwrap();
+        let deviation_from_coingecko = (xaut.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
 }
@@ -0,0 +1,103 @@
+//! Obtains the nanoErg per 1 kg of palladium rate. CoinGecko and most exchanges don't quote
+//! palladium, so every source crosses an ERG/USD rate with the XPD/USD rate of a metals vendor.
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::aggregator::fetch_aggregated;
+use super::assets_exchange_rate::convert_rate;
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::bitpanda;
+use super::coingecko;
+use super::erg_usd::nanoerg_usd_sources;
+use super::exchangerate_host;
+use super::goldapi;
+use super::DataPointSourceError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct KgPd {}
+
+impl Asset for KgPd {}
+
+impl KgPd {
+    pub fn from_troy_ounce(oz: f64) -> f64 {
+        // troy ounces per kg
+        oz * 32.150746568627
+    }
+
+    pub fn from_gram(g: f64) -> f64 {
+        g * 1000.0
+    }
+}
+
+pub const NANOERG_KGPD_SOURCE_NAMES: &[&str] = &["bitpanda", "coingecko", "goldapi"];
+
+#[allow(clippy::type_complexity)]
+pub fn nanoerg_kgpd_source(
+    name: &str,
+) -> Option<
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<KgPd, NanoErg>, DataPointSourceError>>>>,
+> {
+    match name {
+        "bitpanda" => Some(Box::pin(combined_kgpd_nanoerg())),
+        "coingecko" => Some(Box::pin(coingecko_kgpd_nanoerg())),
+        "goldapi" => Some(Box::pin(goldapi_kgpd_nanoerg())),
+        _ => None,
+    }
+}
+
+/// Platinum priced by bitpanda, or by the exchangerate.host forex rates if bitpanda is unreachable
+pub async fn combined_kgpd_nanoerg(
+) -> Result<AssetsExchangeRate<KgPd, NanoErg>, DataPointSourceError> {
+    let kgpd_usd_rate = match bitpanda::get_kgpd_usd().await {
+        Ok(rate) => rate,
+        Err(e) => {
+            log::warn!(
+                "bitpanda XPD/USD failed, falling back to exchangerate.host: {}",
+                e
+            );
+            exchangerate_host::get_kgpd_usd().await?
+        }
+    };
+    let aggregated_usd_nanoerg_rate = fetch_aggregated(nanoerg_usd_sources()).await?;
+    Ok(convert_rate(aggregated_usd_nanoerg_rate, kgpd_usd_rate))
+}
+
+/// CoinGecko ERG/USD crossed with the exchangerate.host XPD/USD rate
+pub async fn coingecko_kgpd_nanoerg(
+) -> Result<AssetsExchangeRate<KgPd, NanoErg>, DataPointSourceError> {
+    Ok(convert_rate(
+        coingecko::get_usd_nanoerg().await?,
+        exchangerate_host::get_kgpd_usd().await?,
+    ))
+}
+
+/// Platinum spot price from goldapi.io, an alternative to bitpanda's
+pub async fn goldapi_kgpd_nanoerg(
+) -> Result<AssetsExchangeRate<KgPd, NanoErg>, DataPointSourceError> {
+    let kgpd_usd_rate = goldapi::get_kgpd_usd().await?;
+    let aggregated_usd_nanoerg_rate = fetch_aggregated(nanoerg_usd_sources()).await?;
+    Ok(convert_rate(aggregated_usd_nanoerg_rate, kgpd_usd_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kgpd_nanoerg_combined() {
+        let combined = tokio_test::block_on(combined_kgpd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko_kgpd_nanoerg()).unwrap();
+        let goldapi = tokio_test::block_on(goldapi_kgpd_nanoerg()).unwrap();
+        for rate in [combined.rate, goldapi.rate] {
+            let deviation_from_coingecko = (rate - coingecko.rate).abs() / coingecko.rate;
+            assert!(
+                deviation_from_coingecko < 0.05,
+                "up to 5% deviation is allowed"
+            );
+        }
+    }
+}
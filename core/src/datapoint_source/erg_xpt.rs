@@ -0,0 +1,103 @@
+//! Obtains the nanoErg per 1 kg of platinum rate. CoinGecko and most exchanges don't quote
+//! platinum, so every source crosses an ERG/USD rate with the XPT/USD rate of a metals vendor.
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::aggregator::fetch_aggregated;
+use super::assets_exchange_rate::convert_rate;
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::bitpanda;
+use super::coingecko;
+use super::erg_usd::nanoerg_usd_sources;
+use super::exchangerate_host;
+use super::goldapi;
+use super::DataPointSourceError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct KgPt {}
+
+impl Asset for KgPt {}
+
+impl KgPt {
+    pub fn from_troy_ounce(oz: f64) -> f64 {
+        // troy ounces per kg
+        oz * 32.150746568627
+    }
+
+    pub fn from_gram(g: f64) -> f64 {
+        g * 1000.0
+    }
+}
+
+pub const NANOERG_KGPT_SOURCE_NAMES: &[&str] = &["bitpanda", "coingecko", "goldapi"];
+
+#[allow(clippy::type_complexity)]
+pub fn nanoerg_kgpt_source(
+    name: &str,
+) -> Option<
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<KgPt, NanoErg>, DataPointSourceError>>>>,
+> {
+    match name {
+        "bitpanda" => Some(Box::pin(combined_kgpt_nanoerg())),
+        "coingecko" => Some(Box::pin(coingecko_kgpt_nanoerg())),
+        "goldapi" => Some(Box::pin(goldapi_kgpt_nanoerg())),
+        _ => None,
+    }
+}
+
+/// Platinum priced by bitpanda, or by the exchangerate.host forex rates if bitpanda is unreachable
+pub async fn combined_kgpt_nanoerg(
+) -> Result<AssetsExchangeRate<KgPt, NanoErg>, DataPointSourceError> {
+    let kgpt_usd_rate = match bitpanda::get_kgpt_usd().await {
+        Ok(rate) => rate,
+        Err(e) => {
+            log::warn!(
+                "bitpanda XPT/USD failed, falling back to exchangerate.host: {}",
+                e
+            );
+            exchangerate_host::get_kgpt_usd().await?
+        }
+    };
+    let aggregated_usd_nanoerg_rate = fetch_aggregated(nanoerg_usd_sources()).await?;
+    Ok(convert_rate(aggregated_usd_nanoerg_rate, kgpt_usd_rate))
+}
+
+/// CoinGecko ERG/USD crossed with the exchangerate.host XPT/USD rate
+pub async fn coingecko_kgpt_nanoerg(
+) -> Result<AssetsExchangeRate<KgPt, NanoErg>, DataPointSourceError> {
+    Ok(convert_rate(
+        coingecko::get_usd_nanoerg().await?,
+        exchangerate_host::get_kgpt_usd().await?,
+    ))
+}
+
+/// Platinum spot price from goldapi.io, an alternative to bitpanda's
+pub async fn goldapi_kgpt_nanoerg(
+) -> Result<AssetsExchangeRate<KgPt, NanoErg>, DataPointSourceError> {
+    let kgpt_usd_rate = goldapi::get_kgpt_usd().await?;
+    let aggregated_usd_nanoerg_rate = fetch_aggregated(nanoerg_usd_sources()).await?;
+    Ok(convert_rate(aggregated_usd_nanoerg_rate, kgpt_usd_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kgpt_nanoerg_combined() {
+        let combined = tokio_test::block_on(combined_kgpt_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko_kgpt_nanoerg()).unwrap();
+        let goldapi = tokio_test::block_on(goldapi_kgpt_nanoerg()).unwrap();
+        for rate in [combined.rate, goldapi.rate] {
+            let deviation_from_coingecko = (rate - coingecko.rate).abs() / coingecko.rate;
+            assert!(
+                deviation_from_coingecko < 0.05,
+                "up to 5% deviation is allowed"
+            );
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Obtains the Ergo network hashrate (hashes per second), for hashrate derivative pools. The
+//! `node` source derives it from the difficulty reported by the connected node, the `explorer`
+//! source reads the hashrate estimated by the explorer.
+
+use std::pin::Pin;
+
+use futures::Future;
+use reqwest::Url;
+use serde::Deserialize;
+
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::response::Quote;
+use super::response::ResponseError;
+use super::DataPointSourceError;
+
+/// Target time between the blocks, the difficulty is the number of hashes expected per block
+const BLOCK_INTERVAL_SECS: f64 = 120.0;
+
+/// The Ergo network
+#[derive(Debug, Clone, Copy)]
+pub struct ErgoNetwork {}
+
+/// Hashes per second
+#[derive(Debug, Clone, Copy)]
+pub struct HashPerSecond {}
+
+impl Asset for ErgoNetwork {}
+impl Asset for HashPerSecond {}
+
+impl HashPerSecond {
+    pub fn from_difficulty(difficulty: f64) -> f64 {
+        (difficulty / BLOCK_INTERVAL_SECS).round()
+    }
+}
+
+pub const ERGO_HASHRATE_SOURCE_NAMES: &[&str] = &["node", "explorer"];
+
+#[allow(clippy::type_complexity)]
+pub fn ergo_hashrate_source(
+    name: &str,
+) -> Option<
+    Pin<
+        Box<
+            dyn Future<
+                Output = Result<
+                    AssetsExchangeRate<ErgoNetwork, HashPerSecond>,
+                    DataPointSourceError,
+                >,
+            >,
+        >,
+    >,
+> {
+    match name {
+        "node" => Some(Box::pin(get_hashrate_node())),
+        "explorer" => Some(Box::pin(get_hashrate_explorer())),
+        _ => None,
+    }
+}
+
+/// `/info` of the node
+#[derive(Deserialize)]
+struct NodeInfo {
+    difficulty: Option<Quote>,
+}
+
+/// `/api/v1/info` of the explorer
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerInfo {
+    hash_rate: Option<Quote>,
+}
+
+/// Joins the path to the base url, keeping the base path even without a trailing slash (e.g. a
+/// node behind a reverse proxy at `https://host/ergo`)
+fn join_path(provider: &str, base: &Url, path: &str) -> Result<Url, ResponseError> {
+    let mut base = base.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(path).map_err(|e| ResponseError::Malformed {
+        provider: provider.to_string(),
+        error: e.to_string(),
+    })
+}
+
+#[cfg(not(test))]
+async fn get_hashrate_node(
+) -> Result<AssetsExchangeRate<ErgoNetwork, HashPerSecond>, DataPointSourceError> {
+    let url = join_path(
+        "node",
+        &crate::oracle_config::ORACLE_CONFIG.node_url,
+        "info",
+    )?;
+    let resp = super::http_client::get("node", url).await?;
+    let info: NodeInfo = super::response::parse_body("node", resp).await?;
+    let difficulty = super::response::price(
+        "node",
+        "$.difficulty",
+        info.difficulty.as_ref(),
+        super::response::DIFFICULTY,
+    )?;
+    Ok(AssetsExchangeRate {
+        per1: ErgoNetwork {},
+        get: HashPerSecond {},
+        rate: HashPerSecond::from_difficulty(difficulty),
+    })
+}
+
+#[cfg(test)]
+async fn get_hashrate_node(
+) -> Result<AssetsExchangeRate<ErgoNetwork, HashPerSecond>, DataPointSourceError> {
+    Ok(AssetsExchangeRate {
+        per1: ErgoNetwork {},
+        get: HashPerSecond {},
+        rate: HashPerSecond::from_difficulty(2_500_000_000_000_000.0),
+    })
+}
+
+#[cfg(not(test))]
+async fn get_hashrate_explorer(
+) -> Result<AssetsExchangeRate<ErgoNetwork, HashPerSecond>, DataPointSourceError> {
+    let config = &crate::oracle_config::ORACLE_CONFIG;
+    let explorer_url = config.explorer_url.clone().unwrap_or_else(|| {
+        crate::explorer_api::explorer_url::default_explorer_api_url(config.network_prefix())
+    });
+    let url = join_path("explorer", &explorer_url, "api/v1/info")?;
+    let resp = super::http_client::get("explorer", url).await?;
+    let info: ExplorerInfo = super::response::parse_body("explorer", resp).await?;
+    let hashrate = super::response::price(
+        "explorer",
+        "$.hashRate",
+        info.hash_rate.as_ref(),
+        super::response::HASHES_PER_SECOND,
+    )?;
+    Ok(AssetsExchangeRate {
+        per1: ErgoNetwork {},
+        get: HashPerSecond {},
+        rate: hashrate.round(),
+    })
+}
+
+#[cfg(test)]
+async fn get_hashrate_explorer(
+) -> Result<AssetsExchangeRate<ErgoNetwork, HashPerSecond>, DataPointSourceError> {
+    Ok(AssetsExchangeRate {
+        per1: ErgoNetwork {},
+        get: HashPerSecond {},
+        rate: 20_900_000_000_000.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::aggregator::fetch_aggregated;
+    use super::*;
+
+    #[test]
+    fn test_ergo_hashrate_aggregated() {
+        let sources = ERGO_HASHRATE_SOURCE_NAMES
+            .iter()
+            .filter_map(|name| ergo_hashrate_source(name))
+            .collect();
+        let pair = tokio_test::block_on(fetch_aggregated(sources)).unwrap();
+        let node = tokio_test::block_on(get_hashrate_node()).unwrap();
+        let deviation = (pair.rate - node.rate).abs() / node.rate;
+        assert!(deviation < 0.05, "up to 5% deviation is allowed");
+    }
+
+    #[test]
+    fn test_hashrate_from_difficulty() {
+        assert_eq!(
+            HashPerSecond::from_difficulty(2_400_000_000_000_000.0),
+            20_000_000_000_000.0
+        );
+    }
+
+    #[test]
+    fn test_join_path() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert_eq!(
+            join_path("node", &url("http://127.0.0.1:9053"), "info").unwrap(),
+            url("http://127.0.0.1:9053/info")
+        );
+        assert_eq!(
+            join_path("node", &url("https://host/ergo"), "info").unwrap(),
+            url("https://host/ergo/info")
+        );
+        assert_eq!(
+            join_path(
+                "explorer",
+                &url("https://api-testnet.ergoplatform.com/"),
+                "api/v1/info"
+            )
+            .unwrap(),
+            url("https://api-testnet.ergoplatform.com/api/v1/info")
+        );
+    }
+
+    #[test]
+    fn test_parse_node_info() {
+        let info: NodeInfo = super::super::response::parse(
+            "node",
+            br#"{"name":"ergo-node","difficulty":2011599413248000,"fullHeight":1250000}"#,
+        )
+        .unwrap();
+        let difficulty = super::super::response::price(
+            "node",
+            "$.difficulty",
+            info.difficulty.as_ref(),
+            super::super::response::DIFFICULTY,
+        )
+        .unwrap();
+        assert_eq!(difficulty, 2_011_599_413_248_000.0);
+    }
+}
@@ -0,0 +1,175 @@
+//! Forex rates from exchangerate.host, the fallback of the metal legs priced by bitpanda and the
+//! USD/JPY leg of the ERG/JPY pair. The platinum and palladium rates also back the `coingecko`
+//! sources of ERG/XPT and ERG/XPD, as CoinGecko doesn't quote these metals.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::Usd;
+use super::erg_jpy::Jpy;
+use super::erg_xau::KgAu;
+use super::erg_xpd::KgPd;
+use super::erg_xpt::KgPt;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct LatestRates {
+    #[serde(default)]
+    rates: HashMap<String, Quote>,
+}
+
+/// Price of 1 unit of the base currency in the symbol currency
+#[cfg(not(test))]
+async fn get_rate(
+    base: &str,
+    symbol: &str,
+    range: super::response::PlausibleRange,
+) -> Result<f64, DataPointSourceError> {
+    // see https://exchangerate.host/documentation
+    let url = format!(
+        "https://api.exchangerate.host/latest?base={}&symbols={}",
+        base, symbol
+    );
+    let resp = super::http_client::get("exchangerate_host", url).await?;
+    let latest: LatestRates = super::response::parse_body("exchangerate_host", resp).await?;
+    Ok(super::response::price(
+        "exchangerate_host",
+        &format!("$.rates.{}", symbol),
+        latest.rates.get(symbol),
+        range,
+    )?)
+}
+
+#[cfg(not(test))]
+pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
+    // USD price of 1 troy ounce of gold
+    let usd_per_troy_ounce = get_rate("XAU", "USD", super::response::USD_PER_TROY_OUNCE).await?;
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: Usd {},
+        rate: KgAu::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
+    let usd_per_troy_ounce = 2052.18;
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: Usd {},
+        rate: KgAu::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_kgpt_usd() -> Result<AssetsExchangeRate<KgPt, Usd>, DataPointSourceError> {
+    // USD price of 1 troy ounce of platinum
+    let usd_per_troy_ounce = get_rate(
+        "XPT",
+        "USD",
+        super::response::USD_PER_TROY_OUNCE_OF_PLATINUM,
+    )
+    .await?;
+    let rate = AssetsExchangeRate {
+        per1: KgPt {},
+        get: Usd {},
+        rate: KgPt::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_kgpt_usd() -> Result<AssetsExchangeRate<KgPt, Usd>, DataPointSourceError> {
+    let usd_per_troy_ounce = 979.1;
+    let rate = AssetsExchangeRate {
+        per1: KgPt {},
+        get: Usd {},
+        rate: KgPt::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_kgpd_usd() -> Result<AssetsExchangeRate<KgPd, Usd>, DataPointSourceError> {
+    // USD price of 1 troy ounce of palladium
+    let usd_per_troy_ounce = get_rate(
+        "XPD",
+        "USD",
+        super::response::USD_PER_TROY_OUNCE_OF_PALLADIUM,
+    )
+    .await?;
+    let rate = AssetsExchangeRate {
+        per1: KgPd {},
+        get: Usd {},
+        rate: KgPd::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_kgpd_usd() -> Result<AssetsExchangeRate<KgPd, Usd>, DataPointSourceError> {
+    let usd_per_troy_ounce = 1024.3;
+    let rate = AssetsExchangeRate {
+        per1: KgPd {},
+        get: Usd {},
+        rate: KgPd::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_jpy_usd() -> Result<AssetsExchangeRate<Jpy, Usd>, DataPointSourceError> {
+    let jpy_per_usd = get_rate("USD", "JPY", super::response::JPY_PER_USD).await?;
+    let rate = AssetsExchangeRate {
+        per1: Jpy {},
+        get: Usd {},
+        rate: 1.0 / jpy_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_jpy_usd() -> Result<AssetsExchangeRate<Jpy, Usd>, DataPointSourceError> {
+    let rate = AssetsExchangeRate {
+        per1: Jpy {},
+        get: Usd {},
+        rate: 1.0 / 149.65,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::bitpanda;
+    use super::super::kraken;
+    use super::*;
+
+    #[test]
+    fn test_kgau_usd_price() {
+        let pair = tokio_test::block_on(get_kgau_usd()).unwrap();
+        let bitpanda = tokio_test::block_on(bitpanda::get_kgau_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_bitpanda = (pair.rate - bitpanda.rate).abs() / bitpanda.rate;
+        assert!(
+            deviation_from_bitpanda < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_jpy_usd_price() {
+        let pair = tokio_test::block_on(get_jpy_usd()).unwrap();
+        let kraken = tokio_test::block_on(kraken::get_jpy_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_kraken = (pair.rate - kraken.rate).abs() / kraken.rate;
+        assert!(
+            deviation_from_kraken < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Datapoint source reading a local file (`data_point_source_file` in the oracle config), for the
+//! feeds written by another process, e.g. on an air-gapped machine. The file is re-read on every
+//! request and must hold the datapoint as an integer.
+
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::oracle_types::Rate;
+
+use super::DataPointSource;
+use super::DataPointSourceError;
+
+#[derive(Debug, Error)]
+pub enum FileSourceError {
+    #[error("failed to read datapoint file {}: {error}", .path.display())]
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    #[error("datapoint file {} doesn't hold an integer: {error}", .path.display())]
+    Parse {
+        path: PathBuf,
+        error: std::num::ParseIntError,
+    },
+    #[error(
+        "datapoint file {} is stale, modified {age_secs}s ago (max {max_age_secs}s)",
+        .path.display()
+    )]
+    Stale {
+        path: PathBuf,
+        age_secs: u64,
+        max_age_secs: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FileSource {
+    pub path: PathBuf,
+    /// The datapoint is rejected if the file was modified longer ago than this
+    pub max_age_secs: u64,
+}
+
+impl FileSource {
+    fn read_datapoint(&self, now: SystemTime) -> Result<Rate, FileSourceError> {
+        let io_error = |error| FileSourceError::Io {
+            path: self.path.clone(),
+            error,
+        };
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .map_err(io_error)?;
+        // a modification time in the future is not stale
+        let age = now.duration_since(modified).unwrap_or(Duration::ZERO);
+        if age.as_secs() > self.max_age_secs {
+            return Err(FileSourceError::Stale {
+                path: self.path.clone(),
+                age_secs: age.as_secs(),
+                max_age_secs: self.max_age_secs,
+            });
+        }
+        let content = std::fs::read_to_string(&self.path).map_err(io_error)?;
+        content
+            .trim()
+            .parse::<i64>()
+            .map(Into::into)
+            .map_err(|error| FileSourceError::Parse {
+                path: self.path.clone(),
+                error,
+            })
+    }
+}
+
+impl DataPointSource for FileSource {
+    fn get_datapoint(&self) -> Result<Rate, DataPointSourceError> {
+        Ok(self.read_datapoint(SystemTime::now())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_datapoint() {
+        let path = std::env::temp_dir().join("oracle_core_test_datapoint_file");
+        std::fs::write(&path, "1668116\n").unwrap();
+        let source = FileSource {
+            path: path.clone(),
+            max_age_secs: 600,
+        };
+        assert_eq!(source.read_datapoint(SystemTime::now()).unwrap(), 1668116);
+        let later = SystemTime::now() + Duration::from_secs(601);
+        assert!(matches!(
+            source.read_datapoint(later),
+            Err(FileSourceError::Stale { .. })
+        ));
+        std::fs::write(&path, "1.5").unwrap();
+        assert!(matches!(
+            source.read_datapoint(SystemTime::now()),
+            Err(FileSourceError::Parse { .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            source.read_datapoint(SystemTime::now()),
+            Err(FileSourceError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct Ticker {
+    last: Option<Quote>,
+    highest_bid: Option<Quote>,
+    lowest_ask: Option<Quote>,
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    // see https://www.gate.io/docs/developers/apiv4/#retrieve-ticker-information
+    // USDT is taken as USD
+    let url = "https://api.gateio.ws/api/v4/spot/tickers?currency_pair=ERG_USDT";
+    let resp = super::http_client::get("gateio", url).await?;
+    let tickers: Vec<Ticker> = super::response::parse_body("gateio", resp).await?;
+    let ticker = tickers.first();
+    let usd_per_erg = super::price_method::price(
+        "gateio",
+        ("$[0].last", ticker.and_then(|t| t.last.as_ref())),
+        (
+            "$[0].highest_bid",
+            ticker.and_then(|t| t.highest_bid.as_ref()),
+        ),
+        (
+            "$[0].lowest_ask",
+            ticker.and_then(|t| t.lowest_ask.as_ref()),
+        ),
+        super::response::USD_PER_ERG,
+    )?;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let usd_per_erg = (1.662 + 1.666) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_erg_usd_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+}
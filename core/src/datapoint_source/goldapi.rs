@@ -0,0 +1,122 @@
+//! Spot gold, platinum and palladium prices from the goldapi.io precious metals API. The API requires a key,
+//! set as the `x-access-token` header of the `goldapi` provider in `source_http_headers`.
+
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::Usd;
+use super::erg_xau::KgAu;
+use super::erg_xpd::KgPd;
+use super::erg_xpt::KgPt;
+use super::response::PlausibleRange;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct MetalPrice {
+    price: Option<Quote>,
+}
+
+/// USD price of 1 troy ounce of the metal
+#[cfg(not(test))]
+async fn get_usd_per_troy_ounce(
+    metal: &str,
+    range: PlausibleRange,
+) -> Result<f64, DataPointSourceError> {
+    // see https://www.goldapi.io/dashboard
+    let url = format!("https://www.goldapi.io/api/{}/USD", metal);
+    let resp = super::http_client::get("goldapi", &url).await?;
+    let metal_price: MetalPrice = super::response::parse_body("goldapi", resp).await?;
+    Ok(super::response::price(
+        "goldapi",
+        "$.price",
+        metal_price.price.as_ref(),
+        range,
+    )?)
+}
+
+#[cfg(test)]
+async fn get_usd_per_troy_ounce(
+    metal: &str,
+    _range: PlausibleRange,
+) -> Result<f64, DataPointSourceError> {
+    Ok(match metal {
+        "XPT" => 980.4,
+        "XPD" => 1025.6,
+        _ => 2050.35,
+    })
+}
+
+pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
+    let usd_per_troy_ounce =
+        get_usd_per_troy_ounce("XAU", super::response::USD_PER_TROY_OUNCE).await?;
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: Usd {},
+        rate: KgAu::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+pub async fn get_kgpt_usd() -> Result<AssetsExchangeRate<KgPt, Usd>, DataPointSourceError> {
+    let usd_per_troy_ounce =
+        get_usd_per_troy_ounce("XPT", super::response::USD_PER_TROY_OUNCE_OF_PLATINUM).await?;
+    let rate = AssetsExchangeRate {
+        per1: KgPt {},
+        get: Usd {},
+        rate: KgPt::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+pub async fn get_kgpd_usd() -> Result<AssetsExchangeRate<KgPd, Usd>, DataPointSourceError> {
+    let usd_per_troy_ounce =
+        get_usd_per_troy_ounce("XPD", super::response::USD_PER_TROY_OUNCE_OF_PALLADIUM).await?;
+    let rate = AssetsExchangeRate {
+        per1: KgPd {},
+        get: Usd {},
+        rate: KgPd::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::bitpanda;
+    use super::*;
+
+    #[test]
+    fn test_kgau_usd_price() {
+        let pair = tokio_test::block_on(get_kgau_usd()).unwrap();
+        let bitpanda = tokio_test::block_on(bitpanda::get_kgau_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_bitpanda = (pair.rate - bitpanda.rate).abs() / bitpanda.rate;
+        assert!(
+            deviation_from_bitpanda < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_kgpt_usd_price() {
+        let pair = tokio_test::block_on(get_kgpt_usd()).unwrap();
+        let bitpanda = tokio_test::block_on(bitpanda::get_kgpt_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_bitpanda = (pair.rate - bitpanda.rate).abs() / bitpanda.rate;
+        assert!(
+            deviation_from_bitpanda < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+    #[test]
+    fn test_kgpd_usd_price() {
+        let pair = tokio_test::block_on(get_kgpd_usd()).unwrap();
+        let bitpanda = tokio_test::block_on(bitpanda::get_kgpd_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_bitpanda = (pair.rate - bitpanda.rate).abs() / bitpanda.rate;
+        assert!(
+            deviation_from_bitpanda < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+}
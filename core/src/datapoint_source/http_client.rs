@@ -0,0 +1,186 @@
+//! Shared HTTP client for the datapoint source requests, with the User-Agent and the extra
+//! per-provider headers from the oracle config
+
+use std::collections::HashMap;
+
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use thiserror::Error;
+
+use crate::oracle_config::REDACTED;
+
+/// Sent if `http_user_agent` is not set in the oracle config, some APIs reject requests with the
+/// default reqwest User-Agent
+pub const DEFAULT_USER_AGENT: &str = concat!("oracle-core/", env!("CARGO_PKG_VERSION"));
+
+/// Names of the API providers queried by the datapoint sources, used as the keys of
+/// `source_http_headers` in the oracle config
+pub const PROVIDER_NAMES: &[&str] = &[
+    "bitfinex",
+    "bitpanda",
+    "bls",
+    "coinbase",
+    "coincap",
+    "coingecko",
+    "cryptocompare",
+    "exchangerate_host",
+    "explorer",
+    "gateio",
+    "goldapi",
+    "htx",
+    "kraken",
+    "kucoin",
+    "messari",
+    "node",
+    "oilpriceapi",
+    "okx",
+    "spectrum",
+    "stooq",
+    "yahoo",
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HttpClientError {
+    #[error("unknown provider {0} in source_http_headers, known providers are {PROVIDER_NAMES:?}")]
+    UnknownProvider(String),
+    #[error("invalid HTTP header name {0}")]
+    InvalidHeaderName(String),
+    #[error("invalid value of HTTP header {0}")]
+    InvalidHeaderValue(String),
+}
+
+pub(super) fn header_map(headers: &HashMap<String, String>) -> Result<HeaderMap, HttpClientError> {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| HttpClientError::InvalidHeaderName(name.clone()))?;
+        let header_value = HeaderValue::from_str(value)
+            .map_err(|_| HttpClientError::InvalidHeaderValue(name.clone()))?;
+        header_map.insert(header_name, header_value);
+    }
+    Ok(header_map)
+}
+
+/// Header names with the values redacted, for the debug output (the headers often carry API keys)
+pub fn redacted_headers(headers: &HashMap<String, String>) -> HashMap<&str, &str> {
+    headers
+        .keys()
+        .map(|name| (name.as_str(), REDACTED))
+        .collect()
+}
+
+/// Checks that the headers are listed for the known providers and are valid HTTP headers
+pub fn validate_http_headers(
+    user_agent: Option<&str>,
+    source_http_headers: &HashMap<String, HashMap<String, String>>,
+) -> Result<(), HttpClientError> {
+    if let Some(user_agent) = user_agent {
+        HeaderValue::from_str(user_agent)
+            .map_err(|_| HttpClientError::InvalidHeaderValue("User-Agent".to_string()))?;
+    }
+    for (provider, headers) in source_http_headers {
+        if !PROVIDER_NAMES.contains(&provider.as_str()) {
+            return Err(HttpClientError::UnknownProvider(provider.clone()));
+        }
+        header_map(headers)?;
+    }
+    Ok(())
+}
+
+/// Client builder with the User-Agent (default if not set) and the extra headers
+fn client_builder(
+    user_agent: Option<&str>,
+    headers: Option<&HashMap<String, String>>,
+) -> Result<reqwest::ClientBuilder, HttpClientError> {
+    let headers = headers.map(header_map).transpose()?.unwrap_or_default();
+    Ok(reqwest::Client::builder()
+        .user_agent(user_agent.unwrap_or(DEFAULT_USER_AGENT))
+        .default_headers(headers))
+}
+
+/// Client builder with the configured User-Agent and the extra headers of the provider
+#[cfg(not(test))]
+pub(super) fn client_builder_for(
+    provider: &str,
+) -> Result<reqwest::ClientBuilder, HttpClientError> {
+    let config = crate::oracle_config::ORACLE_CONFIG_OPT.as_ref().ok();
+    client_builder(
+        config.and_then(|c| c.http_user_agent.as_deref()),
+        config.and_then(|c| c.source_http_headers.get(provider)),
+    )
+}
+
+#[cfg(not(test))]
+fn client(provider: &str) -> Result<reqwest::Client, super::DataPointSourceError> {
+    Ok(client_builder_for(provider)?.build()?)
+}
+
+/// GET request to the provider's API
+#[cfg(not(test))]
+pub async fn get(
+    provider: &str,
+    url: impl reqwest::IntoUrl,
+) -> Result<reqwest::Response, super::DataPointSourceError> {
+    Ok(client(provider)?.get(url).send().await?)
+}
+
+/// GET request with the configured User-Agent and the given headers, for the sources defined in
+/// the oracle config
+pub async fn get_with_headers(
+    headers: &HashMap<String, String>,
+    url: impl reqwest::IntoUrl,
+) -> Result<reqwest::Response, super::DataPointSourceError> {
+    let config = crate::oracle_config::ORACLE_CONFIG_OPT.as_ref().ok();
+    let client = client_builder(
+        config.and_then(|c| c.http_user_agent.as_deref()),
+        Some(headers),
+    )?
+    .build()?;
+    Ok(client.get(url).send().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_http_headers() {
+        let headers = |provider: &str, name: &str, value: &str| {
+            HashMap::from([(
+                provider.to_string(),
+                HashMap::from([(name.to_string(), value.to_string())]),
+            )])
+        };
+        assert_eq!(
+            validate_http_headers(
+                Some("my-oracle/1.0"),
+                &headers("coingecko", "x-cg-demo-api-key", "key")
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate_http_headers(None, &headers("binance", "x-key", "key")),
+            Err(HttpClientError::UnknownProvider("binance".to_string()))
+        );
+        assert_eq!(
+            validate_http_headers(None, &headers("coingecko", "bad header", "key")),
+            Err(HttpClientError::InvalidHeaderName("bad header".to_string()))
+        );
+        assert_eq!(
+            validate_http_headers(None, &headers("coingecko", "x-key", "bad\nvalue")),
+            Err(HttpClientError::InvalidHeaderValue("x-key".to_string()))
+        );
+        assert!(validate_http_headers(Some("bad\nagent"), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_client_builder() {
+        let headers = HashMap::from([("x-api-key".to_string(), "key".to_string())]);
+        assert!(client_builder(None, None).unwrap().build().is_ok());
+        assert!(client_builder(Some("my-oracle/1.0"), Some(&headers))
+            .unwrap()
+            .build()
+            .is_ok());
+    }
+}
@@ -0,0 +1,202 @@
+//! Datapoint source defined in the oracle config (`data_point_source_http`): a number picked from
+//! the JSON response of a URL and multiplied by the scale factor, for the pools of the assets
+//! that have no predefined source.
+
+use std::collections::HashMap;
+
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::oracle_types::Rate;
+
+use super::http_client::header_map;
+use super::http_client::redacted_headers;
+use super::http_client::HttpClientError;
+use super::DataPointSource;
+use super::DataPointSourceError;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HttpJsonSourceError {
+    #[error("invalid JSON path {0}, expected e.g. $.ergo.usd or $.data[0].price")]
+    InvalidJsonPath(String),
+    #[error("scale must be a positive number")]
+    InvalidScale,
+    #[error("invalid headers: {0}")]
+    Headers(#[from] HttpClientError),
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub struct HttpJsonSource {
+    /// Requested with GET
+    pub url: Url,
+    /// Extra HTTP headers (name -> value), e.g. the API key
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Path of the number (or numeric string) in the response, e.g. `$.ergo.usd`
+    pub json_path: String,
+    /// The datapoint is the number multiplied by this and rounded
+    pub scale: f64,
+}
+
+// the headers often carry the API key
+impl std::fmt::Debug for HttpJsonSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpJsonSource")
+            .field("url", &self.url)
+            .field("headers", &redacted_headers(&self.headers))
+            .field("json_path", &self.json_path)
+            .field("scale", &self.scale)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+fn parse_json_path(path: &str) -> Result<Vec<PathSegment>, HttpJsonSourceError> {
+    let invalid = || HttpJsonSourceError::InvalidJsonPath(path.to_string());
+    let mut rest = path.strip_prefix('$').ok_or_else(invalid)?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot
+                .find(|c| c == '.' || c == '[')
+                .unwrap_or(after_dot.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(PathSegment::Key(after_dot[..end].to_string()));
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket.find(']').ok_or_else(invalid)?;
+            let index = after_bracket[..end].parse().map_err(|_| invalid())?;
+            segments.push(PathSegment::Index(index));
+            rest = &after_bracket[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+    Ok(segments)
+}
+
+fn json_path_lookup<'a>(
+    json: &'a serde_json::Value,
+    path: &[PathSegment],
+) -> Option<&'a serde_json::Value> {
+    path.iter().try_fold(json, |value, segment| match segment {
+        PathSegment::Key(key) => value.get(key),
+        PathSegment::Index(index) => value.get(index),
+    })
+}
+
+impl HttpJsonSource {
+    pub fn validate(&self) -> Result<(), HttpJsonSourceError> {
+        parse_json_path(&self.json_path)?;
+        if !(self.scale.is_finite() && self.scale > 0.0) {
+            return Err(HttpJsonSourceError::InvalidScale);
+        }
+        header_map(&self.headers)?;
+        Ok(())
+    }
+
+    /// Datapoint from the response JSON
+    fn rate_from_json(&self, resp_json: &serde_json::Value) -> Result<Rate, DataPointSourceError> {
+        let path = parse_json_path(&self.json_path).map_err(|e| {
+            DataPointSourceError::JsonMissingField {
+                field: e.to_string(),
+                json: resp_json.to_string(),
+            }
+        })?;
+        let value = json_path_lookup(resp_json, &path);
+        // some APIs return the prices as strings
+        let number = value
+            .and_then(|v| v.as_f64().or_else(|| v.as_str()?.parse().ok()))
+            .ok_or_else(|| DataPointSourceError::JsonMissingField {
+                field: format!("{} as f64", self.json_path),
+                json: resp_json.to_string(),
+            })?;
+        Ok(((number * self.scale).round() as i64).into())
+    }
+
+    async fn fetch(&self) -> Result<Rate, DataPointSourceError> {
+        let resp = super::http_client::get_with_headers(&self.headers, self.url.clone()).await?;
+        let resp_json = serde_json::from_slice(&resp.bytes().await?)?;
+        self.rate_from_json(&resp_json)
+    }
+}
+
+impl DataPointSource for HttpJsonSource {
+    fn get_datapoint(&self) -> Result<Rate, DataPointSourceError> {
+        let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
+        tokio_runtime.block_on(self.fetch())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(json_path: &str, scale: f64) -> HttpJsonSource {
+        HttpJsonSource {
+            url: Url::parse("https://api.example.com/price").unwrap(),
+            headers: HashMap::new(),
+            json_path: json_path.to_string(),
+            scale,
+        }
+    }
+
+    #[test]
+    fn test_parse_json_path() {
+        assert_eq!(
+            parse_json_path("$.data[1].price_usd"),
+            Ok(vec![
+                PathSegment::Key("data".to_string()),
+                PathSegment::Index(1),
+                PathSegment::Key("price_usd".to_string()),
+            ])
+        );
+        assert_eq!(parse_json_path("$"), Ok(vec![]));
+        assert!(parse_json_path("ergo.usd").is_err());
+        assert!(parse_json_path("$..usd").is_err());
+        assert!(parse_json_path("$.data[x]").is_err());
+        assert!(parse_json_path("$.data[0").is_err());
+    }
+
+    #[test]
+    fn test_rate_from_json() {
+        let resp_json = serde_json::json!({"ergo": {"usd": 1.25}, "data": [{"price": "0.5"}]});
+        assert_eq!(
+            source("$.ergo.usd", 1000.0)
+                .rate_from_json(&resp_json)
+                .unwrap(),
+            1250
+        );
+        assert_eq!(
+            source("$.data[0].price", 10.0)
+                .rate_from_json(&resp_json)
+                .unwrap(),
+            5
+        );
+        assert!(source("$.ergo.eur", 1.0)
+            .rate_from_json(&resp_json)
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(source("$.ergo.usd", 1e9).validate(), Ok(()));
+        assert_eq!(
+            source("$.ergo.usd", 0.0).validate(),
+            Err(HttpJsonSourceError::InvalidScale)
+        );
+        assert_eq!(
+            source("ergo", 1.0).validate(),
+            Err(HttpJsonSourceError::InvalidJsonPath("ergo".to_string()))
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Debug, Error)]
+pub enum HtxError {
+    #[error("HTX API error {err_code}: {err_msg}")]
+    ApiError { err_code: String, err_msg: String },
+    #[error("HTX API unexpected response status {status:?}")]
+    UnexpectedStatus { status: Option<String> },
+}
+
+/// HTX response envelope
+#[derive(Deserialize)]
+struct Envelope {
+    status: Option<String>,
+    #[serde(default)]
+    tick: Tick,
+    #[serde(rename = "err-code")]
+    err_code: Option<String>,
+    #[serde(rename = "err-msg")]
+    err_msg: Option<String>,
+}
+
+/// Aggregated ticker, the best bid and ask are [price, amount]
+#[derive(Debug, Default, Deserialize)]
+struct Tick {
+    /// Last trade price
+    close: Option<Quote>,
+    #[serde(default)]
+    bid: Vec<Quote>,
+    #[serde(default)]
+    ask: Vec<Quote>,
+}
+
+/// Checks the `status` of the HTX response envelope and returns its `tick` payload
+fn parse_envelope(envelope: Envelope) -> Result<Tick, HtxError> {
+    match envelope.status.as_deref() {
+        Some("ok") => Ok(envelope.tick),
+        Some("error") => Err(HtxError::ApiError {
+            err_code: envelope.err_code.unwrap_or_default(),
+            err_msg: envelope.err_msg.unwrap_or_default(),
+        }),
+        _ => Err(HtxError::UnexpectedStatus {
+            status: envelope.status,
+        }),
+    }
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    // see https://huobiapi.github.io/docs/spot/v1/en/#get-latest-aggregated-ticker
+    // USDT is taken as USD
+    let url = "https://api.huobi.pro/market/detail/merged?symbol=ergusdt";
+    let resp = super::http_client::get("htx", url).await?;
+    let envelope: Envelope = super::response::parse_body("htx", resp).await?;
+    let tick = parse_envelope(envelope)?;
+    let usd_per_erg = super::price_method::price(
+        "htx",
+        ("$.tick.close", tick.close.as_ref()),
+        ("$.tick.bid[0]", tick.bid.first()),
+        ("$.tick.ask[0]", tick.ask.first()),
+        super::response::USD_PER_ERG,
+    )?;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let usd_per_erg = (1.6601 + 1.6632) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_erg_usd_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_parse_envelope() {
+        let envelope =
+            |body: &str| super::super::response::parse::<Envelope>("htx", body.as_bytes()).unwrap();
+        let ok = envelope(r#"{"status":"ok","tick":{"bid":[1.66,10.0]}}"#);
+        assert_eq!(
+            parse_envelope(ok).unwrap().bid.first(),
+            Some(&Quote::Number(1.66))
+        );
+        let err = envelope(
+            r#"{"status":"error","err-code":"invalid-parameter","err-msg":"invalid symbol"}"#,
+        );
+        assert!(matches!(
+            parse_envelope(err),
+            Err(HtxError::ApiError { err_code, .. }) if err_code == "invalid-parameter"
+        ));
+        let unexpected = envelope(r#"{"tick":{}}"#);
+        assert!(matches!(
+            parse_envelope(unexpected),
+            Err(HtxError::UnexpectedStatus { status: None })
+        ));
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::erg_jpy::Jpy;
+use super::response::PlausibleRange;
+use super::response::Quote;
+use super::response::ResponseError;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct TickerResponse {
+    /// Tickers keyed by Kraken's own pair name, which may differ from the requested one
+    #[serde(default)]
+    result: HashMap<String, Ticker>,
+}
+
+#[derive(Deserialize)]
+struct Ticker {
+    /// Last trade closed: [price, lot volume]
+    #[serde(default)]
+    c: Vec<Quote>,
+    /// Best bid: [price, whole lot volume, lot volume]
+    #[serde(default)]
+    b: Vec<Quote>,
+    /// Best ask: [price, whole lot volume, lot volume]
+    #[serde(default)]
+    a: Vec<Quote>,
+}
+
+/// Price of the pair in the Kraken ticker response body, by the last trade (default) or the
+/// mid-price as set in `source_price_methods`
+fn parse_ticker_price(body: &[u8], range: PlausibleRange) -> Result<f64, ResponseError> {
+    let ticker_resp: TickerResponse = super::response::parse("kraken", body)?;
+    let ticker = ticker_resp.result.iter().next();
+    let pair_name = ticker.map(|(name, _)| name.as_str()).unwrap_or_default();
+    super::price_method::price(
+        "kraken",
+        (
+            &format!("$.result.{}.c[0]", pair_name),
+            ticker.and_then(|(_, t)| t.c.first()),
+        ),
+        (
+            &format!("$.result.{}.b[0]", pair_name),
+            ticker.and_then(|(_, t)| t.b.first()),
+        ),
+        (
+            &format!("$.result.{}.a[0]", pair_name),
+            ticker.and_then(|(_, t)| t.a.first()),
+        ),
+        range,
+    )
+}
+
+/// Price of the pair from the Kraken ticker
+#[cfg(not(test))]
+async fn ticker_price(pair: &str, range: PlausibleRange) -> Result<f64, DataPointSourceError> {
+    // see https://docs.kraken.com/rest/#tag/Market-Data/operation/getTickerInformation
+    let url = format!("https://api.kraken.com/0/public/Ticker?pair={}", pair);
+    let resp = super::http_client::get("kraken", &url).await?;
+    Ok(parse_ticker_price(&resp.bytes().await?, range)?)
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let p_float = ticker_price("ERGUSD", super::response::USD_PER_ERG).await?;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_jpy_usd() -> Result<AssetsExchangeRate<Jpy, Usd>, DataPointSourceError> {
+    let jpy_per_usd = ticker_price("USDJPY", super::response::JPY_PER_USD).await?;
+    let rate = AssetsExchangeRate {
+        per1: Jpy {},
+        get: Usd {},
+        rate: 1.0 / jpy_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let p_float = 1.658;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_jpy_usd() -> Result<AssetsExchangeRate<Jpy, Usd>, DataPointSourceError> {
+    let rate = AssetsExchangeRate {
+        per1: Jpy {},
+        get: Usd {},
+        rate: 1.0 / 149.82,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::response::JPY_PER_USD;
+    use super::super::response::USD_PER_ERG;
+    use super::*;
+
+    // response of https://api.kraken.com/0/public/Ticker?pair=ERGUSD
+    const ERGUSD_TICKER: &str = r#"{"error":[],"result":{"ERGUSD":{
+        "a":["1.65900","1248","1248.000"],"b":["1.65600","60","60.000"],
+        "c":["1.65800","25.01338000"],"v":["4810.51328836","21833.24094733"],
+        "p":["1.65128","1.64477"],"t":[37,154],"l":["1.63800","1.62600"],
+        "h":["1.66500","1.66900"],"o":"1.64000"}}}"#;
+
+    #[test]
+    fn test_parse_ticker_price() {
+        assert_eq!(
+            parse_ticker_price(ERGUSD_TICKER.as_bytes(), USD_PER_ERG),
+            Ok(1.658)
+        );
+        // the ERG/USD price is implausible as a JPY/USD rate
+        assert!(matches!(
+            parse_ticker_price(ERGUSD_TICKER.as_bytes(), JPY_PER_USD),
+            Err(ResponseError::Implausible { .. })
+        ));
+        let unknown_pair = r#"{"error":["EQuery:Unknown asset pair"]}"#;
+        assert_eq!(
+            parse_ticker_price(unknown_pair.as_bytes(), USD_PER_ERG),
+            Err(ResponseError::MissingField {
+                provider: "kraken".to_string(),
+                field: "$.result..c[0]".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! ERG/USD price streamed from the Kraken WebSocket ticker. A background thread keeps the last
+//! price in memory, so the aggregator reads it instantly instead of waiting for a REST response.
+//! The stream keeps a connection open, so the source is used only when it's listed in the
+//! `NanoErgUsd` source basket.
+//!
+//! Kraken pushes a ticker update only on a trade, which can be hours apart for ERG/USD in quiet
+//! hours. The last price stays valid as long as the connection is alive, i.e. the heartbeats
+//! (sent every second) keep arriving.
+
+use std::sync::Once;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+pub const SOURCE_NAME: &str = "kraken_ws";
+
+// see https://docs.kraken.com/api/docs/websocket-v2/ticker
+const KRAKEN_WS_URL: &str = "wss://ws.kraken.com/v2";
+const SUBSCRIBE_MESSAGE: &str =
+    r#"{"method": "subscribe", "params": {"channel": "ticker", "symbol": ["ERG/USD"]}}"#;
+/// Kraken sends a heartbeat every second, a silent connection is reconnected after this
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+/// The streamed price is not used if no message (not even a heartbeat) arrived for this long
+const MAX_SILENCE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Error)]
+pub enum KrakenWsError {
+    #[error("no price received from the Kraken stream yet")]
+    NoPrice,
+    #[error("Kraken stream is down, last message {silence_secs}s ago")]
+    Stale { silence_secs: u64 },
+}
+
+/// Last price of the current connection and the time of its last message
+#[derive(Default)]
+struct StreamState {
+    usd_per_erg: Option<f64>,
+    last_message: Option<Instant>,
+}
+
+impl StreamState {
+    fn on_message(&mut self, text: &str, now: Instant) {
+        self.last_message = Some(now);
+        if let Some(usd_per_erg) = parse_ticker(text) {
+            self.usd_per_erg = Some(usd_per_erg);
+        }
+    }
+
+    fn price(&self, now: Instant) -> Result<f64, KrakenWsError> {
+        let usd_per_erg = self.usd_per_erg.ok_or(KrakenWsError::NoPrice)?;
+        let silence = self
+            .last_message
+            .map_or(Duration::MAX, |last| now.saturating_duration_since(last));
+        if silence > MAX_SILENCE {
+            return Err(KrakenWsError::Stale {
+                silence_secs: silence.as_secs(),
+            });
+        }
+        Ok(usd_per_erg)
+    }
+}
+
+static START: Once = Once::new();
+
+lazy_static! {
+    static ref STREAM_STATE: RwLock<StreamState> = RwLock::new(StreamState::default());
+}
+
+/// Starts the background thread streaming the price, if it's not running yet
+pub fn start() {
+    START.call_once(|| {
+        let spawned = std::thread::Builder::new()
+            .name("kraken-ws".to_string())
+            .spawn(|| loop {
+                if let Err(e) = stream() {
+                    log::warn!("Kraken price stream failed, reconnecting: {}", e);
+                }
+                std::thread::sleep(RECONNECT_DELAY);
+            });
+        if let Err(e) = spawned {
+            log::error!("Failed to start the Kraken price stream: {}", e);
+        }
+    });
+}
+
+fn stream() -> Result<(), tungstenite::Error> {
+    let (mut socket, _) = tungstenite::connect(KRAKEN_WS_URL)?;
+    if let tungstenite::stream::MaybeTlsStream::NativeTls(tls) = socket.get_ref() {
+        tls.get_ref().set_read_timeout(Some(READ_TIMEOUT))?;
+    }
+    socket.send(tungstenite::Message::Text(SUBSCRIBE_MESSAGE.to_string()))?;
+    log::info!("Subscribed to the Kraken ERG/USD ticker stream");
+    // the price from the previous connection is replaced by the snapshot sent on subscribe
+    *STREAM_STATE.write().unwrap() = StreamState::default();
+    loop {
+        let message = socket.read()?;
+        if message.is_close() {
+            return Ok(());
+        }
+        if let Ok(text) = message.to_text() {
+            STREAM_STATE
+                .write()
+                .unwrap()
+                .on_message(text, Instant::now());
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StreamMessage {
+    channel: Option<String>,
+    #[serde(default)]
+    data: Vec<TickerData>,
+}
+
+#[derive(Deserialize)]
+struct TickerData {
+    last: Option<Quote>,
+    bid: Option<Quote>,
+    ask: Option<Quote>,
+}
+
+/// Price of a ticker snapshot or update message, by the last trade (default) or the mid-price as
+/// set in `source_price_methods`, `None` for the other messages
+fn parse_ticker(text: &str) -> Option<f64> {
+    let message: StreamMessage = serde_json::from_str(text).ok()?;
+    if message.channel.as_deref() != Some("ticker") {
+        return None;
+    }
+    let ticker = message.data.first();
+    match super::price_method::price(
+        SOURCE_NAME,
+        ("$.data[0].last", ticker.and_then(|d| d.last.as_ref())),
+        ("$.data[0].bid", ticker.and_then(|d| d.bid.as_ref())),
+        ("$.data[0].ask", ticker.and_then(|d| d.ask.as_ref())),
+        super::response::USD_PER_ERG,
+    ) {
+        Ok(price) => Some(price),
+        Err(e) => {
+            log::warn!("Ignoring the Kraken stream message: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    start();
+    let p_float = STREAM_STATE.read().unwrap().price(Instant::now())?;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let p_float = 1.658;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p_float);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ticker() {
+        let update = r#"{"channel": "ticker", "type": "update", "data": [{"symbol": "ERG/USD", "bid": 1.649, "ask": 1.651, "last": 1.65}]}"#;
+        assert_eq!(parse_ticker(update), Some(1.65));
+        let heartbeat = r#"{"channel": "heartbeat"}"#;
+        assert_eq!(parse_ticker(heartbeat), None);
+        let zero = r#"{"channel": "ticker", "type": "update", "data": [{"last": 0}]}"#;
+        assert_eq!(parse_ticker(zero), None);
+    }
+
+    #[test]
+    fn test_price() {
+        let update = r#"{"channel": "ticker", "type": "update", "data": [{"last": 1.65}]}"#;
+        let heartbeat = r#"{"channel": "heartbeat"}"#;
+        let now = Instant::now();
+        let mut state = StreamState::default();
+        state.on_message(heartbeat, now);
+        assert!(matches!(state.price(now), Err(KrakenWsError::NoPrice)));
+        state.on_message(update, now);
+        assert_eq!(state.price(now + Duration::from_secs(5)).unwrap(), 1.65);
+        // no trades for hours, the heartbeats keep the price valid
+        let later = now + Duration::from_secs(3 * 3600);
+        state.on_message(heartbeat, later);
+        assert_eq!(state.price(later + Duration::from_secs(1)).unwrap(), 1.65);
+        // the connection went silent
+        assert!(matches!(
+            state.price(later + MAX_SILENCE + Duration::from_secs(1)),
+            Err(KrakenWsError::Stale { .. })
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use serde::Deserialize;
+
+use super::assets_exchange_rate::convert_rate;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::Btc;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct OrderBookLevel1 {
+    data: Option<BestPrices>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BestPrices {
+    /// Last trade price
+    price: Option<Quote>,
+    best_bid: Option<Quote>,
+    best_ask: Option<Quote>,
+}
+
+/// Returns the price of the given KuCoin spot symbol (e.g. `ERG-BTC`), the middle of the best
+/// bid/ask unless `source_price_methods` sets the last trade
+#[cfg(not(test))]
+async fn get_price(
+    symbol: &str,
+    range: super::response::PlausibleRange,
+) -> Result<f64, DataPointSourceError> {
+    // see https://www.kucoin.com/docs/rest/spot-trading/market-data/get-ticker
+    let url = format!(
+        "https://api.kucoin.com/api/v1/market/orderbook/level1?symbol={}",
+        symbol
+    );
+    let resp = super::http_client::get("kucoin", url).await?;
+    let level1: OrderBookLevel1 = super::response::parse_body("kucoin", resp).await?;
+    let best_prices = level1.data.as_ref();
+    Ok(super::price_method::price(
+        "kucoin",
+        ("$.data.price", best_prices.and_then(|d| d.price.as_ref())),
+        (
+            "$.data.bestBid",
+            best_prices.and_then(|d| d.best_bid.as_ref()),
+        ),
+        (
+            "$.data.bestAsk",
+            best_prices.and_then(|d| d.best_ask.as_ref()),
+        ),
+        range,
+    )?)
+}
+
+#[cfg(not(test))]
+pub async fn get_btc_nanoerg() -> Result<AssetsExchangeRate<Btc, NanoErg>, DataPointSourceError> {
+    let btc_per_erg = get_price("ERG-BTC", super::response::BTC_PER_ERG).await?;
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / btc_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_btc_nanoerg() -> Result<AssetsExchangeRate<Btc, NanoErg>, DataPointSourceError> {
+    let btc_per_erg = (0.00003788 + 0.00003794) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / btc_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_btc() -> Result<AssetsExchangeRate<Usd, Btc>, DataPointSourceError> {
+    // USDT is taken as USD
+    let usd_per_btc = get_price("BTC-USDT", super::response::USD_PER_BTC).await?;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: Btc {},
+        rate: 1.0 / usd_per_btc,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_btc() -> Result<AssetsExchangeRate<Usd, Btc>, DataPointSourceError> {
+    let usd_per_btc = (43_948.9 + 43_949.1) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: Btc {},
+        rate: 1.0 / usd_per_btc,
+    };
+    Ok(rate)
+}
+
+// Calculate ERG/USD through ERG/BTC and BTC/USD
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    Ok(convert_rate(get_btc_nanoerg().await?, get_usd_btc().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_btc_nanoerg_price() {
+        let pair = tokio_test::block_on(get_btc_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_btc_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_usd_nanoerg_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct AssetMetrics {
+    data: Option<AssetMetricsData>,
+}
+
+#[derive(Deserialize)]
+struct AssetMetricsData {
+    market_data: Option<MarketData>,
+}
+
+#[derive(Deserialize)]
+struct MarketData {
+    price_usd: Option<Quote>,
+}
+
+/// ERG/USD from the Messari asset metrics. The API key is sent as the `x-messari-api-key` header
+/// set in `source_http_headers`.
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    // see https://messari.io/api/docs#tag/Assets/operation/Get%20Asset%20Metrics
+    let url = "https://data.messari.io/api/v1/assets/erg/metrics/market-data";
+    let resp = super::http_client::get("messari", url).await?;
+    let metrics: AssetMetrics = super::response::parse_body("messari", resp).await?;
+    let p = super::response::price(
+        "messari",
+        "$.data.market_data.price_usd",
+        metrics
+            .data
+            .and_then(|d| d.market_data)
+            .and_then(|m| m.price_usd)
+            .as_ref(),
+        super::response::USD_PER_ERG,
+    )?;
+    let nanoerg_per_usd = NanoErg::from_erg(1.0 / p);
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: nanoerg_per_usd,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let usd_per_erg = 1.6694;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_erg_usd_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Brent crude oil spot price from the oilpriceapi.com commodities API. The API requires a key,
+//! set as the `Authorization: Token <key>` header of the `oilpriceapi` provider in
+//! `source_http_headers`.
+
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::Usd;
+use super::erg_brent::Bbl;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct LatestPrice {
+    data: Option<LatestPriceData>,
+}
+
+#[derive(Deserialize)]
+struct LatestPriceData {
+    price: Option<Quote>,
+}
+
+#[cfg(not(test))]
+pub async fn get_bbl_usd() -> Result<AssetsExchangeRate<Bbl, Usd>, DataPointSourceError> {
+    // see https://docs.oilpriceapi.com/api-reference/prices/latest
+    let url = "https://api.oilpriceapi.com/v1/prices/latest?by_code=BRENT_CRUDE_USD";
+    let resp = super::http_client::get("oilpriceapi", url).await?;
+    let latest: LatestPrice = super::response::parse_body("oilpriceapi", resp).await?;
+    // USD price of 1 barrel
+    let usd_per_barrel = super::response::price(
+        "oilpriceapi",
+        "$.data.price",
+        latest.data.and_then(|d| d.price).as_ref(),
+        super::response::USD_PER_BARREL_OF_BRENT,
+    )?;
+    let rate = AssetsExchangeRate {
+        per1: Bbl {},
+        get: Usd {},
+        rate: usd_per_barrel,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_bbl_usd() -> Result<AssetsExchangeRate<Bbl, Usd>, DataPointSourceError> {
+    let usd_per_barrel = 82.47;
+    let rate = AssetsExchangeRate {
+        per1: Bbl {},
+        get: Usd {},
+        rate: usd_per_barrel,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bbl_usd_price() {
+        let pair = tokio_test::block_on(get_bbl_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+}
@@ -0,0 +1,148 @@
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::Btc;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::erg_xau::KgAu;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct TickerResponse {
+    #[serde(default)]
+    data: Vec<Ticker>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ticker {
+    last: Option<Quote>,
+    bid_px: Option<Quote>,
+    ask_px: Option<Quote>,
+}
+
+/// Returns the price of the given OKX instrument (e.g. `ERG-USDT`), the middle of the bid/ask
+/// spread unless `source_price_methods` sets the last trade
+#[cfg(not(test))]
+async fn get_ticker_price(
+    inst_id: &str,
+    range: super::response::PlausibleRange,
+) -> Result<f64, DataPointSourceError> {
+    // see https://www.okx.com/docs-v5/en/#order-book-trading-market-data-get-ticker
+    let url = format!(
+        "https://www.okx.com/api/v5/market/ticker?instId={}",
+        inst_id
+    );
+    let resp = super::http_client::get("okx", url).await?;
+    let ticker_resp: TickerResponse = super::response::parse_body("okx", resp).await?;
+    let ticker = ticker_resp.data.first();
+    Ok(super::price_method::price(
+        "okx",
+        ("$.data[0].last", ticker.and_then(|t| t.last.as_ref())),
+        ("$.data[0].bidPx", ticker.and_then(|t| t.bid_px.as_ref())),
+        ("$.data[0].askPx", ticker.and_then(|t| t.ask_px.as_ref())),
+        range,
+    )?)
+}
+
+#[cfg(not(test))]
+pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
+    // Tether Gold (1 XAUT = 1 troy ounce of gold) priced in USDT, USDT is taken as USD
+    let usd_per_troy_ounce =
+        get_ticker_price("XAUT-USDT", super::response::USD_PER_TROY_OUNCE).await?;
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: Usd {},
+        rate: KgAu::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_kgau_usd() -> Result<AssetsExchangeRate<KgAu, Usd>, DataPointSourceError> {
+    let usd_per_troy_ounce = (2048.5 + 2050.5) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: KgAu {},
+        get: Usd {},
+        rate: KgAu::from_troy_ounce(usd_per_troy_ounce),
+    };
+    Ok(rate)
+}
+
+#[cfg(not(test))]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    // USDT is taken as USD
+    let usd_per_erg = get_ticker_price("ERG-USDT", super::response::USD_PER_ERG).await?;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let usd_per_erg = (1.663 + 1.667) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usd_per_erg),
+    };
+    Ok(rate)
+}
+
+// Get USD/BTC. Can be used as a redundant source for ERG/BTC through ERG/USD and USD/BTC
+#[cfg(not(test))]
+pub async fn get_btc_usd() -> Result<AssetsExchangeRate<Btc, Usd>, DataPointSourceError> {
+    // USDT is taken as USD
+    let usd_per_btc = get_ticker_price("BTC-USDT", super::response::USD_PER_BTC).await?;
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: Usd {},
+        rate: usd_per_btc,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_btc_usd() -> Result<AssetsExchangeRate<Btc, Usd>, DataPointSourceError> {
+    let usd_per_btc = (43_951.2 + 43_951.4) / 2.0;
+    let rate = AssetsExchangeRate {
+        per1: Btc {},
+        get: Usd {},
+        rate: usd_per_btc,
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_kgau_usd_price() {
+        let pair: AssetsExchangeRate<KgAu, Usd> = tokio_test::block_on(get_kgau_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+
+    #[test]
+    fn test_erg_usd_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_btc_usd_price() {
+        let pair = tokio_test::block_on(get_btc_usd()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+}
@@ -1,37 +1,162 @@
+use crate::oracle_config::ORACLE_CONFIG_OPT;
 use crate::oracle_types::Rate;
 
-use super::ada_usd::usd_lovelace_sources;
-use super::aggregator::fetch_aggregated;
-use super::erg_btc::nanoerg_btc_sources;
-use super::erg_usd::nanoerg_usd_sources;
-use super::erg_xau::nanoerg_kgau_sources;
+use super::ada_usd::usd_lovelace_source;
+use super::aggregator::fetch_aggregated_with_breakdown;
+use super::aggregator::SourceRate;
+use super::basket::select_sources;
+use super::basket::source_weights;
+use super::erg_ageusd::nanoerg_sigrsv_source;
+use super::erg_ageusd::nanoerg_sigusd_source;
+use super::erg_brent::nanoerg_bbl_source;
+use super::erg_btc::nanoerg_btc_source;
+use super::erg_jpy::nanoerg_jpy_source;
+use super::erg_rsn::nanoerg_rsn_source;
+use super::erg_usd::nanoerg_usd_source;
+use super::erg_xau::nanoerg_kgau_source;
+use super::erg_xpd::nanoerg_kgpd_source;
+use super::erg_xpt::nanoerg_kgpt_source;
+use super::ergo_hashrate::ergo_hashrate_source;
+use super::rates_proxy;
+use super::rates_proxy::RatesProxyRole;
+use super::rates_proxy::SERVED_RATES;
+use super::spx_usd::usdcent_spx_source;
+use super::us_cpi::us_cpi_source;
+use super::usd_btc::usdcent_btc_source;
 use super::DataPointSourceError;
 use super::PredefinedDataPointSource;
 
+/// Aggregated rate of the pair along with the rate of each source, shared with (or taken from)
+/// the other instances on the host if `rates_proxy` is set in the oracle config
 pub fn sync_fetch_predef_source_aggregated(
     predef_datasource: &PredefinedDataPointSource,
-) -> Result<Rate, DataPointSourceError> {
+) -> Result<(Rate, Vec<SourceRate>), DataPointSourceError> {
+    let proxy_config = ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.rates_proxy.as_ref());
+    match proxy_config {
+        Some(config) if config.role == RatesProxyRole::Server => {
+            SERVED_RATES.get_or_fetch(*predef_datasource, config.max_age(), || {
+                sync_fetch_from_sources(predef_datasource)
+            })
+        }
+        Some(config) => match rates_proxy::request(&config.socket_path, *predef_datasource) {
+            Ok(res) => Ok(res),
+            Err(e) => {
+                log::warn!(
+                    "Failed to get the {:?} rate from the rates proxy, fetching it from the sources: {}",
+                    predef_datasource,
+                    e
+                );
+                sync_fetch_from_sources(predef_datasource)
+            }
+        },
+        None => sync_fetch_from_sources(predef_datasource),
+    }
+}
+
+/// Starts serving the rates to the other instances if this instance is the rates proxy server
+pub fn start_rates_proxy_server() {
+    let Some(config) = ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.rates_proxy.as_ref())
+    else {
+        return;
+    };
+    rates_proxy::start_server(config, |pair| {
+        sync_fetch_from_sources(&pair).map_err(|e| e.to_string())
+    });
+}
+
+fn sync_fetch_from_sources(
+    predef_datasource: &PredefinedDataPointSource,
+) -> Result<(Rate, Vec<SourceRate>), DataPointSourceError> {
     let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
-    let rate = tokio_runtime.block_on(fetch_predef_source_aggregated(predef_datasource))?;
-    Ok(rate)
+    tokio_runtime.block_on(fetch_predef_source_aggregated(predef_datasource))
 }
 
 async fn fetch_predef_source_aggregated(
     predef_datasource: &PredefinedDataPointSource,
-) -> Result<Rate, DataPointSourceError> {
-    let rate_float = match predef_datasource {
+) -> Result<(Rate, Vec<SourceRate>), DataPointSourceError> {
+    let weights = source_weights(*predef_datasource);
+    let (rate_float, breakdown) = match predef_datasource {
         PredefinedDataPointSource::NanoErgUsd => {
-            fetch_aggregated(nanoerg_usd_sources()).await?.rate
+            let sources = select_sources(*predef_datasource, nanoerg_usd_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
         }
         PredefinedDataPointSource::NanoErgXau => {
-            fetch_aggregated(nanoerg_kgau_sources()).await?.rate
+            let sources = select_sources(*predef_datasource, nanoerg_kgau_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
         }
         PredefinedDataPointSource::NanoAdaUsd => {
-            fetch_aggregated(usd_lovelace_sources()).await?.rate
+            let sources = select_sources(*predef_datasource, usd_lovelace_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
         }
         PredefinedDataPointSource::NanoErgBTC => {
-            fetch_aggregated(nanoerg_btc_sources()).await?.rate
+            let sources = select_sources(*predef_datasource, nanoerg_btc_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::NanoErgSigUsd => {
+            let sources = select_sources(*predef_datasource, nanoerg_sigusd_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::NanoErgSigRsv => {
+            let sources = select_sources(*predef_datasource, nanoerg_sigrsv_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::NanoErgRsn => {
+            let sources = select_sources(*predef_datasource, nanoerg_rsn_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::NanoErgJpy => {
+            let sources = select_sources(*predef_datasource, nanoerg_jpy_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::NanoErgXpt => {
+            let sources = select_sources(*predef_datasource, nanoerg_kgpt_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::NanoErgXpd => {
+            let sources = select_sources(*predef_datasource, nanoerg_kgpd_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::NanoErgBrent => {
+            let sources = select_sources(*predef_datasource, nanoerg_bbl_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::UsdCentSpx => {
+            let sources = select_sources(*predef_datasource, usdcent_spx_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::UsdCentBtc => {
+            let sources = select_sources(*predef_datasource, usdcent_btc_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::ErgoHashrate => {
+            let sources = select_sources(*predef_datasource, ergo_hashrate_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
+        }
+        PredefinedDataPointSource::UsCpi => {
+            let sources = select_sources(*predef_datasource, us_cpi_source)?;
+            let (rate, breakdown) = fetch_aggregated_with_breakdown(sources, &weights).await?;
+            (rate.rate, breakdown)
         }
     };
-    Ok((rate_float as i64).into())
+    Ok(((rate_float as i64).into(), breakdown))
 }
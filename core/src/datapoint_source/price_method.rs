@@ -0,0 +1,193 @@
+//! How the exchange sources price a pair: by the last trade or by the middle of the top of the
+//! order book (best bid/ask). The mid-price is more robust for the thin ERG pairs during quiet
+//! hours, when the last trade can be hours old or a small outlier fill. The method is set per
+//! provider by `source_price_methods` in the oracle config, each provider keeps its default
+//! method otherwise.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::oracle_config::ORACLE_CONFIG_OPT;
+
+use super::response::PlausibleRange;
+use super::response::Quote;
+use super::response::ResponseError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceMethod {
+    /// Price of the last trade
+    LastTrade,
+    /// Middle of the best bid and ask
+    MidPrice,
+}
+
+/// Providers whose responses have both the last trade and the best bid/ask, with their default
+/// method
+pub const PRICE_METHOD_DEFAULTS: &[(&str, PriceMethod)] = &[
+    ("bitfinex", PriceMethod::MidPrice),
+    ("gateio", PriceMethod::MidPrice),
+    ("htx", PriceMethod::MidPrice),
+    ("kraken", PriceMethod::LastTrade),
+    ("kraken_ws", PriceMethod::LastTrade),
+    ("kucoin", PriceMethod::MidPrice),
+    ("okx", PriceMethod::MidPrice),
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PriceMethodError {
+    #[error("the price method of provider {0} can't be set in source_price_methods, supported providers are {:?}", supported_providers())]
+    UnsupportedProvider(String),
+}
+
+fn supported_providers() -> Vec<&'static str> {
+    PRICE_METHOD_DEFAULTS
+        .iter()
+        .map(|(provider, _)| *provider)
+        .collect()
+}
+
+/// Checks that the methods are set for the providers supporting both of them
+pub fn validate_source_price_methods(
+    source_price_methods: &HashMap<String, PriceMethod>,
+) -> Result<(), PriceMethodError> {
+    for provider in source_price_methods.keys() {
+        if !supported_providers().contains(&provider.as_str()) {
+            return Err(PriceMethodError::UnsupportedProvider(provider.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn method_of(
+    provider: &str,
+    source_price_methods: Option<&HashMap<String, PriceMethod>>,
+) -> PriceMethod {
+    source_price_methods
+        .and_then(|methods| methods.get(provider).copied())
+        .or_else(|| {
+            PRICE_METHOD_DEFAULTS
+                .iter()
+                .find(|(p, _)| *p == provider)
+                .map(|(_, method)| *method)
+        })
+        .unwrap_or(PriceMethod::LastTrade)
+}
+
+/// Method of the provider set in the oracle config, its default if it's not set
+pub fn price_method(provider: &str) -> PriceMethod {
+    method_of(
+        provider,
+        ORACLE_CONFIG_OPT
+            .as_ref()
+            .ok()
+            .map(|c| &c.source_price_methods),
+    )
+}
+
+/// Price by the method of the provider from the (field, value) of the last trade, best bid and
+/// best ask in the provider's response
+pub fn price(
+    provider: &str,
+    last_trade: (&str, Option<&Quote>),
+    bid: (&str, Option<&Quote>),
+    ask: (&str, Option<&Quote>),
+    range: PlausibleRange,
+) -> Result<f64, ResponseError> {
+    price_by(
+        price_method(provider),
+        provider,
+        last_trade,
+        bid,
+        ask,
+        range,
+    )
+}
+
+fn price_by(
+    method: PriceMethod,
+    provider: &str,
+    (last_trade_field, last_trade): (&str, Option<&Quote>),
+    bid: (&str, Option<&Quote>),
+    ask: (&str, Option<&Quote>),
+    range: PlausibleRange,
+) -> Result<f64, ResponseError> {
+    match method {
+        PriceMethod::LastTrade => {
+            super::response::price(provider, last_trade_field, last_trade, range)
+        }
+        PriceMethod::MidPrice => super::response::mid_price(provider, bid, ask, range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::response::USD_PER_ERG;
+    use super::*;
+
+    #[test]
+    fn test_method_of() {
+        assert_eq!(method_of("kraken", None), PriceMethod::LastTrade);
+        assert_eq!(method_of("okx", None), PriceMethod::MidPrice);
+        let methods = HashMap::from([
+            ("kraken".to_string(), PriceMethod::MidPrice),
+            ("okx".to_string(), PriceMethod::LastTrade),
+        ]);
+        assert_eq!(method_of("kraken", Some(&methods)), PriceMethod::MidPrice);
+        assert_eq!(method_of("okx", Some(&methods)), PriceMethod::LastTrade);
+        assert_eq!(method_of("kucoin", Some(&methods)), PriceMethod::MidPrice);
+    }
+
+    #[test]
+    fn test_price_by() {
+        let last_trade = Quote::Text("1.70".to_string());
+        let bid = Quote::Number(1.5);
+        let ask = Quote::Text("1.75".to_string());
+        let price_of = |method| {
+            price_by(
+                method,
+                "kraken",
+                ("$.result.ERGUSD.c[0]", Some(&last_trade)),
+                ("$.result.ERGUSD.b[0]", Some(&bid)),
+                ("$.result.ERGUSD.a[0]", Some(&ask)),
+                USD_PER_ERG,
+            )
+        };
+        assert_eq!(price_of(PriceMethod::LastTrade), Ok(1.7));
+        assert_eq!(price_of(PriceMethod::MidPrice), Ok(1.625));
+        // a missing side fails the mid-price, not the last trade
+        assert!(price_by(
+            PriceMethod::MidPrice,
+            "kraken",
+            ("$.result.ERGUSD.c[0]", Some(&last_trade)),
+            ("$.result.ERGUSD.b[0]", None),
+            ("$.result.ERGUSD.a[0]", Some(&ask)),
+            USD_PER_ERG,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_source_price_methods() {
+        let methods = HashMap::from([("kraken".to_string(), PriceMethod::MidPrice)]);
+        assert_eq!(validate_source_price_methods(&methods), Ok(()));
+        let methods = HashMap::from([("coinbase".to_string(), PriceMethod::MidPrice)]);
+        assert_eq!(
+            validate_source_price_methods(&methods),
+            Err(PriceMethodError::UnsupportedProvider(
+                "coinbase".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let methods: HashMap<String, PriceMethod> =
+            serde_yaml::from_str("kraken: mid_price\nokx: last_trade").unwrap();
+        assert_eq!(methods["kraken"], PriceMethod::MidPrice);
+        assert_eq!(methods["okx"], PriceMethod::LastTrade);
+    }
+}
@@ -0,0 +1,354 @@
+//! Sharing of the fetched rates of the predefined pairs between the oracle-core instances on one
+//! host. The instance with `rates_proxy.role: server` listens on a unix socket and answers the
+//! rate (with the per-source breakdown) of a pair, fetching it only if the last fetch is older
+//! than `max_age_secs`. The instances with `role: client` ask the server instead of the APIs and
+//! fall back to fetching themselves if the server can't be reached. This way the APIs are
+//! requested once per cycle for all the instances.
+//!
+//! The protocol is one JSON line each way: the pair name (e.g. `"NanoErgUsd"`) and the
+//! [`ProxyResponse`]. Unix sockets are not available on the other platforms, where `rates_proxy`
+//! is refused on config load.
+
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::io::BufRead;
+#[cfg(unix)]
+use std::io::BufReader;
+#[cfg(unix)]
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::sync::Arc;
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::sync::Once;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::oracle_types::Rate;
+use crate::pool_config::PredefinedDataPointSource;
+
+use super::aggregator::SourceRate;
+
+const DEFAULT_MAX_AGE_SECS: u64 = 60;
+
+/// The server fetches the rates of the slowest basket in well under this
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatesProxyRole {
+    /// Fetch the rates and serve them to the other instances
+    Server,
+    /// Get the rates from the server
+    Client,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RatesProxyConfig {
+    pub role: RatesProxyRole,
+    pub socket_path: PathBuf,
+    /// Seconds a fetched rate is served before it's fetched again (60 by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+}
+
+impl RatesProxyConfig {
+    pub fn max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_secs.unwrap_or(DEFAULT_MAX_AGE_SECS))
+    }
+
+    #[cfg(unix)]
+    pub fn validate(&self) -> Result<(), RatesProxyError> {
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn validate(&self) -> Result<(), RatesProxyError> {
+        Err(RatesProxyError::Unsupported)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RatesProxyError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("rates proxy server failed to fetch the rate: {0}")]
+    Server(String),
+    #[error("the rates proxy needs unix sockets, which are not supported on this platform")]
+    Unsupported,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyResponse {
+    Rate {
+        rate: Rate,
+        sources: Vec<SourceRate>,
+    },
+    Error(String),
+}
+
+struct CachedRate {
+    rate: Rate,
+    sources: Vec<SourceRate>,
+    fetched: Instant,
+}
+
+/// Last fetched rate of each pair
+#[derive(Default)]
+pub struct RatesCache {
+    rates: Mutex<HashMap<PredefinedDataPointSource, CachedRate>>,
+}
+
+impl RatesCache {
+    /// Cached rate of the pair, fetched if missing or older than `max_age`. The lock is held
+    /// during the fetch, so the concurrent requests wait for it instead of fetching again.
+    pub fn get_or_fetch<E>(
+        &self,
+        pair: PredefinedDataPointSource,
+        max_age: Duration,
+        fetch: impl FnOnce() -> Result<(Rate, Vec<SourceRate>), E>,
+    ) -> Result<(Rate, Vec<SourceRate>), E> {
+        let mut rates = self.rates.lock().unwrap();
+        if let Some(cached) = rates.get(&pair) {
+            if cached.fetched.elapsed() < max_age {
+                return Ok((cached.rate, cached.sources.clone()));
+            }
+        }
+        let (rate, sources) = fetch()?;
+        rates.insert(
+            pair,
+            CachedRate {
+                rate,
+                sources: sources.clone(),
+                fetched: Instant::now(),
+            },
+        );
+        Ok((rate, sources))
+    }
+}
+
+lazy_static! {
+    /// Rates served by this instance, shared with its own main loop
+    pub static ref SERVED_RATES: RatesCache = RatesCache::default();
+}
+
+#[cfg(unix)]
+static START: Once = Once::new();
+
+/// Starts the server thread if this instance is the rates proxy server, the stale socket file
+/// of the previous run is removed
+#[cfg(unix)]
+pub fn start_server<F>(config: &RatesProxyConfig, fetch: F)
+where
+    F: Fn(PredefinedDataPointSource) -> Result<(Rate, Vec<SourceRate>), String>
+        + Send
+        + Sync
+        + 'static,
+{
+    if config.role != RatesProxyRole::Server {
+        return;
+    }
+    let socket_path = config.socket_path.clone();
+    let max_age = config.max_age();
+    START.call_once(move || {
+        let _ = std::fs::remove_file(&socket_path);
+        match UnixListener::bind(&socket_path) {
+            Ok(listener) => {
+                log::info!("Serving the source rates on {}", socket_path.display());
+                serve(listener, max_age, Arc::new(fetch), &SERVED_RATES);
+            }
+            Err(e) => log::error!(
+                "Failed to listen on the rates proxy socket {}: {}",
+                socket_path.display(),
+                e
+            ),
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn start_server<F>(_config: &RatesProxyConfig, _fetch: F)
+where
+    F: Fn(PredefinedDataPointSource) -> Result<(Rate, Vec<SourceRate>), String>
+        + Send
+        + Sync
+        + 'static,
+{
+}
+
+#[cfg(unix)]
+fn serve<F>(listener: UnixListener, max_age: Duration, fetch: Arc<F>, cache: &'static RatesCache)
+where
+    F: Fn(PredefinedDataPointSource) -> Result<(Rate, Vec<SourceRate>), String>
+        + Send
+        + Sync
+        + 'static,
+{
+    let spawned = std::thread::Builder::new()
+        .name("rates-proxy".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::warn!("Rates proxy failed to accept a connection: {}", e);
+                        continue;
+                    }
+                };
+                let fetch = fetch.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, max_age, &*fetch, cache) {
+                        log::warn!("Rates proxy failed to answer a request: {}", e);
+                    }
+                });
+            }
+        });
+    if let Err(e) = spawned {
+        log::error!("Failed to start the rates proxy server: {}", e);
+    }
+}
+
+#[cfg(unix)]
+fn handle_connection(
+    stream: UnixStream,
+    max_age: Duration,
+    fetch: &dyn Fn(PredefinedDataPointSource) -> Result<(Rate, Vec<SourceRate>), String>,
+    cache: &RatesCache,
+) -> Result<(), RatesProxyError> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let pair: PredefinedDataPointSource = serde_json::from_str(&line)?;
+    let response = match cache.get_or_fetch(pair, max_age, || fetch(pair)) {
+        Ok((rate, sources)) => ProxyResponse::Rate { rate, sources },
+        Err(e) => ProxyResponse::Error(e),
+    };
+    let mut response_line = serde_json::to_string(&response)?;
+    response_line.push('\n');
+    (&stream).write_all(response_line.as_bytes())?;
+    Ok(())
+}
+
+/// Rate of the pair from the rates proxy server
+#[cfg(unix)]
+pub fn request(
+    socket_path: &Path,
+    pair: PredefinedDataPointSource,
+) -> Result<(Rate, Vec<SourceRate>), RatesProxyError> {
+    let stream = UnixStream::connect(socket_path)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut request_line = serde_json::to_string(&pair)?;
+    request_line.push('\n');
+    (&stream).write_all(request_line.as_bytes())?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    match serde_json::from_str(&line)? {
+        ProxyResponse::Rate { rate, sources } => Ok((rate, sources)),
+        ProxyResponse::Error(e) => Err(RatesProxyError::Server(e)),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn request(
+    _socket_path: &Path,
+    _pair: PredefinedDataPointSource,
+) -> Result<(Rate, Vec<SourceRate>), RatesProxyError> {
+    Err(RatesProxyError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    fn test_breakdown() -> Vec<SourceRate> {
+        vec![SourceRate {
+            source: "coingecko".to_string(),
+            rate: Some(600_000_000.0),
+            error: None,
+        }]
+    }
+
+    #[test]
+    fn test_cache_fetches_once_within_max_age() {
+        let cache = RatesCache::default();
+        let fetches = AtomicUsize::new(0);
+        let fetch = || {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, String>((Rate::from(600_000_000), test_breakdown()))
+        };
+        let pair = PredefinedDataPointSource::NanoErgUsd;
+        let max_age = Duration::from_secs(60);
+        assert_eq!(
+            cache.get_or_fetch(pair, max_age, fetch).unwrap().0,
+            Rate::from(600_000_000)
+        );
+        assert_eq!(
+            cache.get_or_fetch(pair, max_age, fetch).unwrap().1,
+            test_breakdown()
+        );
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        // another pair, and an expired rate
+        cache
+            .get_or_fetch(PredefinedDataPointSource::NanoErgXau, max_age, fetch)
+            .unwrap();
+        cache.get_or_fetch(pair, Duration::ZERO, fetch).unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_failed_fetch_is_not_cached() {
+        let cache = RatesCache::default();
+        let pair = PredefinedDataPointSource::NanoErgUsd;
+        let max_age = Duration::from_secs(60);
+        assert!(cache
+            .get_or_fetch(pair, max_age, || Err("no datapoints".to_string()))
+            .is_err());
+        assert!(cache
+            .get_or_fetch(pair, max_age, || Ok::<_, String>((
+                Rate::from(1),
+                Vec::new()
+            )))
+            .is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_request_over_socket() {
+        lazy_static! {
+            static ref CACHE: RatesCache = RatesCache::default();
+        }
+        let socket_path = std::env::temp_dir().join("oracle_core_test_rates_proxy.sock");
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let fetch = |pair: PredefinedDataPointSource| match pair {
+            PredefinedDataPointSource::NanoErgUsd => {
+                Ok((Rate::from(600_000_000), test_breakdown()))
+            }
+            _ => Err("pair not served".to_string()),
+        };
+        serve(listener, Duration::from_secs(60), Arc::new(fetch), &CACHE);
+        let (rate, sources) = request(&socket_path, PredefinedDataPointSource::NanoErgUsd).unwrap();
+        assert_eq!(rate, Rate::from(600_000_000));
+        assert_eq!(sources, test_breakdown());
+        assert!(matches!(
+            request(&socket_path, PredefinedDataPointSource::NanoErgXau),
+            Err(RatesProxyError::Server(_))
+        ));
+        std::fs::remove_file(&socket_path).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+//! Parsing and validation of the external API responses. Every source deserializes the response
+//! body into its own typed struct, skipping the fields it doesn't use. The price field must be
+//! present and hold a number (or a numeric string) within the plausible range of the quote, so
+//! that a malformed response or a maintenance page with placeholder values is rejected with an
+//! error naming the provider instead of being averaged into the datapoint.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::DataPointSourceError;
+
+/// Range a quote is expected to stay in. Wide enough for years of price moves, but rejects the
+/// zeros, placeholders and unit mix-ups.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlausibleRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl fmt::Display for PlausibleRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.min, self.max)
+    }
+}
+
+pub const USD_PER_ERG: PlausibleRange = PlausibleRange {
+    min: 0.001,
+    max: 10_000.0,
+};
+pub const BTC_PER_ERG: PlausibleRange = PlausibleRange {
+    min: 1e-9,
+    max: 0.1,
+};
+/// Troy ounces of gold per ERG
+pub const XAU_PER_ERG: PlausibleRange = PlausibleRange {
+    min: 1e-7,
+    max: 1.0,
+};
+pub const JPY_PER_ERG: PlausibleRange = PlausibleRange {
+    min: 0.1,
+    max: 1_000_000.0,
+};
+pub const JPY_PER_USD: PlausibleRange = PlausibleRange {
+    min: 50.0,
+    max: 1_000.0,
+};
+pub const USD_PER_ADA: PlausibleRange = PlausibleRange {
+    min: 0.001,
+    max: 10_000.0,
+};
+pub const USD_PER_BTC: PlausibleRange = PlausibleRange {
+    min: 100.0,
+    max: 10_000_000.0,
+};
+pub const USD_PER_TROY_OUNCE: PlausibleRange = PlausibleRange {
+    min: 100.0,
+    max: 100_000.0,
+};
+pub const USD_PER_GRAM_OF_GOLD: PlausibleRange = PlausibleRange {
+    min: 3.0,
+    max: 3_500.0,
+};
+pub const USD_PER_TROY_OUNCE_OF_PLATINUM: PlausibleRange = PlausibleRange {
+    min: 50.0,
+    max: 50_000.0,
+};
+pub const USD_PER_GRAM_OF_PLATINUM: PlausibleRange = PlausibleRange {
+    min: 1.5,
+    max: 1_500.0,
+};
+/// USD per barrel of Brent crude oil
+pub const USD_PER_BARREL_OF_BRENT: PlausibleRange = PlausibleRange {
+    min: 1.0,
+    max: 1_000.0,
+};
+pub const USD_PER_TROY_OUNCE_OF_PALLADIUM: PlausibleRange = PlausibleRange {
+    min: 50.0,
+    max: 50_000.0,
+};
+pub const USD_PER_GRAM_OF_PALLADIUM: PlausibleRange = PlausibleRange {
+    min: 1.5,
+    max: 1_500.0,
+};
+/// S&P 500 index level (in points, quoted as USD)
+pub const USD_PER_SPX: PlausibleRange = PlausibleRange {
+    min: 100.0,
+    max: 1_000_000.0,
+};
+/// US consumer price index level (1982-84 = 100)
+pub const CPI_INDEX: PlausibleRange = PlausibleRange {
+    min: 10.0,
+    max: 10_000.0,
+};
+/// Ergo network hashrate in hashes per second (1 GH/s to 100 PH/s)
+pub const HASHES_PER_SECOND: PlausibleRange = PlausibleRange {
+    min: 1e9,
+    max: 1e17,
+};
+/// Ergo mining difficulty, the hashes expected per block (2 minutes) at the hashrate above
+pub const DIFFICULTY: PlausibleRange = PlausibleRange {
+    min: 1.2e11,
+    max: 1.2e19,
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ResponseError {
+    #[error("{provider} response doesn't have the expected format: {error}")]
+    Malformed { provider: String, error: String },
+    #[error("{provider} response is missing {field}")]
+    MissingField { provider: String, field: String },
+    #[error("{provider} response has a non-numeric {field}: {value}")]
+    NotANumber {
+        provider: String,
+        field: String,
+        value: String,
+    },
+    #[error("{provider} response has {field} = {value} outside of the plausible range {range}")]
+    Implausible {
+        provider: String,
+        field: String,
+        value: f64,
+        range: PlausibleRange,
+    },
+}
+
+/// Price field of a response, some APIs return the prices as strings
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Quote {
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for Quote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Quote::Number(number) => write!(f, "{}", number),
+            Quote::Text(text) => write!(f, "{:?}", text),
+        }
+    }
+}
+
+/// Deserializes the provider's response body into its typed struct
+pub fn parse<T: DeserializeOwned>(provider: &str, body: &[u8]) -> Result<T, ResponseError> {
+    serde_json::from_slice(body).map_err(|e| ResponseError::Malformed {
+        provider: provider.to_string(),
+        error: e.to_string(),
+    })
+}
+
+/// Reads the response body and deserializes it into the provider's typed struct
+pub async fn parse_body<T: DeserializeOwned>(
+    provider: &str,
+    resp: reqwest::Response,
+) -> Result<T, DataPointSourceError> {
+    let body = resp.bytes().await?;
+    Ok(parse(provider, &body)?)
+}
+
+/// Price in the field (e.g. `$.data[0].bidPx`, used in the errors) of the provider's response
+pub fn price(
+    provider: &str,
+    field: &str,
+    quote: Option<&Quote>,
+    range: PlausibleRange,
+) -> Result<f64, ResponseError> {
+    let quote = quote.ok_or_else(|| ResponseError::MissingField {
+        provider: provider.to_string(),
+        field: field.to_string(),
+    })?;
+    let number = match quote {
+        Quote::Number(number) => Some(*number),
+        Quote::Text(text) => text.parse::<f64>().ok(),
+    }
+    .filter(|number| number.is_finite())
+    .ok_or_else(|| ResponseError::NotANumber {
+        provider: provider.to_string(),
+        field: field.to_string(),
+        value: quote.to_string(),
+    })?;
+    if number < range.min || number > range.max {
+        return Err(ResponseError::Implausible {
+            provider: provider.to_string(),
+            field: field.to_string(),
+            value: number,
+            range,
+        });
+    }
+    Ok(number)
+}
+
+/// Middle of the bid/ask spread in the fields of the provider's response
+pub fn mid_price(
+    provider: &str,
+    (bid_field, bid): (&str, Option<&Quote>),
+    (ask_field, ask): (&str, Option<&Quote>),
+    range: PlausibleRange,
+) -> Result<f64, ResponseError> {
+    let bid = price(provider, bid_field, bid, range)?;
+    let ask = price(provider, ask_field, ask, range)?;
+    Ok((bid + ask) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Ticker {
+        bid_px: Option<Quote>,
+        ask_px: Option<Quote>,
+        ask: Option<Quote>,
+        last: Option<Quote>,
+        vol: Option<Quote>,
+    }
+
+    #[test]
+    fn test_price() {
+        let ticker: Ticker = parse(
+            "okx",
+            br#"{"bidPx": "1.5", "askPx": 1.75, "last": 0, "vol": "n/a", "ts": "1700000000"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            mid_price(
+                "okx",
+                ("$.bidPx", ticker.bid_px.as_ref()),
+                ("$.askPx", ticker.ask_px.as_ref()),
+                USD_PER_ERG
+            ),
+            Ok(1.625)
+        );
+        assert!(matches!(
+            price("okx", "$.ask", ticker.ask.as_ref(), USD_PER_ERG),
+            Err(ResponseError::MissingField { .. })
+        ));
+        assert!(matches!(
+            price("okx", "$.vol", ticker.vol.as_ref(), USD_PER_ERG),
+            Err(ResponseError::NotANumber { .. })
+        ));
+        // a placeholder zero of a maintenance response
+        assert_eq!(
+            price("okx", "$.last", ticker.last.as_ref(), USD_PER_ERG),
+            Err(ResponseError::Implausible {
+                provider: "okx".to_string(),
+                field: "$.last".to_string(),
+                value: 0.0,
+                range: USD_PER_ERG,
+            })
+        );
+        assert!(price("okx", "$.bidPx", ticker.bid_px.as_ref(), USD_PER_BTC).is_err());
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        assert!(matches!(
+            parse::<Ticker>("okx", b"<html>maintenance</html>"),
+            Err(ResponseError::Malformed { .. })
+        ));
+        assert!(matches!(
+            parse::<Ticker>("okx", br#"{"bidPx": true}"#),
+            Err(ResponseError::Malformed { .. })
+        ));
+    }
+}
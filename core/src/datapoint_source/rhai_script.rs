@@ -0,0 +1,169 @@
+//! Datapoint source computed by a Rhai script (`data_point_source_rhai` in the oracle config),
+//! available when oracle-core is built with the `rhai` feature. The script can fetch the data
+//! with `http_get(url)` and `http_get_json(url)` (both also take a map of the extra headers) and
+//! must evaluate to the datapoint, e.g.
+//!
+//! ```text
+//! let price = http_get_json("https://api.example.com/ergo");
+//! 1_000_000_000.0 / price.usd
+//! ```
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::oracle_types::Rate;
+
+use super::DataPointSource;
+use super::DataPointSourceError;
+
+#[derive(Debug, Error)]
+#[cfg_attr(not(feature = "rhai"), allow(dead_code))]
+pub enum RhaiScriptError {
+    #[error("failed to read Rhai script {}: {error}", .path.display())]
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    #[error("Rhai script error: {0}")]
+    Script(String),
+    #[error("Rhai script returned {0}, expected a number")]
+    NotANumber(String),
+    #[error("oracle-core is built without the rhai feature")]
+    FeatureDisabled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RhaiScriptSource {
+    /// Path of the script, re-read on every request
+    pub path: PathBuf,
+}
+
+#[cfg(feature = "rhai")]
+mod engine {
+    use std::time::Duration;
+
+    use rhai::Dynamic;
+    use rhai::Engine;
+    use rhai::EvalAltResult;
+    use rhai::Map;
+
+    use super::RhaiScriptError;
+    use crate::oracle_types::Rate;
+
+    /// Limit of the script operations, so that a runaway script doesn't stall the main loop
+    const MAX_OPERATIONS: u64 = 1_000_000;
+    const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+    fn http_get_with_headers(url: &str, headers: Map) -> Result<String, Box<EvalAltResult>> {
+        let config = crate::oracle_config::ORACLE_CONFIG_OPT.as_ref().ok();
+        let user_agent = config
+            .and_then(|c| c.http_user_agent.as_deref())
+            .unwrap_or(super::super::http_client::DEFAULT_USER_AGENT);
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(user_agent)
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut request = client.get(url);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.to_string());
+        }
+        let resp = request
+            .send()
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| e.to_string())?;
+        Ok(resp.text().map_err(|e| e.to_string())?)
+    }
+
+    fn http_get_json_with_headers(url: &str, headers: Map) -> Result<Dynamic, Box<EvalAltResult>> {
+        let body = http_get_with_headers(url, headers)?;
+        let json: serde_json::Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+        rhai::serde::to_dynamic(json)
+    }
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.register_fn("http_get", |url: &str| {
+            http_get_with_headers(url, Map::new())
+        });
+        engine.register_fn("http_get", http_get_with_headers);
+        engine.register_fn("http_get_json", |url: &str| {
+            http_get_json_with_headers(url, Map::new())
+        });
+        engine.register_fn("http_get_json", http_get_json_with_headers);
+        engine
+    }
+
+    /// Runs the script, rounding a float result
+    pub fn eval(script: &str) -> Result<Rate, RhaiScriptError> {
+        let result: Dynamic = engine()
+            .eval(script)
+            .map_err(|e| RhaiScriptError::Script(e.to_string()))?;
+        if let Ok(int) = result.as_int() {
+            Ok(int.into())
+        } else if let Ok(float) = result.as_float() {
+            Ok((float.round() as i64).into())
+        } else {
+            Err(RhaiScriptError::NotANumber(result.to_string()))
+        }
+    }
+}
+
+impl RhaiScriptSource {
+    #[cfg(feature = "rhai")]
+    fn run(&self) -> Result<Rate, RhaiScriptError> {
+        let script = std::fs::read_to_string(&self.path).map_err(|error| RhaiScriptError::Io {
+            path: self.path.clone(),
+            error,
+        })?;
+        engine::eval(&script)
+    }
+
+    #[cfg(not(feature = "rhai"))]
+    fn run(&self) -> Result<Rate, RhaiScriptError> {
+        Err(RhaiScriptError::FeatureDisabled)
+    }
+}
+
+impl DataPointSource for RhaiScriptSource {
+    fn get_datapoint(&self) -> Result<Rate, DataPointSourceError> {
+        Ok(self.run()?)
+    }
+}
+
+#[cfg(all(test, feature = "rhai"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(engine::eval("let usd = 2; usd * 1000").unwrap(), 2000);
+        assert_eq!(engine::eval("1_000_000_000.0 / 1.6").unwrap(), 625_000_000);
+        assert!(matches!(
+            engine::eval(r#""1000""#),
+            Err(RhaiScriptError::NotANumber(_))
+        ));
+        assert!(matches!(
+            engine::eval("let x = ;"),
+            Err(RhaiScriptError::Script(_))
+        ));
+        assert!(matches!(
+            engine::eval("loop {}"),
+            Err(RhaiScriptError::Script(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_from_file() {
+        let path = std::env::temp_dir().join("oracle_core_test_datapoint.rhai");
+        std::fs::write(&path, "40 + 2").unwrap();
+        let source = RhaiScriptSource { path: path.clone() };
+        assert_eq!(source.get_datapoint().unwrap(), 42);
+        std::fs::remove_file(&path).unwrap();
+        assert!(source.get_datapoint().is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Derives SigUSD and SigRSV prices from the SigmaUSD bank box state (fetched from the explorer)
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::NanoErg;
+use super::erg_ageusd::SigRsv;
+use super::erg_ageusd::SigUsd;
+use super::response::Quote;
+use super::DataPointSourceError;
+
+/// Token id of the SigmaUSD bank NFT
+const BANK_NFT_ID: &str = "7d672d1def471720ca5782fd6473e47e796d9ac0c138d9911346f118b2f6d9d9";
+/// Token id of the ERG/USD oracle pool NFT the bank contract is using
+const ERG_USD_ORACLE_NFT_ID: &str =
+    "011d3364de07e5a26f0c4eef0852cddb387039a921b7154ef3cab22c6eda887f";
+/// SigRSV price (in nanoErgs) used by the bank contract when there is no equity
+const DEFAULT_SIGRSV_PRICE: f64 = 1_000_000.0;
+
+/// State of the SigmaUSD bank box needed to calculate the token prices
+#[derive(Debug, Clone, Copy)]
+pub struct BankState {
+    /// nanoErgs in the bank box
+    pub reserve: f64,
+    /// SigUSD in circulation (in cents)
+    pub circulating_sigusd: f64,
+    /// SigRSV in circulation
+    pub circulating_sigrsv: f64,
+    /// nanoErgs per 1 USD cent (ERG/USD oracle pool datapoint)
+    pub nanoerg_per_usd_cent: f64,
+}
+
+impl BankState {
+    /// nanoErgs per 1 SigUSD (100 cents) as calculated by the bank contract
+    pub fn sigusd_price(&self) -> f64 {
+        let price_per_cent = if self.circulating_sigusd > 0.0 {
+            f64::min(
+                self.nanoerg_per_usd_cent,
+                self.reserve / self.circulating_sigusd,
+            )
+        } else {
+            self.nanoerg_per_usd_cent
+        };
+        price_per_cent * 100.0
+    }
+
+    /// nanoErgs per 1 SigRSV as calculated by the bank contract
+    pub fn sigrsv_price(&self) -> f64 {
+        let liabilities = f64::min(
+            self.circulating_sigusd * self.nanoerg_per_usd_cent,
+            self.reserve,
+        );
+        let equity = self.reserve - liabilities;
+        if self.circulating_sigrsv > 0.0 && equity > 0.0 {
+            equity / self.circulating_sigrsv
+        } else {
+            DEFAULT_SIGRSV_PRICE
+        }
+    }
+}
+
+/// Box of the explorer API with the fields used for the bank state
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerBox {
+    value: Option<Quote>,
+    #[serde(default)]
+    additional_registers: HashMap<String, Register>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Register {
+    rendered_value: Option<Quote>,
+}
+
+#[derive(Deserialize)]
+struct UnspentBoxes {
+    #[serde(default)]
+    items: Vec<ExplorerBox>,
+}
+
+#[cfg(not(test))]
+async fn get_unspent_box_by_token_id(token_id: &str) -> Result<ExplorerBox, DataPointSourceError> {
+    let url = format!(
+        "https://api.ergoplatform.com/api/v1/boxes/unspent/byTokenId/{}",
+        token_id
+    );
+    let resp = super::http_client::get("explorer", url).await?;
+    let unspent: UnspentBoxes = super::response::parse_body("explorer", resp).await?;
+    unspent.items.into_iter().next().ok_or_else(|| {
+        super::response::ResponseError::MissingField {
+            provider: "explorer".to_string(),
+            field: "$.items[0]".to_string(),
+        }
+        .into()
+    })
+}
+
+/// Number in the box field. The amounts are not prices, so any non-negative number is accepted.
+#[cfg(not(test))]
+fn get_number(field: &str, value: Option<&Quote>) -> Result<f64, DataPointSourceError> {
+    let non_negative = super::response::PlausibleRange {
+        min: 0.0,
+        max: f64::MAX,
+    };
+    Ok(super::response::price(
+        "explorer",
+        field,
+        value,
+        non_negative,
+    )?)
+}
+
+#[cfg(not(test))]
+fn get_long_register(ergo_box: &ExplorerBox, register: &str) -> Result<f64, DataPointSourceError> {
+    get_number(
+        &format!("$.additionalRegisters.{}.renderedValue", register),
+        ergo_box
+            .additional_registers
+            .get(register)
+            .and_then(|r| r.rendered_value.as_ref()),
+    )
+}
+
+#[cfg(not(test))]
+pub async fn get_bank_state() -> Result<BankState, DataPointSourceError> {
+    let bank_box = get_unspent_box_by_token_id(BANK_NFT_ID).await?;
+    let oracle_box = get_unspent_box_by_token_id(ERG_USD_ORACLE_NFT_ID).await?;
+    Ok(BankState {
+        reserve: get_number("$.value", bank_box.value.as_ref())?,
+        circulating_sigusd: get_long_register(&bank_box, "R4")?,
+        circulating_sigrsv: get_long_register(&bank_box, "R5")?,
+        nanoerg_per_usd_cent: get_long_register(&oracle_box, "R4")?,
+    })
+}
+
+#[cfg(test)]
+pub async fn get_bank_state() -> Result<BankState, DataPointSourceError> {
+    Ok(BankState {
+        reserve: 1_500_000_000_000_000.0,
+        circulating_sigusd: 30_000_000.0,
+        circulating_sigrsv: 500_000_000.0,
+        nanoerg_per_usd_cent: 6_000_000.0,
+    })
+}
+
+pub async fn get_sigusd_nanoerg(
+) -> Result<AssetsExchangeRate<SigUsd, NanoErg>, DataPointSourceError> {
+    let bank_state = get_bank_state().await?;
+    Ok(AssetsExchangeRate {
+        per1: SigUsd {},
+        get: NanoErg {},
+        rate: bank_state.sigusd_price(),
+    })
+}
+
+pub async fn get_sigrsv_nanoerg(
+) -> Result<AssetsExchangeRate<SigRsv, NanoErg>, DataPointSourceError> {
+    let bank_state = get_bank_state().await?;
+    Ok(AssetsExchangeRate {
+        per1: SigRsv {},
+        get: NanoErg {},
+        rate: bank_state.sigrsv_price(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bank_prices() {
+        let bank_state = BankState {
+            reserve: 1000.0,
+            circulating_sigusd: 100.0,
+            circulating_sigrsv: 50.0,
+            nanoerg_per_usd_cent: 2.0,
+        };
+        assert_eq!(bank_state.sigusd_price(), 200.0);
+        assert_eq!(bank_state.sigrsv_price(), 16.0);
+    }
+
+    #[test]
+    fn test_bank_prices_undercollateralized() {
+        let bank_state = BankState {
+            reserve: 100.0,
+            circulating_sigusd: 100.0,
+            circulating_sigrsv: 50.0,
+            nanoerg_per_usd_cent: 2.0,
+        };
+        // SigUSD holders share the reserve, SigRSV falls back to the default price
+        assert_eq!(bank_state.sigusd_price(), 100.0);
+        assert_eq!(bank_state.sigrsv_price(), DEFAULT_SIGRSV_PRICE);
+    }
+}
@@ -0,0 +1,645 @@
+//! Token prices from the Spectrum DEX ERG/token liquidity pools
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::DeserializeSeed;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+use thiserror::Error;
+
+use crate::oracle_config::DexLiquidityCheck;
+use crate::oracle_config::ORACLE_CONFIG_OPT;
+
+use super::ada_usd::Lovelace;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::Btc;
+use super::assets_exchange_rate::NanoErg;
+use super::assets_exchange_rate::Usd;
+use super::erg_ageusd::SigRsv;
+use super::erg_ageusd::SigUsd;
+use super::erg_rsn::Rsn;
+use super::response::ResponseError;
+use super::DataPointSourceError;
+
+/// Token id of ERG as used in the Spectrum API
+const ERG_TOKEN_ID: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+pub const SIGUSD_TOKEN_ID: &str =
+    "03faf2cb329f2e90d6d23b58d91bbb6c046aa143261cc21f52fbe2824bfcbf04";
+pub const SIGRSV_TOKEN_ID: &str =
+    "003bd19d0187117f130b62e1bcab0939929ff5c7709f843c5c4dd158949285d0";
+/// Rosen Bridge token
+pub const RSN_TOKEN_ID: &str = "8b08cdd5449a9592a9e79711d7d79249d7a03c535d17efaee83e216e80a44c4b";
+/// ADA bridged by Rosen Bridge
+pub const RSADA_TOKEN_ID: &str = "e023c5f382b6e96fbd878f6811aac73345489032157ad5affb84aefd4956c297";
+/// BTC bridged by Rosen Bridge
+pub const RSBTC_TOKEN_ID: &str = "5bf691fbf0c4b17f8f8cece83fa947f62f480bfbd242bd58946f85535125db4d";
+/// Stands for the USDT token id of the oracle config in the tests
+#[cfg(test)]
+const TEST_USDT_TOKEN_ID: &str = "7a51950e5f548549ec1aa63ffdc3f9a0b3f1ea6bd1a1b4d0ff2c1bbb1f6bd1d4";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SpectrumError {
+    #[error("spectrum_usdt_token_id is not set in the oracle config")]
+    UsdtTokenIdNotSet,
+    #[error("invalid token id {0}, expected 64 hex characters")]
+    InvalidTokenId(String),
+    #[error("invalid pool id {0}, expected 64 hex characters")]
+    InvalidPoolId(String),
+    #[error("no pools listed for the token {0}")]
+    NoPoolIds(String),
+}
+
+/// Checks that the token id is a base16-encoded 32 bytes id
+pub fn validate_token_id(token_id: &str) -> Result<(), SpectrumError> {
+    match base16::decode(token_id) {
+        Ok(bytes) if bytes.len() == 32 => Ok(()),
+        _ => Err(SpectrumError::InvalidTokenId(token_id.to_string())),
+    }
+}
+
+/// Checks the pool ids listed by token id in the oracle config
+pub fn validate_pool_ids(pool_ids: &HashMap<String, Vec<String>>) -> Result<(), SpectrumError> {
+    for (token_id, ids) in pool_ids {
+        validate_token_id(token_id)?;
+        if ids.is_empty() {
+            return Err(SpectrumError::NoPoolIds(token_id.clone()));
+        }
+        if let Some(id) = ids.iter().find(|id| validate_token_id(id).is_err()) {
+            return Err(SpectrumError::InvalidPoolId(id.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Ids of the pools the token price is taken from, `None` if all ERG/token pools are used
+fn configured_pool_ids(token_id: &str) -> Option<Vec<String>> {
+    ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.spectrum_pool_ids.get(token_id).cloned())
+}
+
+/// Token id of the USDT bridged to Ergo, the ERG/USDT pools are looked up by it. There is no
+/// default, as a token named USDT can be minted by anyone.
+pub fn usdt_token_id() -> Option<String> {
+    ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.spectrum_usdt_token_id.clone())
+}
+
+/// Locked amounts (in whole ERG and whole tokens) of an ERG/token liquidity pool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolLiquidity {
+    pub erg_locked: f64,
+    pub token_locked: f64,
+}
+
+/// Averages the token per 1 ERG price over the pools weighted by their TVL, so that a single
+/// thin pool can't move the price. The TVL of an ERG/token pool is twice its ERG side, so the
+/// locked ERG is used as the weight. Returns `None` if no pool has liquidity.
+pub fn tvl_weighted_token_per_erg(pools: &[PoolLiquidity]) -> Option<f64> {
+    let pools_with_liquidity = pools
+        .iter()
+        .filter(|p| p.erg_locked > 0.0 && p.token_locked > 0.0);
+    let (weighted_sum, total_weight) =
+        pools_with_liquidity.fold((0.0, 0.0), |(weighted_sum, total_weight), p| {
+            let token_per_erg = p.token_locked / p.erg_locked;
+            (
+                weighted_sum + token_per_erg * p.erg_locked,
+                total_weight + p.erg_locked,
+            )
+        });
+    if total_weight > 0.0 {
+        Some(weighted_sum / total_weight)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum DexLiquidityError {
+    #[error("ERG/{token_id} pools have {erg_locked} ERG locked, below the minimum of {min_erg_locked} ERG")]
+    InsufficientLiquidity {
+        token_id: String,
+        erg_locked: f64,
+        min_erg_locked: f64,
+    },
+    #[error("{trade_erg} ERG trade in ERG/{token_id} pools has {price_impact_percent:.2}% price impact, above the maximum of {max_price_impact_percent}%")]
+    PriceImpactTooHigh {
+        token_id: String,
+        trade_erg: f64,
+        price_impact_percent: f64,
+        max_price_impact_percent: f64,
+    },
+}
+
+/// Price impact (in percent) of selling `trade_erg` ERG into a constant product pool with
+/// `erg_locked` ERG (pool fees are not included)
+pub fn price_impact_percent(erg_locked: f64, trade_erg: f64) -> f64 {
+    trade_erg / (erg_locked + trade_erg) * 100.0
+}
+
+/// Rejects the pools if their total liquidity is below the threshold or the reference trade
+/// (routed over all of them) moves the price too much
+pub fn check_pools_liquidity(
+    token_id: &str,
+    pools: &[PoolLiquidity],
+    check: &DexLiquidityCheck,
+) -> Result<(), DexLiquidityError> {
+    let erg_locked: f64 = pools.iter().map(|p| p.erg_locked).sum();
+    if erg_locked < check.min_erg_locked {
+        return Err(DexLiquidityError::InsufficientLiquidity {
+            token_id: token_id.to_string(),
+            erg_locked,
+            min_erg_locked: check.min_erg_locked,
+        });
+    }
+    let impact = price_impact_percent(erg_locked, check.reference_trade_erg);
+    if impact > check.max_price_impact_percent {
+        return Err(DexLiquidityError::PriceImpactTooHigh {
+            token_id: token_id.to_string(),
+            trade_erg: check.reference_trade_erg,
+            price_impact_percent: impact,
+            max_price_impact_percent: check.max_price_impact_percent,
+        });
+    }
+    Ok(())
+}
+
+#[derive(Default, Deserialize)]
+struct LockedAsset<'a> {
+    #[serde(borrow, default)]
+    id: Cow<'a, str>,
+    amount: Option<f64>,
+    #[serde(default)]
+    decimals: i32,
+}
+
+impl LockedAsset<'_> {
+    /// Locked amount in whole ERG or tokens
+    fn whole_amount(&self) -> Option<f64> {
+        Some(self.amount? / 10f64.powi(self.decimals))
+    }
+}
+
+/// Entry of the pool stats list with the fields used for the liquidity
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PoolStats<'a> {
+    #[serde(borrow, default)]
+    id: Cow<'a, str>,
+    #[serde(borrow, default)]
+    locked_x: LockedAsset<'a>,
+    #[serde(borrow, default)]
+    locked_y: LockedAsset<'a>,
+}
+
+/// Deserializes the pool stats list one pool at a time, keeping only the ERG/token pools of the
+/// token (the listed ones if `pool_ids` is set), so the rest of the (large) list is never
+/// collected
+struct ErgTokenPools<'t> {
+    token_id: &'t str,
+    pool_ids: Option<&'t [String]>,
+}
+
+impl<'de> DeserializeSeed<'de> for ErgTokenPools<'_> {
+    type Value = Vec<PoolLiquidity>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ErgTokenPools<'_> {
+    type Value = Vec<PoolLiquidity>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a list of pool stats")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut pools = Vec::new();
+        while let Some(stats) = seq.next_element::<PoolStats<'de>>()? {
+            if stats.locked_x.id != ERG_TOKEN_ID || stats.locked_y.id != self.token_id {
+                continue;
+            }
+            if let Some(pool_ids) = self.pool_ids {
+                if !pool_ids.iter().any(|id| *id == stats.id) {
+                    continue;
+                }
+            }
+            if let (Some(erg_locked), Some(token_locked)) =
+                (stats.locked_x.whole_amount(), stats.locked_y.whole_amount())
+            {
+                pools.push(PoolLiquidity {
+                    erg_locked,
+                    token_locked,
+                });
+            }
+        }
+        Ok(pools)
+    }
+}
+
+/// Locked amounts of the ERG/token pools of the token (only the listed ones if `pool_ids` is set)
+/// in the pool stats response body
+fn parse_token_pools(
+    token_id: &str,
+    pool_ids: Option<&[String]>,
+    body: &[u8],
+) -> Result<Vec<PoolLiquidity>, ResponseError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    ErgTokenPools { token_id, pool_ids }
+        .deserialize(&mut deserializer)
+        .and_then(|pools| deserializer.end().map(|_| pools))
+        .map_err(|e| ResponseError::Malformed {
+            provider: "spectrum".to_string(),
+            error: e.to_string(),
+        })
+}
+
+/// Returns the locked amounts of the ERG/token liquidity pools of the given token, the ones
+/// listed in `spectrum_pool_ids` of the oracle config or all of them if the token is not listed
+#[cfg(not(test))]
+async fn get_token_pools(token_id: &str) -> Result<Vec<PoolLiquidity>, DataPointSourceError> {
+    let url = "https://api.spectrum.fi/v1/amm/pools/stats";
+    let resp = super::http_client::get("spectrum", url).await?;
+    let pool_ids = configured_pool_ids(token_id);
+    let pools = parse_token_pools(token_id, pool_ids.as_deref(), &resp.bytes().await?)?;
+    if pools.is_empty() {
+        return Err(ResponseError::MissingField {
+            provider: "spectrum".to_string(),
+            field: format!(
+                "lockedX.amount and lockedY.amount for ERG/{} pools",
+                token_id
+            ),
+        }
+        .into());
+    }
+    Ok(pools)
+}
+
+#[cfg(test)]
+async fn get_token_pools(token_id: &str) -> Result<Vec<PoolLiquidity>, DataPointSourceError> {
+    match token_id {
+        SIGUSD_TOKEN_ID => Ok(vec![PoolLiquidity {
+            erg_locked: 500_000.0,
+            token_locked: 830_000.0,
+        }]),
+        SIGRSV_TOKEN_ID => Ok(vec![PoolLiquidity {
+            erg_locked: 100_000.0,
+            token_locked: 38_000_000.0,
+        }]),
+        RSN_TOKEN_ID => Ok(vec![
+            PoolLiquidity {
+                erg_locked: 30_000.0,
+                token_locked: 2_400_000.0,
+            },
+            PoolLiquidity {
+                erg_locked: 50.0,
+                token_locked: 1_000.0,
+            },
+        ]),
+        RSADA_TOKEN_ID => Ok(vec![PoolLiquidity {
+            erg_locked: 200_000.0,
+            token_locked: 550_600.0,
+        }]),
+        RSBTC_TOKEN_ID => Ok(vec![PoolLiquidity {
+            erg_locked: 50_000.0,
+            token_locked: 1.8955,
+        }]),
+        TEST_USDT_TOKEN_ID => Ok(vec![PoolLiquidity {
+            erg_locked: 400_000.0,
+            token_locked: 668_000.0,
+        }]),
+        _ => Err(DataPointSourceError::NoDataPoints),
+    }
+}
+
+/// Token per 1 ERG price weighted by TVL over the ERG/token pools that pass the liquidity check
+async fn get_token_per_erg(token_id: &str) -> Result<f64, DataPointSourceError> {
+    let pools = get_token_pools(token_id).await?;
+    let check = ORACLE_CONFIG_OPT
+        .as_ref()
+        .map(|c| c.dex_liquidity_check)
+        .unwrap_or_default();
+    check_pools_liquidity(token_id, &pools, &check)?;
+    tvl_weighted_token_per_erg(&pools).ok_or(DataPointSourceError::NoDataPoints)
+}
+
+pub async fn get_sigusd_nanoerg(
+) -> Result<AssetsExchangeRate<SigUsd, NanoErg>, DataPointSourceError> {
+    let sigusd_per_erg = get_token_per_erg(SIGUSD_TOKEN_ID).await?;
+    Ok(AssetsExchangeRate {
+        per1: SigUsd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / sigusd_per_erg),
+    })
+}
+
+pub async fn get_sigrsv_nanoerg(
+) -> Result<AssetsExchangeRate<SigRsv, NanoErg>, DataPointSourceError> {
+    let sigrsv_per_erg = get_token_per_erg(SIGRSV_TOKEN_ID).await?;
+    Ok(AssetsExchangeRate {
+        per1: SigRsv {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / sigrsv_per_erg),
+    })
+}
+
+/// RSN price aggregated across the ERG/RSN pools (the configured ones if listed) weighted by their
+/// TVL
+pub async fn get_rsn_nanoerg() -> Result<AssetsExchangeRate<Rsn, NanoErg>, DataPointSourceError> {
+    let rsn_per_erg = get_token_per_erg(RSN_TOKEN_ID).await?;
+    Ok(AssetsExchangeRate {
+        per1: Rsn {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / rsn_per_erg),
+    })
+}
+
+/// rsADA price, used as the ERG leg of the ADA/USD cross-check
+pub async fn get_nanoerg_lovelace(
+) -> Result<AssetsExchangeRate<NanoErg, Lovelace>, DataPointSourceError> {
+    let rsada_per_erg = get_token_per_erg(RSADA_TOKEN_ID).await?;
+    Ok(AssetsExchangeRate {
+        per1: NanoErg {},
+        get: Lovelace {},
+        rate: Lovelace::from_ada(rsada_per_erg) / NanoErg::from_erg(1.0),
+    })
+}
+
+/// ERG/USD price from the ERG/USDT pools, assumes USDT trades at par with USD
+pub async fn get_usd_nanoerg() -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let token_id = usdt_token_id().ok_or(SpectrumError::UsdtTokenIdNotSet)?;
+    get_usd_nanoerg_from_usdt_pools(&token_id).await
+}
+
+async fn get_usd_nanoerg_from_usdt_pools(
+    usdt_token_id: &str,
+) -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let usdt_per_erg = get_token_per_erg(usdt_token_id).await?;
+    Ok(AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / usdt_per_erg),
+    })
+}
+
+/// ERG/USD price from the ERG/SigUSD pools, assumes SigUSD trades at par with USD, i.e. the
+/// on-chain stablecoin rate in place of the exchange prices
+pub async fn get_usd_nanoerg_from_sigusd(
+) -> Result<AssetsExchangeRate<Usd, NanoErg>, DataPointSourceError> {
+    let sigusd = get_sigusd_nanoerg().await?;
+    Ok(AssetsExchangeRate {
+        per1: Usd {},
+        get: NanoErg {},
+        rate: sigusd.rate,
+    })
+}
+
+/// rsBTC price, assumes rsBTC trades at par with BTC
+pub async fn get_btc_nanoerg() -> Result<AssetsExchangeRate<Btc, NanoErg>, DataPointSourceError> {
+    let rsbtc_per_erg = get_token_per_erg(RSBTC_TOKEN_ID).await?;
+    Ok(AssetsExchangeRate {
+        per1: Btc {},
+        get: NanoErg {},
+        rate: NanoErg::from_erg(1.0 / rsbtc_per_erg),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assets_exchange_rate::convert_rate;
+    use super::super::coingecko;
+    use super::*;
+
+    #[test]
+    fn test_sigusd_price() {
+        let pair = tokio_test::block_on(get_sigusd_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+
+    #[test]
+    fn test_sigusd_usd_price() {
+        let pair = tokio_test::block_on(get_usd_nanoerg_from_sigusd()).unwrap();
+        let sigusd = tokio_test::block_on(get_sigusd_nanoerg()).unwrap();
+        assert_eq!(pair.rate, sigusd.rate);
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_sigrsv_price() {
+        let pair = tokio_test::block_on(get_sigrsv_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+
+    #[test]
+    fn test_rsn_price() {
+        let pair = tokio_test::block_on(get_rsn_nanoerg()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+
+    #[test]
+    fn test_rsbtc_price() {
+        let pair = tokio_test::block_on(get_btc_nanoerg()).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_btc_nanoerg()).unwrap();
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_usdt_price() {
+        let pair =
+            tokio_test::block_on(get_usd_nanoerg_from_usdt_pools(TEST_USDT_TOKEN_ID)).unwrap();
+        let coingecko = tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap();
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_validate_token_id() {
+        assert_eq!(validate_token_id(RSBTC_TOKEN_ID), Ok(()));
+        assert!(validate_token_id("USDT").is_err());
+        assert!(validate_token_id(&RSBTC_TOKEN_ID[..62]).is_err());
+    }
+
+    #[test]
+    fn test_rsada_price() {
+        let pair = convert_rate(
+            tokio_test::block_on(get_nanoerg_lovelace()).unwrap(),
+            tokio_test::block_on(coingecko::get_usd_nanoerg()).unwrap(),
+        );
+        let coingecko = tokio_test::block_on(coingecko::get_usd_lovelace()).unwrap();
+        let deviation_from_coingecko = (pair.rate - coingecko.rate).abs() / coingecko.rate;
+        assert!(
+            deviation_from_coingecko < 0.05,
+            "up to 5% deviation is allowed"
+        );
+    }
+
+    #[test]
+    fn test_tvl_weighted_token_per_erg() {
+        let deep = PoolLiquidity {
+            erg_locked: 30_000.0,
+            token_locked: 2_400_000.0,
+        };
+        // thin pool with a price 4 times lower
+        let thin = PoolLiquidity {
+            erg_locked: 10.0,
+            token_locked: 200.0,
+        };
+        let empty = PoolLiquidity {
+            erg_locked: 0.0,
+            token_locked: 0.0,
+        };
+        assert_eq!(tvl_weighted_token_per_erg(&[]), None);
+        assert_eq!(tvl_weighted_token_per_erg(&[empty]), None);
+        assert_eq!(tvl_weighted_token_per_erg(&[deep, empty]), Some(80.0));
+        let weighted = tvl_weighted_token_per_erg(&[deep, thin]).unwrap();
+        assert!((weighted - 80.0).abs() / 80.0 < 0.001);
+    }
+
+    #[test]
+    fn test_check_pools_liquidity() {
+        let check = DexLiquidityCheck {
+            min_erg_locked: 5_000.0,
+            reference_trade_erg: 100.0,
+            max_price_impact_percent: 2.0,
+        };
+        let pool = |erg_locked| PoolLiquidity {
+            erg_locked,
+            token_locked: erg_locked * 80.0,
+        };
+        assert_eq!(
+            check_pools_liquidity(RSN_TOKEN_ID, &[pool(3_000.0), pool(2_000.0)], &check),
+            Ok(())
+        );
+        assert!(matches!(
+            check_pools_liquidity(RSN_TOKEN_ID, &[pool(4_000.0)], &check),
+            Err(DexLiquidityError::InsufficientLiquidity { .. })
+        ));
+        let strict_check = DexLiquidityCheck {
+            max_price_impact_percent: 1.0,
+            ..check
+        };
+        assert!(matches!(
+            check_pools_liquidity(RSN_TOKEN_ID, &[pool(6_000.0)], &strict_check),
+            Err(DexLiquidityError::PriceImpactTooHigh { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_token_pools() {
+        let body = format!(
+            r#"[
+                {{"id": "a", "lockedX": {{"id": "{erg}", "amount": 3000000000000, "decimals": 9}},
+                  "lockedY": {{"id": "{rsn}", "amount": 240000, "decimals": 0}}, "tvl": {{"value": 1}}}},
+                {{"id": "b", "lockedX": {{"id": "{erg}", "amount": 5000000000000, "decimals": 9}},
+                  "lockedY": {{"id": "{sigusd}", "amount": 830000, "decimals": 2}}}},
+                {{"id": "c", "lockedX": {{"id": "{erg}"}}, "lockedY": {{"id": "{rsn}"}}}},
+                {{"id": "d"}}
+            ]"#,
+            erg = ERG_TOKEN_ID,
+            rsn = RSN_TOKEN_ID,
+            sigusd = SIGUSD_TOKEN_ID,
+        );
+        assert_eq!(
+            parse_token_pools(RSN_TOKEN_ID, None, body.as_bytes()),
+            Ok(vec![PoolLiquidity {
+                erg_locked: 3_000.0,
+                token_locked: 240_000.0,
+            }])
+        );
+        assert_eq!(
+            parse_token_pools(SIGUSD_TOKEN_ID, None, body.as_bytes()),
+            Ok(vec![PoolLiquidity {
+                erg_locked: 5_000.0,
+                token_locked: 8_300.0,
+            }])
+        );
+        assert!(matches!(
+            parse_token_pools(RSN_TOKEN_ID, None, br#"{"error": "maintenance"}"#),
+            Err(ResponseError::Malformed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_configured_pools() {
+        let body = format!(
+            r#"[
+                {{"id": "a", "lockedX": {{"id": "{erg}", "amount": 3000000000000, "decimals": 9}},
+                  "lockedY": {{"id": "{rsn}", "amount": 240000, "decimals": 0}}}},
+                {{"id": "b", "lockedX": {{"id": "{erg}", "amount": 50000000000, "decimals": 9}},
+                  "lockedY": {{"id": "{rsn}", "amount": 1000, "decimals": 0}}}},
+                {{"id": "c", "lockedX": {{"id": "{erg}", "amount": 2000000000000, "decimals": 9}},
+                  "lockedY": {{"id": "{rsn}", "amount": 160000, "decimals": 0}}}}
+            ]"#,
+            erg = ERG_TOKEN_ID,
+            rsn = RSN_TOKEN_ID,
+        );
+        // the thin pool b is not listed
+        let pool_ids = vec!["a".to_string(), "c".to_string()];
+        assert_eq!(
+            parse_token_pools(RSN_TOKEN_ID, Some(&pool_ids), body.as_bytes()),
+            Ok(vec![
+                PoolLiquidity {
+                    erg_locked: 3_000.0,
+                    token_locked: 240_000.0,
+                },
+                PoolLiquidity {
+                    erg_locked: 2_000.0,
+                    token_locked: 160_000.0,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate_pool_ids() {
+        let pool_id = "1b694b15467c62f0cd4525e368dbdea2329c713aa200b73df4a622e950551b40";
+        let pool_ids = |token_id: &str, ids: Vec<&str>| -> HashMap<String, Vec<String>> {
+            HashMap::from([(
+                token_id.to_string(),
+                ids.into_iter().map(String::from).collect(),
+            )])
+        };
+        assert_eq!(
+            validate_pool_ids(&pool_ids(RSN_TOKEN_ID, vec![pool_id])),
+            Ok(())
+        );
+        assert_eq!(
+            validate_pool_ids(&pool_ids(RSN_TOKEN_ID, vec![])),
+            Err(SpectrumError::NoPoolIds(RSN_TOKEN_ID.to_string()))
+        );
+        assert_eq!(
+            validate_pool_ids(&pool_ids(RSN_TOKEN_ID, vec!["rsn"])),
+            Err(SpectrumError::InvalidPoolId("rsn".to_string()))
+        );
+        assert!(matches!(
+            validate_pool_ids(&pool_ids("rsn", vec![pool_id])),
+            Err(SpectrumError::InvalidTokenId(_))
+        ));
+    }
+
+    #[test]
+    fn test_price_impact_percent() {
+        assert!((price_impact_percent(9_900.0, 100.0) - 1.0).abs() < 1e-9);
+    }
+}
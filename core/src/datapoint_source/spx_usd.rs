@@ -0,0 +1,71 @@
+//! Obtains the S&P 500 index level in USD cents, averaged over the quotes of two market data
+//! providers
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::stooq;
+use super::yahoo;
+use super::DataPointSourceError;
+
+/// One unit of the S&P 500 index, priced at the index level in USD. The index is quoted only
+/// while the US market is open, the sources return the last close outside of the trading hours.
+#[derive(Debug, Clone, Copy)]
+pub struct Spx {}
+
+/// One hundredth of USD
+#[derive(Debug, Clone, Copy)]
+pub struct UsdCent {}
+
+impl Asset for Spx {}
+impl Asset for UsdCent {}
+
+impl UsdCent {
+    /// The index is published with two decimals, rounded so that e.g. 5222.68 isn't truncated to
+    /// 522267 cents by the float error
+    pub fn from_usd(usd: f64) -> f64 {
+        (usd * 100.0).round()
+    }
+}
+
+pub const USDCENT_SPX_SOURCE_NAMES: &[&str] = &["stooq", "yahoo"];
+
+#[allow(clippy::type_complexity)]
+pub fn usdcent_spx_source(
+    name: &str,
+) -> Option<
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Spx, UsdCent>, DataPointSourceError>>>>,
+> {
+    match name {
+        "stooq" => Some(Box::pin(stooq::get_spx_usdcent())),
+        "yahoo" => Some(Box::pin(yahoo::get_spx_usdcent())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::aggregator::fetch_aggregated;
+    use super::*;
+
+    #[test]
+    fn test_spx_usdcent_aggregated() {
+        let sources = USDCENT_SPX_SOURCE_NAMES
+            .iter()
+            .filter_map(|name| usdcent_spx_source(name))
+            .collect();
+        let pair = tokio_test::block_on(fetch_aggregated(sources)).unwrap();
+        let stooq = tokio_test::block_on(stooq::get_spx_usdcent()).unwrap();
+        let deviation = (pair.rate - stooq.rate).abs() / stooq.rate;
+        assert!(deviation < 0.05, "up to 5% deviation is allowed");
+    }
+
+    #[test]
+    fn test_usdcent_from_usd() {
+        assert_eq!(UsdCent::from_usd(5222.68), 522268.0);
+        assert_eq!(UsdCent::from_usd(4999.999), 500000.0);
+    }
+}
@@ -0,0 +1,77 @@
+//! S&P 500 index level from the stooq.com quotes
+
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::response::Quote;
+use super::spx_usd::Spx;
+use super::spx_usd::UsdCent;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct Quotes {
+    symbols: Vec<SymbolQuote>,
+}
+
+#[derive(Deserialize)]
+struct SymbolQuote {
+    /// Last trade, or the last close outside of the trading hours ("N/D" if not quoted)
+    close: Option<Quote>,
+}
+
+#[cfg(not(test))]
+pub async fn get_spx_usdcent() -> Result<AssetsExchangeRate<Spx, UsdCent>, DataPointSourceError> {
+    let url = "https://stooq.com/q/l/?s=^spx&f=sd2t2c&h&e=json";
+    let resp = super::http_client::get("stooq", url).await?;
+    let quotes: Quotes = super::response::parse_body("stooq", resp).await?;
+    let index_level = super::response::price(
+        "stooq",
+        "$.symbols[0].close",
+        quotes.symbols.first().and_then(|q| q.close.as_ref()),
+        super::response::USD_PER_SPX,
+    )?;
+    let rate = AssetsExchangeRate {
+        per1: Spx {},
+        get: UsdCent {},
+        rate: UsdCent::from_usd(index_level),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_spx_usdcent() -> Result<AssetsExchangeRate<Spx, UsdCent>, DataPointSourceError> {
+    let index_level = 5222.68;
+    let rate = AssetsExchangeRate {
+        per1: Spx {},
+        get: UsdCent {},
+        rate: UsdCent::from_usd(index_level),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spx_usdcent_price() {
+        let pair = tokio_test::block_on(get_spx_usdcent()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+
+    #[test]
+    fn test_parse_not_quoted() {
+        let quotes: Quotes = super::super::response::parse(
+            "stooq",
+            br#"{"symbols":[{"symbol":"^SPX","date":"N/D","time":"N/D","close":"N/D"}]}"#,
+        )
+        .unwrap();
+        assert!(super::super::response::price(
+            "stooq",
+            "$.symbols[0].close",
+            quotes.symbols[0].close.as_ref(),
+            super::super::response::USD_PER_SPX,
+        )
+        .is_err());
+    }
+}
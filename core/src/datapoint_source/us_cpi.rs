@@ -0,0 +1,88 @@
+//! Obtains the US consumer price index (CPI-U) multiplied by `us_cpi_scale` of the oracle config,
+//! for macro-data pools
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use crate::oracle_config::ORACLE_CONFIG_OPT;
+
+use super::assets_exchange_rate::Asset;
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::bls;
+use super::DataPointSourceError;
+
+/// The index is published with three decimals
+pub const DEFAULT_US_CPI_SCALE: u64 = 1000;
+
+/// One point of the CPI-U index (1982-84 = 100)
+#[derive(Debug, Clone, Copy)]
+pub struct CpiU {}
+
+/// The `1 / us_cpi_scale` fraction of an index point
+#[derive(Debug, Clone, Copy)]
+pub struct ScaledIndexPoint {}
+
+impl Asset for CpiU {}
+impl Asset for ScaledIndexPoint {}
+
+pub const US_CPI_SOURCE_NAMES: &[&str] = &["bls"];
+
+#[allow(clippy::type_complexity)]
+pub fn us_cpi_source(
+    name: &str,
+) -> Option<
+    Pin<
+        Box<
+            dyn Future<
+                Output = Result<AssetsExchangeRate<CpiU, ScaledIndexPoint>, DataPointSourceError>,
+            >,
+        >,
+    >,
+> {
+    match name {
+        "bls" => Some(Box::pin(get_us_cpi_bls())),
+        _ => None,
+    }
+}
+
+fn scale() -> u64 {
+    ORACLE_CONFIG_OPT
+        .as_ref()
+        .ok()
+        .and_then(|c| c.us_cpi_scale)
+        .unwrap_or(DEFAULT_US_CPI_SCALE)
+}
+
+/// Rounded, so that e.g. 312.332 isn't truncated to 312331 by the float error
+fn scaled(index_level: f64, scale: u64) -> f64 {
+    (index_level * scale as f64).round()
+}
+
+async fn get_us_cpi_bls() -> Result<AssetsExchangeRate<CpiU, ScaledIndexPoint>, DataPointSourceError>
+{
+    Ok(AssetsExchangeRate {
+        per1: CpiU {},
+        get: ScaledIndexPoint {},
+        rate: scaled(bls::get_cpi_u().await?, scale()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled() {
+        assert_eq!(scaled(312.332, DEFAULT_US_CPI_SCALE), 312332.0);
+        assert_eq!(scaled(312.332, 100), 31233.0);
+        assert_eq!(scaled(312.332, 1), 312.0);
+    }
+
+    #[test]
+    fn test_us_cpi_source() {
+        let pair = tokio_test::block_on(us_cpi_source("bls").unwrap()).unwrap();
+        assert!(pair.rate > 0.0);
+        assert!(us_cpi_source("coingecko").is_none());
+    }
+}
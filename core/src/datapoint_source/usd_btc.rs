@@ -0,0 +1,77 @@
+//! Obtains the BTC price in USD cents, for BTC price pools independent of the ERG price
+
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::assets_exchange_rate::Btc;
+use super::assets_exchange_rate::Usd;
+use super::bitfinex;
+use super::bitpanda;
+use super::coincap;
+use super::okx;
+use super::spx_usd::UsdCent;
+use super::DataPointSourceError;
+
+pub const USDCENT_BTC_SOURCE_NAMES: &[&str] = &["bitfinex", "bitpanda", "coincap", "okx"];
+
+#[allow(clippy::type_complexity)]
+pub fn usdcent_btc_source(
+    name: &str,
+) -> Option<
+    Pin<Box<dyn Future<Output = Result<AssetsExchangeRate<Btc, UsdCent>, DataPointSourceError>>>>,
+> {
+    match name {
+        "bitfinex" => Some(Box::pin(get_btc_usdcent_bitfinex())),
+        "bitpanda" => Some(Box::pin(get_btc_usdcent_bitpanda())),
+        "coincap" => Some(Box::pin(get_btc_usdcent_coincap())),
+        "okx" => Some(Box::pin(get_btc_usdcent_okx())),
+        _ => None,
+    }
+}
+
+fn to_usdcent(usd_per_btc: AssetsExchangeRate<Btc, Usd>) -> AssetsExchangeRate<Btc, UsdCent> {
+    AssetsExchangeRate {
+        per1: usd_per_btc.per1,
+        get: UsdCent {},
+        rate: UsdCent::from_usd(usd_per_btc.rate),
+    }
+}
+
+async fn get_btc_usdcent_bitfinex() -> Result<AssetsExchangeRate<Btc, UsdCent>, DataPointSourceError>
+{
+    Ok(to_usdcent(bitfinex::get_btc_usd().await?))
+}
+
+async fn get_btc_usdcent_bitpanda() -> Result<AssetsExchangeRate<Btc, UsdCent>, DataPointSourceError>
+{
+    Ok(to_usdcent(bitpanda::get_btc_usd().await?))
+}
+
+async fn get_btc_usdcent_coincap() -> Result<AssetsExchangeRate<Btc, UsdCent>, DataPointSourceError>
+{
+    Ok(to_usdcent(coincap::get_btc_usd().await?))
+}
+
+async fn get_btc_usdcent_okx() -> Result<AssetsExchangeRate<Btc, UsdCent>, DataPointSourceError> {
+    Ok(to_usdcent(okx::get_btc_usd().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::aggregator::fetch_aggregated;
+    use super::*;
+
+    #[test]
+    fn test_btc_usdcent_aggregated() {
+        let sources = USDCENT_BTC_SOURCE_NAMES
+            .iter()
+            .filter_map(|name| usdcent_btc_source(name))
+            .collect();
+        let pair = tokio_test::block_on(fetch_aggregated(sources)).unwrap();
+        let coincap = tokio_test::block_on(coincap::get_btc_usd()).unwrap();
+        let deviation = (pair.rate / 100.0 - coincap.rate).abs() / coincap.rate;
+        assert!(deviation < 0.05, "up to 5% deviation is allowed");
+    }
+}
@@ -0,0 +1,76 @@
+//! S&P 500 index level from the Yahoo Finance chart API
+
+use serde::Deserialize;
+
+use super::assets_exchange_rate::AssetsExchangeRate;
+use super::response::Quote;
+use super::spx_usd::Spx;
+use super::spx_usd::UsdCent;
+use super::DataPointSourceError;
+
+#[derive(Deserialize)]
+struct ChartResponse {
+    chart: Chart,
+}
+
+#[derive(Deserialize)]
+struct Chart {
+    #[serde(default)]
+    result: Vec<ChartResult>,
+}
+
+#[derive(Deserialize)]
+struct ChartResult {
+    meta: ChartMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartMeta {
+    regular_market_price: Option<Quote>,
+}
+
+#[cfg(not(test))]
+pub async fn get_spx_usdcent() -> Result<AssetsExchangeRate<Spx, UsdCent>, DataPointSourceError> {
+    let url = "https://query1.finance.yahoo.com/v8/finance/chart/%5EGSPC?interval=1d&range=1d";
+    let resp = super::http_client::get("yahoo", url).await?;
+    let chart: ChartResponse = super::response::parse_body("yahoo", resp).await?;
+    let index_level = super::response::price(
+        "yahoo",
+        "$.chart.result[0].meta.regularMarketPrice",
+        chart
+            .chart
+            .result
+            .first()
+            .and_then(|r| r.meta.regular_market_price.as_ref()),
+        super::response::USD_PER_SPX,
+    )?;
+    let rate = AssetsExchangeRate {
+        per1: Spx {},
+        get: UsdCent {},
+        rate: UsdCent::from_usd(index_level),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+pub async fn get_spx_usdcent() -> Result<AssetsExchangeRate<Spx, UsdCent>, DataPointSourceError> {
+    let index_level = 5221.42;
+    let rate = AssetsExchangeRate {
+        per1: Spx {},
+        get: UsdCent {},
+        rate: UsdCent::from_usd(index_level),
+    };
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spx_usdcent_price() {
+        let pair = tokio_test::block_on(get_spx_usdcent()).unwrap();
+        assert!(pair.rate > 0.0);
+    }
+}
@@ -0,0 +1,406 @@
+//! Archive of the pool epochs (one record per pool box), kept in the data dir.
+//!
+//! New epochs are recorded by the main loop. On the first run the history before that is
+//! reconstructed from the explorer by a backfill job, which pages through all the boxes ever
+//! holding the pool NFT. The job is rate limited to stay within the explorer limits, and its
+//! progress is saved with the archive so that an interrupted backfill resumes where it stopped.
+//!
+//! The datapoints posted by this oracle are archived along the epochs (from the oracle's local
+//! datapoint box, so there is no backfill for them), to chart them against the pool rate.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::box_kind::PoolBox;
+use crate::box_kind::PostedOracleBox;
+use crate::explorer_api::ExplorerApi;
+use crate::explorer_api::ExplorerApiError;
+use crate::format_version::Format;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+use crate::oracle_types::Rate;
+use crate::state_store::StateStore;
+
+/// Boxes requested from the explorer per page
+const BACKFILL_PAGE_SIZE: u64 = 100;
+/// Delay between the explorer requests
+const BACKFILL_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+/// Delay before retrying a request rejected with 429 Too Many Requests
+const BACKFILL_RATE_LIMITED_DELAY: Duration = Duration::from_secs(30);
+/// Delay before retrying a failed request
+const BACKFILL_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+pub const EPOCH_ARCHIVE_FORMAT: Format = Format {
+    name: "epoch archive",
+    migrations: &[],
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochRecord {
+    pub epoch_id: EpochCounter,
+    pub rate: Rate,
+    /// Creation height of the pool box
+    pub height: BlockHeight,
+    pub box_id: String,
+    pub tx_id: String,
+    /// Reward tokens in the pool box, not known for the records archived by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward_tokens: Option<u64>,
+}
+
+/// Datapoint posted by this oracle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatapointRecord {
+    /// Epoch the datapoint was posted for
+    pub epoch_id: EpochCounter,
+    pub rate: Rate,
+    /// Creation height of the oracle box
+    pub height: BlockHeight,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfillProgress {
+    /// Explorer boxes already processed
+    pub offset: u64,
+    pub complete: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EpochArchive {
+    /// Records by epoch id
+    epochs: BTreeMap<u32, EpochRecord>,
+    /// Our posted datapoints by epoch id
+    #[serde(default)]
+    datapoints: BTreeMap<u32, DatapointRecord>,
+    #[serde(default)]
+    pub backfill: BackfillProgress,
+    /// Incremented on every change, used as the ETag of the epoch history served by the REST API
+    #[serde(default)]
+    version: u64,
+}
+
+impl EpochArchive {
+    /// Adds the record, returns `false` if the epoch is already recorded
+    pub fn insert(&mut self, record: EpochRecord) -> bool {
+        if self.epochs.contains_key(&record.epoch_id.0) {
+            return false;
+        }
+        self.epochs.insert(record.epoch_id.0, record);
+        self.version += 1;
+        true
+    }
+
+    /// Adds our datapoint, returns `false` if one is already recorded for the epoch
+    pub fn insert_datapoint(&mut self, record: DatapointRecord) -> bool {
+        if self.datapoints.contains_key(&record.epoch_id.0) {
+            return false;
+        }
+        self.datapoints.insert(record.epoch_id.0, record);
+        self.version += 1;
+        true
+    }
+
+    pub fn set_backfill(&mut self, progress: BackfillProgress) {
+        if self.backfill != progress {
+            self.backfill = progress;
+            self.version += 1;
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn epochs_count(&self) -> usize {
+        self.epochs.len()
+    }
+
+    /// Records starting from the epoch, in the epoch order
+    pub fn records_from(&self, from_epoch_id: EpochCounter, limit: usize) -> Vec<EpochRecord> {
+        self.epochs
+            .range(from_epoch_id.0..)
+            .take(limit)
+            .map(|(_, record)| record.clone())
+            .collect()
+    }
+
+    /// Records of the pool boxes created within the heights (inclusive), in the epoch order
+    pub fn records_between(&self, from: BlockHeight, to: BlockHeight) -> Vec<EpochRecord> {
+        self.epochs
+            .values()
+            .filter(|record| record.height >= from && record.height <= to)
+            .cloned()
+            .collect()
+    }
+
+    /// Our datapoints posted within the heights (inclusive), in the epoch order
+    pub fn datapoints_between(&self, from: BlockHeight, to: BlockHeight) -> Vec<DatapointRecord> {
+        self.datapoints
+            .values()
+            .filter(|record| record.height >= from && record.height <= to)
+            .cloned()
+            .collect()
+    }
+
+    /// Whether we posted a datapoint for the epoch
+    pub fn has_datapoint(&self, epoch_id: EpochCounter) -> bool {
+        self.datapoints.contains_key(&epoch_id.0)
+    }
+
+    /// Number of epochs and reward tokens paid out of the pool box between the consecutive
+    /// records with a known reward token amount. Top-ups of the pool box are not payouts.
+    fn reward_payouts(&self) -> Vec<(u32, u64)> {
+        let amounts: Vec<(u32, u64)> = self
+            .epochs
+            .iter()
+            .filter_map(|(epoch_id, record)| record.reward_tokens.map(|a| (*epoch_id, a)))
+            .collect();
+        amounts
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0, w[0].1.saturating_sub(w[1].1)))
+            .collect()
+    }
+
+    /// Reward tokens distributed to the oracles over the archived epochs
+    pub fn distributed_reward_tokens(&self) -> u64 {
+        self.reward_payouts().iter().map(|(_, paid)| paid).sum()
+    }
+
+    /// Average reward tokens distributed per epoch over the last `max_payouts` payouts, `None`
+    /// if nothing is archived yet
+    pub fn avg_reward_tokens_per_epoch(&self, max_payouts: usize) -> Option<f64> {
+        let payouts = self.reward_payouts();
+        let last = &payouts[payouts.len().saturating_sub(max_payouts)..];
+        let epochs: u64 = last.iter().map(|(epochs, _)| *epochs as u64).sum();
+        let paid: u64 = last.iter().map(|(_, paid)| paid).sum();
+        if epochs == 0 {
+            None
+        } else {
+            Some(paid as f64 / epochs as f64)
+        }
+    }
+}
+
+pub fn epoch_record(pool_box: &dyn PoolBox) -> EpochRecord {
+    let ergo_box = pool_box.get_box();
+    EpochRecord {
+        epoch_id: pool_box.epoch_counter(),
+        rate: pool_box.rate(),
+        height: BlockHeight(ergo_box.creation_height),
+        box_id: String::from(ergo_box.box_id()),
+        tx_id: ergo_box.transaction_id.to_string(),
+        reward_tokens: Some(*pool_box.reward_token().amount.as_u64()),
+    }
+}
+
+/// Adds the current pool box epoch to the archive and saves it if it is a new one
+pub fn record_epoch(
+    archive: &RwLock<EpochArchive>,
+    state_store: &dyn StateStore,
+    pool_box: &dyn PoolBox,
+) {
+    let mut archive = archive.write().unwrap();
+    if archive.insert(epoch_record(pool_box)) {
+        if let Err(e) = state_store.save_epoch_archive(&archive) {
+            log::warn!("Failed to save the epoch archive: {:?}", e);
+        }
+    }
+}
+
+/// Adds our posted datapoint to the archive and saves it if it is a new one
+pub fn record_datapoint(
+    archive: &RwLock<EpochArchive>,
+    state_store: &dyn StateStore,
+    oracle_box: &PostedOracleBox,
+) {
+    let record = DatapointRecord {
+        epoch_id: oracle_box.epoch_counter(),
+        rate: oracle_box.rate(),
+        height: BlockHeight(oracle_box.get_box().creation_height),
+    };
+    let mut archive = archive.write().unwrap();
+    if archive.insert_datapoint(record) {
+        if let Err(e) = state_store.save_epoch_archive(&archive) {
+            log::warn!("Failed to save the epoch archive: {:?}", e);
+        }
+    }
+}
+
+/// Parses a pool box returned by the explorer v1 API, `None` if it's not a pool box
+fn epoch_record_from_explorer_box(explorer_box: &serde_json::Value) -> Option<EpochRecord> {
+    let registers = &explorer_box["additionalRegisters"];
+    let rate = registers["R4"]["renderedValue"]
+        .as_str()?
+        .parse::<i64>()
+        .ok()?;
+    let epoch_id = registers["R5"]["renderedValue"]
+        .as_str()?
+        .parse::<u32>()
+        .ok()?;
+    Some(EpochRecord {
+        epoch_id: EpochCounter(epoch_id),
+        rate: Rate::from(rate),
+        height: BlockHeight(explorer_box["creationHeight"].as_u64()? as u32),
+        box_id: explorer_box["boxId"].as_str()?.to_string(),
+        tx_id: explorer_box["transactionId"].as_str()?.to_string(),
+        reward_tokens: explorer_box["assets"][1]["amount"].as_u64(),
+    })
+}
+
+/// Requests the page, waiting and retrying while the explorer is rate limiting or unavailable
+fn fetch_page(
+    explorer_api: &ExplorerApi,
+    pool_nft_token_id: TokenId,
+    offset: u64,
+) -> serde_json::Value {
+    loop {
+        match explorer_api.get_boxes_by_token_id(pool_nft_token_id, offset, BACKFILL_PAGE_SIZE) {
+            Ok(page) => return page,
+            Err(ExplorerApiError::RequestError(e))
+                if e.status() == Some(StatusCode::TOO_MANY_REQUESTS) =>
+            {
+                log::debug!("Epoch archive backfill is rate limited by the explorer, waiting");
+                std::thread::sleep(BACKFILL_RATE_LIMITED_DELAY);
+            }
+            Err(e) => {
+                log::warn!(
+                    "Epoch archive backfill request at offset {} failed, retrying: {:?}",
+                    offset,
+                    e
+                );
+                std::thread::sleep(BACKFILL_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+/// Reconstructs the archive from all the pool boxes known to the explorer, resuming from the
+/// saved progress. Meant to be run on a separate thread.
+pub fn backfill(
+    archive: Arc<RwLock<EpochArchive>>,
+    state_store: Arc<dyn StateStore>,
+    explorer_api: ExplorerApi,
+    pool_nft_token_id: TokenId,
+) {
+    let mut offset = archive.read().unwrap().backfill.offset;
+    log::info!("Epoch archive backfill started from offset {}", offset);
+    loop {
+        let page = fetch_page(&explorer_api, pool_nft_token_id, offset);
+        let items = page["items"].as_array().cloned().unwrap_or_default();
+        let total = page["total"].as_u64().unwrap_or(0);
+        offset += items.len() as u64;
+        let complete = items.is_empty() || offset >= total;
+        let mut archive = archive.write().unwrap();
+        for record in items.iter().filter_map(epoch_record_from_explorer_box) {
+            archive.insert(record);
+        }
+        archive.set_backfill(BackfillProgress { offset, complete });
+        if let Err(e) = state_store.save_epoch_archive(&archive) {
+            log::warn!("Failed to save the epoch archive: {:?}", e);
+        }
+        if complete {
+            log::info!(
+                "Epoch archive backfill is complete, {} epochs archived",
+                archive.epochs_count()
+            );
+            return;
+        }
+        drop(archive);
+        std::thread::sleep(BACKFILL_REQUEST_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(epoch_id: u32) -> EpochRecord {
+        EpochRecord {
+            epoch_id: EpochCounter(epoch_id),
+            rate: Rate::from(1000 + epoch_id as i64),
+            height: BlockHeight(epoch_id * 30),
+            box_id: format!("box{}", epoch_id),
+            tx_id: format!("tx{}", epoch_id),
+            reward_tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_epoch_record_from_explorer_box() {
+        let explorer_box = json!({
+            "boxId": "a8a5ec8a9d2e4e0d3a8c27c6d2d31c5c3d2c0d6e9e2f7a1b3c4d5e6f7a8b9c0d",
+            "transactionId": "1c5c3d2c0d6e9e2f7a1b3c4d5e6f7a8b9c0da8a5ec8a9d2e4e0d3a8c27c6d2d3",
+            "creationHeight": 1000000,
+            "assets": [
+                { "tokenId": "011d3364de07e5a26f0c4eef0852cddb387039a921b7154ef3cab22c6eda887f", "amount": 1 },
+                { "tokenId": "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1", "amount": 99000 }
+            ],
+            "additionalRegisters": {
+                "R4": { "serializedValue": "05a8c0cb01", "sigmaType": "SLong", "renderedValue": "1668116" },
+                "R5": { "serializedValue": "04e807", "sigmaType": "SInt", "renderedValue": "500" }
+            }
+        });
+        let record = epoch_record_from_explorer_box(&explorer_box).unwrap();
+        assert_eq!(record.epoch_id, EpochCounter(500));
+        assert_eq!(record.rate, 1668116);
+        assert_eq!(record.height, BlockHeight(1000000));
+        assert_eq!(record.reward_tokens, Some(99000));
+        // not a pool box
+        assert!(epoch_record_from_explorer_box(&json!({ "boxId": "b" })).is_none());
+    }
+
+    #[test]
+    fn test_archive_insert_and_range() {
+        let mut archive = EpochArchive::default();
+        for epoch_id in [3, 1, 2, 5] {
+            assert!(archive.insert(record(epoch_id)));
+        }
+        // already recorded by the main loop
+        assert!(!archive.insert(record(2)));
+        assert_eq!(archive.epochs_count(), 4);
+        assert_eq!(archive.version(), 4);
+        archive.set_backfill(BackfillProgress {
+            offset: 4,
+            complete: true,
+        });
+        assert_eq!(archive.version(), 5);
+        let epoch_ids: Vec<u32> = archive
+            .records_from(EpochCounter(2), 2)
+            .iter()
+            .map(|r| r.epoch_id.0)
+            .collect();
+        assert_eq!(epoch_ids, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_distributed_reward_tokens() {
+        let mut archive = EpochArchive::default();
+        assert_eq!(archive.avg_reward_tokens_per_epoch(10), None);
+        // epoch 4 is missing, epoch 6 is a top-up of the pool box
+        for (epoch_id, reward_tokens) in [
+            (1, None),
+            (2, Some(1000)),
+            (3, Some(990)),
+            (5, Some(970)),
+            (6, Some(2000)),
+            (7, Some(1990)),
+        ] {
+            archive.insert(EpochRecord {
+                reward_tokens,
+                ..record(epoch_id)
+            });
+        }
+        assert_eq!(archive.distributed_reward_tokens(), 40);
+        assert_eq!(archive.avg_reward_tokens_per_epoch(10), Some(8.0));
+        assert_eq!(archive.avg_reward_tokens_per_epoch(1), Some(10.0));
+    }
+}
@@ -0,0 +1,101 @@
+//! ErgoPay (EIP-20) deep links for the manual transactions (`--ergopay` of the oracle token
+//! transfer and the ballot vote), to sign them with a mobile wallet holding the key instead of
+//! the node wallet. The static `ergopay:` link carries the reduced transaction, so the wallet
+//! needs no connection to the oracle.
+
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::reduced::reduce_tx;
+use ergo_lib::chain::transaction::reduced::ReducedTransaction;
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializationError;
+use ergo_lib::wallet::signing::TransactionContext;
+use ergo_lib::wallet::signing::TxSigningError;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ErgoPayError {
+    #[error("input box {0} of the transaction is not known")]
+    UnknownInputBox(String),
+    #[error("failed to reduce the transaction: {0}")]
+    Reduce(#[from] TxSigningError),
+    #[error("failed to serialize the reduced transaction: {0}")]
+    Serialization(#[from] SigmaSerializationError),
+    #[error("failed to encode the QR code: {0}")]
+    QrCode(#[from] qrcode::types::QrError),
+}
+
+/// Reduces the transaction spending some of the boxes, ready to be signed by the wallet
+pub fn reduce_transaction(
+    tx: &UnsignedTransaction,
+    available_boxes: &[ErgoBox],
+    state_context: &ErgoStateContext,
+) -> Result<ReducedTransaction, ErgoPayError> {
+    let input_boxes = tx
+        .inputs
+        .iter()
+        .map(|input| {
+            available_boxes
+                .iter()
+                .find(|b| b.box_id() == input.box_id)
+                .cloned()
+                .ok_or_else(|| ErgoPayError::UnknownInputBox(String::from(input.box_id)))
+        })
+        .collect::<Result<Vec<ErgoBox>, ErgoPayError>>()?;
+    let tx_context = TransactionContext::new(tx.clone(), input_boxes, Vec::new())?;
+    Ok(reduce_tx(tx_context, state_context)?)
+}
+
+/// Static ErgoPay link with the base64url-encoded reduced transaction
+pub fn ergopay_link(reduced_tx: &ReducedTransaction) -> Result<String, ErgoPayError> {
+    let bytes = reduced_tx.sigma_serialize_bytes()?;
+    Ok(format!(
+        "ergopay:{}",
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    ))
+}
+
+/// QR code of the link drawn with the unicode block characters
+pub fn qr_code(link: &str) -> Result<String, ErgoPayError> {
+    let code = QrCode::new(link.as_bytes())?;
+    // light modules on a dark terminal background, with the quiet zone around the code
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
+
+/// Prints the ErgoPay link and its QR code for the transaction
+pub fn print_ergopay_request(
+    tx: &UnsignedTransaction,
+    available_boxes: &[ErgoBox],
+    state_context: &ErgoStateContext,
+) -> Result<(), ErgoPayError> {
+    let reduced_tx = reduce_transaction(tx, available_boxes, state_context)?;
+    let link = ergopay_link(&reduced_tx)?;
+    println!("Scan the QR code or open the link with an ErgoPay wallet to sign the transaction:");
+    println!("{}", qr_code(&link)?);
+    println!("{}", link);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qr_code() {
+        let link = format!("ergopay:{}", "A".repeat(600));
+        let code = qr_code(&link).unwrap();
+        assert!(code.lines().count() > 20);
+        // too long for a QR code
+        assert!(matches!(
+            qr_code(&"A".repeat(8000)),
+            Err(ErgoPayError::QrCode(_))
+        ));
+    }
+}
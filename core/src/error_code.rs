@@ -0,0 +1,163 @@
+//! Stable codes (e.g. `ORC-0101`) of the operator-facing failures, printed in the logs and
+//! returned in the API error responses, so that guides and the frontend can map them to the
+//! remediation steps regardless of the message wording.
+//! Codes are never renumbered or reused for another failure.
+
+use std::error::Error;
+use std::fmt;
+
+use ergo_lib::wallet::box_selector::BoxSelectorError;
+use ergo_node_interface::node_interface::NodeError;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::actions::ActionExecError;
+use crate::datapoint_source::DataPointSourceError;
+use crate::oracle_state::DataSourceError;
+use crate::pool_commands::refresh::RefreshActionError;
+use crate::pool_commands::PoolCommandError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    Unknown = 0,
+    NodeRequestFailed = 1,
+    NodeWalletLocked = 2,
+    PoolBoxNotFound = 101,
+    RefreshBoxNotFound = 102,
+    PoolBoxNotConfirmed = 103,
+    PoolContractUnknown = 104,
+    ContractParamsMismatch = 105,
+    DatapointSourceFailed = 201,
+    NotEnoughDatapoints = 202,
+    InsufficientWalletFunds = 301,
+    TxRejected = 302,
+}
+
+impl ErrorCode {
+    pub fn code(&self) -> String {
+        format!("ORC-{:04}", *self as u16)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::Unknown => "unclassified error",
+            ErrorCode::NodeRequestFailed => "request to the node failed",
+            ErrorCode::NodeWalletLocked => "node wallet is locked",
+            ErrorCode::PoolBoxNotFound => "pool box not found",
+            ErrorCode::RefreshBoxNotFound => "refresh box not found",
+            ErrorCode::PoolBoxNotConfirmed => "pool box is not confirmed yet",
+            ErrorCode::PoolContractUnknown => "pool box is guarded by an unknown contract",
+            ErrorCode::ContractParamsMismatch => {
+                "other oracles operate under different contract parameters"
+            }
+            ErrorCode::DatapointSourceFailed => "failed to get the datapoint from the sources",
+            ErrorCode::NotEnoughDatapoints => "not enough datapoints for the refresh",
+            ErrorCode::InsufficientWalletFunds => "wallet boxes can't cover the transaction",
+            ErrorCode::TxRejected => "transaction was rejected by the node",
+        }
+    }
+}
+
+/// Prints the description, the code is printed separately (see `error_code`)
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl Error for ErrorCode {}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.code())
+    }
+}
+
+fn classify(err: &(dyn Error + 'static)) -> Option<ErrorCode> {
+    if let Some(code) = err.downcast_ref::<ErrorCode>() {
+        return Some(*code);
+    }
+    if err.downcast_ref::<ActionExecError>().is_some() {
+        return Some(ErrorCode::TxRejected);
+    }
+    if let Some(RefreshActionError::FailedToReachConsensus { .. }) =
+        err.downcast_ref::<RefreshActionError>()
+    {
+        return Some(ErrorCode::NotEnoughDatapoints);
+    }
+    if let Some(PoolCommandError::PoolBoxNotConfirmed(_)) = err.downcast_ref::<PoolCommandError>() {
+        return Some(ErrorCode::PoolBoxNotConfirmed);
+    }
+    if let Some(DataSourceError::PoolBoxNotFoundError) = err.downcast_ref::<DataSourceError>() {
+        return Some(ErrorCode::PoolBoxNotFound);
+    }
+    if let Some(DataSourceError::RefreshBoxNotFoundError) = err.downcast_ref::<DataSourceError>() {
+        return Some(ErrorCode::RefreshBoxNotFound);
+    }
+    if err.downcast_ref::<DataPointSourceError>().is_some() {
+        return Some(ErrorCode::DatapointSourceFailed);
+    }
+    if err.downcast_ref::<BoxSelectorError>().is_some() {
+        return Some(ErrorCode::InsufficientWalletFunds);
+    }
+    if err.downcast_ref::<NodeError>().is_some() {
+        return Some(ErrorCode::NodeRequestFailed);
+    }
+    None
+}
+
+/// Code of the first classified error in the chain, from the outermost one
+pub fn error_code_of(err: &(dyn Error + 'static)) -> ErrorCode {
+    let mut next = Some(err);
+    while let Some(e) = next {
+        if let Some(code) = classify(e) {
+            return code;
+        }
+        next = e.source();
+    }
+    ErrorCode::Unknown
+}
+
+/// Code attached with `.context(ErrorCode::..)`, otherwise the code of the first classified error
+/// in the chain
+pub fn error_code(err: &anyhow::Error) -> ErrorCode {
+    err.downcast_ref::<ErrorCode>()
+        .copied()
+        .or_else(|| err.chain().find_map(classify))
+        .unwrap_or(ErrorCode::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code() {
+        assert_eq!(ErrorCode::NodeWalletLocked.code(), "ORC-0002");
+        assert_eq!(ErrorCode::InsufficientWalletFunds.code(), "ORC-0301");
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::PoolBoxNotFound).unwrap(),
+            "\"ORC-0101\""
+        );
+
+        let err: anyhow::Error =
+            PoolCommandError::DataSourceError(DataSourceError::RefreshBoxNotFoundError).into();
+        assert_eq!(error_code(&err), ErrorCode::RefreshBoxNotFound);
+        let err = err.context("building the action");
+        assert_eq!(error_code(&err), ErrorCode::RefreshBoxNotFound);
+        let err = err.context(ErrorCode::TxRejected).context("main loop");
+        assert_eq!(error_code(&err), ErrorCode::TxRejected);
+        assert_eq!(
+            error_code(&anyhow::anyhow!("something else")),
+            ErrorCode::Unknown
+        );
+        assert_eq!(
+            error_code_of(&DataSourceError::PoolBoxNotFoundError),
+            ErrorCode::PoolBoxNotFound
+        );
+        assert_eq!(
+            error_code(&ErrorCode::NodeWalletLocked.into()),
+            ErrorCode::NodeWalletLocked
+        );
+    }
+}
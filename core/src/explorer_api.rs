@@ -3,10 +3,13 @@ use std::time::Duration;
 use ergo_lib::chain::transaction::Transaction;
 use ergo_lib::chain::transaction::TxId;
 use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
 use reqwest::blocking::RequestBuilder;
 use reqwest::blocking::Response;
 use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
 use reqwest::Url;
+use serde::Deserialize;
 use thiserror::Error;
 use url::ParseError;
 
@@ -27,6 +30,12 @@ pub enum ExplorerApiError {
     InvalidExplorerUrl(#[from] ParseError),
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerToken {
+    emission_amount: u64,
+}
+
 pub struct ExplorerApi {
     pub url: url::Url,
 }
@@ -64,6 +73,44 @@ impl ExplorerApi {
         log::debug!("get_transaction_v1 response: {}", text);
         Ok(serde_json::from_str(&text)?)
     }
+
+    /// GET /api/v1/tokens/{id}, returns `false` if the token is not known to the explorer
+    pub fn token_exists(&self, token_id: TokenId) -> Result<bool, ExplorerApiError> {
+        let endpoint = "/api/v1/tokens/".to_owned() + &String::from(token_id);
+        match self.send_get_req(&endpoint) {
+            Ok(_) => Ok(true),
+            Err(ExplorerApiError::RequestError(e)) if e.status() == Some(StatusCode::NOT_FOUND) => {
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// GET /api/v1/tokens/{id}, the amount of the token minted
+    pub fn get_token_emission_amount(&self, token_id: TokenId) -> Result<u64, ExplorerApiError> {
+        let endpoint = "/api/v1/tokens/".to_owned() + &String::from(token_id);
+        let text = self.send_get_req(&endpoint)?.text()?;
+        let token: ExplorerToken = serde_json::from_str(&text)?;
+        Ok(token.emission_amount)
+    }
+
+    /// GET /api/v1/boxes/byTokenId/{id}, a page of all the boxes (spent and unspent) holding the
+    /// token, oldest first. The `items` array holds the boxes and `total` is the number of boxes.
+    pub fn get_boxes_by_token_id(
+        &self,
+        token_id: TokenId,
+        offset: u64,
+        limit: u64,
+    ) -> Result<serde_json::Value, ExplorerApiError> {
+        let endpoint = format!(
+            "/api/v1/boxes/byTokenId/{}?offset={}&limit={}",
+            String::from(token_id),
+            offset,
+            limit
+        );
+        let text = self.send_get_req(&endpoint)?.text()?;
+        Ok(serde_json::from_str(&text)?)
+    }
 }
 
 pub(crate) fn ergo_explorer_transaction_link(tx_id: TxId, prefix: NetworkPrefix) -> String {
@@ -84,7 +131,7 @@ pub fn wait_for_tx_confirmation(tx_id: TxId) {
 }
 
 pub fn wait_for_txs_confirmation(tx_ids: Vec<TxId>) {
-    let network = ORACLE_CONFIG.oracle_address.network();
+    let network = ORACLE_CONFIG.network_prefix();
     let timeout = Duration::from_secs(1200);
     let explorer_url = ORACLE_CONFIG
         .explorer_url
@@ -0,0 +1,251 @@
+//! Ledger of the fees paid by our transactions, kept in the data dir so that the totals survive
+//! restarts. It's used by the `cost_capped` participation strategy and served by the REST API
+//! (`/feeHistory`) per day and per epoch, to compare the costs against the reward income. The fee
+//! of a tx is read from its miner fee output, so it's exact even if the fee was changed.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBoxCandidate;
+use ergo_lib::wallet::miner_fee::MINERS_FEE_ADDRESS;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::format_version::Format;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+use crate::scans::SCANS_DIR_PATH;
+use crate::state::BLOCKS_PER_DAY;
+
+const LEDGER_FILE_NAME: &str = "fee_ledger.json";
+
+pub const FEE_LEDGER_FORMAT: Format = Format {
+    name: "fee ledger",
+    migrations: &[],
+};
+
+/// Records older than this (90 days) are dropped
+const RETENTION_BLOCKS: u32 = BLOCKS_PER_DAY * 90;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeRecord {
+    /// Height the tx was submitted at
+    pub height: BlockHeight,
+    /// Pool epoch the tx was submitted in, `None` if the pool was not running
+    pub epoch_id: Option<EpochCounter>,
+    pub tx_id: String,
+    /// Fee in nanoERG
+    pub fee: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyFees {
+    /// First height of the day (`BLOCKS_PER_DAY` blocks)
+    pub day_start_height: BlockHeight,
+    pub txs: usize,
+    pub fees: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EpochFees {
+    pub epoch_id: EpochCounter,
+    pub txs: usize,
+    pub fees: u64,
+}
+
+/// Fee paid by the tx with the given outputs, the value of its miner fee outputs
+pub fn tx_fee<'a>(output_candidates: impl IntoIterator<Item = &'a ErgoBoxCandidate>) -> u64 {
+    let fee_tree = MINERS_FEE_ADDRESS.script().ok();
+    output_candidates
+        .into_iter()
+        .filter(|output| fee_tree.as_ref() == Some(&output.ergo_tree))
+        .map(|output| *output.value.as_u64())
+        .sum()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FeeLedger {
+    records: Vec<FeeRecord>,
+}
+
+impl FeeLedger {
+    pub fn file_path() -> PathBuf {
+        SCANS_DIR_PATH.get().unwrap().join(LEDGER_FILE_NAME)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json_str = std::fs::read_to_string(path)?;
+        Ok(FEE_LEDGER_FORMAT.from_json_str(&json_str)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        std::fs::write(path, FEE_LEDGER_FORMAT.to_json_string(self)?)?;
+        Ok(())
+    }
+
+    /// Rewrites the ledger saved by an older oracle-core in the current format, returns whether
+    /// it was migrated
+    pub fn migrate(path: &Path) -> Result<bool, anyhow::Error> {
+        if !path.exists() {
+            return Ok(false);
+        }
+        match FEE_LEDGER_FORMAT.upgrade_json_str(&std::fs::read_to_string(path)?)? {
+            Some(upgraded) => {
+                std::fs::write(path, upgraded)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Adds the fee of the submitted tx, dropping the records past the retention period
+    pub fn record(&mut self, record: FeeRecord) {
+        let oldest_kept = record.height.0.saturating_sub(RETENTION_BLOCKS);
+        self.records.retain(|r| r.height.0 >= oldest_kept);
+        self.records.push(record);
+    }
+
+    /// Total fees spent at or after the given height
+    pub fn fees_spent_since(&self, height: BlockHeight) -> u64 {
+        self.records
+            .iter()
+            .filter(|r| r.height >= height)
+            .map(|r| r.fee)
+            .sum()
+    }
+
+    pub fn total_fees(&self) -> u64 {
+        self.records.iter().map(|r| r.fee).sum()
+    }
+
+    /// Fees summed per day, oldest first
+    pub fn daily(&self) -> Vec<DailyFees> {
+        let mut days: BTreeMap<u32, DailyFees> = BTreeMap::new();
+        for r in &self.records {
+            let day_start = r.height.0 / BLOCKS_PER_DAY * BLOCKS_PER_DAY;
+            let day = days.entry(day_start).or_insert(DailyFees {
+                day_start_height: BlockHeight(day_start),
+                txs: 0,
+                fees: 0,
+            });
+            day.txs += 1;
+            day.fees += r.fee;
+        }
+        days.into_values().collect()
+    }
+
+    /// Fees summed per pool epoch, oldest first. The txs submitted outside of an epoch are left
+    /// out.
+    pub fn per_epoch(&self) -> Vec<EpochFees> {
+        let mut epochs: BTreeMap<EpochCounter, EpochFees> = BTreeMap::new();
+        for r in &self.records {
+            if let Some(epoch_id) = r.epoch_id {
+                let epoch = epochs.entry(epoch_id).or_insert(EpochFees {
+                    epoch_id,
+                    txs: 0,
+                    fees: 0,
+                });
+                epoch.txs += 1;
+                epoch.fees += r.fee;
+            }
+        }
+        epochs.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+
+    fn record(height: u32, epoch_id: Option<u32>, fee: u64) -> FeeRecord {
+        FeeRecord {
+            height: BlockHeight(height),
+            epoch_id: epoch_id.map(EpochCounter),
+            tx_id: format!("tx{}", height),
+            fee,
+        }
+    }
+
+    #[test]
+    fn test_daily_and_per_epoch() {
+        let mut ledger = FeeLedger::default();
+        ledger.record(record(1000, Some(3), 1_100_000));
+        ledger.record(record(1010, Some(3), 1_100_000));
+        ledger.record(record(1450, None, 2_000_000));
+        ledger.record(record(1500, Some(4), 1_100_000));
+        assert_eq!(ledger.total_fees(), 5_300_000);
+        assert_eq!(ledger.fees_spent_since(BlockHeight(1440)), 3_100_000);
+        assert_eq!(
+            ledger.daily(),
+            vec![
+                DailyFees {
+                    day_start_height: BlockHeight(720),
+                    txs: 2,
+                    fees: 2_200_000
+                },
+                DailyFees {
+                    day_start_height: BlockHeight(1440),
+                    txs: 2,
+                    fees: 3_100_000
+                },
+            ]
+        );
+        assert_eq!(
+            ledger.per_epoch(),
+            vec![
+                EpochFees {
+                    epoch_id: EpochCounter(3),
+                    txs: 2,
+                    fees: 2_200_000
+                },
+                EpochFees {
+                    epoch_id: EpochCounter(4),
+                    txs: 1,
+                    fees: 1_100_000
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_drops_old() {
+        let mut ledger = FeeLedger::default();
+        ledger.record(record(100, Some(1), 1_100_000));
+        ledger.record(record(100 + RETENTION_BLOCKS + 1, Some(2), 1_100_000));
+        assert_eq!(ledger.total_fees(), 1_100_000);
+    }
+
+    #[test]
+    fn test_tx_fee() {
+        let mut outputs = vec![
+            force_any_val::<ErgoBoxCandidate>(),
+            force_any_val::<ErgoBoxCandidate>(),
+        ];
+        assert_eq!(tx_fee(&outputs), 0);
+        // e.g. a pool with a raised fee
+        outputs[1].ergo_tree = MINERS_FEE_ADDRESS.script().unwrap();
+        outputs[1].value = BoxValue::new(2_000_000).unwrap();
+        assert_eq!(tx_fee(&outputs), 2_000_000);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!(
+            "oracle_core_test_fee_ledger_{}.json",
+            std::process::id()
+        ));
+        let mut ledger = FeeLedger::default();
+        ledger.record(record(1000, Some(3), 1_100_000));
+        ledger.save(&path).unwrap();
+        let loaded = FeeLedger::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.records, ledger.records);
+    }
+}
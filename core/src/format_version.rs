@@ -0,0 +1,227 @@
+//! Versions of the on-disk formats of the local state (the state store values, the fee ledger and
+//! the datapoint audit log records). Every saved JSON object carries its `format_version`, and a
+//! value saved by an older oracle-core is brought to the current version by the chain of
+//! migrations of its format when it's loaded (and rewritten on startup), so that upgrading the
+//! binary never requires deleting the data dir. The values written before the versioning are
+//! version 1. A value of a newer version than the binary supports is refused instead of being
+//! misread (e.g. after a downgrade).
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+pub const FORMAT_VERSION_FIELD: &str = "format_version";
+
+/// Upgrades a value from the version at its index in [`Format::migrations`] (plus 1) to the next
+/// version
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+#[derive(Debug, Error)]
+pub enum FormatVersionError {
+    #[error("{format} is of version {version}, this oracle-core supports versions up to {supported}, upgrade oracle-core")]
+    Newer {
+        format: &'static str,
+        version: u32,
+        supported: u32,
+    },
+    #[error("failed to migrate {format} from version {version}: {error}")]
+    Migration {
+        format: &'static str,
+        version: u32,
+        error: String,
+    },
+    #[error("invalid {format}: {error}")]
+    Json {
+        format: &'static str,
+        error: serde_json::Error,
+    },
+}
+
+/// On-disk format of a JSON value
+pub struct Format {
+    pub name: &'static str,
+    /// Migrations from version 1 on, the current version is the number of migrations plus 1
+    pub migrations: &'static [Migration],
+}
+
+impl Format {
+    pub fn current_version(&self) -> u32 {
+        self.migrations.len() as u32 + 1
+    }
+
+    /// Version of the value, 1 if it's not versioned
+    pub fn version_of(value: &Value) -> u32 {
+        value
+            .get(FORMAT_VERSION_FIELD)
+            .and_then(Value::as_u64)
+            .map_or(1, |version| (version as u32).max(1))
+    }
+
+    /// Whether the value has to be migrated to the current version
+    pub fn is_outdated(&self, value: &Value) -> bool {
+        Self::version_of(value) < self.current_version()
+    }
+
+    /// Brings the value to the current version, the version field is removed
+    pub fn migrate(&self, mut value: Value) -> Result<Value, FormatVersionError> {
+        let version = Self::version_of(&value);
+        if version > self.current_version() {
+            return Err(FormatVersionError::Newer {
+                format: self.name,
+                version,
+                supported: self.current_version(),
+            });
+        }
+        if let Some(object) = value.as_object_mut() {
+            object.remove(FORMAT_VERSION_FIELD);
+        }
+        for (from_version, migration) in (version..).zip(&self.migrations[version as usize - 1..]) {
+            log::info!(
+                "Migrating {} from version {} to {}",
+                self.name,
+                from_version,
+                from_version + 1
+            );
+            value = migration(value).map_err(|error| FormatVersionError::Migration {
+                format: self.name,
+                version: from_version,
+                error,
+            })?;
+        }
+        Ok(value)
+    }
+
+    /// Sets the current version in the value
+    pub fn stamp(&self, value: &mut Value) {
+        if let Some(object) = value.as_object_mut() {
+            object.insert(
+                FORMAT_VERSION_FIELD.to_string(),
+                Value::from(self.current_version()),
+            );
+        }
+    }
+
+    pub fn from_json_str<T: DeserializeOwned>(
+        &self,
+        json_str: &str,
+    ) -> Result<T, FormatVersionError> {
+        let value = serde_json::from_str(json_str).map_err(|error| self.json_error(error))?;
+        serde_json::from_value(self.migrate(value)?).map_err(|error| self.json_error(error))
+    }
+
+    pub fn to_json_string<T: Serialize>(&self, value: &T) -> Result<String, FormatVersionError> {
+        let mut value = serde_json::to_value(value).map_err(|error| self.json_error(error))?;
+        self.stamp(&mut value);
+        serde_json::to_string(&value).map_err(|error| self.json_error(error))
+    }
+
+    /// The saved JSON brought to the current version, `None` if it's already current
+    pub fn upgrade_json_str(&self, json_str: &str) -> Result<Option<String>, FormatVersionError> {
+        let value: Value =
+            serde_json::from_str(json_str).map_err(|error| self.json_error(error))?;
+        if !self.is_outdated(&value) {
+            return Ok(None);
+        }
+        let mut value = self.migrate(value)?;
+        self.stamp(&mut value);
+        Ok(Some(value.to_string()))
+    }
+
+    fn json_error(&self, error: serde_json::Error) -> FormatVersionError {
+        FormatVersionError::Json {
+            format: self.name,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::oracle_types::BlockHeight;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        height: BlockHeight,
+        fee: u64,
+    }
+
+    /// Version 2 renamed `h` to `height`, version 3 added the `fee`
+    const TEST_FORMAT: Format = Format {
+        name: "test record",
+        migrations: &[
+            |mut value| {
+                let height = value["h"].take();
+                value["height"] = height;
+                value.as_object_mut().unwrap().remove("h");
+                Ok(value)
+            },
+            |mut value| {
+                value["fee"] = json!(1_000_000);
+                Ok(value)
+            },
+        ],
+    };
+
+    #[test]
+    fn test_migrate_unversioned() {
+        let record: Record = TEST_FORMAT.from_json_str(r#"{"h":42}"#).unwrap();
+        assert_eq!(
+            record,
+            Record {
+                height: BlockHeight(42),
+                fee: 1_000_000
+            }
+        );
+        let record: Record = TEST_FORMAT
+            .from_json_str(r#"{"format_version":2,"height":7}"#)
+            .unwrap();
+        assert_eq!(record.height, BlockHeight(7));
+    }
+
+    #[test]
+    fn test_current_version_round_trip() {
+        let record = Record {
+            height: BlockHeight(1),
+            fee: 2,
+        };
+        let json_str = TEST_FORMAT.to_json_string(&record).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&json_str).unwrap(),
+            json!({"format_version": 3, "height": 1, "fee": 2})
+        );
+        assert_eq!(TEST_FORMAT.upgrade_json_str(&json_str).unwrap(), None);
+        assert_eq!(
+            TEST_FORMAT.from_json_str::<Record>(&json_str).unwrap(),
+            record
+        );
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let upgraded = TEST_FORMAT
+            .upgrade_json_str(r#"{"h":42}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&upgraded).unwrap(),
+            json!({"format_version": 3, "height": 42, "fee": 1_000_000})
+        );
+    }
+
+    #[test]
+    fn test_newer_version_is_refused() {
+        assert!(matches!(
+            TEST_FORMAT.from_json_str::<Record>(r#"{"format_version":4,"height":1,"fee":2}"#),
+            Err(FormatVersionError::Newer {
+                version: 4,
+                supported: 3,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,179 @@
+//! Funding wallet holding the ERG spent by the oracle, next to the oracle wallet.
+//!
+//! The oracle wallet holds the oracle token and its key signs the datapoint boxes, so it has to be
+//! on a hot node. The funding wallet (which can be on another node) pays the box values and the
+//! fees: the publish, refresh and `transfer-oracle-token` txs select their inputs from both
+//! wallets and send the change to the funding wallet, so the oracle wallet is left with the oracle
+//! token only. Such a tx spends the boxes of both keys and is signed on the funding node, with
+//! the oracle key fetched from the oracle node and passed along for the signing call only (the
+//! node doesn't store it). The funding key doesn't leave the funding node.
+
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::Transaction;
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_node_interface::node_interface::NodeError;
+use thiserror::Error;
+
+use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
+use crate::node_interface::SignTransaction;
+use crate::oracle_config::FundingWalletConfig;
+use crate::oracle_config::ORACLE_CONFIG;
+use crate::oracle_config::ORACLE_SECRETS;
+use crate::tx_retry::ResubmitNode;
+use crate::wallet::WalletDataError;
+use crate::wallet::WalletDataSource;
+
+#[derive(Debug, Error)]
+pub enum FundingError {
+    #[error(
+        "ORACLE_FUNDING_NODE_API_KEY environment variable for the funding node API key is not set"
+    )]
+    NoFundingNodeApiKey,
+}
+
+pub fn funding_node_api(config: &FundingWalletConfig) -> Result<NodeApi, FundingError> {
+    let api_key = ORACLE_SECRETS
+        .funding_node_api_key
+        .clone()
+        .ok_or(FundingError::NoFundingNodeApiKey)?;
+    Ok(NodeApi::new(
+        api_key,
+        ORACLE_SECRETS.funding_wallet_password.clone(),
+        &config.node_url,
+    ))
+}
+
+/// Total nanoERG in the boxes
+pub fn wallet_balance(boxes: &[ErgoBox]) -> u64 {
+    boxes.iter().map(|b| *b.value.as_u64()).sum()
+}
+
+/// Oracle wallet together with the funding wallet, the txs are built from the boxes of both and
+/// signed on the funding node
+pub struct FundedWallet<'a> {
+    oracle_node_api: &'a NodeApi,
+    funding_node_api: &'a NodeApi,
+}
+
+impl<'a> FundedWallet<'a> {
+    pub fn new(oracle_node_api: &'a NodeApi, funding_node_api: &'a NodeApi) -> Self {
+        Self {
+            oracle_node_api,
+            funding_node_api,
+        }
+    }
+
+    /// Signs the tx on the funding node with the funding wallet keys and the oracle key
+    pub fn sign_transaction(
+        &self,
+        unsigned_tx: &UnsignedTransaction,
+    ) -> Result<Transaction, NodeApiError> {
+        let oracle_key = self
+            .oracle_node_api
+            .wallet_secret_key(&ORACLE_CONFIG.oracle_address.to_base58())?;
+        self.funding_node_api
+            .sign_transaction_with_secrets(unsigned_tx, &[oracle_key])
+    }
+}
+
+impl WalletDataSource for FundedWallet<'_> {
+    fn get_unspent_wallet_boxes(&self) -> Result<Vec<ErgoBox>, WalletDataError> {
+        Ok(funded_wallet_boxes(
+            WalletDataSource::get_unspent_wallet_boxes(self.oracle_node_api)?,
+            WalletDataSource::get_unspent_wallet_boxes(self.funding_node_api)?,
+        ))
+    }
+
+    /// The change goes to the funding wallet
+    fn get_change_address(&self) -> Result<NetworkAddress, WalletDataError> {
+        WalletDataSource::get_change_address(self.funding_node_api)
+    }
+}
+
+/// Boxes the txs can spend, a box found in both wallets (both nodes track the address) is listed
+/// once
+fn funded_wallet_boxes(oracle_boxes: Vec<ErgoBox>, funding_boxes: Vec<ErgoBox>) -> Vec<ErgoBox> {
+    let mut boxes = oracle_boxes;
+    for funding_box in funding_boxes {
+        if !boxes.iter().any(|b| b.box_id() == funding_box.box_id()) {
+            boxes.push(funding_box);
+        }
+    }
+    boxes
+}
+
+impl ResubmitNode for FundedWallet<'_> {
+    fn is_box_unspent(&self, box_id: &BoxId) -> Result<bool, NodeApiError> {
+        self.oracle_node_api.is_box_unspent(box_id)
+    }
+
+    fn sign_transaction(
+        &self,
+        unsigned_tx: &UnsignedTransaction,
+    ) -> Result<Transaction, NodeApiError> {
+        FundedWallet::sign_transaction(self, unsigned_tx)
+    }
+
+    fn submit_transaction(&self, signed_tx: &Transaction) -> Result<TxId, NodeApiError> {
+        self.oracle_node_api.submit_transaction(signed_tx)
+    }
+}
+
+impl SignTransaction for FundedWallet<'_> {
+    fn sign_transaction(
+        &self,
+        unsigned_tx: &UnsignedTransaction,
+    ) -> crate::node_interface::Result<Transaction> {
+        FundedWallet::sign_transaction(self, unsigned_tx).map_err(|e| match e {
+            NodeApiError::NodeInterfaceError(e) => e,
+            e => NodeError::BadRequest(e.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+    use ergo_lib::ergotree_ir::chain::ergo_box::NonMandatoryRegisters;
+    use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+
+    fn wallet_box(value: u64) -> ErgoBox {
+        ErgoBox::new(
+            BoxValue::try_from(value).unwrap(),
+            force_any_val::<ErgoTree>(),
+            None,
+            NonMandatoryRegisters::empty(),
+            1,
+            force_any_val::<TxId>(),
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_wallet_balance() {
+        assert_eq!(wallet_balance(&[]), 0);
+        assert_eq!(
+            wallet_balance(&[wallet_box(1_000_000), wallet_box(2_500_000)]),
+            3_500_000
+        );
+    }
+
+    #[test]
+    fn test_funded_wallet_boxes() {
+        let oracle_box = wallet_box(1_000_000);
+        let funding_box = wallet_box(2_500_000);
+        let boxes = funded_wallet_boxes(
+            vec![oracle_box.clone()],
+            vec![funding_box.clone(), oracle_box.clone()],
+        );
+        assert_eq!(boxes, vec![oracle_box, funding_box]);
+    }
+}
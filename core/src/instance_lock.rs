@@ -0,0 +1,105 @@
+//! Advisory lock preventing a second oracle started against the same data dir and pool (e.g. a
+//! copy-pasted systemd unit) from posting duplicate datapoints and conflicting refresh txs.
+//!
+//! The lock is an OS file lock (`flock` on Unix, `LockFileEx` on Windows) on a file named after
+//! the pool NFT in the data dir, held for the lifetime of the oracle. The OS releases it when the
+//! oracle exits in any way (Ctrl-C, SIGTERM, a crash), so a stopped oracle never blocks the next
+//! start. The file holds the PID of the running oracle, for the error message only.
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use fs2::FileExt;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum InstanceLockError {
+    #[error(
+        "another oracle (PID {pid}) is running for this pool with the same data dir (lock file {})",
+        .path.display()
+    )]
+    Locked { path: PathBuf, pid: String },
+    #[error("failed to lock the lock file {}: {error}", .path.display())]
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+}
+
+/// Held for the lifetime of the oracle, the lock is released when the file is closed
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+pub fn lock_file_path(data_dir: &Path, pool_nft_token_id: &str) -> PathBuf {
+    data_dir.join(format!("oracle-core-{}.lock", pool_nft_token_id))
+}
+
+impl InstanceLock {
+    pub fn acquire(data_dir: &Path, pool_nft_token_id: &str) -> Result<Self, InstanceLockError> {
+        Self::acquire_with(
+            &lock_file_path(data_dir, pool_nft_token_id),
+            std::process::id(),
+        )
+    }
+
+    fn acquire_with(path: &Path, pid: u32) -> Result<Self, InstanceLockError> {
+        let io_error = |error| InstanceLockError::Io {
+            path: path.to_path_buf(),
+            error,
+        };
+        // not truncated before it's locked, the PID of the running oracle would be lost
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)
+            .map_err(io_error)?;
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.raw_os_error() != fs2::lock_contended_error().raw_os_error() {
+                return Err(io_error(e));
+            }
+            // the locked file can't be read on Windows
+            let holder = std::fs::read_to_string(path).unwrap_or_default();
+            return Err(InstanceLockError::Locked {
+                path: path.to_path_buf(),
+                pid: match holder.trim() {
+                    "" => "unknown".to_string(),
+                    pid => pid.to_string(),
+                },
+            });
+        }
+        file.set_len(0).map_err(io_error)?;
+        write!(file, "{}", pid).map_err(io_error)?;
+        Ok(InstanceLock { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire() {
+        let path = std::env::temp_dir().join(format!(
+            "oracle_core_test_instance_{}.lock",
+            std::process::id()
+        ));
+        let lock = InstanceLock::acquire_with(&path, 100).unwrap();
+        assert!(matches!(
+            InstanceLock::acquire_with(&path, 200),
+            Err(InstanceLockError::Locked { .. })
+        ));
+        // the first oracle stopped, the lock file is left behind
+        drop(lock);
+        assert!(path.exists());
+        let lock = InstanceLock::acquire_with(&path, 200).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "200");
+        drop(lock);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
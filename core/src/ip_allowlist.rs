@@ -0,0 +1,173 @@
+//! Optional allowlist of the client IP ranges for the REST API and metrics servers
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use axum::extract::ConnectInfo;
+use axum::http::Request;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::oracle_config::ORACLE_CONFIG;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum IpCidrError {
+    #[error("invalid IP address in {0}")]
+    InvalidAddress(String),
+    #[error("invalid prefix length in {0}")]
+    InvalidPrefixLength(String),
+}
+
+/// IP address range in the CIDR notation (e.g. `192.168.1.0/24`), a single address if the prefix
+/// length is omitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpCidr {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, canonical_ip(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            (IpAddr::V4(_), IpAddr::V6(_)) | (IpAddr::V6(_), IpAddr::V4(_)) => false,
+        }
+    }
+}
+
+/// IPv4 clients of a dual-stack socket are seen as IPv4-mapped IPv6 addresses
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = IpCidrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address_str, prefix_len_str) = match s.split_once('/') {
+            Some((address_str, prefix_len_str)) => (address_str, Some(prefix_len_str)),
+            None => (s, None),
+        };
+        let address = IpAddr::from_str(address_str)
+            .map_err(|_| IpCidrError::InvalidAddress(s.to_string()))?;
+        let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len_str {
+            Some(prefix_len_str) => prefix_len_str
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix_len| *prefix_len <= max_prefix_len)
+                .ok_or_else(|| IpCidrError::InvalidPrefixLength(s.to_string()))?,
+            None => max_prefix_len,
+        };
+        Ok(IpCidr {
+            address,
+            prefix_len,
+        })
+    }
+}
+
+impl TryFrom<String> for IpCidr {
+    type Error = IpCidrError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        IpCidr::from_str(&s)
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+impl From<IpCidr> for String {
+    fn from(cidr: IpCidr) -> Self {
+        cidr.to_string()
+    }
+}
+
+/// Everyone is allowed if the allowlist is empty
+pub fn is_allowed(allowlist: &[IpCidr], ip: IpAddr) -> bool {
+    allowlist.is_empty() || allowlist.iter().any(|cidr| cidr.contains(ip))
+}
+
+/// Rejects the connections from the addresses not in `api_allowlist` of the oracle config
+pub async fn allowlist_middleware<B>(
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if is_allowed(&ORACLE_CONFIG.api_allowlist, client_addr.ip()) {
+        next.run(req).await
+    } else {
+        log::debug!("Rejected API request from {}", client_addr);
+        StatusCode::FORBIDDEN.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        IpAddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            IpCidr::from_str("10.0.0.5").unwrap().to_string(),
+            "10.0.0.5/32"
+        );
+        assert_eq!(IpCidr::from_str("::1").unwrap().to_string(), "::1/128");
+        assert_eq!(
+            IpCidr::from_str("10.0.0.0/33"),
+            Err(IpCidrError::InvalidPrefixLength("10.0.0.0/33".to_string()))
+        );
+        assert_eq!(
+            IpCidr::from_str("frontend/24"),
+            Err(IpCidrError::InvalidAddress("frontend/24".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let allowlist: Vec<IpCidr> = ["192.168.1.0/24", "10.0.0.5", "fd00::/8"]
+            .iter()
+            .map(|s| IpCidr::from_str(s).unwrap())
+            .collect();
+        assert!(is_allowed(&allowlist, ip("192.168.1.77")));
+        assert!(is_allowed(&allowlist, ip("10.0.0.5")));
+        assert!(is_allowed(&allowlist, ip("::ffff:192.168.1.10")));
+        assert!(is_allowed(&allowlist, ip("fd12::1")));
+        assert!(!is_allowed(&allowlist, ip("192.168.2.1")));
+        assert!(!is_allowed(&allowlist, ip("10.0.0.6")));
+        assert!(!is_allowed(&allowlist, ip("2001:db8::1")));
+        assert!(is_allowed(&[], ip("203.0.113.1")));
+        let any_ipv4 = [IpCidr::from_str("0.0.0.0/0").unwrap()];
+        assert!(is_allowed(&any_ipv4, ip("203.0.113.1")));
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::Path;
 
 use log::LevelFilter;
+use log::Record;
 use log4rs::append::console::ConsoleAppender;
 use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
 use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
@@ -9,14 +10,56 @@ use log4rs::append::rolling_file::RollingFileAppender;
 use log4rs::config::Appender;
 use log4rs::config::Logger;
 use log4rs::config::Root;
+use log4rs::encode::pattern::PatternEncoder;
+use log4rs::encode::writer::simple::SimpleWriter;
+use log4rs::encode::Encode;
 use log4rs::Config;
 
+use crate::oracle_config::OracleSecrets;
+use crate::oracle_config::REDACTED;
+
+/// Encodes records with the default pattern and replaces any secret value (node API key,
+/// wallet password) in the output, so the logs are safe to share.
+#[derive(Debug)]
+struct RedactingEncoder {
+    inner: PatternEncoder,
+    secrets: Vec<String>,
+}
+
+impl RedactingEncoder {
+    fn new(secrets: Vec<String>) -> Self {
+        Self {
+            inner: PatternEncoder::default(),
+            secrets,
+        }
+    }
+}
+
+impl Encode for RedactingEncoder {
+    fn encode(&self, w: &mut dyn log4rs::encode::Write, record: &Record) -> anyhow::Result<()> {
+        let mut buf = SimpleWriter(Vec::new());
+        self.inner.encode(&mut buf, record)?;
+        let line = String::from_utf8_lossy(&buf.0);
+        w.write_all(redact(&line, &self.secrets).as_bytes())?;
+        Ok(())
+    }
+}
+
+fn redact(s: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(s.to_string(), |acc, secret| {
+        acc.replace(secret.as_str(), REDACTED)
+    })
+}
+
 pub fn setup_log(
     cmdline_log_level: Option<LevelFilter>,
     config_log_level: Option<LevelFilter>,
     data_dir: &Path,
 ) {
-    let stdout = ConsoleAppender::builder().build();
+    let secrets = OracleSecrets::values_to_redact();
+    let stdout = ConsoleAppender::builder()
+        .encoder(Box::new(RedactingEncoder::new(secrets.clone())))
+        .build();
 
     // via https://stackoverflow.com/questions/56345288/how-do-i-use-log4rs-rollingfileappender-to-incorporate-rolling-logging#
     let window_size = 3; // log0, log1, log2
@@ -51,6 +94,7 @@ pub fn setup_log(
                 "logfile",
                 Box::new(
                     RollingFileAppender::builder()
+                        .encoder(Box::new(RedactingEncoder::new(secrets)))
                         .build(data_dir.join("oracle-core.log"), Box::new(compound_policy))
                         .unwrap(),
                 ),
@@ -75,3 +119,18 @@ pub fn setup_log(
 
     log_panics::init();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let secrets = vec!["hello".to_string(), "pass123".to_string()];
+        assert_eq!(
+            redact("api_key: hello, wallet_pass: pass123", &secrets),
+            "api_key: <redacted>, wallet_pass: <redacted>"
+        );
+        assert_eq!(redact("nothing to hide", &secrets), "nothing to hide");
+    }
+}
@@ -21,13 +21,24 @@ extern crate lazy_static;
 mod action_report;
 mod actions;
 mod address_util;
+mod alerts;
 mod api;
 mod box_kind;
 mod cli_commands;
+mod contract_switch;
 mod contracts;
+mod datapoint_audit;
 mod datapoint_source;
 mod default_parameters;
+mod epoch_archive;
+mod ergopay;
+mod error_code;
 mod explorer_api;
+mod fee_ledger;
+mod format_version;
+mod funding;
+mod instance_lock;
+mod ip_allowlist;
 mod logging;
 mod metrics;
 mod migrate;
@@ -36,13 +47,26 @@ mod node_interface;
 mod oracle_config;
 mod oracle_state;
 mod oracle_types;
+mod param_mismatch;
+mod pause_signal;
+mod pending_datapoint;
 mod pool_commands;
 mod pool_config;
+mod posted_epoch;
+mod posting_scheduler;
+mod reconciliation;
+mod refresh_debug;
+mod reorg;
+mod safe_mode;
 mod scans;
 mod serde;
 mod spec_token;
 mod state;
+mod state_store;
+mod status_attestation;
 mod templates;
+mod tx_retry;
+mod tx_summary;
 mod util;
 mod wallet;
 
@@ -52,11 +76,14 @@ mod tests;
 use action_report::ActionReportStorage;
 use action_report::PoolActionReport;
 use actions::PoolAction;
-use anyhow::anyhow;
 use anyhow::Context;
 use clap::{Parser, Subcommand};
 use crossbeam::channel::bounded;
+use datapoint_audit::DatapointSnapshot;
 use datapoint_source::RuntimeDataPointSource;
+use epoch_archive::EpochArchive;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::TxId;
 use ergo_lib::ergo_chain_types::Digest32;
 use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
 use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
@@ -64,32 +91,38 @@ use ergo_lib::ergotree_ir::chain::token::TokenAmount;
 use ergo_lib::ergotree_ir::chain::token::TokenId;
 use log::error;
 use log::LevelFilter;
+use metrics::push_metrics_periodically;
 use metrics::start_metrics_server;
 use metrics::update_metrics;
 use node_interface::node_api::NodeApi;
 use node_interface::try_ensure_wallet_unlocked;
+use node_interface::SignTransaction;
 use oracle_config::ORACLE_CONFIG;
 use oracle_config::ORACLE_SECRETS;
 use oracle_state::OraclePool;
 use oracle_types::BlockHeight;
+use oracle_types::EpochCounter;
 use pool_commands::build_action;
 use pool_commands::publish_datapoint::PublishDatapointActionError;
 use pool_commands::refresh::RefreshActionError;
 use pool_commands::PoolCommandError;
-use pool_config::DEFAULT_POOL_CONFIG_FILE_NAME;
+use pool_config::resolve_pool_config_file_path;
 use pool_config::POOL_CONFIG;
+use posted_epoch::LastPostedEpoch;
+use posting_scheduler::PostingScheduler;
+use reorg::ReorgTracker;
 use scans::get_scans_file_path;
 use scans::wait_for_node_rescan;
 use spec_token::RewardTokenId;
 use spec_token::SpecToken;
 use spec_token::TokenIdKind;
 use state::process;
+use state::Participation;
 use state::PoolState;
+use state::BLOCKS_PER_DAY;
 use std::convert::TryFrom;
 use std::env;
 use std::path::Path;
-use std::path::PathBuf;
-use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::RwLock;
 use std::thread;
@@ -99,15 +132,33 @@ use crate::actions::execute_action;
 use crate::address_util::pks_to_network_addresses;
 use crate::api::start_rest_server;
 use crate::box_kind::BallotBox;
-use crate::contracts::ballot::BallotContract;
+use crate::box_kind::OracleBoxWrapper;
+use crate::cli_commands::transfer_oracle_token::TransferRewards;
 use crate::default_parameters::print_contract_hashes;
+use crate::error_code::error_code;
+use crate::error_code::error_code_of;
+use crate::error_code::ErrorCode;
+use crate::explorer_api::explorer_url::default_explorer_api_url;
+use crate::explorer_api::ExplorerApi;
+use crate::fee_ledger::FeeLedger;
+use crate::fee_ledger::FeeRecord;
+use crate::format_version::FormatVersionError;
+use crate::funding::FundedWallet;
+use crate::instance_lock::InstanceLock;
 use crate::migrate::check_migration_to_split_config;
+use crate::oracle_config::resolve_oracle_config_file_path;
 use crate::oracle_config::OracleConfig;
-use crate::oracle_config::DEFAULT_ORACLE_CONFIG_FILE_NAME;
+use crate::oracle_config::ParticipationStrategy;
 use crate::oracle_config::ORACLE_CONFIG_FILE_PATH;
 use crate::oracle_config::ORACLE_CONFIG_OPT;
 use crate::pool_config::POOL_CONFIG_FILE_PATH;
 use crate::scans::NodeScanRegistry;
+use crate::state_store::StateStore;
+use crate::state_store::StateStoreError;
+use crate::status_attestation::StatusAttestationError;
+use crate::tx_retry::QueuedDatapoint;
+use crate::tx_retry::RetryTarget;
+use crate::wallet::WalletDataSource;
 
 const APP_VERSION: &str = concat!(
     "v",
@@ -126,10 +177,14 @@ struct Args {
     /// Increase the logging verbosity
     #[clap(short, long)]
     verbose: bool,
-    /// Set path of oracle configuration file to use. Default is ./oracle_config.yaml
-    #[clap(long)]
+    /// Set path of oracle configuration file to use. If not set, ORACLE_CONFIG_FILE env var is
+    /// used, otherwise oracle_config.yaml is looked up in the current folder and then in
+    /// $XDG_CONFIG_HOME/oracle-core (~/.config/oracle-core). Default is ./oracle_config.yaml
+    #[clap(short = 'c', long, alias = "config")]
     oracle_config_file: Option<String>,
-    /// Set path of pool configuration file to use. Default is ./pool_config.yaml
+    /// Set path of pool configuration file to use. If not set, POOL_CONFIG_FILE env var is used,
+    /// otherwise pool_config.yaml is looked up in the current folder, next to the oracle config file
+    /// and in the user's config folder. Default is ./pool_config.yaml
     #[clap(long)]
     pool_config_file: Option<String>,
     /// Set folder path for the data files (scanIDs.json, logs). Default is the current folder.
@@ -157,6 +212,10 @@ enum Command {
         /// Run in read-only mode
         #[clap(long)]
         read_only: bool,
+        /// Only follow the pool (scans, REST API, metrics, epoch history, alerts) without posting
+        /// datapoints. Needs neither an unlocked wallet nor an oracle token
+        #[clap(long, conflicts_with = "read_only")]
+        observer: bool,
         #[clap(long)]
         /// Set this flag to enable the REST API. NOTE: SSL is not used!
         enable_rest_api: bool,
@@ -164,17 +223,39 @@ enum Command {
 
     /// Send reward tokens accumulated in the oracle box to a chosen address
     ExtractRewardTokens {
-        /// Base58 encoded address to send reward tokens to
-        rewards_address: String,
+        /// Base58 encoded address to send reward tokens to. Default is `reward_beneficiary_address`
+        /// of the oracle config
+        rewards_address: Option<String>,
     },
 
     /// Print the number of reward tokens earned by the oracle (in the last posted/collected oracle box)
     PrintRewardTokens,
 
+    /// Print the decoded contents (registers, tokens, value) of our datapoint box
+    PrintMyDatapointBox,
+
     /// Transfer an oracle token to a chosen address.
     TransferOracleToken {
         /// Base58 encoded address to send oracle token to
         oracle_token_address: String,
+        /// Transfer the reward tokens accumulated in the oracle box to the new operator as well
+        #[clap(long, conflicts_with = "retain_rewards")]
+        with_rewards: bool,
+        /// Send the reward tokens accumulated in the oracle box to this node's change address
+        /// in the same transaction
+        #[clap(long)]
+        retain_rewards: bool,
+        /// Print an ErgoPay link and QR code to sign the transaction with a mobile wallet
+        /// instead of signing it with the node wallet
+        #[clap(long)]
+        ergopay: bool,
+    },
+
+    /// Move the oracle token (and the reward tokens) to a datapoint box tied to another key of the
+    /// node wallet and switch the oracle config to it
+    RotateOracleKey {
+        /// Base58 encoded P2PK address of the new key, must be in the node wallet
+        new_oracle_address: String,
     },
 
     /// Vote to update the oracle pool
@@ -182,11 +263,15 @@ enum Command {
         /// The base16-encoded blake2b hash of the serialized pool box contract for the new pool box.
         new_pool_box_address_hash_str: String,
         /// The creation height of the existing update box.
-        update_box_creation_height: u32,
+        update_box_creation_height: BlockHeight,
         /// The base16-encoded reward token id of the new pool box (if minted)
         reward_token_id_str: Option<String>,
         /// The reward token amount in the pool box at the time of update transaction is committed (if minted).
         reward_token_amount: Option<u64>,
+        /// Print an ErgoPay link and QR code to sign the transaction with a mobile wallet
+        /// instead of signing it with the node wallet
+        #[clap(long)]
+        ergopay: bool,
     },
     /// Initiate the Update Pool transaction.
     /// Updated config file `pool_config_updated.yaml` is expected to be in the current directory
@@ -207,35 +292,109 @@ enum Command {
     /// Print base 64 encodings of the blake2b hash of ergo-tree bytes of each contract
     PrintContractHashes,
 
+    /// Write the JSON config of the oracle pool frontend website (token ids, contract addresses,
+    /// parameters and the operators of the pool)
+    ExportFrontendConfig {
+        /// Name of the JSON file to write
+        output: String,
+        /// REST API endpoint of a known operator, can be repeated
+        #[clap(long)]
+        operator_api: Vec<String>,
+    },
+
     ImportPoolUpdate {
         /// Name of the pool config file (.yaml) with new contract parameters
         pool_config_file: String,
     },
+
+    /// Check the node, wallet, scans, config and datapoint source and print suggested fixes
+    Doctor,
+
+    /// Diagnose why the pool is not refreshing and offer to submit a corrective transaction
+    /// (republish own datapoint or refresh the pool)
+    RecoverPool,
+
+    /// Print the oracle status (node height, epoch of the last datapoint post, version) signed by
+    /// the oracle key, for the pool admin to check the liveness of the operators
+    AttestStatus {
+        /// Confirm that the oracle key may be exported from the node wallet for the signature
+        /// (it's only held in memory while the status is signed)
+        #[clap(long)]
+        allow_key_export: bool,
+    },
+
+    /// Check the signature of a status attestation (JSON file) and print the attested status
+    VerifyStatusAttestation {
+        /// Name of the attestation file
+        input: String,
+    },
+
+    /// Inspect the oracle config
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Export or import the oracle's local state (data dir files), e.g. to move the oracle to
+    /// another host
+    State {
+        #[clap(subcommand)]
+        command: StateCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print the effective oracle config (the config file merged with the defaults and the
+    /// command line options) with the secrets redacted and the source of each setting annotated
+    Show,
+}
+
+#[derive(Debug, Subcommand)]
+enum StateCommand {
+    /// Bundle the data dir files and the config file checksums into a single snapshot file
+    Export {
+        /// Name of the snapshot file to write
+        output: String,
+    },
+    /// Restore the data dir files from a snapshot file. Run it while the oracle is stopped.
+    Import {
+        /// Name of the snapshot file
+        input: String,
+        /// Overwrite the existing data dir files and import even if the config files differ from
+        /// the exported ones
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+/// What the `run` command does with the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunMode {
+    /// Build and submit the pool transactions
+    Oracle,
+    /// Build the pool transactions, but don't submit them
+    ReadOnly,
+    /// Don't build any transactions, only follow the pool
+    Observer,
 }
 
 fn main() {
     let args = Args::parse();
+    let cmdline_options = cli_commands::config::CommandLineOptions {
+        oracle_config_file: args.oracle_config_file.is_some(),
+        pool_config_file: args.pool_config_file.is_some(),
+        data_dir: args.data_dir.is_some(),
+        verbose: args.verbose,
+    };
 
+    let oracle_config_file_path = resolve_oracle_config_file_path(args.oracle_config_file);
+    let pool_config_file_path =
+        resolve_pool_config_file_path(args.pool_config_file, &oracle_config_file_path);
     ORACLE_CONFIG_FILE_PATH
-        .set(
-            PathBuf::from_str(
-                &args
-                    .oracle_config_file
-                    .unwrap_or_else(|| DEFAULT_ORACLE_CONFIG_FILE_NAME.to_string()),
-            )
-            .unwrap(),
-        )
-        .unwrap();
-    POOL_CONFIG_FILE_PATH
-        .set(
-            PathBuf::from_str(
-                &args
-                    .pool_config_file
-                    .unwrap_or_else(|| DEFAULT_POOL_CONFIG_FILE_NAME.to_string()),
-            )
-            .unwrap(),
-        )
+        .set(oracle_config_file_path)
         .unwrap();
+    POOL_CONFIG_FILE_PATH.set(pool_config_file_path).unwrap();
 
     let pool_config_path = POOL_CONFIG_FILE_PATH.get().unwrap();
     let oracle_config_path = ORACLE_CONFIG_FILE_PATH.get().unwrap();
@@ -282,20 +441,88 @@ fn main() {
         Arc::new(RwLock::new(ActionReportStorage::new()));
 
     log_on_launch();
+    if let Command::Doctor = args.command {
+        // runs before the node setup below, which panics if the node is not reachable
+        let exit_code = if cli_commands::doctor::doctor() {
+            exitcode::OK
+        } else {
+            exitcode::SOFTWARE
+        };
+        std::process::exit(exit_code);
+    }
+    if let Command::Config {
+        command: ConfigCommand::Show,
+    } = &args.command
+    {
+        // doesn't need the node either
+        if let Err(e) = cli_commands::config::show_config(cmdline_options) {
+            error!("Failed to show the config: {:?}", e);
+            std::process::exit(exitcode::CONFIG);
+        }
+        std::process::exit(exitcode::OK);
+    }
+    if let Command::State { command } = &args.command {
+        // doesn't need the node either
+        let res = match command {
+            StateCommand::Export { output } => cli_commands::state::export_state(Path::new(output)),
+            StateCommand::Import { input, force } => {
+                cli_commands::state::import_state(Path::new(input), *force)
+            }
+        };
+        if let Err(e) = res {
+            error!(
+                "[{}] Fatal state command error: {:?}",
+                error_code(&e).code(),
+                e
+            );
+            std::process::exit(exitcode::SOFTWARE);
+        }
+        std::process::exit(exitcode::OK);
+    }
+    if let Command::VerifyStatusAttestation { input } = &args.command {
+        // doesn't need the node either
+        match status_attestation::verify_file(Path::new(input)) {
+            Ok(status) => {
+                println!("Valid signature of {}", status.oracle_address);
+                println!("{}", serde_json::to_string_pretty(&status).unwrap());
+                std::process::exit(exitcode::OK);
+            }
+            Err(e) => {
+                error!("Status attestation check failed: {:?}", e);
+                std::process::exit(exitcode::DATAERR);
+            }
+        }
+    }
     let node_api = NodeApi::new(
         ORACLE_SECRETS.node_api_key.clone(),
         ORACLE_SECRETS.wallet_password.clone(),
         &ORACLE_CONFIG.node_url,
     );
-    try_ensure_wallet_unlocked(&node_api);
+    let observer = matches!(args.command, Command::Run { observer: true, .. });
+    if !observer {
+        try_ensure_wallet_unlocked(&node_api);
+    }
     wait_for_node_rescan(&node_api).unwrap();
 
     let pool_config = &POOL_CONFIG;
 
-    let change_address = node_api
-        .get_change_address()
-        .expect("failed to get change address from the node");
-    let network_prefix = change_address.network();
+    let change_address = if observer {
+        // observers don't spend from the node wallet, the oracle address only tells the network
+        ORACLE_CONFIG.oracle_address.clone()
+    } else {
+        node_api
+            .get_change_address()
+            .expect("failed to get change address from the node")
+    };
+    let network_prefix = ORACLE_CONFIG.network_prefix();
+    if change_address.network() != network_prefix {
+        error!(
+            "The node change address {} is not a {} address, check the network in the oracle config",
+            change_address.to_base58(),
+            ORACLE_CONFIG.network()
+        );
+        std::process::exit(exitcode::CONFIG);
+    }
 
     #[allow(clippy::wildcard_enum_match_arm)]
     match args.command {
@@ -321,7 +548,11 @@ fn main() {
                 Ok(())
             })() {
                 {
-                    error!("Fatal advanced-bootstrap error: {:?}", e);
+                    error!(
+                        "[{}] Fatal advanced-bootstrap error: {:?}",
+                        error_code(&e).code(),
+                        e
+                    );
                     std::process::exit(exitcode::SOFTWARE);
                 }
             };
@@ -331,27 +562,103 @@ fn main() {
         }
         Command::Run {
             read_only,
+            observer,
             enable_rest_api,
         } => {
+            let run_mode = if observer {
+                log::info!("Observer mode, following the pool without posting datapoints");
+                RunMode::Observer
+            } else if read_only {
+                RunMode::ReadOnly
+            } else {
+                RunMode::Oracle
+            };
+            // held until the oracle exits
+            let _instance_lock = match InstanceLock::acquire(
+                scans::SCANS_DIR_PATH.get().unwrap(),
+                &String::from(POOL_CONFIG.token_ids.pool_nft_token_id.token_id()),
+            ) {
+                Ok(lock) => lock,
+                Err(e) => {
+                    error!("Refusing to start: {}", e);
+                    std::process::exit(exitcode::TEMPFAIL);
+                }
+            };
             let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
             let (_, repost_receiver) = bounded::<bool>(1);
 
             let node_scan_registry =
                 NodeScanRegistry::ensure_node_registered_scans(&node_api, pool_config).unwrap();
             let oracle_pool = Arc::new(OraclePool::new(&node_scan_registry).unwrap());
+            // the observer has no oracle state of its own to reconcile
+            if run_mode != RunMode::Observer {
+                match node_api.node.current_block_height() {
+                    Ok(height) => reconciliation::log_startup_reconciliation(
+                        &oracle_pool,
+                        BlockHeight(height as u32),
+                    ),
+                    Err(e) => error!("Startup reconciliation: failed to get the height: {:?}", e),
+                }
+            }
             let datapoint_source = RuntimeDataPointSource::new(
                 POOL_CONFIG.data_point_source,
                 ORACLE_CONFIG.data_point_source_custom_script.clone(),
+                ORACLE_CONFIG.data_point_source_http.clone(),
+                ORACLE_CONFIG.data_point_source_file.clone(),
+                ORACLE_CONFIG.data_point_source_rhai.clone(),
             )
             .unwrap();
+            datapoint_source.start_streams();
+            datapoint_source::start_rates_proxy_server();
+            let state_store: Arc<dyn StateStore> = match state_store::open_configured() {
+                Ok(store) => Arc::from(store),
+                Err(e) => {
+                    error!("Failed to open the state store: {:?}", e);
+                    std::process::exit(exitcode::SOFTWARE);
+                }
+            };
+            migrate_local_state(state_store.as_ref());
+            let epoch_archive = Arc::new(RwLock::new(
+                state_store.load_epoch_archive().unwrap_or_else(|e| {
+                    error!(
+                        "Failed to load the epoch archive, starting a new one: {:?}",
+                        e
+                    );
+                    EpochArchive::default()
+                }),
+            ));
+            if !epoch_archive.read().unwrap().backfill.complete {
+                let archive_clone = epoch_archive.clone();
+                let store_clone = state_store.clone();
+                let explorer_api = ExplorerApi::new(
+                    ORACLE_CONFIG
+                        .explorer_url
+                        .clone()
+                        .unwrap_or_else(|| default_explorer_api_url(network_prefix)),
+                );
+                let pool_nft_token_id = POOL_CONFIG.token_ids.pool_nft_token_id.token_id();
+                thread::spawn(move || {
+                    epoch_archive::backfill(
+                        archive_clone,
+                        store_clone,
+                        explorer_api,
+                        pool_nft_token_id,
+                    )
+                });
+            }
 
             // Start Oracle Core GET API Server
             if enable_rest_api {
                 let op_clone = oracle_pool.clone();
+                let archive_clone = epoch_archive.clone();
                 tokio_runtime.spawn(async {
-                    if let Err(e) =
-                        start_rest_server(repost_receiver, op_clone, ORACLE_CONFIG.core_api_port)
-                            .await
+                    if let Err(e) = start_rest_server(
+                        repost_receiver,
+                        op_clone,
+                        archive_clone,
+                        ORACLE_CONFIG.core_api_port,
+                    )
+                    .await
                     {
                         error!("An error occurred while starting the REST server: {}", e);
                         std::process::exit(exitcode::SOFTWARE);
@@ -366,25 +673,124 @@ fn main() {
                     }
                 });
             }
+            if let Some(pushgateway) = ORACLE_CONFIG.metrics_pushgateway.clone() {
+                tokio_runtime.spawn(push_metrics_periodically(pushgateway));
+            }
+            let funding_node_api = if run_mode != RunMode::Observer {
+                funding_node_api()
+            } else {
+                None
+            };
+            let funded_wallet = funding_node_api
+                .as_ref()
+                .map(|funding_node_api| FundedWallet::new(&node_api, funding_node_api));
+            let change_address = match &funded_wallet {
+                Some(funded_wallet) => funded_wallet
+                    .get_change_address()
+                    .expect("failed to get change address from the funding node"),
+                None => change_address,
+            };
+            let mut reorg_tracker = ReorgTracker::new();
+            let mut posting_scheduler =
+                PostingScheduler::new(ORACLE_CONFIG.posting_scheduler.clone());
             loop {
+                // the observer follows the pool config it's given, the operator switches it
+                if run_mode != RunMode::Observer {
+                    match contract_switch::check_pool_contract_switch(
+                        &oracle_pool,
+                        &node_api,
+                        &contract_switch::staged_pool_config_path(),
+                    ) {
+                        Ok(true) => {
+                            log::info!("Pool config is switched, restarting the oracle");
+                            let e = contract_switch::restart();
+                            error!(
+                                "Failed to restart the oracle: {:?}. Please, restart it manually",
+                                e
+                            );
+                            std::process::exit(exitcode::SOFTWARE);
+                        }
+                        Ok(false) => (),
+                        Err(e) => error!("pool contract switch-over check error: {:?}", e),
+                    }
+                }
+                if let Err(e) = param_mismatch::check_contract_params(&oracle_pool) {
+                    error!("contract parameters check error: {:?}", e);
+                }
                 if let Err(e) = main_loop_iteration(
                     oracle_pool.clone(),
-                    read_only,
+                    run_mode,
                     &datapoint_source,
                     &node_api,
+                    funded_wallet.as_ref(),
                     action_report_storage.clone(),
                     &change_address,
+                    state_store.as_ref(),
+                    &mut reorg_tracker,
+                    &mut posting_scheduler,
                 ) {
-                    error!("error: {:?}", e);
+                    error!("[{}] error: {:?}", error_code(&e).code(), e);
+                }
+                if let Ok(pool_box) = oracle_pool.get_pool_box_source().get_pool_box() {
+                    epoch_archive::record_epoch(&epoch_archive, state_store.as_ref(), &pool_box);
+                }
+                if let Ok(Some(OracleBoxWrapper::Posted(oracle_box))) = oracle_pool
+                    .get_local_datapoint_box_source()
+                    .get_local_oracle_datapoint_box()
+                {
+                    epoch_archive::record_datapoint(
+                        &epoch_archive,
+                        state_store.as_ref(),
+                        &oracle_box,
+                    );
                 }
                 // Delay loop restart
                 thread::sleep(Duration::new(30, 0));
             }
         }
+        Command::AttestStatus { allow_key_export } => {
+            if !allow_key_export {
+                error!(
+                    "attest-status exports the oracle key from the node wallet to sign the status, \
+                    run it with --allow-key-export to confirm"
+                );
+                std::process::exit(exitcode::USAGE);
+            }
+            let res = state_store::open_configured()
+                .map_err(StatusAttestationError::from)
+                .and_then(|store| status_attestation::attest(&node_api, store.as_ref()));
+            match res {
+                Ok(attestation) => {
+                    println!("{}", serde_json::to_string_pretty(&attestation).unwrap())
+                }
+                Err(e) => {
+                    error!(
+                        "[{}] Fatal attest-status error: {:?}",
+                        error_code_of(&e).code(),
+                        e
+                    );
+                    std::process::exit(exitcode::SOFTWARE);
+                }
+            }
+        }
         oracle_command => handle_pool_command(oracle_command, &node_api, network_prefix),
     }
 }
 
+/// State context to reduce the transaction for ErgoPay, if requested
+fn ergopay_state_context(node_api: &NodeApi, ergopay: bool) -> Option<ErgoStateContext> {
+    if !ergopay {
+        return None;
+    }
+    match node_api.next_block_state_context() {
+        Ok(state_context) => Some(state_context),
+        Err(e) => {
+            error!("Failed to get the block headers for ErgoPay: {:?}", e);
+            std::process::exit(exitcode::SOFTWARE);
+        }
+    }
+}
+
 /// Handle all other commands
 fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: NetworkPrefix) {
     let height = BlockHeight(node_api.node.current_block_height().unwrap() as u32);
@@ -392,6 +798,20 @@ fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: Net
     let op = OraclePool::new(&node_scan_registry).unwrap();
     match command {
         Command::ExtractRewardTokens { rewards_address } => {
+            let rewards_address = match rewards_address.or_else(|| {
+                ORACLE_CONFIG
+                    .reward_beneficiary_address
+                    .as_ref()
+                    .map(|a| a.to_base58())
+            }) {
+                Some(address) => address,
+                None => {
+                    error!(
+                        "No rewards address given and reward_beneficiary_address is not set in the oracle config"
+                    );
+                    std::process::exit(exitcode::USAGE);
+                }
+            };
             if let Err(e) = cli_commands::extract_reward_tokens::extract_reward_tokens(
                 // TODO: pass the NodeApi instance instead of these three
                 node_api,
@@ -401,32 +821,100 @@ fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: Net
                 rewards_address,
                 height,
             ) {
-                error!("Fatal extract-rewards-token error: {:?}", e);
+                error!(
+                    "[{}] Fatal extract-rewards-token error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
                 std::process::exit(exitcode::SOFTWARE);
             }
         }
 
         Command::PrintRewardTokens => {
-            if let Err(e) = cli_commands::print_reward_tokens::print_reward_tokens(
+            if let Err(e) = FeeLedger::load(&FeeLedger::file_path()).and_then(|fee_ledger| {
+                cli_commands::print_reward_tokens::print_reward_tokens(
+                    op.get_local_datapoint_box_source(),
+                    &fee_ledger,
+                )
+            }) {
+                error!(
+                    "[{}] Fatal print-rewards-token error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::PrintMyDatapointBox => {
+            if let Err(e) = cli_commands::print_my_datapoint_box::print_my_datapoint_box(
                 op.get_local_datapoint_box_source(),
+                POOL_CONFIG.data_point_source,
+                network_prefix,
             ) {
-                error!("Fatal print-rewards-token error: {:?}", e);
+                error!(
+                    "[{}] Fatal print-my-datapoint-box error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
                 std::process::exit(exitcode::SOFTWARE);
             }
         }
 
         Command::TransferOracleToken {
             oracle_token_address,
+            with_rewards,
+            retain_rewards,
+            ergopay,
         } => {
+            let transfer_rewards = if with_rewards {
+                TransferRewards::ToNewOperator
+            } else if retain_rewards {
+                TransferRewards::RetainToOldOperator
+            } else {
+                TransferRewards::RequireExtracted
+            };
+            let funding_node_api = funding_node_api();
+            let funded_wallet = funding_node_api
+                .as_ref()
+                .map(|funding_node_api| FundedWallet::new(node_api, funding_node_api));
+            let (wallet, tx_signer): (&dyn WalletDataSource, &dyn SignTransaction) =
+                match &funded_wallet {
+                    Some(funded_wallet) => (funded_wallet, funded_wallet),
+                    None => (node_api, &node_api.node),
+                };
             if let Err(e) = cli_commands::transfer_oracle_token::transfer_oracle_token(
-                node_api,
-                &node_api.node,
+                wallet,
+                tx_signer,
                 &node_api.node,
                 op.get_local_datapoint_box_source(),
                 oracle_token_address,
+                transfer_rewards,
+                height,
+                ergopay_state_context(node_api, ergopay),
+            ) {
+                error!(
+                    "[{}] Fatal transfer-oracle-token error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+
+        Command::RotateOracleKey { new_oracle_address } => {
+            if let Err(e) = cli_commands::rotate_oracle_key::rotate_oracle_key(
+                node_api,
+                op.get_local_datapoint_box_source(),
+                op.get_local_ballot_box_source(),
+                new_oracle_address,
                 height,
             ) {
-                error!("Fatal transfer-oracle-token error: {:?}", e);
+                error!(
+                    "[{}] Fatal rotate-oracle-key error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
                 std::process::exit(exitcode::SOFTWARE);
             }
         }
@@ -436,6 +924,7 @@ fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: Net
             reward_token_id_str,
             reward_token_amount,
             update_box_creation_height,
+            ergopay,
         } => {
             let reward_token_opt = check_reward_token_opt(reward_token_id_str, reward_token_amount);
             log::debug!(
@@ -450,10 +939,7 @@ fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: Net
                     ))
                     .collect::<Vec<_>>()
             );
-            let ballot_contract = BallotContract::checked_load(
-                &POOL_CONFIG.ballot_box_wrapper_inputs.contract_inputs,
-            )
-            .unwrap();
+            let ballot_contract = &op.get_contracts().ballot;
             if let Err(e) = cli_commands::vote_update_pool::vote_update_pool(
                 node_api,
                 &node_api.node,
@@ -461,11 +947,16 @@ fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: Net
                 op.get_local_ballot_box_source(),
                 new_pool_box_address_hash_str,
                 reward_token_opt,
-                BlockHeight(update_box_creation_height),
+                update_box_creation_height,
                 height,
-                &ballot_contract,
+                ballot_contract,
+                ergopay_state_context(node_api, ergopay),
             ) {
-                error!("Fatal vote-update-pool error: {:?}", e);
+                error!(
+                    "[{}] Fatal vote-update-pool error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
                 std::process::exit(exitcode::SOFTWARE);
             }
         }
@@ -482,7 +973,11 @@ fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: Net
                 reward_token_opt,
                 height,
             ) {
-                error!("Fatal update-pool error: {:?}", e);
+                error!(
+                    "[{}] Fatal update-pool error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
                 std::process::exit(exitcode::SOFTWARE);
             }
         }
@@ -490,7 +985,7 @@ fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: Net
             if let Err(e) =
                 cli_commands::prepare_update::prepare_update(update_file, node_api, height)
             {
-                error!("Fatal update error : {:?}", e);
+                error!("[{}] Fatal update error: {:?}", error_code(&e).code(), e);
                 std::process::exit(exitcode::SOFTWARE);
             }
         }
@@ -509,30 +1004,104 @@ fn handle_pool_command(command: Command, node_api: &NodeApi, network_prefix: Net
                 node_scan_registry,
                 node_api,
             ) {
-                error!("Fatal import pool update error : {:?}", e);
+                error!(
+                    "[{}] Fatal import pool update error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
                 std::process::exit(exitcode::SOFTWARE);
             } else {
                 log::info!("pool config update imported successfully. Please, restart the oracle");
                 std::process::exit(exitcode::OK);
             }
         }
+        Command::ExportFrontendConfig {
+            output,
+            operator_api,
+        } => {
+            if let Err(e) = cli_commands::export_frontend_config::export_frontend_config(
+                &op,
+                &POOL_CONFIG,
+                network_prefix,
+                operator_api,
+                Path::new(&output),
+            ) {
+                error!(
+                    "[{}] Fatal export-frontend-config error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
+        Command::RecoverPool => {
+            let datapoint_source = RuntimeDataPointSource::new(
+                POOL_CONFIG.data_point_source,
+                ORACLE_CONFIG.data_point_source_custom_script.clone(),
+                ORACLE_CONFIG.data_point_source_http.clone(),
+                ORACLE_CONFIG.data_point_source_file.clone(),
+                ORACLE_CONFIG.data_point_source_rhai.clone(),
+            )
+            .unwrap();
+            if let Err(e) =
+                cli_commands::recover_pool::recover_pool(&op, node_api, &datapoint_source, height)
+            {
+                error!(
+                    "[{}] Fatal recover-pool error: {:?}",
+                    error_code(&e).code(),
+                    e
+                );
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        }
         Command::Bootstrap { .. }
         | Command::PrintContractHashes
         | Command::GenerateOracleConfig
+        | Command::Doctor
+        | Command::Config { .. }
+        | Command::State { .. }
+        | Command::AttestStatus { .. }
+        | Command::VerifyStatusAttestation { .. }
         | Command::Run { .. } => unreachable!(),
     }
 }
 
+/// Node of the funding wallet if it's configured, exits if its API key is not set
+fn funding_node_api() -> Option<NodeApi> {
+    ORACLE_CONFIG.funding_wallet.as_ref().map(|config| {
+        let funding_node_api = funding::funding_node_api(config).unwrap_or_else(|e| {
+            error!("Funding wallet error: {}", e);
+            std::process::exit(exitcode::SOFTWARE);
+        });
+        try_ensure_wallet_unlocked(&funding_node_api);
+        funding_node_api
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 fn main_loop_iteration(
     oracle_pool: Arc<OraclePool>,
-    read_only: bool,
+    run_mode: RunMode,
     datapoint_source: &RuntimeDataPointSource,
     node_api: &NodeApi,
+    funded_wallet: Option<&FundedWallet>,
     report_storage: Arc<RwLock<ActionReportStorage>>,
     change_address: &NetworkAddress,
+    state_store: &dyn StateStore,
+    reorg_tracker: &mut ReorgTracker,
+    posting_scheduler: &mut PostingScheduler,
 ) -> std::result::Result<(), anyhow::Error> {
-    if !node_api.node.wallet_status()?.unlocked {
-        return Err(anyhow!("Wallet is locked!"));
+    if run_mode != RunMode::Observer && !node_api.node.wallet_status()?.unlocked {
+        return Err(ErrorCode::NodeWalletLocked.into());
+    }
+    // scans return no boxes until the rescan reaches them, which would look like the pool needs bootstrap
+    if let Some(progress) = node_api.rescan_progress()? {
+        log::info!(
+            "Waiting for node rescan, scanned {}/{} blocks",
+            progress.wallet_height,
+            progress.block_height
+        );
+        return Ok(());
     }
     let height = BlockHeight(
         node_api
@@ -540,6 +1109,23 @@ fn main_loop_iteration(
             .current_block_height()
             .context("Failed to get the current height")? as u32,
     );
+    match reorg_tracker.check(node_api, height) {
+        Ok(Some((reorg, orphaned_txs))) => {
+            log::warn!(
+                "Chain reorganization detected, {} blocks replaced from height {}",
+                reorg.depth,
+                reorg.fork_height
+            );
+            for tx_id in orphaned_txs {
+                log::warn!("Our tx {} was orphaned by the reorg", tx_id);
+            }
+            // the pool state below is read anew from the node's scans, which follow the reorg,
+            // and our orphaned datapoint (if any) is reposted in it
+            report_storage.write().unwrap().invalidate_reports();
+        }
+        Ok(None) => (),
+        Err(e) => log::warn!("Failed to check for chain reorganization: {:?}", e),
+    }
     let pool_state = match oracle_pool.get_live_epoch_state() {
         Ok(live_epoch_state) => PoolState::LiveEpoch(live_epoch_state),
         Err(error) => {
@@ -547,34 +1133,323 @@ fn main_loop_iteration(
             PoolState::NeedsBootstrap
         }
     };
-    let epoch_length = POOL_CONFIG
-        .refresh_box_wrapper_inputs
-        .contract_inputs
-        .contract_parameters()
-        .epoch_length();
-    if let Some(cmd) = process(pool_state, epoch_length, height) {
-        log::debug!("Height {height}. Building action for command: {:?}", cmd);
-        let build_action_tuple_res = build_action(
-            cmd,
-            &oracle_pool,
-            node_api,
-            height,
-            change_address.address(),
-            datapoint_source,
-        );
-        if let Some((action, report)) =
-            log_and_continue_if_non_fatal(change_address.network(), build_action_tuple_res)?
-        {
-            if !read_only {
-                execute_action(action, node_api)?;
-                report_storage.write().unwrap().add(report);
+    let epoch_id = match &pool_state {
+        PoolState::LiveEpoch(live_epoch) => Some(live_epoch.pool_box_epoch_id),
+        PoolState::NeedsBootstrap => None,
+    };
+    let paused = run_mode != RunMode::Observer && pause_signal::check(node_api);
+    if paused {
+        log::warn!("Pool admin signaled to stop posting, not submitting txs");
+    }
+    if run_mode != RunMode::Observer && !paused {
+        let epoch_length = POOL_CONFIG
+            .refresh_box_wrapper_inputs
+            .contract_inputs
+            .contract_parameters()
+            .epoch_length();
+        let mut retry_queue = state_store.load_retry_queue().unwrap_or_else(|e| {
+            error!(
+                "Failed to load the tx retry queue, starting a new one: {:?}",
+                e
+            );
+            Default::default()
+        });
+        let fee_ledger_path = FeeLedger::file_path();
+        let mut fee_ledger = FeeLedger::load(&fee_ledger_path).unwrap_or_else(|e| {
+            error!("Failed to load the fee ledger, starting a new one: {:?}", e);
+            FeeLedger::default()
+        });
+        let mut last_posted_epoch = state_store.load_last_posted_epoch().unwrap_or_else(|e| {
+            error!("Failed to load the last posted epoch: {:?}", e);
+            Default::default()
+        });
+        if !retry_queue.is_empty() && run_mode == RunMode::Oracle && !safe_mode::is_active() {
+            let resubmitted_txs = match funded_wallet {
+                Some(funded_wallet) => retry_queue.resubmit(funded_wallet, height),
+                None => retry_queue.resubmit(node_api, height),
+            };
+            for resubmitted in resubmitted_txs {
+                let tx_id = resubmitted.tx.id();
+                fee_ledger.record(FeeRecord {
+                    height,
+                    epoch_id,
+                    tx_id: tx_id.to_string(),
+                    fee: fee_ledger::tx_fee(resubmitted.tx.output_candidates.iter()),
+                });
+                match &resubmitted.datapoint {
+                    Some(datapoint) => {
+                        pending_datapoint::record_resubmitted(&resubmitted.tx);
+                        record_posted_datapoint(
+                            &datapoint.snapshot,
+                            datapoint.epoch_id,
+                            &tx_id,
+                            &mut last_posted_epoch,
+                            state_store,
+                        )?;
+                        reorg_tracker.track_datapoint_tx(tx_id, height);
+                    }
+                    None => reorg_tracker.track_tx(tx_id, height),
+                }
+                posting_scheduler.track_tx(tx_id);
             }
+            state_store.save_retry_queue(&retry_queue)?;
+            fee_ledger.save(&fee_ledger_path)?;
+        }
+        pending_datapoint::forget_if_spent(node_api);
+        posting_scheduler.forget_settled_txs(|tx_id| node_api.is_tx_unconfirmed(tx_id));
+        let participation = participation(&oracle_pool, &pool_state, &fee_ledger, height)?;
+        let repost = if reorg_tracker.needs_datapoint_repost() {
+            reorg::repost_command(&pool_state)
+        } else {
+            None
         };
+        let cmd = match repost {
+            Some(cmd) => {
+                log::warn!("Height {height}. Reposting our datapoint orphaned by the reorg");
+                Some(cmd)
+            }
+            None => process(pool_state, epoch_length, height, participation).filter(|cmd| {
+                let duplicate = epoch_id.map_or(false, |epoch_id| {
+                    last_posted_epoch.is_duplicate(cmd, epoch_id, |box_id| {
+                        node_api.is_box_unspent(box_id).unwrap_or_else(|e| {
+                            log::warn!("Failed to check our posted datapoint box: {:?}", e);
+                            true
+                        })
+                    })
+                });
+                if duplicate {
+                    log::info!(
+                    "Height {height}. Skipping {cmd:?}, we already posted a datapoint in this epoch"
+                );
+                }
+                !duplicate
+            }),
+        };
+        if let Some(cmd) = cmd {
+            log::debug!("Height {height}. Building action for command: {:?}", cmd);
+            let wallet: &dyn WalletDataSource = match funded_wallet {
+                Some(funded_wallet) => funded_wallet,
+                None => node_api,
+            };
+            let build_action_tuple_res = build_action(
+                cmd,
+                &oracle_pool,
+                wallet,
+                height,
+                change_address.address(),
+                datapoint_source,
+            );
+            if let Some((action, report)) = log_and_continue_if_non_fatal(
+                ORACLE_CONFIG.network_prefix(),
+                build_action_tuple_res,
+            )? {
+                if safe_mode::is_active() {
+                    log::error!(
+                        "Safe mode, not submitting the tx with outputs (restart the oracle once \
+                        the cause of the rejections is fixed):\n{}",
+                        tx_summary::format_tx_outputs(
+                            action.tx(),
+                            &change_address.address(),
+                            ORACLE_CONFIG.network_prefix()
+                        )
+                    );
+                } else if run_mode == RunMode::ReadOnly {
+                    log::info!(
+                        "Read-only mode, not submitting the tx with outputs:\n{}",
+                        tx_summary::format_tx_outputs(
+                            action.tx(),
+                            &change_address.address(),
+                            ORACLE_CONFIG.network_prefix()
+                        )
+                    );
+                } else if posting_scheduler.has_free_slot() {
+                    let fee = fee_ledger::tx_fee(action.tx().output_candidates.iter());
+                    let queued_datapoint = match &report {
+                        PoolActionReport::PublishDatapoint(report) => Some(QueuedDatapoint {
+                            epoch_id,
+                            snapshot: report.snapshot.clone(),
+                        }),
+                        _ => None,
+                    };
+                    // a queued tx is useless after the epoch it was built for
+                    let tx_id = execute_action(
+                        action,
+                        node_api,
+                        funded_wallet,
+                        Some(RetryTarget {
+                            queue: &mut retry_queue,
+                            expires_at: height + epoch_length,
+                            datapoint: queued_datapoint,
+                        }),
+                    );
+                    state_store.save_retry_queue(&retry_queue)?;
+                    safe_mode::record_submission(&tx_id, height);
+                    let tx_id = tx_id?;
+                    if let (PoolActionReport::PublishDatapoint(report), Some(tx_id)) =
+                        (&report, &tx_id)
+                    {
+                        record_posted_datapoint(
+                            &report.snapshot,
+                            epoch_id,
+                            tx_id,
+                            &mut last_posted_epoch,
+                            state_store,
+                        )?;
+                    }
+                    let is_datapoint = matches!(report, PoolActionReport::PublishDatapoint(_));
+                    let mut report_storage = report_storage.write().unwrap();
+                    report_storage.add(report);
+                    if let Some(tx_id) = tx_id {
+                        fee_ledger.record(FeeRecord {
+                            height,
+                            epoch_id,
+                            tx_id: tx_id.to_string(),
+                            fee,
+                        });
+                        fee_ledger.save(&fee_ledger_path)?;
+                        if is_datapoint {
+                            reorg_tracker.track_datapoint_tx(tx_id, height);
+                        } else {
+                            reorg_tracker.track_tx(tx_id, height);
+                        }
+                        posting_scheduler.track_tx(tx_id);
+                    }
+                }
+            };
+        }
+    }
+    if run_mode != RunMode::Observer {
+        if let Err(e) = reconciliation::save_current_snapshot(&oracle_pool, height) {
+            log::warn!("Failed to save the oracle state snapshot: {:?}", e);
+        }
     }
-    update_metrics(oracle_pool)?;
+    update_metrics(oracle_pool, run_mode != RunMode::Observer)?;
     Ok(())
 }
 
+/// Brings the local state saved by an older oracle-core to the current formats. The state saved by
+/// a newer oracle-core is not misread, the oracle exits instead. A value that fails to migrate
+/// otherwise is left as is, to be handled by its loader (as an unreadable value was before).
+fn migrate_local_state(state_store: &dyn StateStore) {
+    fn exit_if_newer(error: &anyhow::Error) {
+        let newer = match error.downcast_ref::<StateStoreError>() {
+            Some(StateStoreError::Format(e)) => matches!(e, FormatVersionError::Newer { .. }),
+            _ => matches!(
+                error.downcast_ref::<FormatVersionError>(),
+                Some(FormatVersionError::Newer { .. })
+            ),
+        };
+        if newer {
+            error!("{}", error);
+            std::process::exit(exitcode::CONFIG);
+        }
+    }
+    match state_store::migrate(state_store) {
+        Ok(keys) => {
+            for key in keys {
+                log::info!("Migrated {} to the current format", key.file_name());
+            }
+        }
+        Err(e) => {
+            let e = anyhow::Error::from(e);
+            exit_if_newer(&e);
+            log::warn!("Failed to migrate the state store: {:?}", e);
+        }
+    }
+    match FeeLedger::migrate(&FeeLedger::file_path()) {
+        Ok(true) => log::info!("Migrated the fee ledger to the current format"),
+        Ok(false) => {}
+        Err(e) => {
+            exit_if_newer(&e);
+            log::warn!("Failed to migrate the fee ledger: {:?}", e);
+        }
+    }
+    match datapoint_audit::migrate(&datapoint_audit::file_path()) {
+        Ok(0) => {}
+        Ok(lines) => log::info!(
+            "Migrated {} datapoint audit log records to the current format",
+            lines
+        ),
+        Err(e) => {
+            exit_if_newer(&e);
+            log::warn!("Failed to migrate the datapoint audit log: {:?}", e);
+        }
+    }
+}
+
+/// Adds our datapoint accepted by the node to the audit log and records the epoch it was posted
+/// in, so that no second datapoint is posted in the epoch. The datapoint box must be kept in
+/// `pending_datapoint` first.
+fn record_posted_datapoint(
+    snapshot: &DatapointSnapshot,
+    epoch_id: Option<EpochCounter>,
+    tx_id: &TxId,
+    last_posted_epoch: &mut LastPostedEpoch,
+    state_store: &dyn StateStore,
+) -> Result<(), anyhow::Error> {
+    record_datapoint_snapshot(snapshot, tx_id);
+    if let (Some(epoch_id), Some(box_id)) = (epoch_id, pending_datapoint::box_id()) {
+        last_posted_epoch.record(epoch_id, box_id);
+        state_store.save_last_posted_epoch(last_posted_epoch)?;
+    }
+    Ok(())
+}
+
+/// Adds the posted datapoint to the audit log
+fn record_datapoint_snapshot(snapshot: &DatapointSnapshot, tx_id: &TxId) {
+    log::info!(
+        "Posted datapoint {} (tx {}) computed at height {} from {} sources",
+        snapshot.datapoint,
+        tx_id,
+        snapshot.height,
+        snapshot.sources.iter().filter(|s| s.rate.is_some()).count()
+    );
+    if let Err(e) = datapoint_audit::append(&datapoint_audit::file_path(), snapshot) {
+        log::warn!("Failed to add the datapoint to the audit log: {:?}", e);
+    }
+}
+
+/// Gathers the data needed by the configured participation strategy
+fn participation(
+    oracle_pool: &OraclePool,
+    pool_state: &PoolState,
+    fee_ledger: &FeeLedger,
+    height: BlockHeight,
+) -> Result<Participation, anyhow::Error> {
+    Ok(match ORACLE_CONFIG.participation_strategy {
+        ParticipationStrategy::Always => Participation::Always,
+        ParticipationStrategy::OnlyWhenNeeded => {
+            let epoch_datapoints = match pool_state {
+                PoolState::LiveEpoch(live_epoch) => oracle_pool
+                    .get_posted_datapoint_boxes_source()
+                    .get_posted_datapoint_boxes()?
+                    .iter()
+                    .filter(|b| b.epoch_counter() == live_epoch.pool_box_epoch_id)
+                    .count(),
+                PoolState::NeedsBootstrap => 0,
+            };
+            let min_datapoints = POOL_CONFIG
+                .refresh_box_wrapper_inputs
+                .contract_inputs
+                .contract_parameters()
+                .min_data_points()
+                .0 as usize;
+            Participation::OnlyWhenNeeded {
+                epoch_datapoints,
+                min_datapoints,
+            }
+        }
+        ParticipationStrategy::CostCapped => {
+            let day_start_height = BlockHeight(height.0.saturating_sub(BLOCKS_PER_DAY));
+            Participation::CostCapped {
+                fees_spent_last_day: fee_ledger.fees_spent_since(day_start_height),
+                next_tx_fee: ORACLE_CONFIG.base_fee,
+                daily_fee_budget: ORACLE_CONFIG.daily_fee_budget.unwrap_or_default(),
+            }
+        }
+    })
+}
+
 fn log_and_continue_if_non_fatal(
     network_prefix: NetworkPrefix,
     res: Result<(PoolAction, PoolActionReport), PoolCommandError>,
@@ -592,13 +1467,24 @@ fn log_and_continue_if_non_fatal(
                     .map(|net_addr| net_addr.to_base58())
                     .collect::<Vec<String>>()
                     .join(", ");
-            log::error!("Refresh failed, not enough datapoints. The minimum number of datapoints within the deviation range: required minumum {expected}, found {found_num} from addresses {found_oracle_addresses},");
+            log::error!("[{}] Refresh failed, not enough datapoints. The minimum number of datapoints within the deviation range: required minumum {expected}, found {found_num} from addresses {found_oracle_addresses},", ErrorCode::NotEnoughDatapoints.code());
             Ok(None)
         }
         Err(PoolCommandError::PublishDatapointActionError(
             PublishDatapointActionError::DataPointSource(e),
         )) => {
-            log::error!("Failed to get datapoint with error: {}", e);
+            log::error!(
+                "[{}] Failed to get datapoint with error: {}",
+                ErrorCode::DatapointSourceFailed.code(),
+                e
+            );
+            Ok(None)
+        }
+        Err(PoolCommandError::PoolBoxNotConfirmed(min_confirmations)) => {
+            log::info!(
+                "Waiting for the pool box to get {} confirmations",
+                min_confirmations
+            );
             Ok(None)
         }
         Err(e) => Err(e.into()),
@@ -1,7 +1,9 @@
 use std::convert::From;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
+use axum::middleware;
 use axum::response::IntoResponse;
 use axum::response::Response;
 use axum::routing::get;
@@ -9,16 +11,26 @@ use axum::Router;
 use ergo_node_interface::scanning::NodeError;
 use once_cell::sync::Lazy;
 use prometheus::Encoder;
+use prometheus::Gauge;
 use prometheus::IntGauge;
 use prometheus::IntGaugeVec;
 use prometheus::Opts;
 use prometheus::TextEncoder;
 use reqwest::StatusCode;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
 use tower_http::cors::CorsLayer;
 
+use crate::alerts;
+use crate::alerts::AlertCategory;
 use crate::box_kind::{OracleBox, PoolBox};
+use crate::ip_allowlist::allowlist_middleware;
+use crate::monitor::check_my_datapoint_rank;
 use crate::monitor::check_oracle_health;
 use crate::monitor::check_pool_health;
+use crate::monitor::DatapointRank;
+use crate::monitor::HealthStatus;
 use crate::monitor::OracleHealth;
 use crate::monitor::PoolHealth;
 use crate::node_interface::node_api::NodeApi;
@@ -251,6 +263,148 @@ static REWARD_TOKENS_IN_BUYBACK_BOX: Lazy<IntGauge> = Lazy::new(|| {
     m
 });
 
+static POOL_CONTRACT_UNKNOWN: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
+        Opts::new(
+            "pool_contract_unknown",
+            "1 if the pool box is guarded by a contract unknown to this oracle (neither active nor staged)",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+static CONTRACT_PARAMS_MISMATCH: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
+        Opts::new(
+            "contract_params_mismatch",
+            "1 if other oracles appear to operate under contract parameters different from the pool config",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+static SAFE_MODE: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
+        Opts::new(
+            "safe_mode",
+            "1 if the oracle stopped submitting txs after repeated script failure rejections",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+static PAUSED: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
+        Opts::new(
+            "paused",
+            "1 if the pool admin signaled to stop posting with the pause signal box",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+static MY_DATAPOINT_RANK: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
+        Opts::new(
+            "my_datapoint_rank",
+            "1-based position of our datapoint among the current epoch datapoints sorted by rate",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+static EPOCH_DATAPOINT_COUNT: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
+        Opts::new(
+            "epoch_datapoint_count",
+            "The number of datapoints posted for the current epoch",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+static MY_DATAPOINT_IS_OUTLIER: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
+        Opts::new(
+            "my_datapoint_is_outlier",
+            "1 if our datapoints deviated from the pool consensus by more than the max deviation on average over the recent epochs",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+static MY_DATAPOINT_FILTERED_OUT: Lazy<IntGauge> = Lazy::new(|| {
+    let m = IntGauge::with_opts(
+        Opts::new(
+            "my_datapoint_filtered_out",
+            "1 if our datapoint is outside of the max deviation range and will not be collected by the refresh",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+static MY_DATAPOINT_AVG_DEVIATION: Lazy<Gauge> = Lazy::new(|| {
+    let m = Gauge::with_opts(
+        Opts::new(
+            "my_datapoint_avg_deviation_percent",
+            "Deviation (in percent) of our datapoints from the pool consensus averaged over the recent epochs",
+        )
+        .namespace("ergo")
+        .subsystem("oracle"),
+    )
+    .unwrap();
+    prometheus::register(Box::new(m.clone())).expect("Failed to register");
+    m
+});
+
+pub fn set_pool_contract_unknown(unknown: bool) {
+    POOL_CONTRACT_UNKNOWN.set(unknown as i64);
+}
+
+pub fn set_contract_params_mismatch(mismatch: bool) {
+    CONTRACT_PARAMS_MISMATCH.set(mismatch as i64);
+}
+
+pub fn set_safe_mode(active: bool) {
+    SAFE_MODE.set(active as i64);
+}
+
+pub fn set_paused(paused: bool) {
+    PAUSED.set(paused as i64);
+}
+
 fn update_pool_health(pool_health: &PoolHealth) {
     POOL_BOX_HEIGHT.set(pool_health.details.pool_box_height.into());
     CURRENT_HEIGHT.set(pool_health.details.current_height.into());
@@ -283,6 +437,14 @@ fn update_oracle_health(oracle_health: &OracleHealth) {
     ORACLE_IS_HEALTHY.set(oracle_health.status as i64);
 }
 
+fn update_my_datapoint_rank(datapoint_rank: &DatapointRank) {
+    MY_DATAPOINT_RANK.set(datapoint_rank.rank as i64);
+    EPOCH_DATAPOINT_COUNT.set(datapoint_rank.total_datapoints as i64);
+    MY_DATAPOINT_IS_OUTLIER.set(datapoint_rank.is_outlier as i64);
+    MY_DATAPOINT_FILTERED_OUT.set(datapoint_rank.filtered_out as i64);
+    MY_DATAPOINT_AVG_DEVIATION.set(datapoint_rank.avg_deviation_percent);
+}
+
 fn update_reward_tokens_in_buyback_box(oracle_pool: Arc<OraclePool>) {
     if let Some(buyback_box) = oracle_pool
         .get_buyback_box_source()
@@ -334,14 +496,19 @@ fn update_my_claimable_reward_tokens(oracle_pool: Arc<OraclePool>) {
     }
 }
 
-pub fn update_metrics(oracle_pool: Arc<OraclePool>) -> Result<(), anyhow::Error> {
+/// Updates the pool metrics and, if `oracle_metrics` is set, the ones of our oracle (skipped in the
+/// observer mode, which has no oracle box or wallet)
+pub fn update_metrics(
+    oracle_pool: Arc<OraclePool>,
+    oracle_metrics: bool,
+) -> Result<(), anyhow::Error> {
     let node_api = NodeApi::new(
         ORACLE_SECRETS.node_api_key.clone(),
         ORACLE_SECRETS.wallet_password.clone(),
         &ORACLE_CONFIG.node_url,
     );
     let current_height = (node_api.node.current_block_height()? as u32).into();
-    let network_prefix = node_api.get_change_address()?.network();
+    let network_prefix = ORACLE_CONFIG.network_prefix();
     let pool_box = &oracle_pool.get_pool_box_source().get_pool_box()?;
     {
         let rate = pool_box.rate();
@@ -356,46 +523,143 @@ pub fn update_metrics(oracle_pool: Arc<OraclePool>) -> Result<(), anyhow::Error>
         network_prefix,
     )?;
     update_pool_health(&pool_health);
-    let oracle_health = check_oracle_health(
-        oracle_pool.clone(),
-        pool_box_height,
-        current_height,
-        pool_health.details.epoch_length,
-    )?;
-    update_oracle_health(&oracle_health);
-    let wallet_balance: i64 = node_api.node.wallet_nano_ergs_balance()? as i64;
-    ORACLE_NODE_WALLET_BALANCE.set(wallet_balance);
+    alerts::update(
+        AlertCategory::PoolStale,
+        pool_health.status == HealthStatus::Down,
+        &format!(
+            "pool box was created at height {}, current height {}",
+            pool_health.details.pool_box_height.0, current_height.0
+        ),
+    );
     POOL_BOX_REWARD_TOKEN_AMOUNT.set(pool_box.reward_token().amount.into());
     update_reward_tokens_in_buyback_box(oracle_pool.clone());
-    update_my_claimable_reward_tokens(oracle_pool);
     update_oracle_claimable_reward_tokens(&pool_health);
+    if oracle_metrics {
+        let oracle_health = check_oracle_health(
+            oracle_pool.clone(),
+            pool_box_height,
+            current_height,
+            pool_health.details.epoch_length,
+        )?;
+        update_oracle_health(&oracle_health);
+        alerts::update(
+            AlertCategory::MissedEpoch,
+            oracle_health.status == HealthStatus::Down,
+            &format!(
+                "our oracle box is behind the pool box created at height {}",
+                pool_box_height.0
+            ),
+        );
+        // keep the values from the last epoch we posted a datapoint in
+        if let Some(datapoint_rank) = check_my_datapoint_rank(oracle_pool.clone())? {
+            update_my_datapoint_rank(&datapoint_rank);
+        }
+        let wallet_balance: i64 = node_api.node.wallet_nano_ergs_balance()? as i64;
+        ORACLE_NODE_WALLET_BALANCE.set(wallet_balance);
+        let low_balance_threshold = ORACLE_CONFIG.alerts.low_balance_threshold;
+        alerts::update(
+            AlertCategory::LowBalance,
+            (wallet_balance as u64) < low_balance_threshold,
+            &format!(
+                "oracle wallet balance {} nanoERG is below {} nanoERG",
+                wallet_balance, low_balance_threshold
+            ),
+        );
+        update_my_claimable_reward_tokens(oracle_pool);
+    }
     Ok(())
 }
 
-async fn serve_metrics() -> impl IntoResponse {
+/// Metrics in the Prometheus text format
+fn encode_metrics() -> String {
     let registry = prometheus::default_registry();
     let metric_families = registry.gather();
     let mut buffer = vec![];
-    let encoder = TextEncoder::new();
-    encoder.encode(&metric_families, &mut buffer).unwrap();
+    TextEncoder::new()
+        .encode(&metric_families, &mut buffer)
+        .unwrap();
+    String::from_utf8(buffer).unwrap()
+}
 
-    let metrics = String::from_utf8(buffer).unwrap();
+async fn serve_metrics() -> impl IntoResponse {
     axum::response::Response::builder()
-        .header("Content-Type", encoder.format_type())
-        .body(metrics)
+        .header("Content-Type", TextEncoder::new().format_type())
+        .body(encode_metrics())
         .unwrap()
 }
 
+/// Prometheus Pushgateway the metrics are pushed to, for the oracles that can't be scraped (e.g.
+/// behind NAT)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PushgatewayConfig {
+    pub url: Url,
+    /// `job` label of the pushed metrics (`oracle-core` by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<String>,
+    /// Seconds between the pushes (60 by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+}
+
+impl PushgatewayConfig {
+    /// URL of the metrics group of the instance, the `instance` label is the scan instance name or
+    /// the oracle address
+    fn group_url(&self, instance: &str) -> Url {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .expect("Pushgateway URL can't be a base")
+            .pop_if_empty()
+            .extend([
+                "metrics",
+                "job",
+                self.job.as_deref().unwrap_or("oracle-core"),
+                "instance",
+                instance,
+            ]);
+        url
+    }
+}
+
+/// Pushes the metrics to the Pushgateway every `interval_secs`, replacing the previous push of
+/// the instance
+pub async fn push_metrics_periodically(config: PushgatewayConfig) {
+    let instance = ORACLE_CONFIG
+        .scan_instance_name
+        .clone()
+        .unwrap_or_else(|| ORACLE_CONFIG.oracle_address.to_base58());
+    let url = config.group_url(&instance);
+    log::info!("Pushing the metrics to {}", url);
+    let client = reqwest::Client::new();
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(config.interval_secs.unwrap_or(60)));
+    loop {
+        interval.tick().await;
+        let res = client
+            .put(url.clone())
+            .header("Content-Type", TextEncoder::new().format_type())
+            .body(encode_metrics())
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        if let Err(e) = res {
+            log::warn!("Failed to push the metrics to the Pushgateway: {}", e);
+        }
+    }
+}
+
 pub async fn start_metrics_server(port_num: u16) -> Result<(), anyhow::Error> {
-    let app = Router::new().route("/metrics", get(serve_metrics)).layer(
-        CorsLayer::new()
-            .allow_origin(tower_http::cors::Any)
-            .allow_methods([axum::http::Method::GET]),
-    );
+    let app = Router::new()
+        .route("/metrics", get(serve_metrics))
+        .layer(
+            CorsLayer::new()
+                .allow_origin(tower_http::cors::Any)
+                .allow_methods([axum::http::Method::GET]),
+        )
+        .layer(middleware::from_fn(allowlist_middleware));
     let addr = SocketAddr::from(([0, 0, 0, 0], port_num));
     log::info!("Starting metrics server on {}", addr);
     axum::Server::try_bind(&addr)?
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
     Ok(())
 }
@@ -419,3 +683,30 @@ impl From<anyhow::Error> for MetricsError {
         MetricsError(format!("Error: {:?}", err))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pushgateway_group_url() {
+        let config = PushgatewayConfig {
+            url: Url::parse("https://push.example.com/").unwrap(),
+            job: None,
+            interval_secs: None,
+        };
+        assert_eq!(
+            config.group_url("erg-usd").as_str(),
+            "https://push.example.com/metrics/job/oracle-core/instance/erg-usd"
+        );
+        let config = PushgatewayConfig {
+            url: Url::parse("http://10.0.0.1:9091/gateway").unwrap(),
+            job: Some("oracles".to_string()),
+            interval_secs: Some(15),
+        };
+        assert_eq!(
+            config.group_url("erg usd").as_str(),
+            "http://10.0.0.1:9091/gateway/metrics/job/oracles/instance/erg%20usd"
+        );
+    }
+}
@@ -1,20 +1,35 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use ergo_lib::ergotree_ir::chain::address::Address;
 use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
 use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use once_cell::sync::Lazy;
 
 use crate::box_kind::CollectedOracleBox;
 use crate::box_kind::OracleBoxWrapper;
+use crate::box_kind::PoolBox;
 use crate::box_kind::PostedOracleBox;
+use crate::oracle_config::ORACLE_CONFIG;
 use crate::oracle_state::DataSourceError;
 use crate::oracle_state::OraclePool;
 use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
 use crate::oracle_types::EpochLength;
 use crate::oracle_types::MinDatapoints;
 use crate::oracle_types::Rate;
+use crate::pool_commands::refresh::calc_pool_rate;
+use crate::pool_commands::refresh::filtered_oracle_boxes_by_rate;
 use crate::pool_config::POOL_CONFIG;
 
+/// Number of the recent epochs the deviation of our datapoint is averaged over if
+/// `datapoint_rank_window` is not set in the oracle config
+const DEFAULT_DATAPOINT_RANK_WINDOW: u32 = 10;
+
+static DEVIATION_HISTORY: Lazy<Mutex<DeviationHistory>> =
+    Lazy::new(|| Mutex::new(DeviationHistory::default()));
+
 #[derive(Debug, serde::Serialize, Copy, Clone, PartialEq, Eq)]
 pub enum HealthStatus {
     Ok = 1,
@@ -249,3 +264,197 @@ pub fn check_oracle_health(
     };
     Ok(health)
 }
+
+/// Deviations (in percent) of our datapoints from the pool consensus in the recent epochs
+#[derive(Debug, Default)]
+pub struct DeviationHistory {
+    deviations: BTreeMap<EpochCounter, f64>,
+}
+
+impl DeviationHistory {
+    /// Records the deviation in the epoch (replacing the earlier one of the same epoch) and
+    /// drops the epochs before the last `window` ones
+    fn record(&mut self, epoch_id: EpochCounter, deviation_percent: f64, window: u32) {
+        self.deviations.insert(epoch_id, deviation_percent);
+        while self.deviations.len() > window.max(1) as usize {
+            self.deviations.pop_first();
+        }
+    }
+
+    fn average(&self) -> f64 {
+        self.deviations.values().sum::<f64>() / self.deviations.len().max(1) as f64
+    }
+}
+
+/// Position of our datapoint among the datapoints posted for the current epoch
+/// (the ones the next refresh is going to collect) and its deviation from the pool consensus
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DatapointRank {
+    pub epoch_id: EpochCounter,
+    pub rate: Rate,
+    /// 1-based position in the datapoints sorted by rate in ascending order
+    pub rank: u32,
+    pub total_datapoints: u32,
+    /// Our datapoint is outside of the largest subset of datapoints within the max deviation
+    /// and will not be collected by the refresh
+    pub filtered_out: bool,
+    /// Deviation (in percent) from the rate the refresh would post, the average of the collected
+    /// datapoints
+    pub deviation_percent: f64,
+    /// Number of the recent epochs (with our datapoint) the deviation is averaged over
+    pub window_epochs: u32,
+    pub avg_deviation_percent: f64,
+    /// Our datapoints deviated from the consensus by more than the max deviation on average over
+    /// the window
+    pub is_outlier: bool,
+}
+
+/// Rank of our datapoint in the epoch, recorded in the history of the last `window` epochs
+pub fn datapoint_rank(
+    epoch_id: EpochCounter,
+    my_rate: Rate,
+    epoch_rates: Vec<Rate>,
+    max_deviation_percent: u32,
+    history: &mut DeviationHistory,
+    window: u32,
+) -> DatapointRank {
+    let rank = epoch_rates.iter().filter(|r| **r < my_rate).count() as u32 + 1;
+    let total_datapoints = epoch_rates.len() as u32;
+    let collected_rates = filtered_oracle_boxes_by_rate(epoch_rates, max_deviation_percent);
+    let filtered_out = !collected_rates.contains(&my_rate);
+    let deviation_percent = if collected_rates.is_empty() {
+        0.0
+    } else {
+        let consensus = i64::from(calc_pool_rate(collected_rates)) as f64;
+        (i64::from(my_rate) as f64 - consensus).abs() / consensus * 100.0
+    };
+    history.record(epoch_id, deviation_percent, window);
+    let avg_deviation_percent = history.average();
+    DatapointRank {
+        epoch_id,
+        rate: my_rate,
+        rank,
+        total_datapoints,
+        filtered_out,
+        deviation_percent,
+        window_epochs: history.deviations.len() as u32,
+        avg_deviation_percent,
+        is_outlier: avg_deviation_percent > max_deviation_percent as f64,
+    }
+}
+
+/// Returns `None` if we have not posted a datapoint for the current epoch
+pub fn check_my_datapoint_rank(
+    oracle_pool: Arc<OraclePool>,
+) -> Result<Option<DatapointRank>, DataSourceError> {
+    let epoch_id = oracle_pool
+        .get_pool_box_source()
+        .get_pool_box()?
+        .epoch_counter();
+    let my_rate = match oracle_pool
+        .get_local_datapoint_box_source()
+        .get_local_oracle_datapoint_box()?
+    {
+        Some(OracleBoxWrapper::Posted(posted_box)) if posted_box.epoch_counter() == epoch_id => {
+            posted_box.rate()
+        }
+        Some(OracleBoxWrapper::Posted(_)) | Some(OracleBoxWrapper::Collected(_)) | None => {
+            return Ok(None)
+        }
+    };
+    let epoch_rates = oracle_pool
+        .get_posted_datapoint_boxes_source()
+        .get_posted_datapoint_boxes()?
+        .into_iter()
+        .filter(|b| b.epoch_counter() == epoch_id)
+        .map(|b| b.rate())
+        .collect();
+    let max_deviation_percent = POOL_CONFIG
+        .refresh_box_wrapper_inputs
+        .contract_inputs
+        .contract_parameters()
+        .max_deviation_percent() as u32;
+    let window = ORACLE_CONFIG
+        .datapoint_rank_window
+        .unwrap_or(DEFAULT_DATAPOINT_RANK_WINDOW);
+    Ok(Some(datapoint_rank(
+        epoch_id,
+        my_rate,
+        epoch_rates,
+        max_deviation_percent,
+        &mut DEVIATION_HISTORY.lock().unwrap(),
+        window,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rates(rates: &[i64]) -> Vec<Rate> {
+        rates.iter().copied().map(Rate::from).collect()
+    }
+
+    #[test]
+    fn test_datapoint_rank() {
+        let mut history = DeviationHistory::default();
+        let epoch_rates = rates(&[95, 100, 101, 102, 150]);
+        let rank = datapoint_rank(
+            EpochCounter(3),
+            Rate::from(101),
+            epoch_rates.clone(),
+            5,
+            &mut history,
+            10,
+        );
+        assert_eq!(rank.rank, 3);
+        assert_eq!(rank.total_datapoints, 5);
+        assert!(!rank.filtered_out);
+        // the refresh would post 101
+        assert_eq!(rank.deviation_percent, 0.0);
+        assert!(!rank.is_outlier);
+        let rank = datapoint_rank(
+            EpochCounter(3),
+            Rate::from(150),
+            epoch_rates,
+            5,
+            &mut history,
+            10,
+        );
+        assert_eq!(rank.rank, 5);
+        assert!(rank.filtered_out);
+        // replaced the earlier sample of the epoch
+        assert_eq!(rank.window_epochs, 1);
+        assert!(rank.is_outlier);
+    }
+
+    #[test]
+    fn test_outlier_over_window() {
+        let mut history = DeviationHistory::default();
+        // 10% off in a single epoch, close to the consensus in the others
+        let rank = |epoch, my_rate, history: &mut DeviationHistory| {
+            datapoint_rank(
+                EpochCounter(epoch),
+                Rate::from(my_rate),
+                rates(&[100, 100, 100, my_rate]),
+                5,
+                history,
+                3,
+            )
+        };
+        let off = rank(1, 110, &mut history);
+        assert!(off.filtered_out);
+        assert!(off.is_outlier);
+        rank(2, 101, &mut history);
+        let last = rank(3, 101, &mut history);
+        assert!(!last.filtered_out);
+        assert_eq!(last.window_epochs, 3);
+        assert!(last.avg_deviation_percent < 5.0);
+        assert!(!last.is_outlier);
+        // repeatedly off
+        rank(4, 110, &mut history);
+        let last = rank(5, 110, &mut history);
+        assert_eq!(last.window_epochs, 3);
+        assert!(last.is_outlier);
+    }
+}
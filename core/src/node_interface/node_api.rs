@@ -1,9 +1,16 @@
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
 use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::Transaction;
 use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergo_chain_types::Header;
+use ergo_lib::ergo_chain_types::PreHeader;
 use ergo_lib::ergotree_ir::chain::address::AddressEncoder;
 use ergo_lib::ergotree_ir::chain::address::AddressEncoderError;
 use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
 use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use ergo_lib::wallet::secret_key::SecretKey;
 use ergo_node_interface::scanning::NodeError;
 use ergo_node_interface::NodeInterface;
 use ergo_node_interface::ScanId;
@@ -12,15 +19,49 @@ use reqwest::Url;
 use serde_json::json;
 use thiserror::Error;
 
+use crate::oracle_config::ORACLE_CONFIG;
+use crate::oracle_config::REDACTED;
+use crate::oracle_types::BlockHeight;
 use crate::scans::ScanID;
 use crate::wallet::WalletDataError;
 use crate::wallet::WalletDataSource;
 
+/// The wallet (and scans) are considered rescanning if they lag more blocks behind the chain
+const MAX_WALLET_HEIGHT_LAG: u32 = 1;
+
+/// Progress of the node wallet (and scans) rescan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct RescanProgress {
+    pub wallet_height: BlockHeight,
+    pub block_height: BlockHeight,
+}
+
+/// Where the node sees a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStatus {
+    /// In a block of the current chain
+    Confirmed,
+    /// In the mempool
+    Unconfirmed,
+    /// Neither, e.g. dropped from the mempool
+    Unknown,
+}
+
 pub struct NodeApi {
     pub node: NodeInterface,
     pub wallet_pass: Option<String>,
 }
 
+// `NodeInterface` holds the node API key, so neither field is printed
+impl std::fmt::Debug for NodeApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeApi")
+            .field("node", &REDACTED)
+            .field("wallet_pass", &self.wallet_pass.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
 impl NodeApi {
     pub fn new(api_key: String, wallet_pass: Option<String>, node_url: &Url) -> Self {
         let node = NodeInterface::from_url(&api_key, node_url.clone());
@@ -71,7 +112,21 @@ impl NodeApi {
         Ok(scan_id)
     }
 
-    pub fn rescan_from_height(&self, height: u32) -> Result<(), NodeApiError> {
+    /// Ids and names of all scans registered in the node
+    pub fn list_scans(&self) -> Result<Vec<(ScanId, String)>, NodeApiError> {
+        let res = self.node.send_get_req("/scan/listAll");
+        let json = self.node.parse_response_to_json(res)?;
+        Ok(json
+            .members()
+            .filter_map(|scan| {
+                let scan_id = scan["scanId"].as_u64()?;
+                let scan_name = scan["scanName"].as_str()?;
+                Some((scan_id.into(), scan_name.to_string()))
+            })
+            .collect())
+    }
+
+    pub fn rescan_from_height(&self, height: BlockHeight) -> Result<(), NodeApiError> {
         log::info!("Triggering wallet rescan");
         self.node.send_post_req(
             "/wallet/rescan",
@@ -85,16 +140,259 @@ impl NodeApi {
         &self,
         unsigned_tx: &UnsignedTransaction,
     ) -> Result<TxId, NodeApiError> {
+        let signed_tx = self.sign_transaction(unsigned_tx)?;
+        self.submit_transaction(&signed_tx)
+    }
+
+    pub fn sign_transaction(
+        &self,
+        unsigned_tx: &UnsignedTransaction,
+    ) -> Result<Transaction, NodeApiError> {
         log::trace!(
             "Signing transaction: {}",
             serde_json::to_string_pretty(&unsigned_tx).unwrap()
         );
-        let signed_tx = self.node.sign_transaction(unsigned_tx, None, None)?;
+        Ok(self.node.sign_transaction(unsigned_tx, None, None)?)
+    }
+
+    /// Signs the tx with the wallet keys and the given keys of another wallet
+    /// (`/wallet/transaction/sign`), the node looks the inputs up in the UTXO set and the mempool
+    pub fn sign_transaction_with_secrets(
+        &self,
+        unsigned_tx: &UnsignedTransaction,
+        secrets: &[SecretKey],
+    ) -> Result<Transaction, NodeApiError> {
+        log::trace!(
+            "Signing transaction with {} extra keys: {}",
+            secrets.len(),
+            serde_json::to_string_pretty(&unsigned_tx).unwrap()
+        );
+        let dlog_secrets: Vec<String> = secrets
+            .iter()
+            .map(|secret| base16::encode_lower(&secret.to_bytes()))
+            .collect();
+        let body = json!({
+            "tx": unsigned_tx,
+            "secrets": { "dlog": dlog_secrets },
+        });
+        let res = self
+            .node
+            .send_post_req("/wallet/transaction/sign", body.to_string());
+        let json = self.node.parse_response_to_json(res)?;
+        Ok(serde_json::from_str(&json.dump())
+            .map_err(|e| NodeError::BadRequest(format!("invalid signed tx: {}", e)))?)
+    }
+
+    pub fn submit_transaction(&self, signed_tx: &Transaction) -> Result<TxId, NodeApiError> {
         log::trace!(
             "Submitting signed transaction: {}",
             serde_json::to_string_pretty(&signed_tx).unwrap()
         );
-        Ok(self.node.submit_transaction(&signed_tx)?)
+        Ok(self.node.submit_transaction(signed_tx)?)
+    }
+
+    /// Whether the box is in the UTXO set or created by a mempool transaction
+    pub fn is_box_unspent(&self, box_id: &BoxId) -> Result<bool, NodeApiError> {
+        let res = self
+            .node
+            .send_get_req(&format!("/utxo/withPool/byId/{}", box_id))?;
+        Ok(res.status().is_success())
+    }
+
+    /// Unspent wallet boxes taking the mempool into account, i.e. including the outputs of our
+    /// unconfirmed transactions and excluding the boxes they spend.
+    pub fn unspent_wallet_boxes_with_unconfirmed(&self) -> Result<Vec<ErgoBox>, NodeApiError> {
+        self.get_boxes("/wallet/boxes/unspent?minConfirmations=-1")
+    }
+
+    /// Unspent wallet boxes included at least `min_confirmations` blocks deep
+    pub fn unspent_wallet_boxes_with_min_confirmations(
+        &self,
+        min_confirmations: u32,
+    ) -> Result<Vec<ErgoBox>, NodeApiError> {
+        self.get_boxes(&format!(
+            "/wallet/boxes/unspent?minConfirmations={}",
+            min_confirmations
+        ))
+    }
+
+    /// Unspent scan boxes taking the mempool into account (see `unspent_wallet_boxes_with_unconfirmed`)
+    pub fn scan_boxes_with_unconfirmed(
+        &self,
+        scan_id: ScanId,
+    ) -> Result<Vec<ErgoBox>, NodeApiError> {
+        self.get_boxes(&format!(
+            "/scan/unspentBoxes/{}?minConfirmations=-1",
+            scan_id
+        ))
+    }
+
+    /// Unspent scan boxes included at least `min_confirmations` blocks deep
+    pub fn scan_boxes_with_min_confirmations(
+        &self,
+        scan_id: ScanId,
+        min_confirmations: u32,
+    ) -> Result<Vec<ErgoBox>, NodeApiError> {
+        self.get_boxes(&format!(
+            "/scan/unspentBoxes/{}?minConfirmations={}",
+            scan_id, min_confirmations
+        ))
+    }
+
+    /// Unspent boxes holding the token, requires the blockchain indexer of the node
+    /// (`extraIndex = true`)
+    pub fn unspent_boxes_by_token_id(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Vec<ErgoBox>, NodeApiError> {
+        let res = self.node.send_get_req(&format!(
+            "/blockchain/box/unspent/byTokenId/{}",
+            String::from(token_id)
+        ));
+        let json = self.node.parse_response_to_json(res)?;
+        json.members()
+            .map(|indexed_box| {
+                serde_json::from_str(&indexed_box.dump())
+                    .map_err(|e| NodeApiError::BoxParseError(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn get_boxes(&self, endpoint: &str) -> Result<Vec<ErgoBox>, NodeApiError> {
+        let res = self.node.send_get_req(endpoint);
+        let json = self.node.parse_response_to_json(res)?;
+        json.members()
+            .map(|wallet_box| {
+                serde_json::from_str(&wallet_box["box"].dump())
+                    .map_err(|e| NodeApiError::BoxParseError(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Returns the progress if the node is rescanning the wallet and scans, while the scans
+    /// don't return all the boxes yet
+    pub fn rescan_progress(&self) -> Result<Option<RescanProgress>, NodeApiError> {
+        let wallet_height = BlockHeight(self.node.wallet_status()?.height);
+        let block_height = BlockHeight(self.node.current_block_height()? as u32);
+        if block_height > wallet_height + MAX_WALLET_HEIGHT_LAG {
+            Ok(Some(RescanProgress {
+                wallet_height,
+                block_height,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Ids of the last `count` block headers by height
+    pub fn last_block_ids(&self, count: u32) -> Result<Vec<(BlockHeight, String)>, NodeApiError> {
+        let res = self
+            .node
+            .send_get_req(&format!("/blocks/lastHeaders/{}", count));
+        let json = self.node.parse_response_to_json(res)?;
+        json.members()
+            .map(
+                |header| match (header["height"].as_u32(), header["id"].as_str()) {
+                    (Some(height), Some(id)) => Ok((BlockHeight(height), id.to_string())),
+                    _ => Err(NodeError::BadRequest(format!(
+                        "header without height and id: {}",
+                        header.dump()
+                    ))
+                    .into()),
+                },
+            )
+            .collect()
+    }
+
+    /// Context of the next block built from the last 10 headers, to reduce the transactions
+    /// signed outside of the node (see `ergopay`)
+    pub fn next_block_state_context(&self) -> Result<ErgoStateContext, NodeApiError> {
+        let res = self.node.send_get_req("/blocks/lastHeaders/10");
+        let json = self.node.parse_response_to_json(res)?;
+        let mut headers = json
+            .members()
+            .map(|header| serde_json::from_str::<Header>(&header.dump()))
+            .collect::<Result<Vec<Header>, _>>()
+            .map_err(|e| NodeError::BadRequest(format!("invalid block header: {}", e)))?;
+        // the node returns the headers from the oldest one, the context expects the newest first
+        headers.reverse();
+        let last_header = headers
+            .first()
+            .cloned()
+            .ok_or_else(|| NodeError::BadRequest("no block headers".to_string()))?;
+        let mut pre_header = PreHeader::from(last_header.clone());
+        pre_header.parent_id = last_header.id;
+        pre_header.height = last_header.height + 1;
+        let headers = headers.try_into().map_err(|headers: Vec<Header>| {
+            NodeError::BadRequest(format!("expected 10 block headers, got {}", headers.len()))
+        })?;
+        Ok(ErgoStateContext::new(pre_header, headers))
+    }
+
+    /// Whether the transaction is in a block of the current chain (known to the wallet) or in
+    /// the mempool
+    pub fn is_tx_known(&self, tx_id: &TxId) -> Result<bool, NodeApiError> {
+        Ok(self.tx_status(tx_id)? != TxStatus::Unknown)
+    }
+
+    /// Whether the wallet transaction is in a block of the current chain, in the mempool or
+    /// neither
+    pub fn tx_status(&self, tx_id: &TxId) -> Result<TxStatus, NodeApiError> {
+        let confirmed_endpoint = format!("/wallet/transactionById?id={}", tx_id);
+        if self
+            .node
+            .send_get_req(&confirmed_endpoint)?
+            .status()
+            .is_success()
+        {
+            return Ok(TxStatus::Confirmed);
+        }
+        let unconfirmed_endpoint = format!("/transactions/unconfirmed/byTransactionId/{}", tx_id);
+        if self
+            .node
+            .send_get_req(&unconfirmed_endpoint)?
+            .status()
+            .is_success()
+        {
+            return Ok(TxStatus::Unconfirmed);
+        }
+        Ok(TxStatus::Unknown)
+    }
+
+    /// Whether the transaction is in the mempool
+    pub fn is_tx_unconfirmed(&self, tx_id: &TxId) -> Result<bool, NodeApiError> {
+        let endpoint = format!("/transactions/unconfirmed/byTransactionId/{}", tx_id);
+        Ok(self.node.send_get_req(&endpoint)?.status().is_success())
+    }
+
+    /// Base58 encoded addresses derived by the node wallet
+    pub fn wallet_addresses(&self) -> Result<Vec<String>, NodeApiError> {
+        let res = self.node.send_get_req("/wallet/addresses");
+        let json = self.node.parse_response_to_json(res)?;
+        Ok(json
+            .members()
+            .filter_map(|address| address.as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Secret key of the wallet address (`/wallet/getPrivateKey`), used by the `attest-status`
+    /// command to sign the status with the oracle key. Not to be kept or called on a request path
+    pub fn wallet_secret_key(&self, address: &str) -> Result<SecretKey, NodeApiError> {
+        let body = json!({ "address": address });
+        let res = self
+            .node
+            .send_post_req("/wallet/getPrivateKey", body.to_string());
+        let json = self.node.parse_response_to_json(res)?;
+        let secret_hex = json
+            .as_str()
+            .ok_or_else(|| NodeError::BadRequest(json.dump()))?;
+        // the node prints the secret as a number, without the leading zeros
+        let bytes = base16::decode(&format!("{:0>64}", secret_hex))
+            .map_err(|_| NodeApiError::InvalidSecretKey)?;
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| NodeApiError::InvalidSecretKey)?;
+        SecretKey::dlog_from_bytes(&bytes).ok_or(NodeApiError::InvalidSecretKey)
     }
 
     /// Unlock wallet
@@ -119,7 +417,15 @@ impl NodeApi {
 
 impl WalletDataSource for NodeApi {
     fn get_unspent_wallet_boxes(&self) -> Result<Vec<ErgoBox>, WalletDataError> {
-        self.node.unspent_boxes().map_err(Into::into)
+        if ORACLE_CONFIG.zero_conf_chaining {
+            self.unspent_wallet_boxes_with_unconfirmed()
+                .map_err(Into::into)
+        } else if ORACLE_CONFIG.min_input_confirmations > 0 {
+            self.unspent_wallet_boxes_with_min_confirmations(ORACLE_CONFIG.min_input_confirmations)
+                .map_err(Into::into)
+        } else {
+            self.node.unspent_boxes().map_err(Into::into)
+        }
     }
 
     fn get_change_address(&self) -> Result<NetworkAddress, WalletDataError> {
@@ -137,4 +443,8 @@ pub enum NodeApiError {
     NoChangeAddressSetInNode,
     #[error("invalid scan id: {0}")]
     InvalidScanId(String),
+    #[error("failed to parse box: {0}")]
+    BoxParseError(String),
+    #[error("node returned an invalid secret key")]
+    InvalidSecretKey,
 }
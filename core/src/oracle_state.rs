@@ -5,6 +5,10 @@ use crate::box_kind::{
     PostedOracleBox, RefreshBoxError, RefreshBoxWrapper, RefreshBoxWrapperInputs, UpdateBoxError,
     UpdateBoxWrapper, UpdateBoxWrapperInputs, VoteBallotBoxWrapper,
 };
+use crate::contracts::oracle::OracleContract;
+use crate::contracts::pool::PoolContract;
+use crate::contracts::refresh::RefreshContract;
+use crate::contracts::Contracts;
 use crate::datapoint_source::DataPointSourceError;
 use crate::oracle_config::ORACLE_CONFIG;
 use crate::oracle_types::{BlockHeight, EpochCounter, Rate};
@@ -17,6 +21,7 @@ use crate::spec_token::{
 use crate::util::get_token_count;
 use anyhow::Error;
 
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
 use ergo_lib::ergotree_ir::mir::constant::TryExtractFromError;
 use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
 use thiserror::Error;
@@ -98,18 +103,21 @@ pub struct OraclePool {
     ballot_boxes_scan: BallotBoxesScan,
     update_box_scan: UpdateBoxScan,
     buyback_box_scan: Option<BuybackBoxScan>,
+    contracts: Contracts,
 }
 
 #[derive(Debug)]
 pub struct OracleDatapointScan {
     scan: GenericTokenScan<OracleTokenId>,
     oracle_box_wrapper_inputs: OracleBoxWrapperInputs,
+    oracle_contract: OracleContract,
 }
 
 #[derive(Debug)]
 pub struct LocalOracleDatapointScan {
     scan: GenericTokenScan<OracleTokenId>,
     oracle_box_wrapper_inputs: OracleBoxWrapperInputs,
+    oracle_contract: OracleContract,
     oracle_pk: ProveDlog,
 }
 
@@ -124,12 +132,14 @@ pub struct LocalBallotBoxScan {
 pub struct PoolBoxScan {
     scan: GenericTokenScan<PoolTokenId>,
     pool_box_wrapper_inputs: PoolBoxWrapperInputs,
+    pool_contract: PoolContract,
 }
 
 #[derive(Debug)]
 pub struct RefreshBoxScan {
     scan: GenericTokenScan<RefreshTokenId>,
     refresh_box_wrapper_inputs: RefreshBoxWrapperInputs,
+    refresh_contract: RefreshContract,
 }
 
 #[derive(Debug)]
@@ -176,15 +186,18 @@ impl OraclePool {
         let pool_config = &POOL_CONFIG;
         let oracle_config = &ORACLE_CONFIG;
         let oracle_pk = oracle_config.oracle_address_p2pk()?;
+        let contracts = Contracts::checked_load(pool_config)?;
 
         // Create all `Scan` structs for protocol
         let oracle_datapoint_scan = OracleDatapointScan {
             scan: node_scan_registry.oracle_token_scan.clone(),
             oracle_box_wrapper_inputs: pool_config.oracle_box_wrapper_inputs.clone(),
+            oracle_contract: contracts.oracle.clone(),
         };
         let local_oracle_datapoint_scan = LocalOracleDatapointScan {
             scan: node_scan_registry.oracle_token_scan.clone(),
             oracle_box_wrapper_inputs: pool_config.oracle_box_wrapper_inputs.clone(),
+            oracle_contract: contracts.oracle.clone(),
             oracle_pk: oracle_pk.clone(),
         };
 
@@ -202,11 +215,13 @@ impl OraclePool {
         let pool_box_scan = PoolBoxScan {
             scan: node_scan_registry.pool_token_scan.clone(),
             pool_box_wrapper_inputs: pool_config.pool_box_wrapper_inputs.clone(),
+            pool_contract: contracts.pool.clone(),
         };
 
         let refresh_box_scan = RefreshBoxScan {
             scan: node_scan_registry.refresh_token_scan.clone(),
             refresh_box_wrapper_inputs: pool_config.refresh_box_wrapper_inputs.clone(),
+            refresh_contract: contracts.refresh.clone(),
         };
 
         let update_box_scan = UpdateBoxScan {
@@ -234,9 +249,28 @@ impl OraclePool {
             refresh_box_scan,
             update_box_scan,
             buyback_box_scan,
+            contracts,
         })
     }
 
+    /// Pool box as found by the pool NFT scan, without checking it against the configured
+    /// pool contract (e.g. to detect that the pool was updated to a new contract)
+    pub fn get_raw_pool_box(&self) -> Result<Option<ErgoBox>> {
+        Ok(self.pool_box_scan.scan.get_box()?)
+    }
+
+    /// Refresh box as found by the refresh NFT scan, without checking it against the configured
+    /// refresh contract
+    pub fn get_raw_refresh_box(&self) -> Result<Option<ErgoBox>> {
+        Ok(self.refresh_box_scan.scan.get_box()?)
+    }
+
+    /// All boxes holding an oracle token, including the ones guarded by a contract other than
+    /// the configured oracle contract
+    pub fn get_raw_oracle_boxes(&self) -> Result<Vec<ErgoBox>> {
+        Ok(self.oracle_datapoint_scan.scan.get_boxes()?)
+    }
+
     /// Create a new `OraclePool` struct with loaded scans
     pub fn load() -> std::result::Result<OraclePool, Error> {
         let node_scan_registry = NodeScanRegistry::load()?;
@@ -274,6 +308,25 @@ impl OraclePool {
         Ok(epoch_state)
     }
 
+    /// Whether the pool box is included at least `min_confirmations` blocks deep
+    pub fn is_pool_box_confirmed(
+        &self,
+        pool_box: &dyn PoolBox,
+        min_confirmations: u32,
+    ) -> Result<bool> {
+        let pool_box_id = pool_box.get_box().box_id();
+        Ok(self
+            .pool_box_scan
+            .scan
+            .get_confirmed_boxes(min_confirmations)?
+            .iter()
+            .any(|b| b.box_id() == pool_box_id))
+    }
+
+    pub fn get_contracts(&self) -> &Contracts {
+        &self.contracts
+    }
+
     pub fn get_pool_box_source(&self) -> &dyn PoolBoxSource {
         &self.pool_box_scan as &dyn PoolBoxSource
     }
@@ -333,11 +386,12 @@ impl OraclePool {
 
 impl PoolBoxSource for PoolBoxScan {
     fn get_pool_box(&self) -> Result<PoolBoxWrapper> {
-        let box_wrapper = PoolBoxWrapper::new(
+        let box_wrapper = PoolBoxWrapper::new_with_known_contract(
             self.scan
                 .get_box()?
                 .ok_or(DataSourceError::PoolBoxNotFoundError)?,
             &self.pool_box_wrapper_inputs,
+            Some(&self.pool_contract),
         )?;
         Ok(box_wrapper)
     }
@@ -356,11 +410,12 @@ impl LocalBallotBoxSource for LocalBallotBoxScan {
 
 impl RefreshBoxSource for RefreshBoxScan {
     fn get_refresh_box(&self) -> Result<RefreshBoxWrapper> {
-        let box_wrapper = RefreshBoxWrapper::new(
+        let box_wrapper = RefreshBoxWrapper::new_with_known_contract(
             self.scan
                 .get_box()?
                 .ok_or(DataSourceError::RefreshBoxNotFoundError)?,
             &self.refresh_box_wrapper_inputs,
+            Some(&self.refresh_contract),
         )?;
         Ok(box_wrapper)
     }
@@ -372,7 +427,14 @@ impl LocalDatapointBoxSource for LocalOracleDatapointScan {
             .scan
             .get_boxes()?
             .into_iter()
-            .filter_map(|b| OracleBoxWrapper::new(b, &self.oracle_box_wrapper_inputs).ok())
+            .filter_map(|b| {
+                OracleBoxWrapper::new_with_known_contract(
+                    b,
+                    &self.oracle_box_wrapper_inputs,
+                    Some(&self.oracle_contract),
+                )
+                .ok()
+            })
             .find(|b| b.public_key() == *self.oracle_pk.h))
     }
 }
@@ -408,7 +470,14 @@ impl PostedDatapointBoxesSource for OracleDatapointScan {
             .scan
             .get_boxes()?
             .into_iter()
-            .filter_map(|b| OracleBoxWrapper::new(b, &self.oracle_box_wrapper_inputs).ok())
+            .filter_map(|b| {
+                OracleBoxWrapper::new_with_known_contract(
+                    b,
+                    &self.oracle_box_wrapper_inputs,
+                    Some(&self.oracle_contract),
+                )
+                .ok()
+            })
             .filter_map(|b| match b {
                 OracleBoxWrapper::Posted(p) => Some(p),
                 OracleBoxWrapper::Collected(_) => None,
@@ -424,7 +493,14 @@ impl CollectedDatapointBoxesSource for OracleDatapointScan {
             .scan
             .get_boxes()?
             .into_iter()
-            .filter_map(|b| OracleBoxWrapper::new(b, &self.oracle_box_wrapper_inputs).ok())
+            .filter_map(|b| {
+                OracleBoxWrapper::new_with_known_contract(
+                    b,
+                    &self.oracle_box_wrapper_inputs,
+                    Some(&self.oracle_contract),
+                )
+                .ok()
+            })
             .filter_map(|b| match b {
                 OracleBoxWrapper::Posted(_) => None,
                 OracleBoxWrapper::Collected(p) => Some(p),
@@ -21,6 +21,13 @@ impl std::ops::Sub<EpochLength> for BlockHeight {
     }
 }
 
+impl BlockHeight {
+    /// Height `epoch_length` blocks ago, 0 if the chain is shorter than that
+    pub fn saturating_sub(self, epoch_length: EpochLength) -> BlockHeight {
+        BlockHeight(self.0.saturating_sub(epoch_length.0 as u32))
+    }
+}
+
 impl std::ops::Add<EpochLength> for BlockHeight {
     type Output = BlockHeight;
     fn add(self, other: EpochLength) -> BlockHeight {
@@ -56,6 +63,13 @@ impl std::fmt::Display for BlockHeight {
     }
 }
 
+impl std::str::FromStr for BlockHeight {
+    type Err = std::num::ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(BlockHeight)
+    }
+}
+
 #[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Serialize, Deserialize, Copy, Clone, From)]
 #[serde(transparent)]
 pub struct EpochLength(pub i32);
@@ -70,10 +84,22 @@ impl From<EpochLength> for i64 {
 #[serde(transparent)]
 pub struct EpochCounter(pub u32);
 
+impl std::fmt::Display for EpochCounter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Serialize, Deserialize, Copy, Clone, From)]
 #[serde(transparent)]
 pub struct MinDatapoints(pub i32);
 
+impl std::fmt::Display for MinDatapoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl From<MinDatapoints> for i64 {
     fn from(min_datapoints: MinDatapoints) -> Self {
         min_datapoints.0 as i64
@@ -118,3 +144,52 @@ impl PartialEq<i64> for Rate {
         self.0 == *other
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_height_arithmetic() {
+        let height = BlockHeight(1000);
+        assert_eq!(height + EpochLength(30), BlockHeight(1030));
+        assert_eq!(height - EpochLength(30), BlockHeight(970));
+        assert_eq!(height + 5, BlockHeight(1005));
+        assert_eq!(height - 5, BlockHeight(995));
+        assert_eq!(i64::from(height), 1000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_block_height_underflow() {
+        let _ = BlockHeight(1) - 2;
+    }
+
+    #[test]
+    fn test_block_height_from_str() {
+        assert_eq!("1234".parse::<BlockHeight>(), Ok(BlockHeight(1234)));
+        assert!("-1".parse::<BlockHeight>().is_err());
+    }
+
+    #[test]
+    fn test_serde_transparent() {
+        assert_eq!(serde_json::to_string(&BlockHeight(7)).unwrap(), "7");
+        assert_eq!(
+            serde_json::from_str::<EpochCounter>("3").unwrap(),
+            EpochCounter(3)
+        );
+        assert_eq!(serde_json::to_string(&Rate::from(42)).unwrap(), "42");
+        assert_eq!(serde_json::to_string(&EpochLength(30)).unwrap(), "30");
+        assert_eq!(
+            serde_json::from_str::<MinDatapoints>("4").unwrap(),
+            MinDatapoints(4)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(BlockHeight(1000).to_string(), "1000");
+        assert_eq!(EpochCounter(5).to_string(), "5");
+        assert_eq!(MinDatapoints(4).to_string(), "4");
+    }
+}
@@ -0,0 +1,154 @@
+//! Detects that other oracles operate under parameters different from our pool config (e.g. after
+//! an update we missed), which otherwise shows up only as our datapoints never being collected.
+
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+
+use crate::alerts;
+use crate::alerts::AlertCategory;
+use crate::box_kind::OracleBoxWrapper;
+use crate::box_kind::OracleBoxWrapperInputs;
+use crate::box_kind::PoolBox;
+use crate::box_kind::PoolBoxWrapper;
+use crate::box_kind::RefreshBoxWrapper;
+use crate::error_code::ErrorCode;
+use crate::metrics::set_contract_params_mismatch;
+use crate::oracle_state::OraclePool;
+use crate::oracle_types::EpochCounter;
+use crate::pool_config::POOL_CONFIG;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParamMismatch {
+    /// Refresh box is not guarded by the refresh contract from our pool config
+    pub refresh_contract_mismatch: bool,
+    /// Number of oracle token boxes not guarded by the oracle contract from our pool config
+    pub foreign_oracle_boxes: usize,
+    /// Number of datapoints posted for an epoch after the one of the pool box
+    pub datapoints_ahead: usize,
+}
+
+impl ParamMismatch {
+    pub fn is_mismatch(&self) -> bool {
+        self.refresh_contract_mismatch || self.foreign_oracle_boxes > 0 || self.datapoints_ahead > 0
+    }
+}
+
+/// Checks the oracle token boxes against our oracle contract and the epoch counter of the pool box.
+/// Datapoints posted for a future epoch mean their oracles follow another pool box.
+pub fn find_oracle_boxes_mismatch(
+    oracle_boxes: Vec<ErgoBox>,
+    oracle_box_wrapper_inputs: &OracleBoxWrapperInputs,
+    pool_box_epoch_counter: Option<EpochCounter>,
+) -> ParamMismatch {
+    let mut mismatch = ParamMismatch::default();
+    for b in oracle_boxes {
+        match OracleBoxWrapper::new(b, oracle_box_wrapper_inputs) {
+            Ok(OracleBoxWrapper::Posted(posted_box)) => {
+                if pool_box_epoch_counter.map_or(false, |pool_epoch_counter| {
+                    posted_box.epoch_counter().0 > pool_epoch_counter.0
+                }) {
+                    mismatch.datapoints_ahead += 1;
+                }
+            }
+            Ok(OracleBoxWrapper::Collected(_)) => (),
+            Err(_) => mismatch.foreign_oracle_boxes += 1,
+        }
+    }
+    mismatch
+}
+
+/// Compares the on-chain refresh and oracle boxes against our pool config and raises an alert on
+/// mismatch. Runs every main loop iteration.
+pub fn check_contract_params(oracle_pool: &OraclePool) -> Result<ParamMismatch, anyhow::Error> {
+    let pool_box_epoch_counter = oracle_pool
+        .get_raw_pool_box()?
+        .and_then(|b| PoolBoxWrapper::new(b, &POOL_CONFIG.pool_box_wrapper_inputs).ok())
+        .map(|pool_box| pool_box.epoch_counter());
+    let mut mismatch = find_oracle_boxes_mismatch(
+        oracle_pool.get_raw_oracle_boxes()?,
+        &POOL_CONFIG.oracle_box_wrapper_inputs,
+        pool_box_epoch_counter,
+    );
+    mismatch.refresh_contract_mismatch = match oracle_pool.get_raw_refresh_box()? {
+        Some(refresh_box) => {
+            RefreshBoxWrapper::new(refresh_box, &POOL_CONFIG.refresh_box_wrapper_inputs).is_err()
+        }
+        None => false,
+    };
+    set_contract_params_mismatch(mismatch.is_mismatch());
+    alerts::update(
+        AlertCategory::ContractParamsMismatch,
+        mismatch.is_mismatch(),
+        "other oracles appear to operate under contract parameters different from the pool config",
+    );
+    if mismatch.is_mismatch() {
+        log::error!(
+            "[{}] CRITICAL: other oracles appear to operate under contract parameters different from \
+            the pool config ({:?}). The pool was probably updated, import the new pool config with \
+            import-pool-update command",
+            ErrorCode::ContractParamsMismatch.code(),
+            mismatch
+        );
+    }
+    Ok(mismatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+    use ergo_lib::ergotree_ir::sigma_protocol::dlog_group::EcPoint;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+    use crate::contracts::oracle::OracleContractParameters;
+    use crate::oracle_types::BlockHeight;
+    use crate::pool_commands::test_utils::generate_token_ids;
+    use crate::pool_commands::test_utils::make_datapoint_box;
+
+    #[test]
+    fn test_find_oracle_boxes_mismatch() {
+        let token_ids = generate_token_ids();
+        let inputs =
+            OracleBoxWrapperInputs::try_from((OracleContractParameters::default(), &token_ids))
+                .unwrap();
+        let make_box = |epoch_counter: i32| {
+            make_datapoint_box(
+                force_any_val::<EcPoint>(),
+                200,
+                EpochCounter(epoch_counter),
+                &token_ids,
+                BoxValue::SAFE_USER_MIN,
+                BlockHeight(100),
+                100,
+            )
+        };
+        let boxes = vec![make_box(4), make_box(5), make_box(5)];
+        assert_eq!(
+            find_oracle_boxes_mismatch(boxes.clone(), &inputs, Some(EpochCounter(5))),
+            ParamMismatch::default()
+        );
+        assert_eq!(
+            find_oracle_boxes_mismatch(boxes.clone(), &inputs, Some(EpochCounter(4))),
+            ParamMismatch {
+                datapoints_ahead: 2,
+                ..Default::default()
+            }
+        );
+        assert!(!find_oracle_boxes_mismatch(boxes.clone(), &inputs, None).is_mismatch());
+
+        // same oracle token, but the oracle contract is built for another pool NFT
+        let mut other_token_ids = generate_token_ids();
+        other_token_ids.oracle_token_id = token_ids.oracle_token_id.clone();
+        let other_inputs = OracleBoxWrapperInputs::try_from((
+            OracleContractParameters::default(),
+            &other_token_ids,
+        ))
+        .unwrap();
+        assert_eq!(
+            find_oracle_boxes_mismatch(boxes, &other_inputs, Some(EpochCounter(5))),
+            ParamMismatch {
+                foreign_oracle_boxes: 3,
+                ..Default::default()
+            }
+        );
+    }
+}
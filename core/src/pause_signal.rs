@@ -0,0 +1,149 @@
+//! On-chain kill switch of the pool admin. The signal box holds an NFT configured in the oracle
+//! config (`pause_signal`), and its presence (or the value of its R4) tells the oracles to stop
+//! posting, e.g. during a known compromise of a datapoint feed. The main loop checks the signal
+//! every iteration and raises the `paused` alert while it's set.
+
+use ergo_lib::ergo_chain_types::Digest32;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::ergo_box::NonMandatoryRegisterId;
+use ergo_lib::ergotree_ir::chain::token::TokenId;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::alerts;
+use crate::alerts::AlertCategory;
+use crate::metrics::set_paused;
+use crate::node_interface::node_api::NodeApi;
+use crate::oracle_config::ORACLE_CONFIG;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PauseSignalConfig {
+    /// NFT of the signal box, controlled by the pool admin
+    pub token_id: String,
+    /// Base16 serialized constant (as shown by the node and the explorer, e.g. `0101` for `true`)
+    /// the R4 of the signal box must hold to pause. Any box with the NFT pauses if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r4_value: Option<String>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PauseSignalError {
+    #[error("invalid token_id {0}")]
+    InvalidTokenId(String),
+    #[error("r4_value {0} is not base16")]
+    InvalidR4Value(String),
+}
+
+impl PauseSignalConfig {
+    pub fn validate(&self) -> Result<(), PauseSignalError> {
+        self.token_id()?;
+        if let Some(r4_value) = &self.r4_value {
+            base16::decode(r4_value)
+                .map_err(|_| PauseSignalError::InvalidR4Value(r4_value.clone()))?;
+        }
+        Ok(())
+    }
+
+    fn token_id(&self) -> Result<TokenId, PauseSignalError> {
+        Digest32::try_from(self.token_id.clone())
+            .map(Into::into)
+            .map_err(|_| PauseSignalError::InvalidTokenId(self.token_id.clone()))
+    }
+
+    /// Whether the unspent boxes holding the NFT signal the pause
+    pub fn is_signaled(&self, signal_boxes: &[ErgoBox]) -> bool {
+        signal_boxes.iter().any(|b| match &self.r4_value {
+            None => true,
+            Some(r4_value) => b
+                .get_register(NonMandatoryRegisterId::R4.into())
+                .and_then(|c| c.sigma_serialize_bytes().ok())
+                .map_or(false, |bytes| {
+                    base16::encode_lower(&bytes) == r4_value.to_lowercase()
+                }),
+        })
+    }
+}
+
+/// Whether the pool admin signals to stop posting. An error reading the signal box is logged and
+/// doesn't pause the oracle.
+pub fn check(node_api: &NodeApi) -> bool {
+    let Some(config) = &ORACLE_CONFIG.pause_signal else {
+        return false;
+    };
+    let signal_boxes = config
+        .token_id()
+        .map_err(anyhow::Error::from)
+        .and_then(|token_id| Ok(node_api.unspent_boxes_by_token_id(token_id)?));
+    let paused = match signal_boxes {
+        Ok(signal_boxes) => config.is_signaled(&signal_boxes),
+        Err(e) => {
+            log::warn!("Failed to check the pause signal box: {:?}", e);
+            return false;
+        }
+    };
+    set_paused(paused);
+    alerts::update(
+        AlertCategory::Paused,
+        paused,
+        &format!(
+            "pool admin signaled to stop posting (signal box with token {})",
+            config.token_id
+        ),
+    );
+    paused
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::chain::ergo_box::box_builder::ErgoBoxCandidateBuilder;
+    use ergo_lib::chain::transaction::TxId;
+    use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+    use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+    use ergo_lib::ergotree_ir::mir::constant::Constant;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+
+    fn signal_box(r4: Option<Constant>) -> ErgoBox {
+        let mut builder =
+            ErgoBoxCandidateBuilder::new(BoxValue::SAFE_USER_MIN, force_any_val::<ErgoTree>(), 1);
+        if let Some(r4) = r4 {
+            builder.set_register_value(NonMandatoryRegisterId::R4, r4);
+        }
+        ErgoBox::from_box_candidate(&builder.build().unwrap(), force_any_val::<TxId>(), 0).unwrap()
+    }
+
+    #[test]
+    fn test_is_signaled() {
+        let config = PauseSignalConfig {
+            token_id: "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1"
+                .to_string(),
+            r4_value: None,
+        };
+        assert!(config.validate().is_ok());
+        assert!(!config.is_signaled(&[]));
+        assert!(config.is_signaled(&[signal_box(None)]));
+
+        let config = PauseSignalConfig {
+            r4_value: Some("0101".to_string()),
+            ..config
+        };
+        assert!(!config.is_signaled(&[signal_box(None)]));
+        assert!(!config.is_signaled(&[signal_box(Some(false.into()))]));
+        assert!(config.is_signaled(&[signal_box(Some(true.into()))]));
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = PauseSignalConfig {
+            token_id: "not a token id".to_string(),
+            r4_value: None,
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(PauseSignalError::InvalidTokenId(_))
+        ));
+    }
+}
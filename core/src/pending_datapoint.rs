@@ -0,0 +1,166 @@
+//! Our last published datapoint box, kept until it's spent. Right after the datapoint tx is
+//! submitted the box may not show up in the scan results yet, and a refresh built in that window
+//! would leave our datapoint out and exclude us from the reward distribution. The refresh takes
+//! the known box (possibly still unconfirmed) in place of our box from the scans.
+
+use std::sync::Mutex;
+
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::Transaction;
+use ergo_lib::ergo_chain_types::EcPoint;
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+
+use crate::box_kind::OracleBoxWrapperInputs;
+use crate::box_kind::PostedOracleBox;
+use crate::node_interface::node_api::NodeApi;
+use crate::oracle_state::DataSourceError;
+use crate::oracle_state::PostedDatapointBoxesSource;
+
+lazy_static! {
+    static ref PENDING_DATAPOINT: Mutex<Option<ErgoBox>> = Mutex::new(None);
+}
+
+/// Keeps the datapoint box created by the submitted datapoint tx (its first output)
+pub fn record(tx: &UnsignedTransaction) {
+    let datapoint_box = ErgoBox::from_box_candidate(tx.output_candidates.first(), tx.id(), 0);
+    match datapoint_box {
+        Ok(b) => *PENDING_DATAPOINT.lock().unwrap() = Some(b),
+        Err(e) => log::warn!("Failed to keep the published datapoint box: {:?}", e),
+    }
+}
+
+/// Keeps the datapoint box created by the datapoint tx resubmitted from the retry queue
+pub fn record_resubmitted(tx: &Transaction) {
+    *PENDING_DATAPOINT.lock().unwrap() = Some(tx.outputs.first().clone());
+}
+
+/// Forgets the box once it's spent (e.g. by a refresh) or its tx is dropped from the mempool
+pub fn forget_if_spent(node_api: &NodeApi) {
+    let mut pending = PENDING_DATAPOINT.lock().unwrap();
+    if let Some(b) = pending.as_ref() {
+        match node_api.is_box_unspent(&b.box_id()) {
+            Ok(true) => (),
+            Ok(false) => *pending = None,
+            Err(e) => log::warn!("Failed to check the published datapoint box: {:?}", e),
+        }
+    }
+}
+
+/// Id of the published datapoint box, until it's spent
+pub fn box_id() -> Option<BoxId> {
+    PENDING_DATAPOINT
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(ErgoBox::box_id)
+}
+
+fn pending_posted_box(inputs: &OracleBoxWrapperInputs) -> Option<PostedOracleBox> {
+    let pending = PENDING_DATAPOINT.lock().unwrap().clone()?;
+    PostedOracleBox::new(pending, inputs).ok()
+}
+
+/// Posted datapoint boxes from the scans with our pending datapoint box in place of ours
+pub fn with_pending(
+    mut posted: Vec<PostedOracleBox>,
+    pending: Option<PostedOracleBox>,
+    my_oracle_pk: &EcPoint,
+) -> Vec<PostedOracleBox> {
+    if let Some(pending) = pending {
+        let pending_box_id = pending.get_box().box_id();
+        if !posted
+            .iter()
+            .any(|b| b.get_box().box_id() == pending_box_id)
+        {
+            log::info!(
+                "Our datapoint box {:?} is not in the scans yet, using the published one",
+                pending_box_id
+            );
+            // our box from the scans is spent by the pending datapoint tx
+            posted.retain(|b| &b.public_key() != my_oracle_pk);
+            posted.push(pending);
+        }
+    }
+    posted
+}
+
+/// Posted datapoint boxes source including our pending datapoint box
+pub struct WithPendingDatapoint<'a> {
+    pub scanned: &'a dyn PostedDatapointBoxesSource,
+    pub inputs: &'a OracleBoxWrapperInputs,
+    pub my_oracle_pk: EcPoint,
+}
+
+impl PostedDatapointBoxesSource for WithPendingDatapoint<'_> {
+    fn get_posted_datapoint_boxes(&self) -> Result<Vec<PostedOracleBox>, DataSourceError> {
+        Ok(with_pending(
+            self.scanned.get_posted_datapoint_boxes()?,
+            pending_posted_box(self.inputs),
+            &self.my_oracle_pk,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+    use crate::contracts::oracle::OracleContractParameters;
+    use crate::oracle_types::BlockHeight;
+    use crate::oracle_types::EpochCounter;
+    use crate::pool_commands::test_utils::generate_token_ids;
+    use crate::pool_commands::test_utils::make_datapoint_box;
+
+    #[test]
+    fn test_with_pending() {
+        let token_ids = generate_token_ids();
+        let inputs =
+            OracleBoxWrapperInputs::try_from((OracleContractParameters::default(), &token_ids))
+                .unwrap();
+        let my_pk = force_any_val::<EcPoint>();
+        let other_pk = force_any_val::<EcPoint>();
+        let posted_box = |pk: &EcPoint, datapoint: i64, epoch_counter: u32| {
+            PostedOracleBox::new(
+                make_datapoint_box(
+                    pk.clone(),
+                    datapoint,
+                    EpochCounter(epoch_counter),
+                    &token_ids,
+                    BoxValue::SAFE_USER_MIN,
+                    BlockHeight(100),
+                    100,
+                ),
+                &inputs,
+            )
+            .unwrap()
+        };
+        let other = posted_box(&other_pk, 200, 2);
+        let my_old = posted_box(&my_pk, 190, 1);
+        let my_pending = posted_box(&my_pk, 210, 2);
+
+        let merged = with_pending(
+            vec![other.clone(), my_old.clone()],
+            Some(my_pending.clone()),
+            &my_pk,
+        );
+        assert_eq!(merged.len(), 2);
+        assert!(merged.iter().any(|b| b.get_box() == my_pending.get_box()));
+        assert!(!merged.iter().any(|b| b.get_box() == my_old.get_box()));
+
+        // the scans caught up
+        let merged = with_pending(
+            vec![other.clone(), my_pending.clone()],
+            Some(my_pending.clone()),
+            &my_pk,
+        );
+        assert_eq!(merged.len(), 2);
+
+        let merged = with_pending(vec![other, my_old.clone()], None, &my_pk);
+        assert!(merged.iter().any(|b| b.get_box() == my_old.get_box()));
+    }
+}
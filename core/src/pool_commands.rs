@@ -9,6 +9,7 @@ use crate::datapoint_source::RuntimeDataPointSource;
 use crate::oracle_config::ORACLE_CONFIG;
 use crate::oracle_state::{DataSourceError, OraclePool};
 use crate::oracle_types::BlockHeight;
+use crate::pending_datapoint::WithPendingDatapoint;
 use crate::pool_config::POOL_CONFIG;
 use crate::wallet::WalletDataSource;
 
@@ -47,6 +48,8 @@ pub enum PoolCommandError {
     AddressEncoder(#[from] AddressEncoderError),
     #[error("Wrong oracle address type")]
     WrongOracleAddressType,
+    #[error("pool box has less than {0} confirmations")]
+    PoolBoxNotConfirmed(u32),
 }
 
 pub fn build_action(
@@ -60,6 +63,10 @@ pub fn build_action(
     let refresh_box_source = op.get_refresh_box_source();
     let datapoint_boxes_source = op.get_posted_datapoint_boxes_source();
     let pool_box = op.get_pool_box_source().get_pool_box()?;
+    let min_confirmations = ORACLE_CONFIG.min_input_confirmations;
+    if min_confirmations > 0 && !op.is_pool_box_confirmed(&pool_box, min_confirmations)? {
+        return Err(PoolCommandError::PoolBoxNotConfirmed(min_confirmations));
+    }
     let current_epoch_counter = pool_box.epoch_counter();
     let oracle_public_key =
         if let Address::P2Pk(public_key) = ORACLE_CONFIG.oracle_address.address() {
@@ -104,7 +111,11 @@ pub fn build_action(
         PoolCommand::Refresh => build_refresh_action(
             op.get_pool_box_source(),
             refresh_box_source,
-            datapoint_boxes_source,
+            &WithPendingDatapoint {
+                scanned: datapoint_boxes_source,
+                inputs: &POOL_CONFIG.oracle_box_wrapper_inputs,
+                my_oracle_pk: oracle_public_key.clone(),
+            },
             POOL_CONFIG
                 .refresh_box_wrapper_inputs
                 .contract_inputs
@@ -120,6 +131,8 @@ pub fn build_action(
             change_address,
             &oracle_public_key,
             op.get_buyback_box_source(),
+            ORACLE_CONFIG.buyback_top_up,
+            ORACLE_CONFIG.refresh_tx_limits,
         )
         .map_err(Into::into)
         .map(|(action, report)| (action.into(), report.into())),
@@ -6,7 +6,7 @@ use ergo_lib::{
     ergotree_interpreter::sigma_protocol::prover::ContextExtension,
     ergotree_ir::chain::{address::Address, token::TokenAmount},
     wallet::{
-        box_selector::{BoxSelector, BoxSelectorError, SimpleBoxSelector},
+        box_selector::{BoxSelector, BoxSelectorError},
         tx_builder::{TxBuilder, TxBuilderError},
     },
 };
@@ -17,12 +17,13 @@ use crate::{
     actions::PublishDataPointAction,
     box_kind::{make_oracle_box_candidate, OracleBox, OracleBoxWrapper, OracleBoxWrapperInputs},
     contracts::oracle::{OracleContract, OracleContractError},
+    datapoint_audit::DatapointSnapshot,
     datapoint_source::{DataPointSource, DataPointSourceError},
     oracle_config::BASE_FEE,
     oracle_state::DataSourceError,
     oracle_types::{BlockHeight, EpochCounter},
     spec_token::{OracleTokenId, RewardTokenId, SpecToken},
-    wallet::{WalletDataError, WalletDataSource},
+    wallet::{configured_box_selector, WalletDataError, WalletDataSource},
 };
 
 #[derive(Debug, Error)]
@@ -54,7 +55,7 @@ pub fn build_subsequent_publish_datapoint_action(
     new_epoch_counter: EpochCounter,
     reward_token_id: &RewardTokenId,
 ) -> Result<(PublishDataPointAction, PublishDatapointActionReport), PublishDatapointActionError> {
-    let new_datapoint = datapoint_source.get_datapoint()?;
+    let (new_datapoint, sources) = datapoint_source.get_datapoint_with_breakdown()?;
     let in_oracle_box = local_datapoint_box;
 
     let outbox_reward_tokens = if reward_token_id != &in_oracle_box.reward_token().token_id {
@@ -79,7 +80,7 @@ pub fn build_subsequent_publish_datapoint_action(
 
     let mut unspent_boxes = wallet.get_unspent_wallet_boxes()?;
     let tx_fee = *BASE_FEE;
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let target_tokens = vec![
         in_oracle_box.oracle_token().into(),
         outbox_reward_tokens.into(),
@@ -102,7 +103,7 @@ pub fn build_subsequent_publish_datapoint_action(
     tx_builder.set_context_extension(in_oracle_box.get_box().box_id(), ctx_ext);
     let tx = tx_builder.build()?;
     let report = PublishDatapointActionReport {
-        posted_datapoint: new_datapoint,
+        snapshot: DatapointSnapshot::new(height, new_datapoint, sources),
     };
     Ok((PublishDataPointAction { tx }, report))
 }
@@ -116,10 +117,10 @@ pub fn build_publish_first_datapoint_action(
     inputs: OracleBoxWrapperInputs,
     datapoint_source: &dyn DataPointSource,
 ) -> Result<(PublishDataPointAction, PublishDatapointActionReport), PublishDatapointActionError> {
-    let new_datapoint = datapoint_source.get_datapoint()?;
+    let (new_datapoint, sources) = datapoint_source.get_datapoint_with_breakdown()?;
     let unspent_boxes = wallet.get_unspent_wallet_boxes()?;
     let tx_fee = *BASE_FEE;
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let oracle_token: SpecToken<OracleTokenId> = SpecToken {
         token_id: inputs.oracle_token_id.clone(),
         amount: TokenAmount::try_from(1).unwrap(),
@@ -166,7 +167,7 @@ pub fn build_publish_first_datapoint_action(
     tx_builder.set_context_extension(box_id, ctx_ext);
     let tx = tx_builder.build()?;
     let report = PublishDatapointActionReport {
-        posted_datapoint: new_datapoint,
+        snapshot: DatapointSnapshot::new(height, new_datapoint, sources),
     };
     Ok((PublishDataPointAction { tx }, report))
 }
@@ -8,6 +8,8 @@ use crate::box_kind::PoolBoxWrapper;
 use crate::box_kind::PostedOracleBox;
 use crate::box_kind::RefreshBox;
 use crate::box_kind::RefreshBoxWrapper;
+use crate::oracle_config::BuybackTopUp;
+use crate::oracle_config::RefreshTxLimits;
 use crate::oracle_config::BASE_FEE;
 use crate::oracle_state::BuybackBoxSource;
 use crate::oracle_state::DataSourceError;
@@ -20,6 +22,7 @@ use crate::oracle_types::MinDatapoints;
 use crate::oracle_types::Rate;
 use crate::spec_token::RewardTokenId;
 use crate::spec_token::SpecToken;
+use crate::wallet::configured_box_selector;
 use crate::wallet::WalletDataError;
 use crate::wallet::WalletDataSource;
 
@@ -27,28 +30,40 @@ use ergo_lib::chain::ergo_box::box_builder::ErgoBoxCandidateBuilderError;
 use ergo_lib::ergo_chain_types::EcPoint;
 use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
 use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
 use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBoxCandidate;
 use ergo_lib::ergotree_ir::chain::token::TokenAmount;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializationError;
 use ergo_lib::wallet::box_selector::BoxSelection;
 use ergo_lib::wallet::box_selector::BoxSelector;
 use ergo_lib::wallet::box_selector::BoxSelectorError;
-use ergo_lib::wallet::box_selector::SimpleBoxSelector;
 use ergo_lib::wallet::tx_builder::TxBuilder;
 use ergo_lib::wallet::tx_builder::TxBuilderError;
 use thiserror::Error;
 
 use std::convert::TryInto;
 
+/// Execution cost of the refresh tx without the datapoints (interpreter init, pool, refresh and
+/// wallet boxes), a rough upper estimate based on the node cost parameters
+const REFRESH_TX_BASE_COST: u64 = 40_000;
+/// Execution cost added by a datapoint (oracle box input and output, its tokens and the
+/// contract checks)
+const REFRESH_TX_COST_PER_DATAPOINT: u64 = 8_000;
+/// Size of an input besides the spent box itself (box id, proof and context extension), the
+/// spent box size is used as the size of the corresponding output
+const REFRESH_TX_INPUT_SIZE: usize = 40;
+/// Size of the wallet inputs, the change and the fee outputs
+const REFRESH_TX_WALLET_BOXES_SIZE: usize = 1_000;
+
 #[derive(Debug, Error)]
 pub enum RefreshActionError {
     #[error("Refresh failed, not enough datapoints. The minimum number of datapoints within the deviation range: required minumum {expected}, found {found_num} from public keys {found_public_keys:?},")]
     FailedToReachConsensus {
         found_public_keys: Vec<EcPoint>,
         found_num: i32,
-        expected: i32,
+        expected: MinDatapoints,
     },
-    #[error("Not enough datapoints left during the removal of the outliers")]
-    NotEnoughDatapoints,
     #[error("data source error: {0}")]
     DataSourceError(#[from] DataSourceError),
     #[error("WalletData error: {0}")]
@@ -61,6 +76,16 @@ pub enum RefreshActionError {
     ErgoBoxCandidateBuilderError(#[from] ErgoBoxCandidateBuilderError),
     #[error("failed to found my own oracle box in the filtered posted oracle boxes")]
     MyOracleBoxNoFound,
+    #[error("sigma serialization error: {0}")]
+    SigmaSerialization(#[from] SigmaSerializationError),
+    #[error("not enough reward tokens in the pool box to pay the oracles: required {required}, found {found} (buyback box rewards included)")]
+    NotEnoughRewardTokens { required: u64, found: u64 },
+    #[error("refresh tx with the minimum {datapoints} datapoints exceeds the limits (estimated size {size} bytes, cost {cost})")]
+    TxOverLimits {
+        datapoints: usize,
+        size: usize,
+        cost: u64,
+    },
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -75,6 +100,8 @@ pub fn build_refresh_action(
     change_address: Address,
     my_oracle_pk: &EcPoint,
     buyback_box_source: Option<&dyn BuybackBoxSource>,
+    buyback_top_up: BuybackTopUp,
+    tx_limits: RefreshTxLimits,
 ) -> Result<(RefreshAction, RefreshActionReport), RefreshActionError> {
     let tx_fee = *BASE_FEE;
     let in_pool_box = pool_box_source.get_pool_box()?;
@@ -85,7 +112,7 @@ pub fn build_refresh_action(
         .get_posted_datapoint_boxes()?
         .into_iter()
         .filter(|b| {
-            b.get_box().creation_height > min_start_height.0
+            BlockHeight(b.get_box().creation_height) > min_start_height
                 && b.epoch_counter() == in_pool_box_epoch_id
         })
         .collect();
@@ -95,7 +122,7 @@ pub fn build_refresh_action(
     let valid_in_oracle_boxes_datapoints = filtered_oracle_boxes_by_rate(
         in_oracle_boxes.iter().map(|b| b.rate()).collect(),
         deviation_range,
-    )?;
+    );
     let valid_in_oracle_boxes = in_oracle_boxes
         .into_iter()
         .filter(|b| valid_in_oracle_boxes_datapoints.contains(&b.rate()))
@@ -103,13 +130,20 @@ pub fn build_refresh_action(
     if (valid_in_oracle_boxes.len() as i32) < min_data_points.0 {
         return Err(RefreshActionError::FailedToReachConsensus {
             found_num: valid_in_oracle_boxes.len() as i32,
-            expected: min_data_points.0,
+            expected: min_data_points,
             found_public_keys: valid_in_oracle_boxes
                 .iter()
                 .map(|b| b.public_key())
                 .collect(),
         });
     }
+    let valid_in_oracle_boxes = fit_refresh_tx_limits(
+        &[in_pool_box.get_box(), in_refresh_box.get_box()],
+        valid_in_oracle_boxes,
+        min_data_points,
+        my_oracle_pk,
+        tx_limits,
+    )?;
     let rate = calc_pool_rate(valid_in_oracle_boxes.iter().map(|b| b.rate()).collect());
     let reward_decrement = valid_in_oracle_boxes.len() as u64 * 2;
     let out_refresh_box = build_out_refresh_box(&in_refresh_box, height)?;
@@ -122,7 +156,7 @@ pub fn build_refresh_action(
         .flatten();
 
     let unspent_boxes = wallet.get_unspent_wallet_boxes()?;
-    let box_selector = SimpleBoxSelector::new();
+    let box_selector = configured_box_selector();
     let selection = box_selector.select(unspent_boxes, tx_fee, &[])?;
 
     let mut input_boxes = vec![
@@ -145,36 +179,42 @@ pub fn build_refresh_action(
         valid_in_oracle_boxes.len()
     );
 
-    let out_pool_box = build_out_pool_box(&in_pool_box, height, rate, reward_decrement, None)?;
-    let mut output_candidates = vec![out_pool_box, out_refresh_box];
+    let pool_reward = *in_pool_box.reward_token().amount.as_u64();
+    let mut buyback_reward = None;
+    let mut out_buyback_box = None;
     if let Some(buyback_box) = in_buyback_box_opt {
         log::debug!("Found buyback box id {:?}", buyback_box.get_box().box_id());
-        if let Some(buyback_reward_token) = buyback_box.reward_token() {
-            log::debug!(
-                "Found reward tokens in buyback box and including it in the tx. Amount: {:?}",
-                buyback_reward_token.amount
-            );
-            input_boxes.push(buyback_box.get_box().clone());
-            let out_pool_box_w_buyback_rewards = build_out_pool_box(
-                &in_pool_box,
-                height,
-                rate,
-                reward_decrement,
-                Some(
-                    (buyback_reward_token.amount.as_u64() - 1)
-                        .try_into()
-                        .unwrap(),
-                ),
-            )?;
-            let out_buyback_box = buyback_box.new_with_one_reward_token(height);
-            output_candidates.remove(0);
-            output_candidates.insert(0, out_pool_box_w_buyback_rewards);
-            // should be at index 2 (checked in the contract of the buyback input box)
-            output_candidates.push(out_buyback_box);
-        } else {
-            log::debug!("No reward tokens in buyback box");
+        // the buyback box keeps one reward token
+        let available = buyback_box
+            .reward_token()
+            .map(|t| *t.amount.as_u64() - 1)
+            .unwrap_or(0);
+        match buyback_top_up_amount(buyback_top_up, pool_reward, reward_decrement, available) {
+            Some(amount) => {
+                log::info!(
+                    "Moving {} reward tokens from the buyback box to the pool box (pool box has {})",
+                    amount,
+                    pool_reward
+                );
+                input_boxes.push(buyback_box.get_box().clone());
+                buyback_reward = Some(amount.try_into().unwrap());
+                out_buyback_box = Some(buyback_box.new_with_one_reward_token(height));
+            }
+            None => log::debug!(
+                "Not including the buyback box, it has {} reward tokens to move",
+                available
+            ),
         }
     };
+    let out_pool_box =
+        build_out_pool_box(&in_pool_box, height, rate, reward_decrement, buyback_reward)?;
+    let mut output_candidates = vec![out_pool_box, out_refresh_box];
+    if let Some(out_buyback_box) = out_buyback_box {
+        // should be at index 2 (checked in the contract of the buyback input box)
+        output_candidates.push(out_buyback_box);
+    }
+    // output index of the first oracle box
+    let oracle_boxes_out_offset = output_candidates.len() as i32;
     input_boxes.append(&mut valid_in_oracle_raw_boxes);
     input_boxes.append(selection.boxes.as_vec().clone().as_mut());
     output_candidates.append(&mut out_oracle_boxes);
@@ -200,7 +240,7 @@ pub fn build_refresh_action(
         .iter()
         .enumerate()
         .for_each(|(idx, ob)| {
-            let outindex = (idx as i32 + 2).into(); // first two output boxes are pool box and refresh box
+            let outindex = (idx as i32 + oracle_boxes_out_offset).into();
             let ob_ctx_ext = ContextExtension {
                 values: vec![(0, outindex)].into_iter().collect(),
             };
@@ -213,74 +253,128 @@ pub fn build_refresh_action(
             .map(|b| b.public_key())
             .collect(),
     };
-    Ok((RefreshAction { tx }, report))
+    Ok((RefreshAction { tx, input_boxes }, report))
+}
+
+/// Amount of reward tokens moved from the buyback box (having `available` tokens besides the one
+/// it keeps) to the pool box, `None` if the buyback box is not spent
+fn buyback_top_up_amount(
+    strategy: BuybackTopUp,
+    pool_reward: u64,
+    reward_decrement: u64,
+    available: u64,
+) -> Option<u64> {
+    if available == 0 {
+        return None;
+    }
+    match strategy {
+        BuybackTopUp::Always => Some(available),
+        BuybackTopUp::WhenLow if pool_reward <= reward_decrement => Some(available),
+        BuybackTopUp::WhenLow | BuybackTopUp::Never => None,
+    }
+}
+
+/// Estimated size and execution cost of the refresh tx spending the boxes and the datapoints
+fn estimate_refresh_tx(
+    boxes: &[&ErgoBox],
+    oracle_boxes: &[PostedOracleBox],
+) -> Result<(usize, u64), RefreshActionError> {
+    let mut size = REFRESH_TX_WALLET_BOXES_SIZE;
+    for b in boxes
+        .iter()
+        .copied()
+        .chain(oracle_boxes.iter().map(|ob| ob.get_box()))
+    {
+        size += REFRESH_TX_INPUT_SIZE + b.sigma_serialize_bytes()?.len();
+    }
+    let cost = REFRESH_TX_BASE_COST + REFRESH_TX_COST_PER_DATAPOINT * oracle_boxes.len() as u64;
+    Ok((size, cost))
+}
+
+/// Drops the datapoints farthest from the pool rate (never our own) until the estimated refresh
+/// tx fits into the node limits. The refresh can't be split into several txs, so it fails if
+/// the minimum number of datapoints doesn't fit.
+fn fit_refresh_tx_limits(
+    boxes: &[&ErgoBox],
+    mut oracle_boxes: Vec<PostedOracleBox>,
+    min_data_points: MinDatapoints,
+    my_oracle_pk: &EcPoint,
+    tx_limits: RefreshTxLimits,
+) -> Result<Vec<PostedOracleBox>, RefreshActionError> {
+    loop {
+        let (size, cost) = estimate_refresh_tx(boxes, &oracle_boxes)?;
+        if size <= tx_limits.max_size && cost <= tx_limits.max_cost {
+            return Ok(oracle_boxes);
+        }
+        if oracle_boxes.len() as i32 <= min_data_points.0 {
+            return Err(RefreshActionError::TxOverLimits {
+                datapoints: oracle_boxes.len(),
+                size,
+                cost,
+            });
+        }
+        let rate = i64::from(calc_pool_rate(
+            oracle_boxes.iter().map(|b| b.rate()).collect(),
+        ));
+        let farthest_index = oracle_boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| &b.public_key() != my_oracle_pk)
+            .max_by_key(|(_, b)| (i64::from(b.rate()) - rate).abs())
+            .map(|(index, _)| index)
+            .ok_or(RefreshActionError::MyOracleBoxNoFound)?;
+        let dropped = oracle_boxes.remove(farthest_index);
+        log::warn!(
+            "Refresh tx (estimated size {} bytes, cost {}) exceeds the limits, dropping the datapoint {} of {:?}",
+            size,
+            cost,
+            dropped.rate(),
+            dropped.public_key()
+        );
+    }
 }
 
-fn filtered_oracle_boxes_by_rate<T>(
+/// Returns the largest subset of datapoints where the min rate is within `deviation_range`
+/// percent of the max rate. If several subsets of the same size qualify, the one with the
+/// smallest spread is chosen.
+pub(crate) fn filtered_oracle_boxes_by_rate<T>(
     oracle_boxes: Vec<T>,
     deviation_range: u32,
-) -> Result<Vec<Rate>, RefreshActionError>
+) -> Vec<Rate>
 where
     T: Into<Rate>,
-    T: Clone,
 {
-    let oracle_boxes = oracle_boxes
+    let mut rates = oracle_boxes
         .into_iter()
         .map(|b| b.into())
-        .collect::<Vec<_>>();
-    if oracle_boxes.is_empty() {
-        return Ok(oracle_boxes);
-    }
-    let mut successful_boxes = oracle_boxes.clone();
-    // The min oracle box's rate must be within deviation_range(5%) of that of the max
-    while !deviation_check(deviation_range, successful_boxes.clone()) {
-        // Removing largest deviation outlier
-        successful_boxes = remove_largest_local_deviation_datapoint(successful_boxes)?;
+        .collect::<Vec<Rate>>();
+    rates.sort();
+    // (start, end) of the best window found so far in sorted rates, end is exclusive
+    let mut best = (0, 0);
+    let mut start = 0;
+    for end in 0..rates.len() {
+        // The min oracle box's rate must be within deviation_range(5%) of that of the max
+        while start < end && !deviation_check(deviation_range, rates[start], rates[end]) {
+            start += 1;
+        }
+        let best_len = best.1 - best.0;
+        let window_len = end + 1 - start;
+        if window_len > best_len
+            || (window_len == best_len
+                && rates[end] - rates[start] < rates[best.1 - 1] - rates[best.0])
+        {
+            best = (start, end + 1);
+        }
     }
-    // dbg!(&successful_boxes);
-    Ok(successful_boxes)
+    rates[best.0..best.1].to_vec()
 }
 
-fn deviation_check(max_deviation_range: u32, datapoint_boxes: Vec<Rate>) -> bool {
-    let min_datapoint = datapoint_boxes.clone().into_iter().min().unwrap();
-    let max_datapoint = datapoint_boxes.into_iter().max().unwrap();
+fn deviation_check(max_deviation_range: u32, min_datapoint: Rate, max_datapoint: Rate) -> bool {
     let deviation_delta = max_datapoint * (max_deviation_range as i64) / 100;
     max_datapoint - min_datapoint <= deviation_delta
 }
 
-/// Finds whether the max or the min value in a list of sorted Datapoint boxes
-/// deviates more compared to their adjacted datapoint, and then removes
-/// said datapoint which deviates further.
-fn remove_largest_local_deviation_datapoint(
-    datapoint_boxes: Vec<Rate>,
-) -> Result<Vec<Rate>, RefreshActionError> {
-    // Check if sufficient number of datapoint boxes to start removing
-    if datapoint_boxes.len() <= 2 {
-        Err(RefreshActionError::NotEnoughDatapoints)
-    } else {
-        let mean = datapoint_boxes.clone().into_iter().sum::<Rate>().as_f32()
-            / datapoint_boxes.len() as f32;
-        let min_datapoint = *datapoint_boxes.iter().min().unwrap();
-        let max_datapoint = *datapoint_boxes.iter().max().unwrap();
-        let front_deviation = max_datapoint.as_f32() - mean;
-        let back_deviation = mean - min_datapoint.as_f32();
-        if front_deviation >= back_deviation {
-            // Remove largest datapoint if front deviation is greater
-            Ok(datapoint_boxes
-                .into_iter()
-                .filter(|dp| *dp != max_datapoint)
-                .collect())
-        } else {
-            // Remove smallest datapoint if back deviation is greater
-            Ok(datapoint_boxes
-                .into_iter()
-                .filter(|dp| *dp != min_datapoint)
-                .collect())
-        }
-    }
-}
-
-fn calc_pool_rate(oracle_boxes_rates: Vec<Rate>) -> Rate {
+pub(crate) fn calc_pool_rate(oracle_boxes_rates: Vec<Rate>) -> Rate {
     let datapoints_sum: i64 = oracle_boxes_rates.clone().into_iter().map(i64::from).sum();
     (datapoints_sum / oracle_boxes_rates.len() as i64).into()
 }
@@ -294,15 +388,16 @@ fn build_out_pool_box(
 ) -> Result<ErgoBoxCandidate, RefreshActionError> {
     let new_epoch_counter = EpochCounter(in_pool_box.epoch_counter().0 + 1);
     let reward_token = in_pool_box.reward_token();
-    let decremented = reward_token
-        .amount
-        .checked_sub(&reward_decrement.try_into().unwrap())
-        .unwrap();
-    let new_reward_amount = if let Some(buyback_reward) = buyback_reward {
-        decremented.checked_add(&buyback_reward).unwrap()
-    } else {
-        decremented
-    };
+    let available =
+        *reward_token.amount.as_u64() + buyback_reward.map(|r| *r.as_u64()).unwrap_or(0);
+    // the pool box keeps at least one reward token
+    if available <= reward_decrement {
+        return Err(RefreshActionError::NotEnoughRewardTokens {
+            required: reward_decrement + 1,
+            found: available,
+        });
+    }
+    let new_reward_amount: TokenAmount = (available - reward_decrement).try_into().unwrap();
     let new_reward_token: SpecToken<RewardTokenId> = SpecToken {
         token_id: reward_token.token_id,
         amount: new_reward_amount,
@@ -589,6 +684,8 @@ mod tests {
             change_address.address(),
             &oracle_pub_key,
             None,
+            BuybackTopUp::Always,
+            RefreshTxLimits::default(),
         )
         .unwrap();
 
@@ -604,16 +701,87 @@ mod tests {
         ];
         possible_input_boxes.append(&mut in_oracle_boxes_raw);
         possible_input_boxes.append(&mut wallet_mock.get_unspent_wallet_boxes().unwrap());
+        assert_eq!(
+            action
+                .input_boxes
+                .iter()
+                .map(|b| b.box_id())
+                .collect::<Vec<_>>(),
+            action
+                .tx
+                .inputs
+                .iter()
+                .map(|i| i.box_id)
+                .collect::<Vec<_>>()
+        );
 
         let tx_context = TransactionContext::new(
             action.tx.clone(),
-            find_input_boxes(action.tx, possible_input_boxes),
+            find_input_boxes(action.tx, possible_input_boxes.clone()),
             Vec::new(),
         )
         .unwrap();
 
         let _signed_tx = wallet.sign_transaction(tx_context, &ctx, None).unwrap();
 
+        // room for 4 datapoints only
+        let tight_limits = RefreshTxLimits {
+            max_cost: REFRESH_TX_BASE_COST + 4 * REFRESH_TX_COST_PER_DATAPOINT,
+            ..RefreshTxLimits::default()
+        };
+        let (trimmed_action, trimmed_report) = build_refresh_action(
+            &pool_box_mock,
+            &refresh_box_mock,
+            &(DatapointSourceMock {
+                datapoints: in_oracle_boxes.clone(),
+            }),
+            5,
+            MinDatapoints(4),
+            &wallet_mock,
+            height,
+            change_address.address(),
+            &oracle_pub_key,
+            None,
+            BuybackTopUp::Always,
+            tight_limits,
+        )
+        .unwrap();
+        assert_eq!(trimmed_report.oracle_boxes_collected.len(), 4);
+        assert!(trimmed_report
+            .oracle_boxes_collected
+            .contains(&*oracle_pub_key));
+        let tx_context = TransactionContext::new(
+            trimmed_action.tx.clone(),
+            find_input_boxes(trimmed_action.tx, possible_input_boxes.clone()),
+            Vec::new(),
+        )
+        .unwrap();
+        let _signed_tx = wallet.sign_transaction(tx_context, &ctx, None).unwrap();
+
+        let over_limits_res = build_refresh_action(
+            &pool_box_mock,
+            &refresh_box_mock,
+            &(DatapointSourceMock {
+                datapoints: in_oracle_boxes.clone(),
+            }),
+            5,
+            MinDatapoints(4),
+            &wallet_mock,
+            height,
+            change_address.address(),
+            &oracle_pub_key,
+            None,
+            BuybackTopUp::Always,
+            RefreshTxLimits {
+                max_cost: REFRESH_TX_BASE_COST + 3 * REFRESH_TX_COST_PER_DATAPOINT,
+                ..RefreshTxLimits::default()
+            },
+        );
+        assert!(matches!(
+            over_limits_res.unwrap_err(),
+            RefreshActionError::TxOverLimits { datapoints: 4, .. }
+        ));
+
         let wrong_epoch_id_datapoints_mock = DatapointSourceMock {
             datapoints: make_datapoint_boxes(
                 oracle_pub_keys.clone(),
@@ -636,14 +804,16 @@ mod tests {
             change_address.address(),
             &oracle_pub_key,
             None,
+            BuybackTopUp::Always,
+            RefreshTxLimits::default(),
         );
         dbg!(&wrong_epoch_res);
         assert!(matches!(
             wrong_epoch_res.unwrap_err(),
             RefreshActionError::FailedToReachConsensus {
                 found_public_keys: _,
-                found_num: _,
-                expected: _,
+                found_num: 0,
+                expected: MinDatapoints(4),
             }
         ));
 
@@ -685,6 +855,8 @@ mod tests {
             change_address.address(),
             &oracle_pub_key,
             Some(&buyback_source),
+            BuybackTopUp::Always,
+            RefreshTxLimits::default(),
         )
         .unwrap();
 
@@ -748,30 +920,89 @@ mod tests {
                 .as_u64(),
             &189,
             "reward tokens should be added to the pool box"
+        );
+
+        // the pool box has enough reward tokens for the epoch
+        let (action_when_low, _) = build_refresh_action(
+            &pool_box_mock,
+            &refresh_box_mock,
+            &(DatapointSourceMock {
+                datapoints: in_oracle_boxes.clone(),
+            }),
+            5,
+            MinDatapoints(4),
+            &wallet_mock,
+            height,
+            change_address.address(),
+            &oracle_pub_key,
+            Some(&buyback_source),
+            BuybackTopUp::WhenLow,
+            RefreshTxLimits::default(),
         )
+        .unwrap();
+        let buyback_box_id = buyback_source.buyback_box.get_box().box_id();
+        assert!(
+            !action_when_low
+                .input_boxes
+                .iter()
+                .any(|b| b.box_id() == buyback_box_id),
+            "buyback box should not be spent"
+        );
+    }
+
+    #[test]
+    fn test_buyback_top_up_amount() {
+        assert_eq!(
+            buyback_top_up_amount(BuybackTopUp::Always, 200, 10, 99),
+            Some(99)
+        );
+        assert_eq!(
+            buyback_top_up_amount(BuybackTopUp::Always, 200, 10, 0),
+            None
+        );
+        assert_eq!(
+            buyback_top_up_amount(BuybackTopUp::WhenLow, 200, 10, 99),
+            None
+        );
+        assert_eq!(
+            buyback_top_up_amount(BuybackTopUp::WhenLow, 10, 10, 99),
+            Some(99)
+        );
+        assert_eq!(buyback_top_up_amount(BuybackTopUp::Never, 10, 10, 99), None);
     }
 
     #[test]
     fn test_oracle_deviation_check() {
         assert_eq!(
-            filtered_oracle_boxes_by_rate(vec![95, 96, 97, 98, 99, 200], 5).unwrap(),
+            filtered_oracle_boxes_by_rate(vec![95, 96, 97, 98, 99, 200], 5),
             vec![95, 96, 97, 98, 99]
         );
         assert_eq!(
-            filtered_oracle_boxes_by_rate(vec![70, 95, 96, 97, 98, 99, 200], 5).unwrap(),
+            filtered_oracle_boxes_by_rate(vec![70, 95, 96, 97, 98, 99, 200], 5),
             vec![95, 96, 97, 98, 99]
         );
         assert_eq!(
-            filtered_oracle_boxes_by_rate(vec![70, 95, 96, 97, 98, 99], 5).unwrap(),
+            filtered_oracle_boxes_by_rate(vec![70, 95, 96, 97, 98, 99], 5),
             vec![95, 96, 97, 98, 99]
         );
         assert_eq!(
-            filtered_oracle_boxes_by_rate(vec![70, 70, 95, 96, 97, 98, 99], 5).unwrap(),
+            filtered_oracle_boxes_by_rate(vec![70, 70, 95, 96, 97, 98, 99], 5),
             vec![95, 96, 97, 98, 99]
         );
         assert_eq!(
-            filtered_oracle_boxes_by_rate(vec![95, 96, 97, 98, 99, 200, 200], 5).unwrap(),
+            filtered_oracle_boxes_by_rate(vec![95, 96, 97, 98, 99, 200, 200], 5),
             vec![95, 96, 97, 98, 99]
         );
+        // greedy outlier removal from either end would leave only [96, 97] here
+        assert_eq!(
+            filtered_oracle_boxes_by_rate(vec![90, 96, 97, 102, 103, 106], 5),
+            vec![102, 103, 106]
+        );
+        // among the largest subsets the one with the smallest spread is chosen
+        assert_eq!(
+            filtered_oracle_boxes_by_rate(vec![90, 94, 95, 99], 5),
+            vec![94, 95]
+        );
+        assert!(filtered_oracle_boxes_by_rate(Vec::<Rate>::new(), 5).is_empty());
     }
 }
@@ -19,6 +19,8 @@ use crate::contracts::oracle::OracleContractError;
 use crate::contracts::pool::PoolContractError;
 use crate::contracts::refresh::RefreshContractError;
 use crate::contracts::update::UpdateContractError;
+use crate::oracle_config::resolve_config_file_path;
+use crate::oracle_config::user_config_dir;
 use crate::spec_token::BallotTokenId;
 use crate::spec_token::BuybackTokenId;
 use crate::spec_token::OracleTokenId;
@@ -28,7 +30,31 @@ use crate::spec_token::RewardTokenId;
 use crate::spec_token::UpdateTokenId;
 
 pub const DEFAULT_POOL_CONFIG_FILE_NAME: &str = "pool_config.yaml";
+/// Pool config file path, used if the path is not set on the command line
+pub const POOL_CONFIG_FILE_ENV_VAR: &str = "POOL_CONFIG_FILE";
 pub static POOL_CONFIG_FILE_PATH: sync::OnceCell<PathBuf> = sync::OnceCell::new();
+/// Pool config file path set on the command line, or in the `POOL_CONFIG_FILE` env var,
+/// or the first existing file in the working directory, next to the oracle config file and in the user's config dir.
+/// Falls back to the working directory if the file is not found anywhere.
+pub fn resolve_pool_config_file_path(
+    cmdline_path: Option<String>,
+    oracle_config_file_path: &Path,
+) -> PathBuf {
+    let search_paths: Vec<PathBuf> = std::iter::once(PathBuf::from(DEFAULT_POOL_CONFIG_FILE_NAME))
+        .chain(
+            oracle_config_file_path
+                .parent()
+                .map(|dir| dir.join(DEFAULT_POOL_CONFIG_FILE_NAME)),
+        )
+        .chain(user_config_dir().map(|dir| dir.join(DEFAULT_POOL_CONFIG_FILE_NAME)))
+        .collect();
+    resolve_config_file_path(
+        cmdline_path,
+        std::env::var(POOL_CONFIG_FILE_ENV_VAR).ok(),
+        &search_paths,
+    )
+}
+
 lazy_static! {
     pub static ref POOL_CONFIG: PoolConfig = PoolConfig::load().unwrap();
     pub static ref POOL_CONFIG_OPT: Result<PoolConfig, String> =
@@ -51,13 +77,24 @@ pub struct PoolConfig {
     pub buyback_token_id: Option<BuybackTokenId>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[allow(clippy::enum_variant_names)]
 pub enum PredefinedDataPointSource {
     NanoErgUsd,
     NanoErgXau,
     NanoAdaUsd,
     NanoErgBTC,
+    NanoErgSigUsd,
+    NanoErgSigRsv,
+    NanoErgRsn,
+    NanoErgJpy,
+    NanoErgXpt,
+    NanoErgXpd,
+    NanoErgBrent,
+    UsdCentSpx,
+    UsdCentBtc,
+    ErgoHashrate,
+    UsCpi,
 }
 
 /// Holds the token ids of every important token used by the oracle pool.
@@ -0,0 +1,134 @@
+//! Epoch counter of our last successful datapoint post. After a restart, or while the scans lag
+//! behind the mempool, the scans still show our previous datapoint box and the next iteration
+//! would post another datapoint in the same epoch, paying the fee twice. The epoch and the posted
+//! box are kept in the state store and checked before a publish action is built.
+
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::format_version::Format;
+use crate::oracle_types::EpochCounter;
+use crate::pool_commands::PoolCommand;
+
+pub const LAST_POSTED_EPOCH_FORMAT: Format = Format {
+    name: "last posted epoch",
+    // version 2 added the posted box id
+    migrations: &[|mut value| {
+        if let Some(object) = value.as_object_mut() {
+            object.insert("box_id".to_string(), Value::Null);
+        }
+        Ok(value)
+    }],
+};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastPostedEpoch {
+    epoch_id: Option<EpochCounter>,
+    /// Our datapoint box posted in the epoch
+    box_id: Option<BoxId>,
+}
+
+impl LastPostedEpoch {
+    pub fn epoch_id(&self) -> Option<EpochCounter> {
+        self.epoch_id
+    }
+
+    pub fn record(&mut self, epoch_id: EpochCounter, box_id: BoxId) {
+        self.epoch_id = Some(epoch_id);
+        self.box_id = Some(box_id);
+    }
+
+    /// Whether the command would post a second datapoint in the pool epoch while our datapoint
+    /// box posted in it is still unspent (in the scans or the mempool). A refresh and a republish
+    /// (of a stale datapoint or before the storage rent) are never duplicates. If our box is lost
+    /// (e.g. its tx is dropped from the mempool or orphaned by a reorg), we post again.
+    pub fn is_duplicate(
+        &self,
+        cmd: &PoolCommand,
+        pool_epoch_id: EpochCounter,
+        is_box_unspent: impl FnOnce(&BoxId) -> bool,
+    ) -> bool {
+        match cmd {
+            PoolCommand::Refresh | PoolCommand::PublishSubsequentDataPoint { republish: true } => {
+                false
+            }
+            PoolCommand::PublishFirstDataPoint
+            | PoolCommand::PublishSubsequentDataPoint { republish: false } => {
+                self.epoch_id == Some(pool_epoch_id)
+                    && self.box_id.as_ref().map_or(false, is_box_unspent)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+    use crate::state_store::InMemoryStateStore;
+    use crate::state_store::StateStore;
+
+    #[test]
+    fn test_is_duplicate() {
+        let mut last_posted = LastPostedEpoch::default();
+        let publish = PoolCommand::PublishSubsequentDataPoint { republish: false };
+        assert!(!last_posted.is_duplicate(&publish, EpochCounter(5), |_| true));
+        let box_id = force_any_val::<ErgoBox>().box_id();
+        last_posted.record(EpochCounter(5), box_id);
+        assert!(last_posted.is_duplicate(&publish, EpochCounter(5), |id| *id == box_id));
+        assert!(last_posted.is_duplicate(
+            &PoolCommand::PublishFirstDataPoint,
+            EpochCounter(5),
+            |_| true
+        ));
+        assert!(!last_posted.is_duplicate(&PoolCommand::Refresh, EpochCounter(5), |_| true));
+        // the next epoch
+        assert!(!last_posted.is_duplicate(&publish, EpochCounter(6), |_| true));
+    }
+
+    #[test]
+    fn test_republish_is_not_duplicate() {
+        let mut last_posted = LastPostedEpoch::default();
+        last_posted.record(EpochCounter(5), force_any_val::<ErgoBox>().box_id());
+        // e.g. our datapoint went stale while the pool stalled in the epoch
+        let republish = PoolCommand::PublishSubsequentDataPoint { republish: true };
+        assert!(!last_posted.is_duplicate(&republish, EpochCounter(5), |_| true));
+    }
+
+    #[test]
+    fn test_lost_box_is_not_duplicate() {
+        let mut last_posted = LastPostedEpoch::default();
+        last_posted.record(EpochCounter(5), force_any_val::<ErgoBox>().box_id());
+        // the posted tx was dropped from the mempool or orphaned by a reorg
+        let publish = PoolCommand::PublishSubsequentDataPoint { republish: false };
+        assert!(!last_posted.is_duplicate(&publish, EpochCounter(5), |_| false));
+        assert!(!last_posted.is_duplicate(
+            &PoolCommand::PublishFirstDataPoint,
+            EpochCounter(5),
+            |_| false
+        ));
+    }
+
+    #[test]
+    fn test_migrate_unversioned() {
+        let loaded: LastPostedEpoch = LAST_POSTED_EPOCH_FORMAT
+            .from_json_str(r#"{"epoch_id":7}"#)
+            .unwrap();
+        assert_eq!(loaded.epoch_id(), Some(EpochCounter(7)));
+        assert_eq!(loaded.box_id, None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let store: Box<dyn StateStore> = Box::<InMemoryStateStore>::default();
+        let mut last_posted = LastPostedEpoch::default();
+        last_posted.record(EpochCounter(42), force_any_val::<ErgoBox>().box_id());
+        store.save_last_posted_epoch(&last_posted).unwrap();
+        let loaded = store.load_last_posted_epoch().unwrap();
+        assert_eq!(loaded, last_posted);
+    }
+}
@@ -0,0 +1,127 @@
+//! Scheduler of the txs built by the main loop. Our txs spend the wallet boxes and the outputs of
+//! each other, and a long chain of them waiting in the mempool is both fragile (a dropped tx
+//! invalidates the rest) and heavy on the node API, so no more than `max_in_flight_txs` of our
+//! txs are waiting in the mempool at a time. An action built while the cap is reached is dropped
+//! and built again in a later iteration, on top of the new chain state.
+
+use ergo_lib::chain::transaction::TxId;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::node_interface::node_api::NodeApiError;
+
+const DEFAULT_MAX_IN_FLIGHT_TXS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PostingSchedulerConfig {
+    /// Max. number of our submitted txs not in a block yet
+    pub max_in_flight_txs: usize,
+}
+
+impl Default for PostingSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight_txs: DEFAULT_MAX_IN_FLIGHT_TXS,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PostingSchedulerConfigError {
+    #[error("max_in_flight_txs must be at least 1")]
+    NoInFlightTxs,
+}
+
+impl PostingSchedulerConfig {
+    pub fn validate(&self) -> Result<(), PostingSchedulerConfigError> {
+        if self.max_in_flight_txs == 0 {
+            return Err(PostingSchedulerConfigError::NoInFlightTxs);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PostingScheduler {
+    config: PostingSchedulerConfig,
+    /// Our submitted txs not in a block yet
+    in_flight_txs: Vec<TxId>,
+}
+
+impl PostingScheduler {
+    pub fn new(config: PostingSchedulerConfig) -> Self {
+        Self {
+            config,
+            in_flight_txs: Vec::new(),
+        }
+    }
+
+    /// Tracks our submitted tx until it leaves the mempool
+    pub fn track_tx(&mut self, tx_id: TxId) {
+        self.in_flight_txs.push(tx_id);
+    }
+
+    /// Forgets the tracked txs that left the mempool (got into a block or dropped). A tx whose
+    /// status can't be checked is kept.
+    pub fn forget_settled_txs(
+        &mut self,
+        is_in_mempool: impl Fn(&TxId) -> Result<bool, NodeApiError>,
+    ) {
+        self.in_flight_txs.retain(|tx_id| {
+            is_in_mempool(tx_id).unwrap_or_else(|e| {
+                log::warn!("Failed to check the status of our tx {}: {:?}", tx_id, e);
+                true
+            })
+        });
+    }
+
+    /// Whether another tx can be submitted, logs the deferred action if not
+    pub fn has_free_slot(&self) -> bool {
+        let has_free_slot = self.in_flight_txs.len() < self.config.max_in_flight_txs;
+        if !has_free_slot {
+            log::info!(
+                "Deferring the action, {} of our txs are in the mempool already",
+                self.in_flight_txs.len()
+            );
+        }
+        has_free_slot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+
+    fn scheduler(max_in_flight_txs: usize) -> PostingScheduler {
+        PostingScheduler::new(PostingSchedulerConfig { max_in_flight_txs })
+    }
+
+    #[test]
+    fn test_in_flight_cap() {
+        let mut scheduler = scheduler(2);
+        assert!(scheduler.has_free_slot());
+        scheduler.track_tx(force_any_val::<TxId>());
+        assert!(scheduler.has_free_slot());
+        scheduler.track_tx(force_any_val::<TxId>());
+        assert!(!scheduler.has_free_slot());
+        // the status of the tracked txs can't be checked
+        scheduler.forget_settled_txs(|_| Err(NodeApiError::NoChangeAddressSetInNode));
+        assert!(!scheduler.has_free_slot());
+        // the tracked txs got into a block
+        scheduler.forget_settled_txs(|_| Ok(false));
+        assert!(scheduler.has_free_slot());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(PostingSchedulerConfig::default().validate().is_ok());
+        assert_eq!(
+            scheduler(0).config.validate(),
+            Err(PostingSchedulerConfigError::NoInFlightTxs)
+        );
+    }
+}
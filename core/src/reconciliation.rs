@@ -0,0 +1,297 @@
+//! Compares the oracle state saved by the last run with the on-chain state on startup, so that
+//! what happened while the oracle was down is visible in the log.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::box_kind::OracleBox;
+use crate::box_kind::OracleBoxWrapper;
+use crate::box_kind::PoolBox;
+use crate::oracle_state::OraclePool;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+use crate::scans::SCANS_DIR_PATH;
+
+const SNAPSHOT_FILE_NAME: &str = "oracle_snapshot.json";
+
+/// Our view of the pool at some height
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleSnapshot {
+    pub height: BlockHeight,
+    pub pool_box_epoch_id: Option<EpochCounter>,
+    pub datapoint_box: Option<DatapointBoxSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatapointBoxSnapshot {
+    /// Epoch of the posted datapoint, `None` if the box was collected by a refresh
+    pub posted_epoch_id: Option<EpochCounter>,
+    pub creation_height: BlockHeight,
+    pub reward_tokens: u64,
+}
+
+pub fn snapshot_file_path() -> PathBuf {
+    SCANS_DIR_PATH.get().unwrap().join(SNAPSHOT_FILE_NAME)
+}
+
+pub fn take_snapshot(
+    oracle_pool: &OraclePool,
+    height: BlockHeight,
+) -> Result<OracleSnapshot, anyhow::Error> {
+    let pool_box_epoch_id = oracle_pool
+        .get_pool_box_source()
+        .get_pool_box()
+        .ok()
+        .map(|pool_box| pool_box.epoch_counter());
+    let datapoint_box = oracle_pool
+        .get_local_datapoint_box_source()
+        .get_local_oracle_datapoint_box()?
+        .map(|b| DatapointBoxSnapshot {
+            posted_epoch_id: match &b {
+                OracleBoxWrapper::Posted(posted_box) => Some(posted_box.epoch_counter()),
+                OracleBoxWrapper::Collected(_) => None,
+            },
+            creation_height: BlockHeight(b.get_box().creation_height),
+            reward_tokens: *b.reward_token().amount.as_u64(),
+        });
+    Ok(OracleSnapshot {
+        height,
+        pool_box_epoch_id,
+        datapoint_box,
+    })
+}
+
+pub fn save_snapshot(snapshot: &OracleSnapshot, path: &Path) -> Result<(), anyhow::Error> {
+    std::fs::write(path, serde_json::to_string_pretty(snapshot)?)?;
+    Ok(())
+}
+
+/// Saves the current state for the reconciliation on the next startup
+pub fn save_current_snapshot(
+    oracle_pool: &OraclePool,
+    height: BlockHeight,
+) -> Result<(), anyhow::Error> {
+    save_snapshot(&take_snapshot(oracle_pool, height)?, &snapshot_file_path())
+}
+
+pub fn load_snapshot(path: &Path) -> Result<Option<OracleSnapshot>, anyhow::Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let json_str = std::fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&json_str)?))
+}
+
+/// Lines of the reconciliation report and whether each is a discrepancy worth a warning
+pub fn reconcile(
+    previous: Option<&OracleSnapshot>,
+    current: &OracleSnapshot,
+) -> Vec<(bool, String)> {
+    let mut report = Vec::new();
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            report.push((
+                false,
+                "no saved state from a previous run, nothing to compare".to_string(),
+            ));
+            return report;
+        }
+    };
+    let blocks_down = current.height.0.saturating_sub(previous.height.0);
+    report.push((
+        false,
+        format!(
+            "last saved state is from height {}, {} blocks ago",
+            previous.height.0, blocks_down
+        ),
+    ));
+    match (previous.pool_box_epoch_id, current.pool_box_epoch_id) {
+        (Some(prev_epoch), Some(cur_epoch)) => report.push((
+            false,
+            format!(
+                "pool advanced {} epochs (epoch {} -> {})",
+                cur_epoch.0.saturating_sub(prev_epoch.0),
+                prev_epoch.0,
+                cur_epoch.0
+            ),
+        )),
+        (Some(prev_epoch), None) => report.push((
+            true,
+            format!(
+                "pool box was at epoch {} but is not found now",
+                prev_epoch.0
+            ),
+        )),
+        (None, Some(cur_epoch)) => {
+            report.push((false, format!("pool box found at epoch {}", cur_epoch.0)))
+        }
+        (None, None) => report.push((true, "pool box is not found".to_string())),
+    }
+    match (&previous.datapoint_box, &current.datapoint_box) {
+        (Some(prev_box), Some(cur_box)) => {
+            if let Some(posted_epoch_id) = cur_box.posted_epoch_id {
+                let is_current_epoch = current.pool_box_epoch_id == Some(posted_epoch_id);
+                report.push((
+                    !is_current_epoch,
+                    format!(
+                        "our last posted datapoint is for epoch {}{}",
+                        posted_epoch_id.0,
+                        if is_current_epoch {
+                            ""
+                        } else {
+                            ", not the current one"
+                        }
+                    ),
+                ));
+            } else {
+                report.push((
+                    false,
+                    "our datapoint box was collected by a refresh".to_string(),
+                ));
+            }
+            if cur_box.reward_tokens >= prev_box.reward_tokens {
+                report.push((
+                    false,
+                    format!(
+                        "our datapoint box earned {} reward tokens ({} -> {})",
+                        cur_box.reward_tokens - prev_box.reward_tokens,
+                        prev_box.reward_tokens,
+                        cur_box.reward_tokens
+                    ),
+                ));
+            } else {
+                report.push((
+                    true,
+                    format!(
+                        "our datapoint box reward tokens decreased ({} -> {}), were they extracted?",
+                        prev_box.reward_tokens, cur_box.reward_tokens
+                    ),
+                ));
+            }
+        }
+        (Some(_), None) => report.push((
+            true,
+            "our datapoint box is not found anymore, was the oracle token transferred?".to_string(),
+        )),
+        (None, Some(cur_box)) => report.push((
+            false,
+            format!(
+                "our datapoint box appeared with {} reward tokens",
+                cur_box.reward_tokens
+            ),
+        )),
+        (None, None) => report.push((true, "our datapoint box is not found".to_string())),
+    }
+    report
+}
+
+/// Logs the one-shot startup reconciliation report
+pub fn log_startup_reconciliation(oracle_pool: &OraclePool, height: BlockHeight) {
+    let path = snapshot_file_path();
+    let previous = match load_snapshot(&path) {
+        Ok(previous) => previous,
+        Err(e) => {
+            log::warn!(
+                "Startup reconciliation: failed to load saved state from {}: {:?}",
+                path.display(),
+                e
+            );
+            None
+        }
+    };
+    let current = match take_snapshot(oracle_pool, height) {
+        Ok(current) => current,
+        Err(e) => {
+            log::warn!(
+                "Startup reconciliation: failed to get on-chain state: {:?}",
+                e
+            );
+            return;
+        }
+    };
+    for (is_discrepancy, line) in reconcile(previous.as_ref(), &current) {
+        if is_discrepancy {
+            log::warn!("Startup reconciliation: {}", line);
+        } else {
+            log::info!("Startup reconciliation: {}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(
+        height: BlockHeight,
+        pool_epoch: EpochCounter,
+        posted_epoch: Option<EpochCounter>,
+        reward_tokens: u64,
+    ) -> OracleSnapshot {
+        OracleSnapshot {
+            height,
+            pool_box_epoch_id: Some(pool_epoch),
+            datapoint_box: Some(DatapointBoxSnapshot {
+                posted_epoch_id: posted_epoch,
+                creation_height: height,
+                reward_tokens,
+            }),
+        }
+    }
+
+    fn discrepancies(report: Vec<(bool, String)>) -> Vec<String> {
+        report
+            .into_iter()
+            .filter(|(is_discrepancy, _)| *is_discrepancy)
+            .map(|(_, line)| line)
+            .collect()
+    }
+
+    #[test]
+    fn test_reconcile_no_discrepancies() {
+        let previous = snapshot(
+            BlockHeight(1000),
+            EpochCounter(10),
+            Some(EpochCounter(10)),
+            5,
+        );
+        let current = snapshot(
+            BlockHeight(1100),
+            EpochCounter(13),
+            Some(EpochCounter(13)),
+            7,
+        );
+        assert!(discrepancies(reconcile(Some(&previous), &current)).is_empty());
+        assert!(discrepancies(reconcile(None, &current)).is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_discrepancies() {
+        let previous = snapshot(
+            BlockHeight(1000),
+            EpochCounter(10),
+            Some(EpochCounter(10)),
+            5,
+        );
+        // missed the current epoch and rewards were extracted
+        let current = snapshot(
+            BlockHeight(1100),
+            EpochCounter(13),
+            Some(EpochCounter(11)),
+            1,
+        );
+        assert_eq!(discrepancies(reconcile(Some(&previous), &current)).len(), 2);
+        let no_datapoint_box = OracleSnapshot {
+            datapoint_box: None,
+            ..current
+        };
+        assert_eq!(
+            discrepancies(reconcile(Some(&previous), &no_datapoint_box)).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Bundle of the last refresh transaction rejected by the node (e.g. failing the contract
+//! validation) with everything needed to reproduce the rejection: the unsigned transaction with
+//! the context extensions and the input boxes with their registers. It is saved in the data dir
+//! and served by the REST API (`/refreshDebugBundle`), so that the pool operators can share it
+//! and debug the rejection together.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use serde::Serialize;
+
+use crate::actions::RefreshAction;
+use crate::scans::SCANS_DIR_PATH;
+use crate::APP_VERSION;
+
+const BUNDLE_FILE_NAME: &str = "refresh_debug_bundle.json";
+
+#[derive(Debug, Serialize)]
+pub struct RefreshDebugBundle<'a> {
+    pub oracle_core_version: &'a str,
+    /// Rejection reason reported by the node
+    pub node_error: String,
+    pub unsigned_tx: &'a UnsignedTransaction,
+    /// Boxes spent by the transaction, in the order of its inputs
+    pub input_boxes: &'a [ErgoBox],
+}
+
+impl<'a> RefreshDebugBundle<'a> {
+    pub fn new(action: &'a RefreshAction, node_error: String) -> Self {
+        RefreshDebugBundle {
+            oracle_core_version: APP_VERSION,
+            node_error,
+            unsigned_tx: &action.tx,
+            input_boxes: &action.input_boxes,
+        }
+    }
+
+    pub fn file_path() -> PathBuf {
+        SCANS_DIR_PATH.get().unwrap().join(BUNDLE_FILE_NAME)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The last saved bundle, `None` if no refresh was rejected yet
+pub fn load_last_bundle(path: &Path) -> Result<Option<serde_json::Value>, anyhow::Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let json_str = std::fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&json_str)?))
+}
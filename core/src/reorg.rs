@@ -0,0 +1,198 @@
+//! Detects chain reorganizations by tracking the ids of the recent blocks, so that our
+//! transactions orphaned by a reorg are noticed instead of being assumed final. If the reorg
+//! orphans our datapoint tx, the datapoint is reposted.
+
+use std::collections::BTreeMap;
+
+use ergo_lib::chain::transaction::TxId;
+
+use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
+use crate::oracle_types::BlockHeight;
+use crate::pool_commands::PoolCommand;
+use crate::state::PoolState;
+
+/// Number of the recent blocks tracked, deeper reorgs are not detected
+pub const TRACKED_BLOCKS: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reorg {
+    /// Height of the first block replaced by the reorg
+    pub fork_height: BlockHeight,
+    /// Number of the replaced blocks among the tracked ones
+    pub depth: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct ReorgTracker {
+    /// Block ids by height
+    recent_blocks: BTreeMap<BlockHeight, String>,
+    /// Our submitted transactions with the height they were submitted at
+    submitted_txs: Vec<(TxId, BlockHeight)>,
+    /// Our last datapoint tx
+    datapoint_tx: Option<TxId>,
+    /// Our last datapoint tx was orphaned and not reposted yet
+    datapoint_orphaned: bool,
+}
+
+impl ReorgTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the tracked blocks with the latest headers and returns the reorg if any tracked
+    /// block was replaced
+    pub fn update_blocks(&mut self, latest_blocks: Vec<(BlockHeight, String)>) -> Option<Reorg> {
+        let replaced_heights: Vec<BlockHeight> = latest_blocks
+            .iter()
+            .filter(|(height, id)| {
+                self.recent_blocks
+                    .get(height)
+                    .map_or(false, |tracked_id| tracked_id != id)
+            })
+            .map(|(height, _)| *height)
+            .collect();
+        let reorg = replaced_heights.iter().min().map(|fork_height| Reorg {
+            fork_height: *fork_height,
+            depth: replaced_heights.len() as u32,
+        });
+        for (height, id) in latest_blocks {
+            self.recent_blocks.insert(height, id);
+        }
+        if let Some(max_height) = self.recent_blocks.keys().next_back().copied() {
+            let min_height = BlockHeight(max_height.0.saturating_sub(TRACKED_BLOCKS));
+            self.recent_blocks = self.recent_blocks.split_off(&min_height);
+        }
+        reorg
+    }
+
+    pub fn track_tx(&mut self, tx_id: TxId, height: BlockHeight) {
+        self.submitted_txs.push((tx_id, height));
+    }
+
+    /// Tracks our datapoint tx, which reposts the orphaned datapoint if there was one
+    pub fn track_datapoint_tx(&mut self, tx_id: TxId, height: BlockHeight) {
+        self.track_tx(tx_id, height);
+        self.datapoint_tx = Some(tx_id);
+        self.datapoint_orphaned = false;
+    }
+
+    /// Whether our datapoint was orphaned by a reorg and has to be reposted
+    pub fn needs_datapoint_repost(&self) -> bool {
+        self.datapoint_orphaned
+    }
+
+    fn forget_orphaned_txs(&mut self, orphaned_txs: &[TxId]) {
+        self.submitted_txs
+            .retain(|(tx_id, _)| !orphaned_txs.contains(tx_id));
+        if self
+            .datapoint_tx
+            .map_or(false, |tx_id| orphaned_txs.contains(&tx_id))
+        {
+            self.datapoint_tx = None;
+            self.datapoint_orphaned = true;
+        }
+    }
+
+    /// Drops the transactions submitted before the tracked blocks, they can't be orphaned by a
+    /// detected reorg anymore
+    fn forget_old_txs(&mut self, current_height: BlockHeight) {
+        let min_height = current_height.0.saturating_sub(TRACKED_BLOCKS);
+        self.submitted_txs
+            .retain(|(_, submitted_height)| submitted_height.0 >= min_height);
+    }
+
+    /// Checks the recent blocks and returns our transactions orphaned by a reorg (neither in the
+    /// current chain nor in the mempool)
+    pub fn check(
+        &mut self,
+        node_api: &NodeApi,
+        current_height: BlockHeight,
+    ) -> Result<Option<(Reorg, Vec<TxId>)>, NodeApiError> {
+        self.forget_old_txs(current_height);
+        let reorg = match self.update_blocks(node_api.last_block_ids(TRACKED_BLOCKS)?) {
+            Some(reorg) => reorg,
+            None => return Ok(None),
+        };
+        let mut orphaned_txs = Vec::new();
+        for (tx_id, _) in &self.submitted_txs {
+            if !node_api.is_tx_known(tx_id)? {
+                orphaned_txs.push(*tx_id);
+            }
+        }
+        self.forget_orphaned_txs(&orphaned_txs);
+        Ok(Some((reorg, orphaned_txs)))
+    }
+}
+
+/// Command reposting our orphaned datapoint in the current pool epoch. It's posted right away,
+/// regardless of the epoch timing and the participation strategy, as we took part in the epoch
+/// already.
+pub fn repost_command(pool_state: &PoolState) -> Option<PoolCommand> {
+    match pool_state {
+        PoolState::NeedsBootstrap => None,
+        PoolState::LiveEpoch(live_epoch) => Some(match live_epoch.local_datapoint_box_state {
+            None => PoolCommand::PublishFirstDataPoint,
+            Some(_) => PoolCommand::PublishSubsequentDataPoint { republish: true },
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+
+    fn blocks(heights: std::ops::RangeInclusive<u32>, fork: &str) -> Vec<(BlockHeight, String)> {
+        heights
+            .map(|h| (BlockHeight(h), format!("{}{}", fork, h)))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_reorg() {
+        let mut tracker = ReorgTracker::new();
+        assert_eq!(tracker.update_blocks(blocks(100..=129, "a")), None);
+        // chain advanced by two blocks
+        assert_eq!(tracker.update_blocks(blocks(102..=131, "a")), None);
+        assert_eq!(tracker.recent_blocks.len(), TRACKED_BLOCKS as usize + 1);
+    }
+
+    #[test]
+    fn test_orphaned_datapoint_needs_repost() {
+        let mut tracker = ReorgTracker::new();
+        let refresh_tx = force_any_val::<TxId>();
+        let datapoint_tx = force_any_val::<TxId>();
+        tracker.track_tx(refresh_tx, BlockHeight(100));
+        tracker.track_datapoint_tx(datapoint_tx, BlockHeight(101));
+        tracker.forget_orphaned_txs(&[refresh_tx]);
+        assert!(!tracker.needs_datapoint_repost());
+        tracker.forget_orphaned_txs(&[datapoint_tx]);
+        assert!(tracker.needs_datapoint_repost());
+        assert!(tracker.submitted_txs.is_empty());
+        // reposted
+        tracker.track_datapoint_tx(force_any_val::<TxId>(), BlockHeight(102));
+        assert!(!tracker.needs_datapoint_repost());
+    }
+
+    #[test]
+    fn test_reorg() {
+        let mut tracker = ReorgTracker::new();
+        tracker.update_blocks(blocks(100..=129, "a"));
+        // last three blocks are replaced and a new one is added
+        let mut latest_blocks = blocks(101..=126, "a");
+        latest_blocks.extend(blocks(127..=130, "b"));
+        assert_eq!(
+            tracker.update_blocks(latest_blocks),
+            Some(Reorg {
+                fork_height: BlockHeight(127),
+                depth: 3
+            })
+        );
+        // the new chain is tracked now
+        let mut latest_blocks = blocks(102..=126, "a");
+        latest_blocks.extend(blocks(127..=131, "b"));
+        assert_eq!(tracker.update_blocks(latest_blocks), None);
+    }
+}
@@ -0,0 +1,150 @@
+//! Safe mode entered when the node rejects our txs for a failed script several times in a row.
+//! Such rejections usually mean our pool config no longer matches the contracts on chain (or a
+//! bug in the tx building), so retrying every epoch only burns fees. In the safe mode the oracle
+//! stops submitting txs but keeps running, with the API reporting the degraded status and the
+//! `safe_mode` alert raised. The safe mode is left on restart, after the cause is fixed.
+
+use std::sync::Mutex;
+
+use ergo_lib::chain::transaction::TxId;
+use ergo_node_interface::node_interface::NodeError;
+use serde::Serialize;
+
+use crate::actions::ActionExecError;
+use crate::alerts;
+use crate::alerts::AlertCategory;
+use crate::metrics::set_safe_mode;
+use crate::node_interface::node_api::NodeApiError;
+use crate::oracle_config::ORACLE_CONFIG;
+use crate::oracle_types::BlockHeight;
+
+/// Default `safe_mode_rejection_threshold`
+const DEFAULT_REJECTION_THRESHOLD: u32 = 3;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SafeModeStatus {
+    pub active: bool,
+    /// Number of our txs in a row rejected for a failed script
+    pub consecutive_rejections: u32,
+    /// Height the safe mode was entered at
+    pub since_height: Option<BlockHeight>,
+    /// Node message of the last rejection
+    pub last_rejection: Option<String>,
+}
+
+impl SafeModeStatus {
+    /// Counts the submission outcome, returns `true` if the safe mode is entered by it. A
+    /// `threshold` of 0 disables the safe mode.
+    fn record(&mut self, rejection: Option<&str>, height: BlockHeight, threshold: u32) -> bool {
+        match rejection {
+            Some(msg) => {
+                self.consecutive_rejections += 1;
+                self.last_rejection = Some(msg.to_string());
+                let enter =
+                    !self.active && threshold > 0 && self.consecutive_rejections >= threshold;
+                if enter {
+                    self.active = true;
+                    self.since_height = Some(height);
+                }
+                enter
+            }
+            None => {
+                self.consecutive_rejections = 0;
+                false
+            }
+        }
+    }
+}
+
+lazy_static! {
+    static ref SAFE_MODE: Mutex<SafeModeStatus> = Mutex::new(SafeModeStatus::default());
+}
+
+pub fn status() -> SafeModeStatus {
+    SAFE_MODE.lock().unwrap().clone()
+}
+
+pub fn is_active() -> bool {
+    SAFE_MODE.lock().unwrap().active
+}
+
+/// Whether the node rejected the tx because one of its input scripts failed to validate
+pub fn is_script_failure(node_msg: &str) -> bool {
+    node_msg.contains("Scripts of all transaction inputs should pass verification")
+        || node_msg.contains("Script reduced to false")
+        || node_msg.contains("ReducedToFalse")
+}
+
+/// Node message of the script failure rejection in the error chain
+fn script_failure_message(e: &anyhow::Error) -> Option<&str> {
+    match e.downcast_ref::<ActionExecError>() {
+        Some(ActionExecError::NodeError(NodeApiError::NodeInterfaceError(
+            NodeError::BadRequest(msg),
+        ))) if is_script_failure(msg) => Some(msg),
+        _ => None,
+    }
+}
+
+/// Counts the outcome of our tx submission. A submitted tx resets the count, failures other than
+/// the script rejections don't change it.
+pub fn record_submission(result: &Result<Option<TxId>, anyhow::Error>, height: BlockHeight) {
+    let rejection = match result {
+        Ok(Some(_)) => None,
+        Ok(None) => return,
+        Err(e) => match script_failure_message(e) {
+            Some(msg) => Some(msg),
+            None => return,
+        },
+    };
+    let threshold = ORACLE_CONFIG
+        .safe_mode_rejection_threshold
+        .unwrap_or(DEFAULT_REJECTION_THRESHOLD);
+    let mut safe_mode = SAFE_MODE.lock().unwrap();
+    if safe_mode.record(rejection, height, threshold) {
+        let message = format!(
+            "{} txs in a row rejected by the node for a failed script, stopped submitting txs \
+            until restart. Last rejection: {}",
+            safe_mode.consecutive_rejections,
+            safe_mode.last_rejection.as_deref().unwrap_or_default()
+        );
+        log::error!("Entering safe mode: {}", message);
+        set_safe_mode(true);
+        alerts::update(AlertCategory::SafeMode, true, &message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut status = SafeModeStatus::default();
+        let msg = "Scripts of all transaction inputs should pass verification";
+        assert!(!status.record(Some(msg), BlockHeight(100), 2));
+        // a submitted tx resets the count
+        assert!(!status.record(None, BlockHeight(101), 2));
+        assert_eq!(status.consecutive_rejections, 0);
+        assert!(!status.record(Some(msg), BlockHeight(102), 2));
+        assert!(status.record(Some(msg), BlockHeight(103), 2));
+        assert!(status.active);
+        assert_eq!(status.since_height, Some(BlockHeight(103)));
+        assert!(!status.record(Some(msg), BlockHeight(104), 2));
+        assert_eq!(status.since_height, Some(BlockHeight(103)));
+
+        let mut disabled = SafeModeStatus::default();
+        for height in 0..10 {
+            assert!(!disabled.record(Some(msg), BlockHeight(height), 0));
+        }
+        assert!(!disabled.active);
+    }
+
+    #[test]
+    fn test_is_script_failure() {
+        assert!(is_script_failure(
+            "Malformed transaction: Scripts of all transaction inputs should pass verification. \
+            7ac2...: #0 => Success((false,1036))"
+        ));
+        assert!(!is_script_failure("Double spending attempt"));
+    }
+}
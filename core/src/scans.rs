@@ -46,11 +46,25 @@ pub trait ScanGetBoxes: NodeScanId {
             ORACLE_SECRETS.wallet_password.clone(),
             &ORACLE_CONFIG.node_url,
         );
-        let boxes = node_api.node.scan_boxes(self.scan_id())?;
+        let boxes = if ORACLE_CONFIG.zero_conf_chaining {
+            node_api.scan_boxes_with_unconfirmed(self.scan_id())?
+        } else {
+            node_api.node.scan_boxes(self.scan_id())?
+        };
         Ok(boxes)
     }
 
     fn get_box(&self) -> Result<Option<ErgoBox>, ScanError> {
         Ok(self.get_boxes()?.first().cloned())
     }
+
+    /// Boxes included at least `min_confirmations` blocks deep
+    fn get_confirmed_boxes(&self, min_confirmations: u32) -> Result<Vec<ErgoBox>, ScanError> {
+        let node_api = NodeApi::new(
+            ORACLE_SECRETS.node_api_key.clone(),
+            ORACLE_SECRETS.wallet_password.clone(),
+            &ORACLE_CONFIG.node_url,
+        );
+        Ok(node_api.scan_boxes_with_min_confirmations(self.scan_id(), min_confirmations)?)
+    }
 }
@@ -7,6 +7,7 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_json::json;
 
+use super::scan_name_prefix;
 use super::NodeScanId;
 use super::ScanError;
 use super::ScanGetBoxes;
@@ -26,8 +27,12 @@ impl<T: TokenIdKind + Clone> GenericTokenScan<T> {
         }
     }
 
-    pub fn register(node_api: &NodeApi, token_id: &T) -> Result<Self, ScanError> {
-        let scan_name = format!("token scan for  {}", String::from(token_id.token_id()));
+    pub fn register(node_api: &NodeApi, token_id: &T, instance: &str) -> Result<Self, ScanError> {
+        let scan_name = format!(
+            "{} token scan for {}",
+            scan_name_prefix(instance),
+            String::from(token_id.token_id())
+        );
         let id = node_api.register_scan(scan_name, Self::tracking_rule(token_id))?;
         Ok(GenericTokenScan::<T> {
             id,
@@ -3,16 +3,19 @@ use std::path::PathBuf;
 use crate::node_interface::node_api::NodeApi;
 use crate::node_interface::node_api::NodeApiError;
 use crate::pool_config::PoolConfig;
+use crate::pool_config::POOL_CONFIG;
 use crate::spec_token::BallotTokenId;
 use crate::spec_token::BuybackTokenId;
 use crate::spec_token::OracleTokenId;
 use crate::spec_token::PoolTokenId;
 use crate::spec_token::RefreshTokenId;
+use crate::spec_token::TokenIdKind;
 use crate::spec_token::UpdateTokenId;
 
 use crate::oracle_config::ORACLE_CONFIG;
 use ::serde::Deserialize;
 use ::serde::Serialize;
+use ergo_node_interface::ScanId;
 use once_cell::sync;
 use thiserror::Error;
 
@@ -26,6 +29,20 @@ pub fn get_scans_file_path() -> PathBuf {
     SCANS_DIR_PATH.get().unwrap().join("scanIDs.json")
 }
 
+/// Name of this oracle-core instance in the node scan names, `scan_instance_name` from the oracle
+/// config or the pool NFT id
+pub fn scan_instance_name(pool_config: &PoolConfig) -> String {
+    ORACLE_CONFIG
+        .scan_instance_name
+        .clone()
+        .unwrap_or_else(|| String::from(pool_config.token_ids.pool_nft_token_id.token_id()))
+}
+
+/// Names of the node scans registered by the instance start with it
+pub fn scan_name_prefix(instance: &str) -> String {
+    format!("oracle-core[{}]", instance)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct NodeScanRegistry {
     #[serde(rename = "All Datapoints Scan")]
@@ -39,6 +56,10 @@ pub struct NodeScanRegistry {
     #[serde(rename = "Update Box Scan")]
     pub update_token_scan: GenericTokenScan<UpdateTokenId>,
     pub buyback_token_scan: Option<GenericTokenScan<BuybackTokenId>>,
+    /// Instance that registered the scans, `None` for the scans registered before the scan names
+    /// were namespaced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
 }
 
 impl NodeScanRegistry {
@@ -63,19 +84,25 @@ impl NodeScanRegistry {
         pool_config: &PoolConfig,
     ) -> std::result::Result<Self, anyhow::Error> {
         log::info!("Registering UTXO-Set Scans");
+        let instance = scan_instance_name(pool_config);
+        let token_ids = &pool_config.token_ids;
         let oracle_token_scan =
-            GenericTokenScan::register(node_api, &pool_config.token_ids.oracle_token_id)?;
+            GenericTokenScan::register(node_api, &token_ids.oracle_token_id, &instance)?;
         let pool_token_scan =
-            GenericTokenScan::register(node_api, &pool_config.token_ids.pool_nft_token_id)?;
+            GenericTokenScan::register(node_api, &token_ids.pool_nft_token_id, &instance)?;
         let ballot_token_scan =
-            GenericTokenScan::register(node_api, &pool_config.token_ids.ballot_token_id)?;
+            GenericTokenScan::register(node_api, &token_ids.ballot_token_id, &instance)?;
         let refresh_token_scan =
-            GenericTokenScan::register(node_api, &pool_config.token_ids.refresh_nft_token_id)?;
+            GenericTokenScan::register(node_api, &token_ids.refresh_nft_token_id, &instance)?;
         let update_token_scan =
-            GenericTokenScan::register(node_api, &pool_config.token_ids.update_nft_token_id)?;
+            GenericTokenScan::register(node_api, &token_ids.update_nft_token_id, &instance)?;
         let buyback_token_scan =
             if let Some(buyback_token_id) = pool_config.buyback_token_id.clone() {
-                Some(GenericTokenScan::register(node_api, &buyback_token_id)?)
+                Some(GenericTokenScan::register(
+                    node_api,
+                    &buyback_token_id,
+                    &instance,
+                )?)
             } else {
                 None
             };
@@ -86,6 +113,7 @@ impl NodeScanRegistry {
             refresh_token_scan,
             update_token_scan,
             buyback_token_scan,
+            instance: Some(instance),
         };
         registry.save_to_json_file(&get_scans_file_path())?;
         node_api.rescan_from_height(ORACLE_CONFIG.scan_start_height)?;
@@ -98,25 +126,68 @@ impl NodeScanRegistry {
         let json_str =
             std::fs::read_to_string(path).map_err(|e| NodeScanRegistryError::Io(e.to_string()))?;
         let registry = Self::load_from_json_str(&json_str)?;
+        registry.check_instance(&scan_instance_name(&POOL_CONFIG))?;
         Ok(registry)
     }
 
+    /// Fails if the scans were registered by another instance (e.g. its data dir is shared with us)
+    pub fn check_instance(&self, instance: &str) -> Result<(), NodeScanRegistryError> {
+        match &self.instance {
+            Some(registry_instance) if registry_instance != instance => {
+                Err(NodeScanRegistryError::ForeignInstance {
+                    expected: instance.to_string(),
+                    found: registry_instance.clone(),
+                })
+            }
+            Some(_) | None => Ok(()),
+        }
+    }
+
+    /// Deregisters the scan unless the node knows it under the name of another instance
+    fn deregister_owned_scan(
+        &self,
+        node_api: &NodeApi,
+        node_scans: &[(ScanId, String)],
+        scan_id: ScanId,
+    ) -> Result<(), NodeApiError> {
+        if let Some(instance) = &self.instance {
+            let owned = node_scans
+                .iter()
+                .any(|(id, name)| *id == scan_id && name.starts_with(&scan_name_prefix(instance)));
+            if !owned {
+                log::warn!(
+                    "Scan {} is not registered by this instance ({}), not deregistering it",
+                    scan_id,
+                    instance
+                );
+                return Ok(());
+            }
+        }
+        node_api.deregister_scan(scan_id)?;
+        Ok(())
+    }
+
     pub fn ensure_node_registered_scans(
         node_api: &NodeApi,
         pool_config: &PoolConfig,
     ) -> std::result::Result<Self, anyhow::Error> {
         let path = get_scans_file_path();
         log::info!("Loading scan IDs from {}", path.display());
+        let instance = scan_instance_name(pool_config);
         let registry = if let Ok(json_str) = std::fs::read_to_string(path) {
             let loaded_registry = Self::load_from_json_str(&json_str)?;
+            loaded_registry.check_instance(&instance)?;
             if let Some(pool_config_buyback_token_id) = pool_config.buyback_token_id.clone() {
                 log::info!("Buyback token is found in pool config, checking if scan is registered");
                 if loaded_registry.buyback_token_scan.is_some() {
                     log::info!("Buyback token scan is already registered");
                     loaded_registry
                 } else {
-                    let buyback_token_scan =
-                        GenericTokenScan::register(node_api, &pool_config_buyback_token_id)?;
+                    let buyback_token_scan = GenericTokenScan::register(
+                        node_api,
+                        &pool_config_buyback_token_id,
+                        &instance,
+                    )?;
                     node_api.rescan_from_height(ORACLE_CONFIG.scan_start_height)?;
                     let new_registry = Self {
                         buyback_token_scan: Some(buyback_token_scan),
@@ -130,7 +201,11 @@ impl NodeScanRegistry {
                 if let Some(buy_back_token_scan) = loaded_registry.buyback_token_scan.clone() {
                     log::info!("No buyback token in the pool config but scan is registered. Deregistering it");
                     // but registry has buyback token scan, deregister it
-                    node_api.deregister_scan(buy_back_token_scan.scan_id())?;
+                    loaded_registry.deregister_owned_scan(
+                        node_api,
+                        &node_api.list_scans()?,
+                        buy_back_token_scan.scan_id(),
+                    )?;
                     let new_registry = Self {
                         buyback_token_scan: None,
                         ..loaded_registry
@@ -150,13 +225,19 @@ impl NodeScanRegistry {
     }
 
     pub fn deregister_all_scans(self, node_api: &NodeApi) -> Result<(), NodeApiError> {
-        node_api.deregister_scan(self.oracle_token_scan.scan_id())?;
-        node_api.deregister_scan(self.pool_token_scan.scan_id())?;
-        node_api.deregister_scan(self.ballot_token_scan.scan_id())?;
-        node_api.deregister_scan(self.refresh_token_scan.scan_id())?;
-        node_api.deregister_scan(self.update_token_scan.scan_id())?;
-        if let Some(buy_back_token_scan) = self.buyback_token_scan {
-            node_api.deregister_scan(buy_back_token_scan.scan_id())?;
+        let node_scans = node_api.list_scans()?;
+        let mut scan_ids = vec![
+            self.oracle_token_scan.scan_id(),
+            self.pool_token_scan.scan_id(),
+            self.ballot_token_scan.scan_id(),
+            self.refresh_token_scan.scan_id(),
+            self.update_token_scan.scan_id(),
+        ];
+        if let Some(buy_back_token_scan) = &self.buyback_token_scan {
+            scan_ids.push(buy_back_token_scan.scan_id());
+        }
+        for scan_id in scan_ids {
+            self.deregister_owned_scan(node_api, &node_scans, scan_id)?;
         }
         Ok(())
     }
@@ -191,13 +272,14 @@ pub enum NodeScanRegistryError {
     Parse(String),
     #[error("Error reading/writing file: {0}")]
     Io(String),
+    #[error("Scans file belongs to the instance {found}, not {expected}. Use a separate data dir for each instance")]
+    ForeignInstance { expected: String, found: String },
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::scans::NodeScanId;
-    use ergo_node_interface::ScanId;
     use expect_test::expect;
     use pretty_assertions::assert_eq;
 
@@ -230,6 +312,7 @@ mod tests {
             refresh_token_scan: GenericTokenScan::new(ScanId::from(188)),
             update_token_scan: GenericTokenScan::new(ScanId::from(186)),
             buyback_token_scan: None,
+            instance: None,
         };
         let json_str = registry.save_to_json_str();
         expect_json(
@@ -255,6 +338,7 @@ mod tests {
             refresh_token_scan: GenericTokenScan::new(ScanId::from(188)),
             update_token_scan: GenericTokenScan::new(ScanId::from(186)),
             buyback_token_scan: None,
+            instance: None,
         };
         let json_str = registry.save_to_json_str();
         let registry2 = NodeScanRegistry::load_from_json_str(&json_str).unwrap();
@@ -270,9 +354,35 @@ mod tests {
             refresh_token_scan: GenericTokenScan::new(ScanId::from(188)),
             update_token_scan: GenericTokenScan::new(ScanId::from(186)),
             buyback_token_scan: Some(GenericTokenScan::new(ScanId::from(192))),
+            instance: Some("usd".to_string()),
         };
         let json_str = registry.save_to_json_str();
         let registry2 = NodeScanRegistry::load_from_json_str(&json_str).unwrap();
         assert_eq!(registry, registry2);
     }
+
+    #[test]
+    fn check_instance() {
+        let mut registry = NodeScanRegistry::load_from_json_str(
+            r#"{
+            "All Datapoints Scan": "185",
+            "Pool Box Scan": "187",
+            "Ballot Box Scan": "191",
+            "Refresh Box Scan": "188",
+            "Update Box Scan": "186",
+            "buyback_token_scan": null
+            }"#,
+        )
+        .unwrap();
+        // scans registered before the namespacing are taken as ours
+        assert_eq!(registry.instance, None);
+        assert!(registry.check_instance("usd").is_ok());
+        registry.instance = Some("usd".to_string());
+        assert!(registry.check_instance("usd").is_ok());
+        assert!(matches!(
+            registry.check_instance("xau"),
+            Err(NodeScanRegistryError::ForeignInstance { found, .. }) if found == "usd"
+        ));
+        assert_eq!(scan_name_prefix("usd"), "oracle-core[usd]");
+    }
 }
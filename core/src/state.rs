@@ -6,7 +6,7 @@ use crate::oracle_types::EpochLength;
 use crate::pool_commands::PoolCommand;
 
 pub struct EpochState {
-    epoch_start_height: u64,
+    epoch_start_height: BlockHeight,
 }
 
 /// Enum for the state that the oracle pool is currently in
@@ -16,10 +16,68 @@ pub enum PoolState {
     LiveEpoch(LiveEpochState),
 }
 
+/// Number of blocks in a day (2 minutes per block)
+pub const BLOCKS_PER_DAY: u32 = 720;
+
+/// Boxes older than this (4 years) can be spent by the miners to collect the storage rent
+pub const STORAGE_RENT_PERIOD: u32 = 1_051_200;
+
+/// Our datapoint box is rebuilt this many blocks (30 days) before the storage rent period ends
+pub const STORAGE_RENT_MARGIN: u32 = BLOCKS_PER_DAY * 30;
+
+/// Whether the box created at the height is about to become subject to the storage rent
+fn is_storage_rent_due(box_height: BlockHeight, current_height: BlockHeight) -> bool {
+    current_height.0 >= box_height.0 + STORAGE_RENT_PERIOD - STORAGE_RENT_MARGIN
+}
+
+/// Our datapoint posting policy (see `ParticipationStrategy`) with the data it needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Participation {
+    Always,
+    OnlyWhenNeeded {
+        /// Datapoints posted by all oracles for the current epoch
+        epoch_datapoints: usize,
+        min_datapoints: usize,
+    },
+    CostCapped {
+        /// Fees spent by our transactions in the last `BLOCKS_PER_DAY` blocks
+        fees_spent_last_day: u64,
+        next_tx_fee: u64,
+        daily_fee_budget: u64,
+    },
+}
+
+impl Participation {
+    fn allows_publish(
+        &self,
+        live_epoch: &LiveEpochState,
+        epoch_length: EpochLength,
+        current_height: BlockHeight,
+    ) -> bool {
+        match *self {
+            Participation::Always => true,
+            Participation::OnlyWhenNeeded {
+                epoch_datapoints,
+                min_datapoints,
+            } => {
+                let late_in_epoch = current_height.0
+                    > live_epoch.latest_pool_box_height.0 + (epoch_length.0 as u32) * 3 / 4;
+                late_in_epoch && epoch_datapoints < min_datapoints
+            }
+            Participation::CostCapped {
+                fees_spent_last_day,
+                next_tx_fee,
+                daily_fee_budget,
+            } => fees_spent_last_day + next_tx_fee <= daily_fee_budget,
+        }
+    }
+}
+
 pub fn process(
     pool_state: PoolState,
     epoch_length: EpochLength,
     current_height: BlockHeight,
+    participation: Participation,
 ) -> Option<PoolCommand> {
     let min_start_height = current_height - epoch_length;
     match pool_state {
@@ -31,7 +89,26 @@ pub fn process(
         }
         PoolState::LiveEpoch(live_epoch) => {
             log::debug!("Height {current_height}. Live epoch state: {live_epoch:?}");
-            if let Some(local_datapoint_box_state) = live_epoch.local_datapoint_box_state {
+            let publish = |cmd: PoolCommand| {
+                if participation.allows_publish(&live_epoch, epoch_length, current_height) {
+                    Some(cmd)
+                } else {
+                    log::info!(
+                        "Height {current_height}. Skipping {cmd:?} due to participation {participation:?}"
+                    );
+                    None
+                }
+            };
+            if let Some(local_datapoint_box_state) = live_epoch.local_datapoint_box_state.clone() {
+                let (Collected { height } | Posted { height, .. }) = local_datapoint_box_state;
+                if is_storage_rent_due(height, current_height) {
+                    // the miners could spend the box otherwise, republish regardless of the
+                    // participation strategy to recreate it
+                    log::warn!(
+                        "Height {current_height}. Our datapoint box created at {height} is close to the storage rent period, republishing"
+                    );
+                    return Some(PoolCommand::PublishSubsequentDataPoint { republish: true });
+                }
                 match local_datapoint_box_state {
                     Collected { height: _ } => {
                         // publish datapoint after some blocks have passed after the pool box published
@@ -40,14 +117,14 @@ pub fn process(
                         if current_height.0
                             > live_epoch.latest_pool_box_height.0 + (epoch_length.0 as u32) / 2
                         {
-                            Some(PoolCommand::PublishSubsequentDataPoint { republish: false })
+                            publish(PoolCommand::PublishSubsequentDataPoint { republish: false })
                         } else {
                             None
                         }
                     }
                     Posted { epoch_id, height } => {
                         if height < min_start_height || epoch_id != live_epoch.pool_box_epoch_id {
-                            Some(PoolCommand::PublishSubsequentDataPoint { republish: true })
+                            publish(PoolCommand::PublishSubsequentDataPoint { republish: true })
                         } else if live_epoch.latest_pool_box_height < min_start_height
                             && epoch_id == live_epoch.pool_box_epoch_id
                         {
@@ -59,10 +136,148 @@ pub fn process(
                 }
             } else {
                 // no local datapoint found
-                Some(PoolCommand::PublishFirstDataPoint)
+                publish(PoolCommand::PublishFirstDataPoint)
             }
         }
     }
 }
 
-// TODO: add tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle_state::LocalDatapointState;
+    use crate::oracle_types::EpochCounter;
+
+    const EPOCH_LENGTH: EpochLength = EpochLength(30);
+
+    fn live_epoch(local_datapoint_box_state: Option<LocalDatapointState>) -> PoolState {
+        PoolState::LiveEpoch(LiveEpochState {
+            pool_box_epoch_id: EpochCounter(5),
+            latest_pool_datapoint: 100i64.into(),
+            latest_pool_box_height: BlockHeight(1000),
+            local_datapoint_box_state,
+        })
+    }
+
+    fn collected() -> PoolState {
+        live_epoch(Some(LocalDatapointState::Collected {
+            height: BlockHeight(1000),
+        }))
+    }
+
+    #[test]
+    fn test_always() {
+        assert!(matches!(
+            process(
+                collected(),
+                EPOCH_LENGTH,
+                BlockHeight(1016),
+                Participation::Always
+            ),
+            Some(PoolCommand::PublishSubsequentDataPoint { republish: false })
+        ));
+        assert!(matches!(
+            process(
+                live_epoch(None),
+                EPOCH_LENGTH,
+                BlockHeight(1001),
+                Participation::Always
+            ),
+            Some(PoolCommand::PublishFirstDataPoint)
+        ));
+        // too early in the epoch
+        assert!(process(
+            collected(),
+            EPOCH_LENGTH,
+            BlockHeight(1010),
+            Participation::Always
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_only_when_needed() {
+        let short_of_datapoints = Participation::OnlyWhenNeeded {
+            epoch_datapoints: 2,
+            min_datapoints: 4,
+        };
+        let enough_datapoints = Participation::OnlyWhenNeeded {
+            epoch_datapoints: 4,
+            min_datapoints: 4,
+        };
+        // not late in the epoch yet
+        assert!(process(
+            collected(),
+            EPOCH_LENGTH,
+            BlockHeight(1016),
+            short_of_datapoints
+        )
+        .is_none());
+        assert!(matches!(
+            process(
+                collected(),
+                EPOCH_LENGTH,
+                BlockHeight(1023),
+                short_of_datapoints
+            ),
+            Some(PoolCommand::PublishSubsequentDataPoint { republish: false })
+        ));
+        assert!(process(
+            collected(),
+            EPOCH_LENGTH,
+            BlockHeight(1023),
+            enough_datapoints
+        )
+        .is_none());
+        // refresh is not affected
+        let posted = live_epoch(Some(LocalDatapointState::Posted {
+            epoch_id: EpochCounter(5),
+            height: BlockHeight(1010),
+        }));
+        assert!(matches!(
+            process(posted, EPOCH_LENGTH, BlockHeight(1031), enough_datapoints),
+            Some(PoolCommand::Refresh)
+        ));
+    }
+
+    #[test]
+    fn test_cost_capped() {
+        let within_budget = Participation::CostCapped {
+            fees_spent_last_day: 9_000_000,
+            next_tx_fee: 1_000_000,
+            daily_fee_budget: 10_000_000,
+        };
+        let over_budget = Participation::CostCapped {
+            fees_spent_last_day: 9_500_000,
+            next_tx_fee: 1_000_000,
+            daily_fee_budget: 10_000_000,
+        };
+        assert!(matches!(
+            process(collected(), EPOCH_LENGTH, BlockHeight(1016), within_budget),
+            Some(PoolCommand::PublishSubsequentDataPoint { republish: false })
+        ));
+        assert!(process(collected(), EPOCH_LENGTH, BlockHeight(1016), over_budget).is_none());
+    }
+
+    #[test]
+    fn test_storage_rent_due() {
+        let over_budget = Participation::CostCapped {
+            fees_spent_last_day: 9_500_000,
+            next_tx_fee: 1_000_000,
+            daily_fee_budget: 10_000_000,
+        };
+        let rent_due_height = BlockHeight(1000 + STORAGE_RENT_PERIOD - STORAGE_RENT_MARGIN);
+        assert!(process(
+            collected(),
+            EPOCH_LENGTH,
+            BlockHeight(rent_due_height.0 - 1),
+            over_budget
+        )
+        .is_none());
+        // regardless of the participation strategy
+        assert!(matches!(
+            process(collected(), EPOCH_LENGTH, rent_due_height, over_budget),
+            Some(PoolCommand::PublishSubsequentDataPoint { republish: true })
+        ));
+    }
+}
@@ -0,0 +1,483 @@
+//! Persistence of the oracle's local state (the epoch archive, the tx retry queue and the epoch of
+//! our last datapoint post). The main loop and the commands load and save the state through the
+//! [`StateStore`] trait, the backend is selected by `state_store` in the oracle config:
+//! - `files` (default) keeps each value in its own JSON file in the data dir;
+//! - `sqlite` (needs the `sqlite` feature) keeps them in a single SQLite database in the data dir,
+//!   importing the JSON files of the `files` backend on the first start.
+//!
+//! The values are versioned (see [`crate::format_version`]), the values saved by an older
+//! oracle-core are migrated on startup by [`migrate`]. The SQLite schema is versioned with its
+//! `user_version`.
+//!
+//! The in-memory backend is used in the tests.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::epoch_archive::EpochArchive;
+use crate::epoch_archive::EPOCH_ARCHIVE_FORMAT;
+use crate::format_version::Format;
+use crate::format_version::FormatVersionError;
+use crate::oracle_config::ORACLE_CONFIG;
+use crate::posted_epoch::LastPostedEpoch;
+use crate::posted_epoch::LAST_POSTED_EPOCH_FORMAT;
+use crate::scans::SCANS_DIR_PATH;
+use crate::tx_retry::RetryQueue;
+use crate::tx_retry::RETRY_QUEUE_FORMAT;
+
+#[derive(Debug, Error)]
+pub enum StateStoreError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid {key:?} in the state store: {error}")]
+    Json {
+        key: StateKey,
+        error: serde_json::Error,
+    },
+    #[error("{0}")]
+    Format(#[from] FormatVersionError),
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("state database schema is of version {version}, this oracle-core supports versions up to {supported}, upgrade oracle-core")]
+    NewerSchema { version: u32, supported: u32 },
+    #[error(
+        "oracle-core is built without the sqlite feature, rebuild it with `--features sqlite`"
+    )]
+    SqliteFeatureDisabled,
+}
+
+/// Backend of the state store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateStoreBackend {
+    /// JSON files in the data dir
+    #[default]
+    Files,
+    /// SQLite database in the data dir
+    Sqlite,
+}
+
+/// Values kept in the state store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateKey {
+    EpochArchive,
+    RetryQueue,
+    LastPostedEpoch,
+}
+
+impl StateKey {
+    pub const ALL: [StateKey; 3] = [
+        StateKey::EpochArchive,
+        StateKey::RetryQueue,
+        StateKey::LastPostedEpoch,
+    ];
+
+    /// File name of the value in the `files` backend (and in the state snapshot)
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            StateKey::EpochArchive => "epoch_archive.json",
+            StateKey::RetryQueue => "tx_retry_queue.json",
+            StateKey::LastPostedEpoch => "last_posted_epoch.json",
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<StateKey> {
+        StateKey::ALL
+            .into_iter()
+            .find(|key| key.file_name() == file_name)
+    }
+
+    pub fn format(&self) -> &'static Format {
+        match self {
+            StateKey::EpochArchive => &EPOCH_ARCHIVE_FORMAT,
+            StateKey::RetryQueue => &RETRY_QUEUE_FORMAT,
+            StateKey::LastPostedEpoch => &LAST_POSTED_EPOCH_FORMAT,
+        }
+    }
+}
+
+/// Storage of the JSON-encoded state values
+pub trait StateStore: Send + Sync {
+    /// The value, `None` if it was never saved
+    fn get(&self, key: StateKey) -> Result<Option<String>, StateStoreError>;
+
+    fn put(&self, key: StateKey, value: &str) -> Result<(), StateStoreError>;
+}
+
+impl<'a> dyn StateStore + 'a {
+    fn load<T: DeserializeOwned + Default>(&self, key: StateKey) -> Result<T, StateStoreError> {
+        match self.get(key)? {
+            Some(json_str) => {
+                let value = serde_json::from_str(&json_str)
+                    .map_err(|error| StateStoreError::Json { key, error })?;
+                serde_json::from_value(key.format().migrate(value)?)
+                    .map_err(|error| StateStoreError::Json { key, error })
+            }
+            None => Ok(T::default()),
+        }
+    }
+
+    fn save<T: Serialize>(&self, key: StateKey, value: &T) -> Result<(), StateStoreError> {
+        let mut value =
+            serde_json::to_value(value).map_err(|error| StateStoreError::Json { key, error })?;
+        key.format().stamp(&mut value);
+        self.put(key, &value.to_string())
+    }
+
+    pub fn load_epoch_archive(&self) -> Result<EpochArchive, StateStoreError> {
+        self.load(StateKey::EpochArchive)
+    }
+
+    pub fn save_epoch_archive(&self, archive: &EpochArchive) -> Result<(), StateStoreError> {
+        self.save(StateKey::EpochArchive, archive)
+    }
+
+    pub fn load_retry_queue(&self) -> Result<RetryQueue, StateStoreError> {
+        self.load(StateKey::RetryQueue)
+    }
+
+    pub fn save_retry_queue(&self, queue: &RetryQueue) -> Result<(), StateStoreError> {
+        self.save(StateKey::RetryQueue, queue)
+    }
+
+    pub fn load_last_posted_epoch(&self) -> Result<LastPostedEpoch, StateStoreError> {
+        self.load(StateKey::LastPostedEpoch)
+    }
+
+    pub fn save_last_posted_epoch(
+        &self,
+        last_posted_epoch: &LastPostedEpoch,
+    ) -> Result<(), StateStoreError> {
+        self.save(StateKey::LastPostedEpoch, last_posted_epoch)
+    }
+}
+
+/// Rewrites the values saved by an older oracle-core in the current version of their format,
+/// returns the migrated keys
+pub fn migrate(store: &dyn StateStore) -> Result<Vec<StateKey>, StateStoreError> {
+    let mut migrated = Vec::new();
+    for key in StateKey::ALL {
+        if let Some(json_str) = store.get(key)? {
+            if let Some(upgraded) = key.format().upgrade_json_str(&json_str)? {
+                store.put(key, &upgraded)?;
+                migrated.push(key);
+            }
+        }
+    }
+    Ok(migrated)
+}
+
+/// Opens the backend set in the oracle config in the data dir
+pub fn open_configured() -> Result<Box<dyn StateStore>, StateStoreError> {
+    open(ORACLE_CONFIG.state_store, SCANS_DIR_PATH.get().unwrap())
+}
+
+pub fn open(
+    backend: StateStoreBackend,
+    data_dir: &Path,
+) -> Result<Box<dyn StateStore>, StateStoreError> {
+    match backend {
+        StateStoreBackend::Files => Ok(Box::new(FileStateStore::new(data_dir.to_path_buf()))),
+        #[cfg(feature = "sqlite")]
+        StateStoreBackend::Sqlite => Ok(Box::new(sqlite::SqliteStateStore::open(data_dir)?)),
+        #[cfg(not(feature = "sqlite"))]
+        StateStoreBackend::Sqlite => Err(StateStoreError::SqliteFeatureDisabled),
+    }
+}
+
+/// Each value in its own JSON file in the data dir
+pub struct FileStateStore {
+    dir: PathBuf,
+}
+
+impl FileStateStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn file_path(&self, key: StateKey) -> PathBuf {
+        self.dir.join(key.file_name())
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn get(&self, key: StateKey) -> Result<Option<String>, StateStoreError> {
+        let path = self.file_path(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(std::fs::read_to_string(path)?))
+    }
+
+    fn put(&self, key: StateKey, value: &str) -> Result<(), StateStoreError> {
+        std::fs::write(self.file_path(key), value)?;
+        Ok(())
+    }
+}
+
+/// Values kept in memory only, for the tests
+#[derive(Default)]
+pub struct InMemoryStateStore {
+    values: Mutex<HashMap<StateKey, String>>,
+}
+
+impl StateStore for InMemoryStateStore {
+    fn get(&self, key: StateKey) -> Result<Option<String>, StateStoreError> {
+        Ok(self.values.lock().unwrap().get(&key).cloned())
+    }
+
+    fn put(&self, key: StateKey, value: &str) -> Result<(), StateStoreError> {
+        self.values.lock().unwrap().insert(key, value.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+
+    use super::FileStateStore;
+    use super::StateKey;
+    use super::StateStore;
+    use super::StateStoreError;
+
+    const DATABASE_FILE_NAME: &str = "oracle_state.sqlite";
+
+    /// Schema migrations from version 0 (empty database) on, the schema version (`user_version`)
+    /// is the number of the applied migrations. The databases created before the versioning have
+    /// the `state` table and version 0, hence `IF NOT EXISTS`.
+    const SCHEMA_MIGRATIONS: &[&str] =
+        &["CREATE TABLE IF NOT EXISTS state (key TEXT PRIMARY KEY, value TEXT NOT NULL)"];
+
+    /// Values in a single key-value table of a SQLite database
+    pub struct SqliteStateStore {
+        conn: Mutex<Connection>,
+    }
+
+    impl SqliteStateStore {
+        /// Opens (or creates) the database in the data dir. The values missing in the database
+        /// are imported from the JSON files of the `files` backend, if there are any.
+        pub fn open(data_dir: &Path) -> Result<Self, StateStoreError> {
+            let store =
+                Self::with_connection(Connection::open(data_dir.join(DATABASE_FILE_NAME))?)?;
+            let files = FileStateStore::new(data_dir.to_path_buf());
+            for key in StateKey::ALL {
+                if store.get(key)?.is_none() {
+                    if let Some(value) = files.get(key)? {
+                        log::info!("Importing {} into the state database", key.file_name());
+                        store.put(key, &value)?;
+                    }
+                }
+            }
+            Ok(store)
+        }
+
+        pub fn with_connection(mut conn: Connection) -> Result<Self, StateStoreError> {
+            migrate_schema(&mut conn)?;
+            Ok(Self {
+                conn: Mutex::new(conn),
+            })
+        }
+    }
+
+    /// Applies the schema migrations missing in the database, each in its own transaction
+    fn migrate_schema(conn: &mut Connection) -> Result<(), StateStoreError> {
+        let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let supported = SCHEMA_MIGRATIONS.len() as u32;
+        if version > supported {
+            return Err(StateStoreError::NewerSchema { version, supported });
+        }
+        for (from_version, migration) in (version..).zip(&SCHEMA_MIGRATIONS[version as usize..]) {
+            log::info!(
+                "Migrating the state database schema from version {} to {}",
+                from_version,
+                from_version + 1
+            );
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", from_version + 1)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    impl StateStore for SqliteStateStore {
+        fn get(&self, key: StateKey) -> Result<Option<String>, StateStoreError> {
+            Ok(self
+                .conn
+                .lock()
+                .unwrap()
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    params![key.file_name()],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        }
+
+        fn put(&self, key: StateKey, value: &str) -> Result<(), StateStoreError> {
+            self.conn.lock().unwrap().execute(
+                "INSERT INTO state (key, value) VALUES (?1, ?2) \
+                ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                params![key.file_name(), value],
+            )?;
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_get_and_put() {
+            let store =
+                SqliteStateStore::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+            assert_eq!(store.get(StateKey::RetryQueue).unwrap(), None);
+            store.put(StateKey::RetryQueue, r#"{"txs":[]}"#).unwrap();
+            store.put(StateKey::RetryQueue, r#"{"txs":[1]}"#).unwrap();
+            assert_eq!(
+                store.get(StateKey::RetryQueue).unwrap().as_deref(),
+                Some(r#"{"txs":[1]}"#)
+            );
+            assert_eq!(store.get(StateKey::EpochArchive).unwrap(), None);
+        }
+
+        #[test]
+        fn test_schema_migrations() {
+            let conn = Connection::open_in_memory().unwrap();
+            conn.execute(
+                "CREATE TABLE state (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO state (key, value) VALUES ('tx_retry_queue.json', '{\"txs\":[]}')",
+                [],
+            )
+            .unwrap();
+            let store = SqliteStateStore::with_connection(conn).unwrap();
+            let conn = store.conn.into_inner().unwrap();
+            let version: u32 = conn
+                .query_row("PRAGMA user_version", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(version, SCHEMA_MIGRATIONS.len() as u32);
+            let store = SqliteStateStore::with_connection(conn).unwrap();
+            assert_eq!(
+                store.get(StateKey::RetryQueue).unwrap().as_deref(),
+                Some(r#"{"txs":[]}"#)
+            );
+        }
+
+        #[test]
+        fn test_newer_schema_is_refused() {
+            let conn = Connection::open_in_memory().unwrap();
+            conn.pragma_update(None, "user_version", SCHEMA_MIGRATIONS.len() as u32 + 1)
+                .unwrap();
+            assert!(matches!(
+                SqliteStateStore::with_connection(conn),
+                Err(StateStoreError::NewerSchema { .. })
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+    use crate::oracle_types::EpochCounter;
+
+    #[test]
+    fn test_load_default_if_never_saved() {
+        let store: Box<dyn StateStore> = Box::<InMemoryStateStore>::default();
+        assert_eq!(
+            store.load_last_posted_epoch().unwrap(),
+            LastPostedEpoch::default()
+        );
+        assert!(store.load_retry_queue().unwrap().is_empty());
+        assert_eq!(store.load_epoch_archive().unwrap().epochs_count(), 0);
+    }
+
+    #[test]
+    fn test_file_store_save_and_load() {
+        let dir = std::env::temp_dir().join("oracle_core_test_file_state_store");
+        std::fs::create_dir_all(&dir).unwrap();
+        let store: Box<dyn StateStore> = Box::new(FileStateStore::new(dir.clone()));
+        let mut last_posted_epoch = LastPostedEpoch::default();
+        last_posted_epoch.record(EpochCounter(42), force_any_val::<ErgoBox>().box_id());
+        store.save_last_posted_epoch(&last_posted_epoch).unwrap();
+        assert!(dir.join("last_posted_epoch.json").exists());
+        assert_eq!(store.load_last_posted_epoch().unwrap(), last_posted_epoch);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let store: Box<dyn StateStore> = Box::<InMemoryStateStore>::default();
+        store.put(StateKey::LastPostedEpoch, "not json").unwrap();
+        assert!(matches!(
+            store.load_last_posted_epoch(),
+            Err(StateStoreError::Json {
+                key: StateKey::LastPostedEpoch,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_save_stamps_the_format_version() {
+        let store: Box<dyn StateStore> = Box::<InMemoryStateStore>::default();
+        store
+            .save_last_posted_epoch(&LastPostedEpoch::default())
+            .unwrap();
+        let value: serde_json::Value =
+            serde_json::from_str(&store.get(StateKey::LastPostedEpoch).unwrap().unwrap()).unwrap();
+        assert_eq!(
+            Format::version_of(&value),
+            LAST_POSTED_EPOCH_FORMAT.current_version()
+        );
+        assert_eq!(
+            store.load_last_posted_epoch().unwrap(),
+            LastPostedEpoch::default()
+        );
+        assert!(migrate(store.as_ref()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_newer_value_is_refused() {
+        let store: Box<dyn StateStore> = Box::<InMemoryStateStore>::default();
+        store
+            .put(StateKey::RetryQueue, r#"{"format_version":999,"txs":[]}"#)
+            .unwrap();
+        assert!(matches!(
+            store.load_retry_queue(),
+            Err(StateStoreError::Format(FormatVersionError::Newer { .. }))
+        ));
+        assert!(migrate(store.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_key_file_names() {
+        for key in StateKey::ALL {
+            assert_eq!(StateKey::from_file_name(key.file_name()), Some(key));
+        }
+        assert_eq!(StateKey::from_file_name("scanIDs.json"), None);
+    }
+}
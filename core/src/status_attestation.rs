@@ -0,0 +1,199 @@
+//! Status attestations: the oracle's current status (height, epoch of our last datapoint post,
+//! version) signed by the oracle key, so that the pool admin collecting them from the operators
+//! can check the liveness claims against the oracle addresses of the pool. The signed message is
+//! the `status` JSON string as is, the signature is a Schnorr signature of the oracle address key
+//! (the same as `signMessage` of the Ergo wallets).
+
+use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use ergo_lib::ergotree_interpreter::sigma_protocol::verifier::verify_signature;
+use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::address::AddressEncoder;
+use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+use ergo_lib::wallet::secret_key::SecretKey;
+use ergo_lib::wallet::Wallet;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
+use crate::oracle_config::ORACLE_CONFIG;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+use crate::pool_config::POOL_CONFIG;
+use crate::state_store::StateStore;
+use crate::state_store::StateStoreError;
+
+#[derive(Debug, Error)]
+pub enum StatusAttestationError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("node API error: {0}")]
+    NodeApi(#[from] NodeApiError),
+    #[error("node error: {0}")]
+    Node(#[from] ergo_node_interface::scanning::NodeError),
+    #[error("state store error: {0}")]
+    StateStore(#[from] StateStoreError),
+    #[error("invalid status JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0} is not a P2PK address")]
+    NotP2PkAddress(String),
+    #[error("invalid oracle address: {0}")]
+    InvalidAddress(String),
+    #[error("secret key from the node is not the key of the oracle address")]
+    WrongSecretKey,
+    #[error("failed to sign the status: {0}")]
+    Signing(String),
+    #[error("invalid signature encoding: {0}")]
+    SignatureEncoding(#[from] base16::DecodeError),
+    #[error("signature does not match the oracle address")]
+    InvalidSignature,
+}
+
+/// Status claimed by the operator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OracleStatusClaim {
+    /// Base58 P2PK address of the oracle, the status is signed by its key
+    pub oracle_address: String,
+    pub pool_nft_id: String,
+    /// Height of the node at the time of the attestation
+    pub height: BlockHeight,
+    /// Epoch of our last datapoint post
+    pub last_posted_epoch: Option<EpochCounter>,
+    pub version: String,
+    /// Unix time (seconds) of the attestation
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusAttestation {
+    /// JSON of the [`OracleStatusClaim`], the signed message
+    pub status: String,
+    /// Base16 signature of the `status` bytes
+    pub signature: String,
+}
+
+/// Current status of the oracle signed by the oracle key from the node wallet. The key is exported
+/// from the node (`/wallet/getPrivateKey`) for the signature only and dropped right after, so this
+/// is only done on the explicit request of the operator (the `attest-status` command).
+pub fn attest(
+    node_api: &NodeApi,
+    state_store: &dyn StateStore,
+) -> Result<StatusAttestation, StatusAttestationError> {
+    let oracle_address = ORACLE_CONFIG.oracle_address.clone();
+    let claim = OracleStatusClaim {
+        oracle_address: oracle_address.to_base58(),
+        pool_nft_id: String::from(POOL_CONFIG.token_ids.pool_nft_token_id.token_id()),
+        height: BlockHeight(node_api.node.current_block_height()? as u32),
+        last_posted_epoch: state_store.load_last_posted_epoch()?.epoch_id(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    let secret = node_api.wallet_secret_key(&oracle_address.to_base58())?;
+    sign(&claim, secret)
+}
+
+pub fn sign(
+    claim: &OracleStatusClaim,
+    secret: SecretKey,
+) -> Result<StatusAttestation, StatusAttestationError> {
+    let public_key = p2pk(&claim.oracle_address)?;
+    if !matches!(&secret, SecretKey::DlogSecretKey(dlog) if dlog.public_image() == public_key) {
+        return Err(StatusAttestationError::WrongSecretKey);
+    }
+    let status = serde_json::to_string(claim)?;
+    let signature = Wallet::from_secrets(vec![secret])
+        .sign_message(public_key.into(), status.as_bytes())
+        .map_err(|e| StatusAttestationError::Signing(e.to_string()))?;
+    Ok(StatusAttestation {
+        status,
+        signature: base16::encode_lower(&signature),
+    })
+}
+
+/// Checks the signature against the oracle address of the status and returns the status
+pub fn verify(
+    attestation: &StatusAttestation,
+) -> Result<OracleStatusClaim, StatusAttestationError> {
+    let claim: OracleStatusClaim = serde_json::from_str(&attestation.status)?;
+    let public_key = p2pk(&claim.oracle_address)?;
+    let signature = base16::decode(&attestation.signature)?;
+    if verify_signature(public_key.into(), attestation.status.as_bytes(), &signature)
+        .unwrap_or(false)
+    {
+        Ok(claim)
+    } else {
+        Err(StatusAttestationError::InvalidSignature)
+    }
+}
+
+pub fn verify_file(path: &Path) -> Result<OracleStatusClaim, StatusAttestationError> {
+    let attestation: StatusAttestation = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    verify(&attestation)
+}
+
+fn p2pk(address: &str) -> Result<ProveDlog, StatusAttestationError> {
+    let network_address: NetworkAddress =
+        AddressEncoder::unchecked_parse_network_address_from_str(address)
+            .map_err(|e| StatusAttestationError::InvalidAddress(e.to_string()))?;
+    match network_address.address() {
+        Address::P2Pk(public_key) => Ok(public_key),
+        _ => Err(StatusAttestationError::NotP2PkAddress(address.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInput;
+    use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+
+    use super::*;
+
+    fn test_claim(public_key: ProveDlog) -> OracleStatusClaim {
+        OracleStatusClaim {
+            oracle_address: NetworkAddress::new(NetworkPrefix::Mainnet, &Address::P2Pk(public_key))
+                .to_base58(),
+            pool_nft_id: "011d3364de07e5a26f0c4eef0852cddb387039a921b7154ef3cab22c6eda887f"
+                .to_string(),
+            height: BlockHeight(1_250_000),
+            last_posted_epoch: Some(EpochCounter(4_321)),
+            version: "2.0.1".to_string(),
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret = DlogProverInput::random();
+        let claim = test_claim(secret.public_image());
+        let attestation = sign(&claim, SecretKey::DlogSecretKey(secret)).unwrap();
+        assert_eq!(verify(&attestation).unwrap(), claim);
+
+        let tampered = StatusAttestation {
+            status: attestation
+                .status
+                .replace("\"last_posted_epoch\":4321", "\"last_posted_epoch\":4322"),
+            ..attestation
+        };
+        assert!(matches!(
+            verify(&tampered),
+            Err(StatusAttestationError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_sign_with_another_key() {
+        let claim = test_claim(DlogProverInput::random().public_image());
+        assert!(matches!(
+            sign(&claim, SecretKey::DlogSecretKey(DlogProverInput::random())),
+            Err(StatusAttestationError::WrongSecretKey)
+        ));
+    }
+}
@@ -0,0 +1,385 @@
+//! Queue of the transactions the node failed to sign or accept (e.g. it was unreachable for a
+//! moment at the epoch boundary). They are signed (if they were queued unsigned) and resubmitted
+//! on the next main loop iterations until they get accepted, expire or their inputs get spent by
+//! another transaction. The queue is kept in the state store so that a restart doesn't drop them.
+//! A queued datapoint tx keeps its epoch and datapoint snapshot, so that once it's accepted it's
+//! recorded as posted just like a datapoint tx accepted right away.
+
+use derive_more::From;
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::Transaction;
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergotree_ir::chain::ergo_box::BoxId;
+use ergo_node_interface::node_interface::NodeError;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::datapoint_audit::DatapointSnapshot;
+use crate::format_version::Format;
+use crate::node_interface::node_api::NodeApi;
+use crate::node_interface::node_api::NodeApiError;
+use crate::oracle_types::BlockHeight;
+use crate::oracle_types::EpochCounter;
+
+pub const RETRY_QUEUE_FORMAT: Format = Format {
+    name: "tx retry queue",
+    // version 2 queues the unsigned txs as well
+    migrations: &[|mut value| {
+        if let Some(txs) = value.get_mut("txs").and_then(Value::as_array_mut) {
+            for queued in txs.iter_mut().filter_map(|queued| queued.as_object_mut()) {
+                if let Some(tx) = queued.remove("tx") {
+                    queued.insert("tx".to_string(), json!({ "signed": tx }));
+                }
+            }
+        }
+        Ok(value)
+    }],
+};
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, From, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuedTransaction {
+    Signed(Transaction),
+    /// The node failed to sign it
+    Unsigned(UnsignedTransaction),
+}
+
+impl QueuedTransaction {
+    pub fn id(&self) -> TxId {
+        match self {
+            QueuedTransaction::Signed(tx) => tx.id(),
+            QueuedTransaction::Unsigned(tx) => tx.id(),
+        }
+    }
+
+    fn input_ids(&self) -> Vec<BoxId> {
+        match self {
+            QueuedTransaction::Signed(tx) => tx.inputs.iter().map(|input| input.box_id).collect(),
+            QueuedTransaction::Unsigned(tx) => tx.inputs.iter().map(|input| input.box_id).collect(),
+        }
+    }
+}
+
+/// Our datapoint posted by a queued tx
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedDatapoint {
+    /// Pool epoch the datapoint was built in, `None` if the pool was not running
+    pub epoch_id: Option<EpochCounter>,
+    pub snapshot: DatapointSnapshot,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedTx {
+    pub tx: QueuedTransaction,
+    /// Dropped after this height, so that e.g. a datapoint is not posted in a later epoch
+    pub expires_at: BlockHeight,
+    pub attempts: u32,
+    /// Set if the tx posts our datapoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datapoint: Option<QueuedDatapoint>,
+}
+
+/// Where a tx the node failed to sign or accept is queued
+pub struct RetryTarget<'a> {
+    pub queue: &'a mut RetryQueue,
+    /// Height the queued tx expires after
+    pub expires_at: BlockHeight,
+    /// Set if the tx posts our datapoint
+    pub datapoint: Option<QueuedDatapoint>,
+}
+
+/// Queued tx accepted by the node on resubmission
+#[derive(Debug)]
+pub struct ResubmittedTx {
+    pub tx: Transaction,
+    /// Set if the tx posts our datapoint
+    pub datapoint: Option<QueuedDatapoint>,
+}
+
+/// Node calls made by the resubmission
+pub trait ResubmitNode {
+    fn is_box_unspent(&self, box_id: &BoxId) -> Result<bool, NodeApiError>;
+    fn sign_transaction(
+        &self,
+        unsigned_tx: &UnsignedTransaction,
+    ) -> Result<Transaction, NodeApiError>;
+    fn submit_transaction(&self, signed_tx: &Transaction) -> Result<TxId, NodeApiError>;
+}
+
+impl ResubmitNode for NodeApi {
+    fn is_box_unspent(&self, box_id: &BoxId) -> Result<bool, NodeApiError> {
+        NodeApi::is_box_unspent(self, box_id)
+    }
+
+    fn sign_transaction(
+        &self,
+        unsigned_tx: &UnsignedTransaction,
+    ) -> Result<Transaction, NodeApiError> {
+        NodeApi::sign_transaction(self, unsigned_tx)
+    }
+
+    fn submit_transaction(&self, signed_tx: &Transaction) -> Result<TxId, NodeApiError> {
+        NodeApi::submit_transaction(self, signed_tx)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RetryQueue {
+    txs: Vec<QueuedTx>,
+}
+
+/// Whether the submission is worth retrying. A tx rejected by the node (bad request) would be
+/// rejected again, while e.g. an unreachable node might accept it on the next attempt.
+pub fn is_retryable(err: &NodeApiError) -> bool {
+    !matches!(
+        err,
+        NodeApiError::NodeInterfaceError(NodeError::BadRequest(_))
+    )
+}
+
+impl QueuedTx {
+    /// The signed tx, signed by the node first if it was queued unsigned
+    fn signed(&mut self, node_api: &impl ResubmitNode) -> Result<Transaction, NodeApiError> {
+        let tx = match &self.tx {
+            QueuedTransaction::Signed(tx) => tx.clone(),
+            QueuedTransaction::Unsigned(tx) => node_api.sign_transaction(tx)?,
+        };
+        self.tx = QueuedTransaction::Signed(tx.clone());
+        Ok(tx)
+    }
+}
+
+impl RetryQueue {
+    pub fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    /// Adds the tx, replacing the queued ones spending any of its inputs (they are superseded)
+    pub fn push(
+        &mut self,
+        tx: impl Into<QueuedTransaction>,
+        expires_at: BlockHeight,
+        datapoint: Option<QueuedDatapoint>,
+    ) {
+        let tx = tx.into();
+        let input_ids = tx.input_ids();
+        self.txs.retain(|queued| {
+            !queued
+                .tx
+                .input_ids()
+                .iter()
+                .any(|box_id| input_ids.contains(box_id))
+        });
+        self.txs.push(QueuedTx {
+            tx,
+            expires_at,
+            attempts: 0,
+            datapoint,
+        });
+    }
+
+    /// Removes and returns the txs expired at the given height
+    pub fn take_expired(&mut self, height: BlockHeight) -> Vec<QueuedTx> {
+        let (expired, pending) = std::mem::take(&mut self.txs)
+            .into_iter()
+            .partition(|queued| height > queued.expires_at);
+        self.txs = pending;
+        expired
+    }
+
+    /// Resubmits the queued txs, returns the accepted ones. The expired txs, the ones with spent
+    /// inputs and the ones rejected by the node are dropped.
+    pub fn resubmit(
+        &mut self,
+        node_api: &impl ResubmitNode,
+        height: BlockHeight,
+    ) -> Vec<ResubmittedTx> {
+        for expired in self.take_expired(height) {
+            log::warn!(
+                "Dropping the queued tx {} expired at height {} after {} attempts",
+                expired.tx.id(),
+                expired.expires_at,
+                expired.attempts
+            );
+        }
+        let mut submitted = Vec::new();
+        let mut pending = Vec::new();
+        for mut queued in std::mem::take(&mut self.txs) {
+            let tx_id = queued.tx.id();
+            let inputs_unspent = queued
+                .tx
+                .input_ids()
+                .iter()
+                .map(|box_id| node_api.is_box_unspent(box_id))
+                .collect::<Result<Vec<bool>, NodeApiError>>()
+                .map(|unspent| unspent.into_iter().all(|u| u));
+            match inputs_unspent {
+                Ok(true) => (),
+                Ok(false) => {
+                    log::info!("Dropping the queued tx {}, its inputs are spent", tx_id);
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to check the inputs of the queued tx {}: {}",
+                        tx_id,
+                        e
+                    );
+                    pending.push(queued);
+                    continue;
+                }
+            }
+            queued.attempts += 1;
+            match queued
+                .signed(node_api)
+                .and_then(|tx| node_api.submit_transaction(&tx).map(|_| tx))
+            {
+                Ok(tx) => {
+                    log::info!(
+                        "Queued tx {} submitted on attempt {}",
+                        tx_id,
+                        queued.attempts
+                    );
+                    submitted.push(ResubmittedTx {
+                        tx,
+                        datapoint: queued.datapoint,
+                    });
+                }
+                Err(e) if is_retryable(&e) => {
+                    log::warn!(
+                        "Failed to resubmit the queued tx {} (attempt {}): {}",
+                        tx_id,
+                        queued.attempts,
+                        e
+                    );
+                    pending.push(queued);
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Dropping the queued tx {} the node failed to sign or rejected: {}",
+                        tx_id,
+                        e
+                    )
+                }
+            }
+        }
+        self.txs = pending;
+        submitted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+    use crate::oracle_types::Rate;
+    use crate::state_store::InMemoryStateStore;
+    use crate::state_store::StateStore;
+
+    /// Node accepting the signed txs, with every box unspent
+    struct AcceptingNode;
+
+    impl ResubmitNode for AcceptingNode {
+        fn is_box_unspent(&self, _: &BoxId) -> Result<bool, NodeApiError> {
+            Ok(true)
+        }
+
+        fn sign_transaction(&self, _: &UnsignedTransaction) -> Result<Transaction, NodeApiError> {
+            Err(NodeApiError::NoChangeAddressSetInNode)
+        }
+
+        fn submit_transaction(&self, signed_tx: &Transaction) -> Result<TxId, NodeApiError> {
+            Ok(signed_tx.id())
+        }
+    }
+
+    #[test]
+    fn test_push_and_expire() {
+        let mut queue = RetryQueue::default();
+        let tx = force_any_val::<Transaction>();
+        queue.push(tx.clone(), BlockHeight(110), None);
+        queue.push(force_any_val::<Transaction>(), BlockHeight(120), None);
+        assert!(queue.take_expired(BlockHeight(110)).is_empty());
+        let expired = queue.take_expired(BlockHeight(111));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].tx.id(), tx.id());
+        assert_eq!(queue.take_expired(BlockHeight(121)).len(), 1);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_push_replaces_tx_spending_same_inputs() {
+        let mut queue = RetryQueue::default();
+        let tx = force_any_val::<Transaction>();
+        queue.push(tx.clone(), BlockHeight(110), None);
+        // rebuilt tx spending the same inputs
+        let mut rebuilt_tx = force_any_val::<Transaction>();
+        rebuilt_tx.inputs = tx.inputs.clone();
+        queue.push(rebuilt_tx, BlockHeight(120), None);
+        let queued = queue.take_expired(BlockHeight(200));
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].expires_at, BlockHeight(120));
+    }
+
+    #[test]
+    fn test_resubmit_returns_datapoint() {
+        let mut queue = RetryQueue::default();
+        let datapoint_tx = force_any_val::<Transaction>();
+        let datapoint = QueuedDatapoint {
+            epoch_id: Some(EpochCounter(7)),
+            snapshot: DatapointSnapshot::new(BlockHeight(100), Rate::from(1_000_000), vec![]),
+        };
+        queue.push(
+            datapoint_tx.clone(),
+            BlockHeight(110),
+            Some(datapoint.clone()),
+        );
+        queue.push(force_any_val::<Transaction>(), BlockHeight(110), None);
+        let resubmitted = queue.resubmit(&AcceptingNode, BlockHeight(105));
+        assert!(queue.is_empty());
+        assert_eq!(resubmitted.len(), 2);
+        assert_eq!(resubmitted[0].tx.id(), datapoint_tx.id());
+        assert_eq!(resubmitted[0].datapoint, Some(datapoint));
+        assert_eq!(resubmitted[1].datapoint, None);
+    }
+
+    #[test]
+    fn test_migrate_signed_txs() {
+        let tx = force_any_val::<Transaction>();
+        let v1 = json!({
+            "txs": [{ "tx": serde_json::to_value(&tx).unwrap(), "expires_at": 110, "attempts": 2 }]
+        });
+        let mut queue: RetryQueue = RETRY_QUEUE_FORMAT.from_json_str(&v1.to_string()).unwrap();
+        let queued = queue.take_expired(BlockHeight(111));
+        assert_eq!(queued.len(), 1);
+        assert!(
+            matches!(&queued[0].tx, QueuedTransaction::Signed(signed) if signed.id() == tx.id())
+        );
+        assert_eq!(queued[0].attempts, 2);
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(!is_retryable(&NodeApiError::NodeInterfaceError(
+            NodeError::BadRequest("Malformed transaction".to_string())
+        )));
+        assert!(is_retryable(&NodeApiError::NoChangeAddressSetInNode));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let store: Box<dyn StateStore> = Box::<InMemoryStateStore>::default();
+        let mut queue = RetryQueue::default();
+        let tx = force_any_val::<Transaction>();
+        queue.push(tx.clone(), BlockHeight(110), None);
+        store.save_retry_queue(&queue).unwrap();
+        let mut loaded = store.load_retry_queue().unwrap();
+        let queued = loaded.take_expired(BlockHeight(111));
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].tx.id(), tx.id());
+    }
+}
@@ -0,0 +1,202 @@
+//! Human-readable breakdown of the transaction outputs, printed before the operator confirms a
+//! transaction and in the read-only (dry-run) mode instead of the raw ErgoTrees
+
+use std::fmt;
+
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::chain::address::NetworkPrefix;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBoxCandidate;
+use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::wallet::miner_fee::MINERS_FEE_ADDRESS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    /// Miner fee
+    Fee,
+    /// Back to our change address
+    Change,
+    /// Guarded by a contract (pool, refresh, oracle, ballot box, etc.)
+    Contract,
+    /// To a P2PK address other than ours
+    Payment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSummary {
+    pub index: usize,
+    pub kind: OutputKind,
+    /// Base58 encoded address, or base16 encoded ErgoTree if it can't be encoded as an address
+    pub destination: String,
+    pub value: u64,
+    /// Base16 encoded token id and amount
+    pub tokens: Vec<(String, u64)>,
+}
+
+fn output_kind(ergo_tree: &ErgoTree, change_address: &Address) -> OutputKind {
+    if MINERS_FEE_ADDRESS
+        .script()
+        .map_or(false, |fee_tree| &fee_tree == ergo_tree)
+    {
+        OutputKind::Fee
+    } else if change_address
+        .script()
+        .map_or(false, |change_tree| &change_tree == ergo_tree)
+    {
+        OutputKind::Change
+    } else {
+        match Address::recreate_from_ergo_tree(ergo_tree) {
+            Ok(Address::P2Pk(_)) => OutputKind::Payment,
+            Ok(Address::P2SH(_)) | Ok(Address::P2S(_)) | Err(_) => OutputKind::Contract,
+        }
+    }
+}
+
+fn output_summary(
+    index: usize,
+    output: &ErgoBoxCandidate,
+    change_address: &Address,
+    network_prefix: NetworkPrefix,
+) -> OutputSummary {
+    let destination = match Address::recreate_from_ergo_tree(&output.ergo_tree) {
+        Ok(address) => NetworkAddress::new(network_prefix, &address).to_base58(),
+        Err(_) => output
+            .ergo_tree
+            .sigma_serialize_bytes()
+            .map(|bytes| base16::encode_lower(&bytes))
+            .unwrap_or_default(),
+    };
+    let tokens = output
+        .tokens
+        .as_ref()
+        .map(|tokens| {
+            tokens
+                .iter()
+                .map(|t| (String::from(t.token_id), *t.amount.as_u64()))
+                .collect()
+        })
+        .unwrap_or_default();
+    OutputSummary {
+        index,
+        kind: output_kind(&output.ergo_tree, change_address),
+        destination,
+        value: *output.value.as_u64(),
+        tokens,
+    }
+}
+
+pub fn summarize_outputs(
+    tx: &UnsignedTransaction,
+    change_address: &Address,
+    network_prefix: NetworkPrefix,
+) -> Vec<OutputSummary> {
+    tx.output_candidates
+        .iter()
+        .enumerate()
+        .map(|(index, output)| output_summary(index, output, change_address, network_prefix))
+        .collect()
+}
+
+impl fmt::Display for OutputKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutputKind::Fee => "fee",
+            OutputKind::Change => "change",
+            OutputKind::Contract => "contract",
+            OutputKind::Payment => "payment",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for OutputSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} [{}] {} ERG to {}",
+            self.index,
+            self.kind,
+            self.value as f64 / 1_000_000_000.0,
+            self.destination
+        )?;
+        for (token_id, amount) in &self.tokens {
+            write!(f, "\n    token {}: {}", token_id, amount)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outputs of the transaction, one per line
+pub fn format_tx_outputs(
+    tx: &UnsignedTransaction,
+    change_address: &Address,
+    network_prefix: NetworkPrefix,
+) -> String {
+    summarize_outputs(tx, change_address, network_prefix)
+        .iter()
+        .map(|output| output.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInput;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+    use crate::box_kind::PoolBox;
+    use crate::contracts::pool::PoolContractParameters;
+    use crate::oracle_config::BASE_FEE;
+    use crate::oracle_types::{BlockHeight, EpochCounter};
+    use crate::pool_commands::test_utils::{generate_token_ids, make_pool_box};
+
+    #[test]
+    fn test_output_kind() {
+        let change_address = Address::P2Pk(force_any_val::<DlogProverInput>().public_image());
+        let other_address = Address::P2Pk(force_any_val::<DlogProverInput>().public_image());
+        assert_eq!(
+            output_kind(&MINERS_FEE_ADDRESS.script().unwrap(), &change_address),
+            OutputKind::Fee
+        );
+        assert_eq!(
+            output_kind(&change_address.script().unwrap(), &change_address),
+            OutputKind::Change
+        );
+        assert_eq!(
+            output_kind(&other_address.script().unwrap(), &change_address),
+            OutputKind::Payment
+        );
+        let pool_box = make_pool_box(
+            200,
+            EpochCounter(1),
+            *BASE_FEE,
+            BlockHeight(100),
+            &PoolContractParameters::default(),
+            &generate_token_ids(),
+        );
+        assert_eq!(
+            output_kind(&pool_box.get_box().ergo_tree, &change_address),
+            OutputKind::Contract
+        );
+    }
+
+    #[test]
+    fn test_output_summary_display() {
+        let summary = OutputSummary {
+            index: 1,
+            kind: OutputKind::Contract,
+            destination: "<pool contract address>".to_string(),
+            value: 1_000_000,
+            tokens: vec![("01".repeat(32), 1)],
+        };
+        assert!(summary
+            .to_string()
+            .starts_with("#1 [contract] 0.001 ERG to <pool contract address>"));
+        assert!(summary
+            .to_string()
+            .ends_with(&format!("\n    token {}: 1", "01".repeat(32))));
+    }
+}
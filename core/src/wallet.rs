@@ -1,10 +1,19 @@
 use ergo_lib::ergotree_ir::chain::address::AddressEncoderError;
 use ergo_lib::ergotree_ir::chain::address::NetworkAddress;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
 use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::token::Token;
+use ergo_lib::wallet::box_selector::BoxSelection;
+use ergo_lib::wallet::box_selector::BoxSelector;
+use ergo_lib::wallet::box_selector::BoxSelectorError;
+use ergo_lib::wallet::box_selector::ErgoBoxAssets;
+use ergo_lib::wallet::box_selector::SimpleBoxSelector;
 use ergo_node_interface::node_interface::NodeError;
 use thiserror::Error;
 
 use crate::node_interface::node_api::NodeApiError;
+use crate::oracle_config::BoxSelectionStrategy;
+use crate::oracle_config::BOX_SELECTION_STRATEGY;
 
 #[derive(Debug, Error)]
 pub enum WalletDataError {
@@ -23,3 +32,81 @@ pub trait WalletDataSource {
     fn get_unspent_wallet_boxes(&self) -> Result<Vec<ErgoBox>, WalletDataError>;
     fn get_change_address(&self) -> Result<NetworkAddress, WalletDataError>;
 }
+
+/// Orders the inputs by the strategy and passes them to `SimpleBoxSelector`, which collects the
+/// inputs in the given order until the target balance and tokens are reached
+#[derive(Debug, Clone, Copy)]
+pub struct StrategyBoxSelector {
+    strategy: BoxSelectionStrategy,
+}
+
+impl StrategyBoxSelector {
+    pub fn new(strategy: BoxSelectionStrategy) -> Self {
+        Self { strategy }
+    }
+}
+
+impl<T: ErgoBoxAssets + Clone> BoxSelector<T> for StrategyBoxSelector {
+    fn select(
+        &self,
+        mut inputs: Vec<T>,
+        target_balance: BoxValue,
+        target_tokens: &[Token],
+    ) -> Result<BoxSelection<T>, BoxSelectorError> {
+        match self.strategy {
+            BoxSelectionStrategy::Simple => (),
+            BoxSelectionStrategy::SmallestFirst => inputs.sort_by_key(|b| *b.value().as_u64()),
+            BoxSelectionStrategy::LargestFirst => {
+                inputs.sort_by_key(|b| std::cmp::Reverse(*b.value().as_u64()))
+            }
+        }
+        SimpleBoxSelector::new().select(inputs, target_balance, target_tokens)
+    }
+}
+
+/// Box selector used by the action builders, with the strategy from the oracle config
+pub fn configured_box_selector() -> StrategyBoxSelector {
+    StrategyBoxSelector::new(*BOX_SELECTION_STRATEGY)
+}
+
+#[cfg(test)]
+mod tests {
+    use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInput;
+    use sigma_test_util::force_any_val;
+
+    use super::*;
+    use crate::pool_commands::test_utils::make_wallet_unspent_box;
+
+    #[test]
+    fn test_strategy_box_selector() {
+        let secret = force_any_val::<DlogProverInput>();
+        let boxes: Vec<ErgoBox> = [5_000_000u64, 1_000_000, 3_000_000]
+            .into_iter()
+            .map(|value| {
+                make_wallet_unspent_box(secret.public_image(), BoxValue::new(value).unwrap(), None)
+            })
+            .collect();
+        let target = BoxValue::new(2_000_000).unwrap();
+        let selected_values = |strategy| -> Vec<u64> {
+            StrategyBoxSelector::new(strategy)
+                .select(boxes.clone(), target, &[])
+                .unwrap()
+                .boxes
+                .iter()
+                .map(|b| *b.value.as_u64())
+                .collect()
+        };
+        assert_eq!(
+            selected_values(BoxSelectionStrategy::Simple),
+            vec![5_000_000]
+        );
+        assert_eq!(
+            selected_values(BoxSelectionStrategy::SmallestFirst),
+            vec![1_000_000, 3_000_000]
+        );
+        assert_eq!(
+            selected_values(BoxSelectionStrategy::LargestFirst),
+            vec![5_000_000]
+        );
+    }
+}